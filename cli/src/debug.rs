@@ -0,0 +1,190 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use cypher::objects::Object;
+use cypher::vm::{
+    chunk::Chunk,
+    debug::{code_lines, Breakpoint, Console, Debugger, Resume, StopReason},
+    vm::VM,
+};
+
+const HELP: &str = "  s, step              run to the next line, into calls
+  n, next              run to the next line of this function
+  o, out               run until this function returns
+  c, continue          run to the next breakpoint
+  b, break [file:]line stop at a line, of the script when no file is given
+  d, delete [file:]line remove a breakpoint
+  breaks               list the breakpoints
+  bt, backtrace        list the frames, innermost first
+  f, frame n           inspect frame n of the backtrace
+  locals, upvalues, globals
+                       print the variables of the frame
+  p, print code        evaluate code in the frame, assignments stick
+  l, list              print the source around the line
+  q, quit              stop the program
+  h, help";
+
+// lines printed by list on each side of the current one
+const CONTEXT: u32 = 3;
+
+/*
+  starts debugging the script in chunk on vm, stopping before its first
+  line unless breakpoints were given
+*/
+pub fn attach(vm: &mut VM, chunk: &Chunk, breakpoints: &[String]) -> Result<(), String> {
+    let lines = code_lines(chunk);
+    let mut debugger_breakpoints = vec![];
+    for text in breakpoints {
+        debugger_breakpoints.push(resolve(text, &chunk.file, &lines)?);
+    }
+    let mut debugger = Debugger::new(Box::new(Terminal {
+        script: chunk.file.to_owned(),
+        lines,
+        frame: 0,
+        sources: HashMap::new(),
+    }));
+    if !debugger_breakpoints.is_empty() {
+        debugger.set_resume(Resume::Continue);
+    }
+    for breakpoint in debugger_breakpoints {
+        debugger.add_breakpoint(breakpoint);
+    }
+    vm.set_hook(Some(Box::new(debugger)));
+    Ok(())
+}
+
+// a breakpoint in the script moves down to the next line with code
+fn resolve(text: &str, script: &str, lines: &BTreeSet<u32>) -> Result<Breakpoint, String> {
+    let mut breakpoint = Breakpoint::parse(text, script).ok_or_else(|| format!("invalid breakpoint {}, use file:line", text))?;
+    if breakpoint.file == script {
+        breakpoint.line = match lines.range(breakpoint.line..).next() {
+            Some(&line) => line,
+            None => return Err(format!("{} has no code at or after line {}", script, breakpoint.line)),
+        };
+    }
+    Ok(breakpoint)
+}
+
+// reads commands from stdin, the end of the input quits
+struct Terminal {
+    script: String,
+    lines: BTreeSet<u32>,
+    // the frame inspected, 0 is the innermost
+    frame: usize,
+    sources: HashMap<String, Vec<String>>,
+}
+
+impl Console for Terminal {
+    fn paused(&mut self, vm: &mut VM, breakpoints: &mut Vec<Breakpoint>, reason: StopReason) -> Resume {
+        self.frame = 0;
+        if let Some(location) = vm.location() {
+            let (file, line) = (location.file.to_owned(), location.line);
+            let reason = match reason {
+                StopReason::Breakpoint => "breakpoint at ",
+                StopReason::Step => "",
+            };
+            println!("{}{}:{} in {}", reason, file, line, location.function);
+            self.print_source(&file, line, line);
+        }
+        let stdin = io::stdin();
+        loop {
+            print!("(debug) ");
+            let _ = io::stdout().flush();
+            let mut input = String::new();
+            if matches!(stdin.lock().read_line(&mut input), Ok(0) | Err(_)) {
+                return Resume::Quit;
+            }
+            let (command, rest) = match input.trim().split_once(' ') {
+                Some((command, rest)) => (command, rest.trim()),
+                None => (input.trim(), ""),
+            };
+            match command {
+                "s" | "step" => return Resume::StepInto,
+                "n" | "next" => return Resume::StepOver,
+                "o" | "out" => return Resume::StepOut,
+                "c" | "continue" => return Resume::Continue,
+                "q" | "quit" => return Resume::Quit,
+                "b" | "break" => match resolve(rest, &self.script, &self.lines) {
+                    Ok(breakpoint) => {
+                        println!("breakpoint at {}", breakpoint);
+                        if !breakpoints.contains(&breakpoint) {
+                            breakpoints.push(breakpoint);
+                        }
+                    }
+                    Err(err) => println!("{}", err),
+                },
+                "d" | "delete" => match Breakpoint::parse(rest, &self.script) {
+                    Some(breakpoint) if breakpoints.contains(&breakpoint) => {
+                        breakpoints.retain(|other| *other != breakpoint)
+                    }
+                    _ => println!("no breakpoint at {}", rest),
+                },
+                "breaks" => {
+                    for breakpoint in breakpoints.iter() {
+                        println!("{}", breakpoint);
+                    }
+                }
+                "bt" | "backtrace" => {
+                    for (index, frame) in vm.backtrace().iter().enumerate() {
+                        let marker = if index == self.frame { '>' } else { ' ' };
+                        println!("{}{:>3} {} at {}:{}", marker, index, frame.function, frame.file, frame.line);
+                    }
+                }
+                "f" | "frame" => match rest.parse() {
+                    Ok(frame) if vm.frame_info(frame).is_some() => {
+                        self.frame = frame;
+                        let info = vm.frame_info(frame).unwrap();
+                        println!("{} at {}:{}", info.function, info.file, info.line);
+                    }
+                    _ => println!("no frame {}", rest),
+                },
+                "locals" => print_variables(vm.frame_info(self.frame).map(|info| info.locals).unwrap_or_default()),
+                "upvalues" => print_variables(vm.frame_info(self.frame).map(|info| info.upvalues).unwrap_or_default()),
+                "globals" => print_variables(vm.frame_globals(self.frame)),
+                "p" | "print" => match vm.eval_in_frame(self.frame, rest) {
+                    Ok(value) => println!("{}", show(&value)),
+                    Err(err) => println!("{}", err),
+                },
+                "l" | "list" => {
+                    if let Some(info) = vm.frame_info(self.frame) {
+                        self.print_source(&info.file, info.line.saturating_sub(CONTEXT).max(1), info.line + CONTEXT);
+                    }
+                }
+                "h" | "help" => println!("{}", HELP),
+                "" => {}
+                _ => println!("unknown command {}, h lists them", command),
+            }
+        }
+    }
+}
+
+impl Terminal {
+    fn print_source(&mut self, file: &str, from: u32, to: u32) {
+        let source = self
+            .sources
+            .entry(file.to_owned())
+            .or_insert_with(|| fs::read_to_string(file).map(|code| code.lines().map(str::to_owned).collect()).unwrap_or_default());
+        for line in from..=to {
+            if let Some(text) = line.checked_sub(1).and_then(|index| source.get(index as usize)) {
+                println!("{:>5} | {}", line, text);
+            }
+        }
+    }
+}
+
+fn print_variables(variables: Vec<(String, Object)>) {
+    for (name, value) in variables {
+        println!("{} = {}", name, show(&value));
+    }
+}
+
+// strings are quoted so that "1" and 1 tell apart
+fn show(value: &Object) -> String {
+    match value {
+        Object::Str(s) => format!("{:?}", s),
+        value => value.to_string(),
+    }
+}
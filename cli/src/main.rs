@@ -1,48 +1,385 @@
-use std::fs;
+use std::{env, fs, path::Path, process};
+
+mod debug;
 
 use cypher::compiler::compiler::Compiler;
+use cypher::compiler::optimize::optimize;
+use cypher::errors::ErrorKind;
+use cypher::eval::Eval;
 use cypher::lexer::scanner::Scanner;
+use cypher::objects::Object;
 use cypher::parser::parser::Parser;
-use cypher::vm::{chunk::Chunk, disassemble::Disassembler, object::Object, vm::VM};
+use cypher::policy::{Capability, Policy};
+use cypher::stdlib::io;
+use cypher::vm::{
+    chunk::Chunk,
+    cyc,
+    disassemble::Disassembler,
+    interner::Interner,
+    peephole,
+    trace::{TraceFormat, Tracer},
+    verify::verify,
+    vm::{Machine, VM},
+};
+
+#[derive(PartialEq)]
+enum Backend {
+    Tree,
+    Vm,
+    // the vm running register code, see vm::register
+    Register,
+}
+
+#[derive(PartialEq)]
+enum Command {
+    // runs a source or .cyc file
+    Run,
+    // writes the bytecode of a source file to output, next to it by default
+    Compile { output: Option<String> },
+    // runs a source file under the debugger, see debug
+    Debug { breakpoints: Vec<String> },
+}
+
+struct Options {
+    command: Command,
+    backend: Backend,
+    disassemble: bool,
+    dump_ast: bool,
+    // fold constants and drop dead code before running or compiling
+    optimize: bool,
+    gc_stress: bool,
+    gc_stats: bool,
+    profile: bool,
+    trace: bool,
+    // json lines of the trace go to this file
+    trace_json: Option<String>,
+    module_path: Vec<String>,
+    policy: Policy,
+    filename: String,
+    // everything after the script name, see io.args()
+    script_args: Vec<String>,
+}
+
+const USAGE: &str = "usage: cli [run] [--backend=tree|vm|register] [-O] [--disassemble] [--dump-ast] [--module-path=dir]...
+           [--gc-stress] [--gc-stats] [--profile] [--trace] [--trace-json=file]
+           [--sandbox] [--allow=group,...] [--max-instructions=n] [--max-memory=bytes] <file.cy|file.cyc> [args]...
+       cli compile [-O] [--disassemble] <file.cy> [-o file.cyc]
+       cli debug [--backend=vm|register] [--break=[file:]line]... <file.cy> [args]...
+
+  compile writes bytecode that run loads without the source, only the
+  vm backend runs it. the register backend compiles source to register
+  code for the same vm, compile only writes stack code
+
+  debug stops before the first line, or at the first breakpoint when
+  there are any, and reads commands from stdin, h lists them
+
+  -O folds constant expressions and removes code that cannot run
+  before the program is run or compiled, then fuses common runs of
+  bytecode, imported modules are left as they are
+
+  --sandbox denies every native group, --allow turns groups back on:
+  fs, env, process, time, random. the limits need the vm or register
+  backend, --max-memory is a budget for every byte allocated during the
+  run, memory freed again still counts
+  --gc-stress collects garbage on every allocation, --gc-stats prints
+  what the collector did to stderr once the vm is done
 
+  --profile prints the functions and lines the vm spent the most time
+  in and how often each opcode ran to stderr once it is done
+  --trace prints every instruction before the vm runs it with its frame,
+  line and stack to stderr, --trace-json writes them as json lines";
 
+fn parse_args() -> Options {
+    let mut options = Options {
+        command: Command::Run,
+        backend: Backend::Vm,
+        disassemble: false,
+        dump_ast: false,
+        optimize: false,
+        gc_stress: false,
+        gc_stats: false,
+        profile: false,
+        trace: false,
+        trace_json: None,
+        module_path: vec![],
+        policy: Policy::permissive(),
+        filename: "examples/hello.cy".to_owned(),
+        script_args: vec![],
+    };
+    let mut args = env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("run") => {
+            args.next();
+        }
+        Some("compile") => {
+            args.next();
+            options.command = Command::Compile { output: None };
+        }
+        Some("debug") => {
+            args.next();
+            options.command = Command::Debug { breakpoints: vec![] };
+        }
+        _ => {}
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => match (&mut options.command, args.next()) {
+                (Command::Compile { output }, Some(path)) => *output = Some(path),
+                (Command::Compile { .. }, None) => usage_error("-o needs a file name"),
+                _ => usage_error("-o is only used with compile"),
+            },
+            "--backend=tree" => options.backend = Backend::Tree,
+            "--backend=vm" => options.backend = Backend::Vm,
+            "--backend=register" => options.backend = Backend::Register,
+            "--disassemble" => options.disassemble = true,
+            "--dump-ast" => options.dump_ast = true,
+            "-O" => options.optimize = true,
+            "--gc-stress" => options.gc_stress = true,
+            "--gc-stats" => options.gc_stats = true,
+            "--profile" => options.profile = true,
+            "--trace" => options.trace = true,
+            "--sandbox" => {
+                for capability in Capability::ALL {
+                    options.policy = options.policy.deny(capability);
+                }
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with("--module-path=") => {
+                options.module_path.push(arg["--module-path=".len()..].to_owned())
+            }
+            _ if arg.starts_with("--break=") => match &mut options.command {
+                Command::Debug { breakpoints } => breakpoints.push(arg["--break=".len()..].to_owned()),
+                _ => usage_error("--break is only used with debug"),
+            },
+            _ if arg.starts_with("--trace-json=") => options.trace_json = Some(arg["--trace-json=".len()..].to_owned()),
+            _ if arg.starts_with("--allow=") => {
+                for name in arg["--allow=".len()..].split(',') {
+                    match Capability::from_name(name) {
+                        Some(capability) => options.policy = options.policy.allow(capability),
+                        None => usage_error(&format!("unknown capability {}", name)),
+                    }
+                }
+            }
+            _ if arg.starts_with("--max-instructions=") => {
+                match arg["--max-instructions=".len()..].parse() {
+                    Ok(limit) => options.policy.max_instructions = Some(limit),
+                    Err(_) => usage_error(&format!("invalid limit in {}", arg)),
+                }
+            }
+            _ if arg.starts_with("--max-memory=") => match arg["--max-memory=".len()..].parse() {
+                Ok(limit) => options.policy.max_memory = Some(limit),
+                Err(_) => usage_error(&format!("invalid limit in {}", arg)),
+            },
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option {}\n{}", arg, USAGE);
+                process::exit(2);
+            }
+            _ if matches!(options.command, Command::Compile { .. }) => options.filename = arg,
+            _ => {
+                options.filename = arg;
+                options.script_args = args.by_ref().collect();
+            }
+        }
+    }
+    options
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(2);
+}
 
 fn main() {
-    let filename="examples/hello.cy".to_owned();
-    let code = fs::read_to_string(filename.to_owned()).unwrap();
+    let options = parse_args();
+    let filename = options.filename.to_owned();
+    let bytes = match fs::read(&filename) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("unable to read {}: {}", filename, err);
+            process::exit(1);
+        }
+    };
 
-    /* 
+    if cyc::is_bytecode(&bytes) {
+        if options.command != Command::Run {
+            usage_error(&format!("{} is already compiled", filename));
+        }
+        if options.backend == Backend::Tree {
+            usage_error("the tree backend runs source files only");
+        }
+        let mut vm = new_vm(&options);
+        // verified here as well so that --disassemble never reads broken code
+        let file = match cyc::read(&bytes, &vm.interner()).and_then(|file| verify(&file.chunk).map(|_| file)) {
+            Ok(file) => file,
+            Err(err) => {
+                eprintln!("unable to load {}: {}", filename, err);
+                process::exit(1);
+            }
+        };
+        warn_if_stale(&filename, file.source_hash);
+        let result = run_vm(&mut vm, file.chunk, &options);
+        exit_on_error(result);
+        return;
+    }
+
+    let code = match String::from_utf8(bytes) {
+        Ok(code) => code,
+        Err(_) => {
+            eprintln!("unable to read {}: not utf-8 source", filename);
+            process::exit(1);
+        }
+    };
+
+    /*
        vector containing raw string literals by line num
     */
-    let lines:Vec<&str>=code.split("\n").collect();
+    let lines:Vec<&str>=code.split('\n').collect();
     let mut lex = Scanner::new(&code,filename.to_owned(),&lines);
     let mut par=Parser::new(&mut lex,&filename,&lines);
-    let program = par.parse_program();
-    // let  mut evaluator=Eval::new(&program);
-    let _=fs::write("examples/hello.json", program.to_string().as_str());
-    // println!("{}",program.to_string().as_str());
-    // evaluator.run();
-    let compiler=Compiler::new(filename.to_owned());
-
-    let chunk=compiler.compile_program(program);
+    let mut program = par.parse_program();
+    if options.optimize {
+        program = optimize(program);
+    }
+    if options.dump_ast {
+        println!("{}", program);
+    }
 
-    // let mut chunk:Chunk =Chunk::new("Main".to_owned());
+    if let Command::Compile { output } = &options.command {
+        if options.backend == Backend::Register {
+            usage_error("compile writes stack code only");
+        }
+        let mut chunk = Compiler::with_interner(filename.to_owned(), Interner::new()).compile_program(program);
+        if options.optimize {
+            peephole::optimize(&mut chunk);
+        }
+        if options.disassemble {
+            Disassembler::new(&chunk).run();
+        }
+        let output = match output {
+            Some(output) => output.to_owned(),
+            None => Path::new(&filename).with_extension("cyc").display().to_string(),
+        };
+        if let Err(err) = fs::write(&output, cyc::write(&chunk, &code)) {
+            eprintln!("unable to write {}: {}", output, err);
+            process::exit(1);
+        }
+        return;
+    }
 
-    // chunk.write_byte(2);
-    // let index=chunk.add_constant(Object::Number(3.0));
-    // chunk.write_byte(index as u8);
-
-    // chunk.write_byte(2);
-    // let index=chunk.add_constant(Object::Number(4.0));
-    // chunk.write_byte(index as u8);
-
-    // chunk.write_byte(5);
-    let mut dis:Disassembler=Disassembler::new(&chunk);
-    dis.run();
+    let result = match options.backend {
+        Backend::Tree if options.profile || options.trace || options.trace_json.is_some() => {
+            usage_error("--profile and --trace need the vm or register backend")
+        }
+        Backend::Tree if matches!(options.command, Command::Debug { .. }) => {
+            usage_error("the debugger needs the vm or register backend")
+        }
+        Backend::Tree => {
+            let mut evaluator = Eval::new(&program);
+            evaluator.set_filename(filename.to_owned());
+            // the tree walker does not count instructions or allocations
+            if evaluator.set_policy(options.policy.clone()).is_err() {
+                usage_error("--max-instructions and --max-memory need the vm or register backend");
+            }
+            for dir in &options.module_path {
+                evaluator.modules().add_search_path(dir);
+            }
+            evaluator.modules().add_builtin(io::module(options.script_args.clone()));
+            evaluator.run()
+        }
+        Backend::Vm => {
+            let mut vm = new_vm(&options);
+            let compiler = Compiler::with_interner(filename.to_owned(), vm.interner());
+            let mut chunk = compiler.compile_program(program);
+            if options.optimize {
+                peephole::optimize(&mut chunk);
+            }
+            run_vm(&mut vm, chunk, &options)
+        }
+        // the peephole pass only knows stack code
+        Backend::Register => {
+            let mut vm = new_vm(&options);
+            let chunk = vm.compile(filename.to_owned(), program).unwrap_or_else(|err| {
+                println!("{}", err);
+                process::exit(1);
+            });
+            run_vm(&mut vm, chunk, &options)
+        }
+    };
+    exit_on_error(result);
+}
 
-    let mut vm=VM::new();
+fn new_vm(options: &Options) -> VM {
+    let mut vm = VM::new();
+    vm.set_policy(options.policy.clone());
+    for dir in &options.module_path {
+        vm.modules().add_search_path(dir);
+    }
+    vm.modules().add_builtin(io::module(options.script_args.clone()));
+    vm.set_gc_stress(options.gc_stress);
+    vm.set_profiling(options.profile);
+    if let Some(path) = &options.trace_json {
+        match fs::File::create(path) {
+            Ok(file) => vm.set_tracer(Some(Tracer::new(Box::new(std::io::BufWriter::new(file)), TraceFormat::Json))),
+            Err(err) => {
+                eprintln!("unable to write {}: {}", path, err);
+                process::exit(1);
+            }
+        }
+    } else if options.trace {
+        vm.set_tracer(Some(Tracer::new(Box::new(std::io::stderr()), TraceFormat::Text)));
+    }
+    if options.backend == Backend::Register {
+        vm.set_machine(Machine::Register);
+    }
+    vm
+}
 
-    vm.run(chunk);
+fn run_vm(vm: &mut VM, chunk: Chunk, options: &Options) -> Object {
+    if let Command::Debug { breakpoints } = &options.command {
+        if let Err(err) = debug::attach(vm, &chunk, breakpoints) {
+            usage_error(&err);
+        }
+    }
+    if options.disassemble {
+        let mut dis: Disassembler = Disassembler::new(&chunk);
+        dis.run();
+    }
+    let result = vm.run(chunk);
+    // flushes the json trace before an error exits the process
+    vm.set_tracer(None);
+    if options.gc_stats {
+        let stats = vm.gc_stats();
+        eprintln!(
+            "gc: {} collections, {} objects allocated, {} freed, {} live ({} bytes)",
+            stats.collections, stats.allocated, stats.freed, stats.live, stats.live_bytes
+        );
+    }
+    if let Some(profile) = vm.profile() {
+        eprint!("{}", profile);
+    }
+    result
+}
 
+// the source next to a .cyc file was changed since it was compiled
+fn warn_if_stale(filename: &str, source_hash: u64) {
+    let source = Path::new(filename).with_extension("cy");
+    if let Ok(code) = fs::read_to_string(&source) {
+        if cyc::hash_source(&code) != source_hash {
+            eprintln!("warning: {} is older than {}, compile it again", filename, source.display());
+        }
+    }
+}
 
+// exits with the status of a failed program, returns if it succeeded
+fn exit_on_error(result: Object) {
+    if let Object::Error(err) = result {
+        if let ErrorKind::Exit(code) = err.kind {
+            process::exit(code);
+        }
+        eprintln!("{}", err);
+        process::exit(70);
+    }
 }
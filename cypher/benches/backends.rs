@@ -0,0 +1,145 @@
+/*
+  a few typical programs run on every backend, the tree walking Eval,
+  the vm on stack code with and without the peephole pass and the vm on
+  register code. prints the best time of a few runs of each and fails
+  when the backends disagree about the result
+*/
+use std::time::Instant;
+
+use cypher::{
+    compiler::{compiler::Compiler, register::RegisterCompiler},
+    eval::Eval,
+    objects::Object,
+    vm::{peephole, vm::VM},
+};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+use common::parse;
+
+const RUNS: usize = 3;
+
+const PROGRAMS: &[(&str, &str)] = &[
+    (
+        "fib",
+        "def fib(n) {
+    if n < 2 {
+        return n
+    }
+    return fib(n - 1) + fib(n - 2)
+}
+return fib(20)",
+    ),
+    (
+        "loops",
+        "let total = 0
+for let i = 0; i < 300; i++ {
+    let j = 0
+    while j < 300 {
+        if (i + j) % 3 == 0 {
+            total = total + 1
+        }
+        j++
+    }
+}
+return total",
+    ),
+    (
+        "string building",
+        "let s = \"\"
+for let i = 0; i < 2000; i++ {
+    s = s + \"ab\"
+    if i % 100 == 0 {
+        s = s + \"\\n\"
+    }
+}
+return s.len()",
+    ),
+    (
+        "array sort",
+        "let values = []
+let seed = 7
+for let i = 0; i < 1000; i++ {
+    seed = (seed * 1103 + 12345) % 65536
+    values = values.push(seed)
+}
+def descending(a, b) {
+    return b - a
+}
+let up = values.sort()
+let down = values.sort(descending)
+return [up[0], up[999], down[0], down[999]]",
+    ),
+    (
+        "method dispatch",
+        "class Counter {
+    def init() {
+        self.count = 0
+    }
+    def add(n) {
+        self.count = self.count + n
+        return self
+    }
+}
+class Doubler < Counter {
+    def add(n) {
+        return super.add(n * 2)
+    }
+}
+let c = Counter()
+let d = Doubler()
+for let i = 0; i < 20000; i++ {
+    c.add(1)
+    d.add(1)
+}
+return [c.count, d.count]",
+    ),
+];
+
+fn run(backend: &str, code: &str) -> Object {
+    let program = parse(code);
+    match backend {
+        "tree" => Eval::new(&program).run(),
+        "vm" => VM::new().run(Compiler::new("bench.cy".to_owned()).compile_program(program)),
+        "vm -O" => {
+            let mut chunk = Compiler::new("bench.cy".to_owned()).compile_program(program);
+            peephole::optimize(&mut chunk);
+            VM::new().run(chunk)
+        }
+        _ => VM::new().run(RegisterCompiler::new("bench.cy".to_owned()).compile_program(program)),
+    }
+}
+
+// the best time of a few runs in ms, parsing and compiling included
+fn measure(backend: &str, code: &str) -> (Object, f64) {
+    let mut best = f64::MAX;
+    let mut result = Object::Nil;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        result = run(backend, code);
+        best = best.min(start.elapsed().as_secs_f64() * 1000.0);
+    }
+    (result, best)
+}
+
+fn main() {
+    let backends = ["tree", "vm", "vm -O", "register"];
+    print!("{:<16}", "program");
+    for backend in backends {
+        print!(" {:>10}", backend);
+    }
+    println!("  (ms)");
+    for (name, code) in PROGRAMS {
+        print!("{:<16}", name);
+        let mut expected = None;
+        for backend in backends {
+            let (result, ms) = measure(backend, code);
+            assert!(!matches!(result, Object::Error(_)), "{} on {}: {}", name, backend, result);
+            // the tree walker is the reference
+            let expected = expected.get_or_insert_with(|| result.clone());
+            assert_eq!(&result, expected, "{} on {}", name, backend);
+            print!(" {:>10.1}", ms);
+        }
+        println!();
+    }
+}
@@ -0,0 +1,77 @@
+/*
+  instructions the vm dispatches for a few programs as compiled, after
+  the peephole pass and compiled to register code, with the time each
+  run took
+*/
+use std::time::Instant;
+
+use cypher::vm::{chunk::Chunk, peephole, vm::VM};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+use common::{compile, compile_register};
+
+const PROGRAMS: &[(&str, &str)] = &[
+    (
+        "counting loop",
+        "def count(n) {
+    let total = 0
+    for let i = 0; i < n; i++ {
+        if i == 3 {
+            total = total + 1
+        }
+    }
+    return total
+}
+return count(300000)",
+    ),
+    (
+        "while loop",
+        "def sum(n) {
+    let i = 0
+    let total = 0
+    while i < n {
+        total = total + i
+        i = i + 1
+    }
+    return total
+}
+return sum(300000)",
+    ),
+    (
+        "fib",
+        "def fib(n) {
+    if n < 2 {
+        return n
+    }
+    return fib(n - 1) + fib(n - 2)
+}
+return fib(22)",
+    ),
+];
+
+fn measure(chunk: Chunk) -> (u64, f64) {
+    let mut vm = VM::new();
+    let start = Instant::now();
+    vm.run(chunk);
+    (vm.instruction_count(), start.elapsed().as_secs_f64() * 1000.0)
+}
+
+fn main() {
+    println!(
+        "{:<16} {:>12} {:>12} {:>8} {:>12} {:>10} {:>10} {:>10}",
+        "program", "plain", "peephole", "saved", "register", "plain ms", "peep ms", "reg ms"
+    );
+    for (name, code) in PROGRAMS {
+        let (plain, plain_ms) = measure(compile(code));
+        let mut chunk = compile(code);
+        peephole::optimize(&mut chunk);
+        let (fused, fused_ms) = measure(chunk);
+        let (register, register_ms) = measure(compile_register(code));
+        let saved = 100.0 * (plain - fused) as f64 / plain as f64;
+        println!(
+            "{:<16} {:>12} {:>12} {:>7.1}% {:>12} {:>10.1} {:>10.1} {:>10.1}",
+            name, plain, fused, saved, register, plain_ms, fused_ms, register_ms
+        );
+    }
+}
@@ -1,75 +1,212 @@
-use std::process::ExitCode;
+use std::rc::Rc;
 
 use crate::{
+    errors::compile::CompileError,
     lexer::token::{Token, TokenType},
+    objects::Object,
     parser::expr::{Expression, Program, Statement},
-    vm::{chunk::Chunk, object::Object, opcode::Opcode},
+    vm::{chunk::Chunk, interner::Interner, object::CompiledFunction, opcode::Opcode},
 };
 
-use super::symbol_table::{Symbol, SymbolScope, SymbolTable};
+use super::{
+    symbol_table::SymbolScope,
+    unit::{FunctionKind, TryBlock, Tries, Unit},
+};
 
 pub struct Compiler {
-    chunk: Chunk,
-    filename: String,
-    scope_depth: usize,
-    symboltable:SymbolTable
+    unit: Unit,
 }
 
 impl Compiler {
     pub fn new(filename: String) -> Self {
+        Self::with_interner(filename, Interner::new())
+    }
+
+    /*
+      a compiler putting its names and strings in interner, pass the one
+      of the vm that will run the code so they are shared
+    */
+    pub fn with_interner(filename: String, interner: Interner) -> Self {
         Self {
-            chunk: Chunk::new(filename.to_owned()),
-            filename,
-            scope_depth: 0,
-            symboltable:SymbolTable::new()
+            unit: Unit::new(filename, interner),
         }
     }
 
+    fn emit_byte(&mut self, byte: u8) {
+        self.unit.chunk.write_byte(byte, self.unit.line);
+    }
+
+    fn emit_op(&mut self, opcode: Opcode) {
+        self.emit_byte(opcode as u8);
+    }
+
+    fn emit_constant(&mut self, obj: Object) {
+        let index = self.unit.add_constant(obj);
+        self.emit_op(Opcode::LC);
+        self.emit_byte(index as u8);
+    }
+
+    // emits a jump with a placeholder target, returns the offset to patch
+    fn emit_jump(&mut self, opcode: Opcode) -> usize {
+        self.emit_op(opcode);
+        self.emit_byte(0xff);
+        self.emit_byte(0xff);
+        self.unit.chunk.get_code_length() - 2
+    }
+
+    fn emit_loop(&mut self, start: usize) {
+        let offset = self.emit_jump(Opcode::JMP);
+        self.unit.patch_jump_to(offset, start);
+    }
+
+    fn emit_return_self(&mut self) {
+        self.emit_op(Opcode::GETLOCAL);
+        self.emit_byte(0);
+    }
+
     fn emit_opcode(&mut self, operator: &Token) {
         // println!("{}",operator);
+        self.unit.set_line(operator);
         match operator.kind {
-            TokenType::Plus => self.chunk.write_byte(Opcode::ADD as u8),
-            TokenType::Minus => self.chunk.write_byte(Opcode::SUB as u8),
-            TokenType::Star => self.chunk.write_byte(Opcode::MUL as u8),
-            TokenType::Slash => self.chunk.write_byte(Opcode::DIV as u8),
-            TokenType::Modulo => self.chunk.write_byte(Opcode::MOD as u8),
-            TokenType::GreaterThan => self.chunk.write_byte(Opcode::GT as u8),
-            TokenType::GreaterThanOrEqual => self.chunk.write_byte(Opcode::GOE as u8),
-            TokenType::LessThan => self.chunk.write_byte(Opcode::LT as u8),
-            TokenType::LessThanOrEqual => self.chunk.write_byte(Opcode::LOE as u8),
-            _ => self.chunk.write_byte(Opcode::NOP as u8),
+            TokenType::Plus => self.emit_op(Opcode::ADD),
+            TokenType::Minus => self.emit_op(Opcode::SUB),
+            TokenType::Star => self.emit_op(Opcode::MUL),
+            TokenType::Slash => self.emit_op(Opcode::DIV),
+            TokenType::Modulo => self.emit_op(Opcode::MOD),
+            TokenType::Power => self.emit_op(Opcode::POW),
+            TokenType::IntDivide => self.emit_op(Opcode::IDIV),
+            TokenType::GreaterThan => self.emit_op(Opcode::GT),
+            TokenType::GreaterThanOrEqual => self.emit_op(Opcode::GOE),
+            TokenType::LessThan => self.emit_op(Opcode::LT),
+            TokenType::LessThanOrEqual => self.emit_op(Opcode::LOE),
+            TokenType::Equal => self.emit_op(Opcode::EQ),
+            TokenType::NotEqual => self.emit_op(Opcode::NEQ),
+            TokenType::Is => self.emit_op(Opcode::IS),
+            _ => self.emit_op(Opcode::NOP),
+        }
+    }
+
+    pub fn compile_program(self, program: Program) -> Chunk {
+        match self.try_compile_program(program) {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1);
+            }
         }
     }
 
-    pub fn compile_program(mut self, program: Program) -> Chunk {
+    pub fn try_compile_program(mut self, program: Program) -> Result<Chunk, CompileError> {
         self.compile_statements(&program.body);
-        self.chunk
+        self.emit_constant(Object::Nil);
+        self.emit_op(Opcode::RET);
+        self.unit.finish()
     }
 
     fn compile_statements(&mut self, statements: &Vec<Statement>) {
         for statement in statements {
             self.compile_statement(statement)
         }
+    }
+
+    fn end_scope(&mut self) {
+        self.unit.scope_depth -= 1;
+        let dropped = self.unit.symboltable.end_scope(self.unit.scope_depth);
+        self.unit.chunk.end_locals(self.unit.symboltable.local_count());
+        for symbol in dropped {
+            if symbol.captured {
+                self.emit_op(Opcode::CLOSEUPVAL);
+            } else {
+                self.emit_op(Opcode::POP);
+            }
+        }
+    }
+
+    // a local in the next slot, named for debuggers unless it is hidden
+    fn define_local(&mut self, name: String) -> usize {
+        self.unit.chunk.begin_local(&name, self.unit.symboltable.local_count());
+        let slot = self.unit.symboltable.define(name, self.unit.scope_depth);
+        self.unit.check_limit(slot + 1, "local variables");
+        slot
+    }
+
+    /*
+      binds the value on top of the stack to name, globals are stored by
+      name while locals simply stay in their stack slot
+    */
+    fn define_variable(&mut self, name: &Token) {
+        let name = name.value.as_ref().unwrap().to_owned();
+        if self.unit.scope_depth == 0 {
+            self.define_global(&name);
+        } else {
+            self.define_local(name);
+        }
+    }
 
-        
+    fn define_global(&mut self, name: &str) {
+        let index = self.unit.string_constant(name);
+        self.emit_op(Opcode::DEFGLOBAL);
+        self.emit_byte(index as u8);
+    }
+
+    fn get_variable(&mut self, name: &Token) {
+        self.unit.set_line(name);
+        let symbol = self.unit.symboltable.resolve(name.value.as_ref().unwrap());
+        match symbol.scope {
+            SymbolScope::LOCAL => {
+                self.emit_op(Opcode::GETLOCAL);
+                self.emit_byte(symbol.index as u8);
+            }
+            SymbolScope::UPVALUE => {
+                self.emit_op(Opcode::GETUPVAL);
+                self.emit_byte(symbol.index as u8);
+            }
+            SymbolScope::GLOBAL => {
+                let index = self.unit.string_constant(&symbol.name);
+                self.emit_op(Opcode::GETGLOBAL);
+                self.emit_byte(index as u8);
+            }
+        }
+    }
+
+    fn set_variable(&mut self, name: &Token) {
+        self.unit.set_line(name);
+        let symbol = self.unit.symboltable.resolve(name.value.as_ref().unwrap());
+        match symbol.scope {
+            SymbolScope::LOCAL => {
+                self.emit_op(Opcode::SETLOCAL);
+                self.emit_byte(symbol.index as u8);
+            }
+            SymbolScope::UPVALUE => {
+                self.emit_op(Opcode::SETUPVAL);
+                self.emit_byte(symbol.index as u8);
+            }
+            SymbolScope::GLOBAL => {
+                let index = self.unit.string_constant(&symbol.name);
+                self.emit_op(Opcode::SETGLOBAL);
+                self.emit_byte(index as u8);
+            }
+        }
     }
 
     fn compile_statement(&mut self, statement: &Statement) {
         match statement {
-            Statement::ExpressionStatement { expr } => self.compile_expression(expr),
+            Statement::ExpressionStatement { expr } => {
+                self.compile_expression(expr);
+                self.emit_op(Opcode::POP);
+            }
             Statement::PrintStatement { expr } => {
                 self.compile_expression(expr);
-                self.chunk.write_byte(Opcode::PRINT as u8)
+                self.emit_op(Opcode::PRINT)
             }
             Statement::VariableStatement { ident, expr } => {
-                if let Some(statement)=expr {
-                    self.compile_statement(statement);
-                }
-                else{
-                    self.chunk.add_constant(Object::Nil);
+                self.unit.set_line(ident);
+                match expr.as_deref() {
+                    Some(Statement::ExpressionStatement { expr }) => self.compile_expression(expr),
+                    Some(statement) => self.compile_statement(statement),
+                    None => self.emit_constant(Object::Nil),
                 }
-                self.symboltable.define(ident.value.as_ref().unwrap().to_owned(),self.scope_depth,SymbolScope::GLOBAL,self.chunk.constants.len()-1);
-                
+                self.define_variable(ident);
             },
             Statement::IFStatement {
                 condition,
@@ -77,46 +214,278 @@ impl Compiler {
                 _else,
             } => {
                 self.compile_expression(condition);
-                self.chunk.write_byte(Opcode::JNE as u8);
-                self.chunk.write_byte(199);
-                let jmp_index = self.chunk.get_code_length() - 1;
-                
-                self.compile_statement(then);
-                self.chunk.write_byte(Opcode::JMP as u8);
-                self.chunk.write_byte(199);
-                let then_index = self.chunk.get_code_length() - 1;
-                // self.chunk.code[then_index-1]=(self.chunk.get_code_length()-1) as u8;
+                let else_jump = self.emit_jump(Opcode::JNE);
 
-                if _else.is_some() {
-                    self.compile_statement(_else.as_ref().unwrap());
-                    let else_index=self.chunk.get_code_length();
+                self.compile_statement(then);
 
-                    self.chunk.code[jmp_index] = (then_index+1) as u8;
-                    self.chunk.code[then_index]=else_index as u8;
+                if let Some(_else) = _else {
+                    let end_jump = self.emit_jump(Opcode::JMP);
+                    self.unit.patch_jump(else_jump);
+                    self.compile_statement(_else);
+                    self.unit.patch_jump(end_jump);
                 } else {
-                    self.chunk.code[jmp_index] = then_index as u8;
+                    self.unit.patch_jump(else_jump);
                 }
             }
             Statement::BlockStatement { statements } => {
+                self.unit.begin_scope();
                 self.compile_statements(statements);
+                self.end_scope();
             }
             Statement::WhileStatement { condition, body } => {
+                let loop_start = self.unit.chunk.get_code_length();
                 self.compile_expression(condition);
-                self.chunk.write_byte(Opcode::JNE as u8);
-                self.chunk.write_byte(144);
-                let cond_index = self.chunk.get_code_length() - 1;
+                let exit_jump = self.emit_jump(Opcode::JNE);
                 self.compile_statement(body);
-
-                self.chunk.code[cond_index] = (self.chunk.get_code_length() - 1) as u8;
+                self.emit_loop(loop_start);
+                self.unit.patch_jump(exit_jump);
             }
             Statement::ForStatement {
                 initializer,
                 condition,
                 increment,
                 body,
-            } => todo!(),
-            Statement::FunctionDeclaration { name, args, body } => todo!(),
-            Statement::ReturnStatement { expr } => todo!(),
+            } => {
+                self.unit.begin_scope();
+                self.compile_statement(initializer);
+                let loop_start = self.unit.chunk.get_code_length();
+                self.compile_expression(condition);
+                let exit_jump = self.emit_jump(Opcode::JNE);
+                self.compile_statement(body);
+                self.compile_expression(increment);
+                self.emit_op(Opcode::POP);
+                self.emit_loop(loop_start);
+                self.unit.patch_jump(exit_jump);
+                self.end_scope();
+            }
+            Statement::FunctionDeclaration { name, args, body } => {
+                self.unit.set_line(name);
+                // declared before the body is compiled so the function can call itself
+                if self.unit.scope_depth > 0 {
+                    self.unit.symboltable
+                        .define(name.value.as_ref().unwrap().to_owned(), self.unit.scope_depth);
+                }
+                self.compile_function(name, args, body, FunctionKind::Function);
+                if self.unit.scope_depth == 0 {
+                    self.define_variable(name);
+                }
+            }
+            Statement::ReturnStatement { expr } => {
+                match expr {
+                    Some(expr) => self.compile_expression(expr),
+                    None => self.emit_constant(Object::Nil),
+                }
+                self.compile_finally_blocks();
+                if self.unit.kind == FunctionKind::Initializer {
+                    self.emit_op(Opcode::POP);
+                    self.emit_return_self();
+                }
+                self.emit_op(Opcode::RET);
+            }
+            Statement::ClassDeclaration { name, superclass, methods } => {
+                self.unit.set_line(name);
+                let index = self.unit.name_constant(name);
+                match superclass {
+                    /*
+                      the superclass lives in a local named super that methods
+                      capture. it is read before the class is named, so that
+                      class A < A finds the A declared before it
+                    */
+                    Some(superclass) if self.unit.scope_depth == 0 => {
+                        self.unit.begin_scope();
+                        self.get_variable(superclass);
+                        let parent = self.define_local("super".to_owned());
+                        self.unit.set_line(name);
+                        self.emit_op(Opcode::CLASS);
+                        self.emit_byte(index as u8);
+                        self.define_global(name.value.as_ref().unwrap());
+                        self.get_variable(name);
+                        self.emit_op(Opcode::GETLOCAL);
+                        self.emit_byte(parent as u8);
+                        self.emit_op(Opcode::INHERIT);
+                    }
+                    Some(superclass) => {
+                        self.emit_op(Opcode::CLASS);
+                        self.emit_byte(index as u8);
+                        self.get_variable(superclass);
+                        let class = self.define_local(name.value.as_ref().unwrap().to_owned());
+                        self.unit.begin_scope();
+                        let parent = self.define_local("super".to_owned());
+                        for slot in [class, parent] {
+                            self.emit_op(Opcode::GETLOCAL);
+                            self.emit_byte(slot as u8);
+                        }
+                        self.emit_op(Opcode::INHERIT);
+                    }
+                    None => {
+                        self.emit_op(Opcode::CLASS);
+                        self.emit_byte(index as u8);
+                        self.define_variable(name);
+                    }
+                }
+
+                // the class stays on the stack while its methods are attached
+                self.get_variable(name);
+                for method in methods {
+                    if let Statement::FunctionDeclaration { name, args, body } = method {
+                        self.unit.set_line(name);
+                        self.compile_function(name, args, body, FunctionKind::method(name));
+                        let index = self.unit.name_constant(name);
+                        self.emit_op(Opcode::METHOD);
+                        self.emit_byte(index as u8);
+                    }
+                }
+                self.emit_op(Opcode::POP);
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+            }
+            Statement::ImportStatement { path, alias } => {
+                self.unit.set_line(path);
+                let index = self.unit.name_constant(path);
+                self.emit_op(Opcode::IMPORT);
+                self.emit_byte(index as u8);
+                self.define_variable(alias);
+            }
+            Statement::FromImportStatement { path, names } => {
+                self.unit.set_line(path);
+                let index = self.unit.name_constant(path);
+                // the module is cached after the first IMPORT
+                for name in names {
+                    self.emit_op(Opcode::IMPORT);
+                    self.emit_byte(index as u8);
+                    let name_index = self.unit.name_constant(name);
+                    self.emit_op(Opcode::GETPROP);
+                    self.emit_byte(name_index as u8);
+                    self.define_variable(name);
+                }
+            }
+            // the importer reads exported names from the module globals
+            Statement::ExportStatement { declaration } => self.compile_statement(declaration),
+            Statement::ThrowStatement { keyword, expr } => {
+                self.compile_expression(expr);
+                self.unit.set_line(keyword);
+                self.emit_op(Opcode::THROW);
+            }
+            Statement::TryStatement { body, name, handler, finally } => {
+                self.compile_try(body, name.as_ref(), handler.as_deref(), finally)
+            }
+        }
+    }
+
+    /*
+      try body catch name handler finally block compiles to
+
+            TRY catch
+            body
+            ENDTRY
+            JMP done
+        catch:              error on the stack
+            TRY rethrow     when there is a finally block
+            handler         with the error as the local name
+            ENDTRY
+        done:
+            finally
+            JMP end
+        rethrow:            error raised in handler, or body without catch
+            finally
+            THROW
+        end:
+    */
+    fn compile_try(
+        &mut self,
+        body: &Statement,
+        name: Option<&Token>,
+        handler: Option<&Statement>,
+        finally: &Option<Box<Statement>>,
+    ) {
+        let has_finally = finally.is_some();
+        let mut rethrow_jumps = vec![];
+        let mut done_jumps = vec![];
+
+        let catch_jump = self.emit_jump(Opcode::TRY);
+        self.compile_guarded(TryBlock { handler: true, finally: finally.clone() }, body);
+        self.emit_op(Opcode::ENDTRY);
+        done_jumps.push(self.emit_jump(Opcode::JMP));
+
+        match (name, handler) {
+            (Some(name), Some(handler)) => {
+                self.unit.patch_jump(catch_jump);
+                if has_finally {
+                    rethrow_jumps.push(self.emit_jump(Opcode::TRY));
+                }
+                // the error pushed by the vm is the first local of the catch scope
+                self.unit.begin_scope();
+                self.define_local(name.value.as_ref().unwrap().to_owned());
+                self.compile_guarded(TryBlock { handler: has_finally, finally: finally.clone() }, handler);
+                self.end_scope();
+                if has_finally {
+                    self.emit_op(Opcode::ENDTRY);
+                }
+            }
+            _ => rethrow_jumps.push(catch_jump),
+        }
+
+        for jump in done_jumps {
+            self.unit.patch_jump(jump);
+        }
+        if let Some(finally) = finally {
+            self.compile_statement(finally);
+            let end_jump = self.emit_jump(Opcode::JMP);
+            for jump in rethrow_jumps {
+                self.unit.patch_jump(jump);
+            }
+            // the error stays below the locals of the finally block, after
+            // a catch clause so does the error it caught
+            let hidden = if handler.is_some() { 2 } else { 1 };
+            self.unit.begin_scope();
+            for _ in 0..hidden {
+                self.define_local(String::new());
+            }
+            self.compile_statement(finally);
+            self.unit.forget_scope();
+            self.emit_op(Opcode::THROW);
+            self.unit.patch_jump(end_jump);
+        }
+    }
+
+    // see Tries::unwind_tries, the return value is kept on the stack in a hidden local
+    fn compile_finally_blocks(&mut self) {
+        if !self.unit.has_finally() {
+            // RET drops the handlers of the frame
+            return;
+        }
+        self.unit.begin_scope();
+        self.define_local(String::new());
+        self.unwind_tries();
+        self.unit.forget_scope();
+    }
+
+    fn compile_function(&mut self, name: &Token, args: &[Token], body: &Statement, kind: FunctionKind) {
+        let function_name = name.value.as_ref().unwrap().to_owned();
+        let enclosing = self.unit.enter_function(&function_name, kind);
+        for arg in args {
+            self.define_local(arg.value.as_ref().unwrap().to_owned());
+        }
+
+        self.compile_statement(body);
+        if kind == FunctionKind::Initializer {
+            self.emit_return_self();
+        } else {
+            self.emit_constant(Object::Nil);
+        }
+        self.emit_op(Opcode::RET);
+        let (chunk, upvalues) = self.unit.exit_function(enclosing);
+
+        let function = CompiledFunction::new(function_name, args.len(), upvalues.len(), chunk);
+        // the closure is made where the function is declared, not where its body ends
+        self.unit.set_line(name);
+        let index = self.unit.add_constant(Object::CompiledFunction(Rc::new(function)));
+        self.emit_op(Opcode::CLOSURE);
+        self.emit_byte(index as u8);
+        for upvalue in upvalues {
+            self.emit_byte(upvalue.is_local as u8);
+            self.emit_byte(upvalue.index as u8);
         }
     }
 
@@ -124,68 +493,127 @@ impl Compiler {
         match expression {
             Expression::VariableAssignment { identifier, expr } => {
                 self.compile_expression(expr);
-                if let Some(symbol)=self.symboltable.resolve(identifier.value.as_ref().unwrap()){
-                    
-                }
+                self.set_variable(identifier);
             },
             Expression::GroupingExpression { exp } => {
                 self.compile_expression(exp);
             }
             Expression::UnaryExpression { op, rhs } => {
                 self.compile_expression(rhs);
-                self.emit_opcode(op)
+                self.unit.set_line(op);
+                match op.kind {
+                    TokenType::Minus => self.emit_op(Opcode::NEG),
+                    _ => self.emit_op(Opcode::NOT),
+                }
             }
             Expression::BinaryExpression { lhs, op, rhs } => {
                 self.compile_expression(lhs);
-                self.compile_expression(rhs);
-                self.emit_opcode(op)
+                match op.kind {
+                    TokenType::And | TokenType::Or => {
+                        self.unit.set_line(op);
+                        let opcode = if op.kind == TokenType::And {
+                            Opcode::AND
+                        } else {
+                            Opcode::OR
+                        };
+                        let end_jump = self.emit_jump(opcode);
+                        self.compile_expression(rhs);
+                        self.unit.patch_jump(end_jump);
+                    }
+                    _ => {
+                        self.compile_expression(rhs);
+                        self.emit_opcode(op)
+                    }
+                }
             }
             Expression::IncrementDecrement { op, identifier } => {
-
-                self.emit_opcode(op);
-            },
-            Expression::ArrayDeclaration { elements } => todo!(),
-            Expression::ArrayIndexing { ident, index } => todo!(),
-            Expression::FunctionCall { calle, args } => todo!(),
-            Expression::GetExpression { identifier, exp } => todo!(),
-            Expression::Literal { value } => match value.kind {
-                TokenType::Number => {
-                    self.chunk.write_byte(Opcode::LC as u8);
-                    let index = self.chunk.add_constant(Object::Number(
-                        value
-                            .value
-                            .as_ref()
-                            .unwrap()
-                            .to_owned()
-                            .parse()
-                            .unwrap_or(0.0),
-                    ));
-                    self.chunk.write_byte(index as u8);
-                }
-                TokenType::Identifier=>{
-                    if let Some(symbol)=self.symboltable.resolve(value.value.as_ref().unwrap()){
-                        self.chunk.write_byte(Opcode::GETGLOBAL as u8);
-                        self.chunk.write_byte(symbol.index as u8);
-                    }
-                    // self.chunk.write_byte(Opcode::GETGLOBAL as u8);
+                self.get_variable(identifier);
+                self.emit_constant(Object::Number(1.0));
+                self.unit.set_line(op);
+                match op.kind {
+                    TokenType::Increment => self.emit_op(Opcode::ADD),
+                    _ => self.emit_op(Opcode::SUB),
                 }
-                TokenType::True=>{
-                    self.chunk.write_byte(Opcode::LC as u8);
-                    let index=self.chunk.add_constant(Object::Boolean(true));
-                    self.chunk.write_byte(index as u8);
+                self.set_variable(identifier);
+            },
+            Expression::ArrayDeclaration { elements } => {
+                for element in elements {
+                    self.compile_expression(element);
                 }
-                TokenType::False=>{
-                    self.chunk.write_byte(Opcode::LC as u8);
-                    let index=self.chunk.add_constant(Object::Boolean(false));
-                    self.chunk.write_byte(index as u8);
+                self.unit.check_limit(elements.len() + 1, "array elements");
+                self.emit_op(Opcode::ARRAY);
+                self.emit_byte(elements.len() as u8);
+            }
+            Expression::ArrayIndexing { ident, index } => {
+                self.compile_expression(ident);
+                self.compile_expression(index);
+                self.emit_op(Opcode::INDEX);
+            }
+            Expression::FunctionCall { calle, args } => {
+                self.compile_expression(calle);
+                for arg in args {
+                    self.compile_expression(arg);
                 }
-                TokenType::String=>{
-                    self.chunk.write_byte(Opcode::LC as u8);
-                    let index=self.chunk.add_constant(Object::Str(value.value.as_ref().unwrap().to_owned()));
-                    self.chunk.write_byte(index as u8);
+                self.unit.check_limit(args.len() + 1, "arguments");
+                self.emit_op(Opcode::CALL);
+                self.emit_byte(args.len() as u8);
+            }
+            Expression::GetExpression { identifier, exp } => {
+                self.compile_expression(exp);
+                self.unit.set_line(identifier);
+                let index = self.unit.name_constant(identifier);
+                self.emit_op(Opcode::GETPROP);
+                self.emit_byte(index as u8);
+            }
+            Expression::SuperExpression { keyword, method } => {
+                let location = keyword.get_location();
+                self.get_variable(&Token::new(TokenType::Identifier, location, Some("self".to_owned())));
+                self.get_variable(&Token::new(TokenType::Identifier, location, Some("super".to_owned())));
+                self.unit.set_line(method);
+                let index = self.unit.name_constant(method);
+                self.emit_op(Opcode::GETSUPER);
+                self.emit_byte(index as u8);
+            }
+            Expression::SetExpression { identifier, exp, value } => {
+                self.compile_expression(exp);
+                self.compile_expression(value);
+                self.unit.set_line(identifier);
+                let index = self.unit.name_constant(identifier);
+                self.emit_op(Opcode::SETPROP);
+                self.emit_byte(index as u8);
+            }
+            Expression::Literal { value } => {
+                self.unit.set_line(value);
+                match value.kind {
+                    TokenType::Number => {
+                        let number = value.value.as_ref().unwrap().parse().unwrap_or(0.0);
+                        self.emit_constant(Object::Number(number));
+                    }
+                    TokenType::Identifier => self.get_variable(value),
+                    TokenType::True => self.emit_constant(Object::Bool(true)),
+                    TokenType::False => self.emit_constant(Object::Bool(false)),
+                    TokenType::String => {
+                        let index = self.unit.string_constant(value.value.as_ref().unwrap());
+                        self.emit_op(Opcode::LC);
+                        self.emit_byte(index as u8);
+                    }
+                    _ => self.emit_constant(Object::Nil),
                 }
-                _ => todo!(),
-            },
+            }
         }
     }
 }
+
+impl Tries for Compiler {
+    fn unit(&mut self) -> &mut Unit {
+        &mut self.unit
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        self.compile_statement(statement);
+    }
+
+    fn emit_end_try(&mut self) {
+        self.emit_op(Opcode::ENDTRY);
+    }
+}
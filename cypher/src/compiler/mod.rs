@@ -1,5 +1,9 @@
 
 
+#[allow(clippy::module_inception)]
 pub mod compiler;
 
-pub mod  symbol_table;
\ No newline at end of file
+pub mod optimize;
+pub mod register;
+pub mod  symbol_table;
+mod unit;
@@ -0,0 +1,331 @@
+use crate::{
+    lexer::token::{Location, Token, TokenType},
+    objects::Object,
+    parser::expr::{Expression, Program, Statement},
+};
+
+/*
+  rewrites a program into one that does the same with less work, run
+  before either backend:
+
+    - arithmetic, comparisons and string concatenation of constants are
+      folded into a single constant, operations that would fail at
+      runtime are left alone so the error still happens
+    - and, or and ! with a constant left operand are decided
+    - branches and loops whose condition is constant are dropped or
+      replaced by the code that always runs
+    - statements after a return or throw in the same block are removed
+    - !!x becomes x where only the truthiness of the value is used, and
+      !!!x is always !x
+
+  expressions are evaluated in the same order as before, nothing with a
+  side effect is ever removed except code that cannot run
+*/
+pub fn optimize(program: Program) -> Program {
+    Program::new(optimize_statements(program.body))
+}
+
+fn optimize_statements(statements: Vec<Statement>) -> Vec<Statement> {
+    let mut optimized = vec![];
+    for statement in statements {
+        let ends_block = matches!(statement, Statement::ReturnStatement { .. } | Statement::ThrowStatement { .. });
+        if let Some(statement) = optimize_statement(statement) {
+            optimized.push(statement);
+        }
+        if ends_block {
+            break;
+        }
+    }
+    optimized
+}
+
+// a statement that may be left out entirely, None when it does nothing
+fn optimize_statement(statement: Statement) -> Option<Statement> {
+    let statement = match statement {
+        Statement::ExpressionStatement { expr } => Statement::ExpressionStatement { expr: fold(expr) },
+        Statement::PrintStatement { expr } => Statement::PrintStatement { expr: fold(expr) },
+        Statement::VariableStatement { ident, expr } => Statement::VariableStatement {
+            ident,
+            expr: expr.map(|statement| Box::new(optimize_body(*statement))),
+        },
+        Statement::IFStatement { condition, then, _else } => {
+            let condition = fold_condition(condition);
+            match constant(&condition) {
+                Some(value) if value.is_truthy() => return Some(optimize_body(*then)),
+                Some(_) => return _else.and_then(|_else| optimize_statement(*_else)),
+                None => Statement::IFStatement {
+                    condition,
+                    then: Box::new(optimize_body(*then)),
+                    _else: _else.and_then(|_else| optimize_statement(*_else)).map(Box::new),
+                },
+            }
+        }
+        Statement::BlockStatement { statements } => Statement::BlockStatement {
+            statements: optimize_statements(statements),
+        },
+        Statement::WhileStatement { condition, body } => {
+            let condition = fold_condition(condition);
+            if constant(&condition).is_some_and(|value| !value.is_truthy()) {
+                return None;
+            }
+            Statement::WhileStatement {
+                condition,
+                body: Box::new(optimize_body(*body)),
+            }
+        }
+        Statement::ForStatement {
+            initializer,
+            condition,
+            increment,
+            body,
+        } => {
+            let initializer = optimize_body(*initializer);
+            let condition = fold_condition(condition);
+            // the initializer still runs once, in a scope of its own
+            if constant(&condition).is_some_and(|value| !value.is_truthy()) {
+                return Some(Statement::BlockStatement {
+                    statements: vec![initializer],
+                });
+            }
+            Statement::ForStatement {
+                initializer: Box::new(initializer),
+                condition,
+                increment: fold(increment),
+                body: Box::new(optimize_body(*body)),
+            }
+        }
+        Statement::FunctionDeclaration { name, args, body } => Statement::FunctionDeclaration {
+            name,
+            args,
+            body: Box::new(optimize_body(*body)),
+        },
+        Statement::ReturnStatement { expr } => Statement::ReturnStatement { expr: expr.map(fold) },
+        Statement::ClassDeclaration {
+            name,
+            superclass,
+            methods,
+        } => Statement::ClassDeclaration {
+            name,
+            superclass,
+            methods: methods.into_iter().map(optimize_body).collect(),
+        },
+        Statement::ExportStatement { declaration } => Statement::ExportStatement {
+            declaration: Box::new(optimize_body(*declaration)),
+        },
+        Statement::ThrowStatement { keyword, expr } => Statement::ThrowStatement { keyword, expr: fold(expr) },
+        Statement::TryStatement {
+            body,
+            name,
+            handler,
+            finally,
+        } => Statement::TryStatement {
+            body: Box::new(optimize_body(*body)),
+            name,
+            handler: handler.map(|handler| Box::new(optimize_body(*handler))),
+            finally: finally.map(|finally| Box::new(optimize_body(*finally))),
+        },
+        statement @ (Statement::ImportStatement { .. } | Statement::FromImportStatement { .. }) => statement,
+    };
+    Some(statement)
+}
+
+// a statement that has to stay, an empty block takes the place of one that does nothing
+fn optimize_body(statement: Statement) -> Statement {
+    optimize_statement(statement).unwrap_or(Statement::BlockStatement { statements: vec![] })
+}
+
+fn fold(expression: Expression) -> Expression {
+    match expression {
+        Expression::VariableAssignment { identifier, expr } => Expression::VariableAssignment {
+            identifier,
+            expr: Box::new(fold(*expr)),
+        },
+        // grouping only matters to the parser
+        Expression::GroupingExpression { exp } => fold(*exp),
+        Expression::UnaryExpression { op, rhs } => fold_unary(op, fold(*rhs)),
+        Expression::BinaryExpression { lhs, op, rhs } => fold_binary(fold(*lhs), op, fold(*rhs)),
+        Expression::ArrayDeclaration { elements } => Expression::ArrayDeclaration {
+            elements: elements.into_iter().map(fold).collect(),
+        },
+        Expression::ArrayIndexing { ident, index } => Expression::ArrayIndexing {
+            ident: Box::new(fold(*ident)),
+            index: Box::new(fold(*index)),
+        },
+        Expression::FunctionCall { calle, args } => Expression::FunctionCall {
+            calle: Box::new(fold(*calle)),
+            args: args.into_iter().map(fold).collect(),
+        },
+        Expression::GetExpression { identifier, exp } => Expression::GetExpression {
+            identifier,
+            exp: Box::new(fold(*exp)),
+        },
+        Expression::SetExpression { identifier, exp, value } => Expression::SetExpression {
+            identifier,
+            exp: Box::new(fold(*exp)),
+            value: Box::new(fold(*value)),
+        },
+        expression @ (Expression::IncrementDecrement { .. }
+        | Expression::SuperExpression { .. }
+        | Expression::Literal { .. }) => expression,
+    }
+}
+
+/*
+  folds an expression whose value is only tested for truthiness, as the
+  condition of an if or a loop, so double negations can go
+*/
+fn fold_condition(expression: Expression) -> Expression {
+    match fold(expression) {
+        Expression::UnaryExpression { op, rhs } if is_not(&op) => match *rhs {
+            Expression::UnaryExpression { op: inner, rhs } if is_not(&inner) => fold_condition(*rhs),
+            rhs => Expression::UnaryExpression { op, rhs: Box::new(rhs) },
+        },
+        // both operands of and, or decide the truthiness of the result
+        Expression::BinaryExpression { lhs, op, rhs } if matches!(op.kind, TokenType::And | TokenType::Or) => {
+            Expression::BinaryExpression {
+                lhs: Box::new(fold_condition(*lhs)),
+                op,
+                rhs: Box::new(fold_condition(*rhs)),
+            }
+        }
+        expression => expression,
+    }
+}
+
+fn is_not(op: &Token) -> bool {
+    matches!(op.kind, TokenType::Not | TokenType::Bang)
+}
+
+// whether an expression always yields true or false
+fn is_bool(expression: &Expression) -> bool {
+    match expression {
+        Expression::UnaryExpression { op, .. } => is_not(op),
+        Expression::BinaryExpression { op, .. } => matches!(
+            op.kind,
+            TokenType::Equal
+                | TokenType::NotEqual
+                | TokenType::LessThan
+                | TokenType::GreaterThan
+                | TokenType::LessThanOrEqual
+                | TokenType::GreaterThanOrEqual
+                | TokenType::Is
+        ),
+        Expression::Literal { value } => matches!(value.kind, TokenType::True | TokenType::False),
+        _ => false,
+    }
+}
+
+fn fold_unary(op: Token, rhs: Expression) -> Expression {
+    match (op.kind, constant(&rhs)) {
+        (TokenType::Minus, Some(Object::Number(n))) => return literal(Object::Number(-n), op.get_location()),
+        (TokenType::Not | TokenType::Bang, Some(value)) => {
+            return literal(Object::Bool(!value.is_truthy()), op.get_location())
+        }
+        _ => {}
+    }
+    if is_not(&op) {
+        if let Expression::UnaryExpression { op: inner, rhs: operand } = &rhs {
+            // !!!x is !x, and !!x is x when x is already true or false
+            if is_not(inner) && is_bool(operand) {
+                let Expression::UnaryExpression { rhs: operand, .. } = rhs else {
+                    unreachable!()
+                };
+                return *operand;
+            }
+        }
+    }
+    Expression::UnaryExpression { op, rhs: Box::new(rhs) }
+}
+
+fn fold_binary(lhs: Expression, op: Token, rhs: Expression) -> Expression {
+    // the left operand decides whether the right one is evaluated
+    if let Some(left) = constant(&lhs) {
+        match op.kind {
+            TokenType::And if !left.is_truthy() => return lhs,
+            TokenType::Or if left.is_truthy() => return lhs,
+            TokenType::And | TokenType::Or => return rhs,
+            _ => {}
+        }
+    }
+    let folded = match (constant(&lhs), constant(&rhs)) {
+        (Some(left), Some(right)) => evaluate(&left, op.kind, &right),
+        _ => None,
+    };
+    match folded {
+        Some(value) => literal(value, lhs_location(&lhs)),
+        None => Expression::BinaryExpression {
+            lhs: Box::new(lhs),
+            op,
+            rhs: Box::new(rhs),
+        },
+    }
+}
+
+// the value of a binary operation on constants, None where it fails at runtime
+fn evaluate(left: &Object, op: TokenType, right: &Object) -> Option<Object> {
+    let value = match (op, left, right) {
+        (TokenType::Equal, _, _) => Object::Bool(left == right),
+        (TokenType::NotEqual, _, _) => Object::Bool(left != right),
+        (_, Object::Number(a), Object::Number(b)) => match op {
+            TokenType::Plus => Object::Number(a + b),
+            TokenType::Minus => Object::Number(a - b),
+            TokenType::Star => Object::Number(a * b),
+            TokenType::Slash => Object::Number(a / b),
+            TokenType::Modulo => Object::Number(a % b),
+            TokenType::Power => Object::Number(a.powf(*b)),
+            TokenType::IntDivide => Object::Number((a / b).floor()),
+            TokenType::GreaterThan => Object::Bool(a > b),
+            TokenType::GreaterThanOrEqual => Object::Bool(a >= b),
+            TokenType::LessThan => Object::Bool(a < b),
+            TokenType::LessThanOrEqual => Object::Bool(a <= b),
+            _ => return None,
+        },
+        (_, Object::Str(a), Object::Str(b)) => match op {
+            TokenType::Plus => Object::string(format!("{}{}", a, b)),
+            TokenType::GreaterThan => Object::Bool(a > b),
+            TokenType::GreaterThanOrEqual => Object::Bool(a >= b),
+            TokenType::LessThan => Object::Bool(a < b),
+            TokenType::LessThanOrEqual => Object::Bool(a <= b),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(value)
+}
+
+// the value of a literal, identifiers are not constants
+fn constant(expression: &Expression) -> Option<Object> {
+    let Expression::Literal { value } = expression else {
+        return None;
+    };
+    match value.kind {
+        TokenType::Number => Some(Object::Number(value.value.as_ref().unwrap().parse().unwrap_or(0.0))),
+        TokenType::String => Some(Object::string(value.value.as_ref().unwrap().as_str())),
+        TokenType::True => Some(Object::Bool(true)),
+        TokenType::False => Some(Object::Bool(false)),
+        TokenType::Nil => Some(Object::Nil),
+        _ => None,
+    }
+}
+
+// a literal for a folded value, numbers are written so they parse back exactly
+fn literal(value: Object, location: Location) -> Expression {
+    let (kind, text) = match value {
+        Object::Number(n) => (TokenType::Number, Some(n.to_string())),
+        Object::Str(s) => (TokenType::String, Some(s.to_string())),
+        Object::Bool(true) => (TokenType::True, None),
+        Object::Bool(false) => (TokenType::False, None),
+        _ => (TokenType::Nil, None),
+    };
+    Expression::Literal {
+        value: Token::new(kind, location, text),
+    }
+}
+
+// where the code of a folded expression started, its line is kept
+fn lhs_location(expression: &Expression) -> Location {
+    match expression {
+        Expression::Literal { value } => value.get_location(),
+        _ => unreachable!("only literals are folded"),
+    }
+}
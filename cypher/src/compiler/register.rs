@@ -0,0 +1,777 @@
+use std::rc::Rc;
+
+use crate::{
+    errors::compile::CompileError,
+    lexer::token::{Token, TokenType},
+    objects::Object,
+    parser::expr::{Expression, Program, Statement},
+    vm::{
+        chunk::Chunk,
+        interner::Interner,
+        object::CompiledFunction,
+        register::{Op, WIDTH},
+    },
+};
+
+use super::{
+    symbol_table::SymbolScope,
+    unit::{FunctionKind, TryBlock, Tries, Unit},
+};
+
+/*
+  compiles a program to the instructions of vm::register. a local lives
+  in the register the symbol table numbers it with, an expression is
+  compiled into a register picked by its parent and uses the registers
+  above the locals for its operands, they are free again once the
+  statement is done
+*/
+pub struct RegisterCompiler {
+    unit: Unit,
+    // lowest register holding neither a local nor a temporary in use
+    free: usize,
+    // registers the function being compiled needs so far
+    registers: usize,
+}
+
+impl RegisterCompiler {
+    pub fn new(filename: String) -> Self {
+        Self::with_interner(filename, Interner::new())
+    }
+
+    // see Compiler::with_interner
+    pub fn with_interner(filename: String, interner: Interner) -> Self {
+        Self {
+            unit: Unit::new(filename, interner),
+            free: 1,
+            registers: 1,
+        }
+    }
+
+    fn emit_word(&mut self, word: [u8; WIDTH]) {
+        for byte in word {
+            self.unit.chunk.write_byte(byte, self.unit.line);
+        }
+    }
+
+    fn emit(&mut self, op: Op, a: usize, b: usize, c: usize) {
+        self.emit_word([op as u8, a as u8, b as u8, c as u8]);
+    }
+
+    // see Compiler::emit_jump, a is the register the jump tests
+    fn emit_jump(&mut self, op: Op, a: usize) -> usize {
+        self.emit_word([op as u8, a as u8, 0xff, 0xff]);
+        self.unit.chunk.get_code_length() - 2
+    }
+
+    fn emit_loop(&mut self, start: usize) {
+        let offset = self.emit_jump(Op::JMP, 0);
+        self.unit.patch_jump_to(offset, start);
+    }
+
+    fn emit_move(&mut self, target: usize, source: usize) {
+        if target != source {
+            self.emit(Op::MOVE, target, source, 0);
+        }
+    }
+
+    // a temporary register on top of the ones in use
+    fn alloc(&mut self) -> usize {
+        let register = self.free;
+        self.free += 1;
+        self.reserve(register);
+        register
+    }
+
+    fn reserve(&mut self, register: usize) {
+        self.unit.check_limit(register + 1, "registers");
+        self.registers = self.registers.max(register + 1);
+    }
+
+    // the registers above the locals are free at the start of every statement
+    fn release(&mut self) {
+        self.free = self.unit.symboltable.local_count();
+    }
+
+    fn define_local(&mut self, name: String) -> usize {
+        self.unit.chunk.begin_local(&name, self.unit.symboltable.local_count());
+        let register = self.unit.symboltable.define(name, self.unit.scope_depth);
+        self.free = self.free.max(register + 1);
+        self.reserve(register);
+        register
+    }
+
+    // see Compiler::compile_program
+    pub fn compile_program(self, program: Program) -> Chunk {
+        match self.try_compile_program(program) {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pub fn try_compile_program(mut self, program: Program) -> Result<Chunk, CompileError> {
+        self.compile_statements(&program.body);
+        self.release();
+        let register = self.alloc();
+        let nil = self.unit.add_constant(Object::Nil);
+        self.emit(Op::LOADK, register, nil, 0);
+        self.emit(Op::RET, register, 0, 0);
+        self.unit.chunk.registers = self.registers;
+        self.unit.finish()
+    }
+
+    fn compile_statements(&mut self, statements: &Vec<Statement>) {
+        for statement in statements {
+            self.compile_statement(statement)
+        }
+    }
+
+    fn end_scope(&mut self) {
+        self.unit.scope_depth -= 1;
+        let dropped = self.unit.symboltable.end_scope(self.unit.scope_depth);
+        self.unit.chunk.end_locals(self.unit.symboltable.local_count());
+        if let Some(first) = dropped.iter().filter(|symbol| symbol.captured).map(|symbol| symbol.index).min() {
+            self.emit(Op::CLOSE, first, 0, 0);
+        }
+    }
+
+    /*
+      binds the value in register to name, a global is stored by name
+      and a local is the register itself, which has to be the next one
+      after the locals
+    */
+    fn define_variable(&mut self, name: &Token, register: usize) {
+        let name = name.value.as_ref().unwrap().to_owned();
+        if self.unit.scope_depth == 0 {
+            let index = self.unit.string_constant(&name);
+            self.emit(Op::DEFGLOBAL, register, index, 0);
+        } else {
+            let local = self.define_local(name);
+            debug_assert_eq!(local, register);
+        }
+    }
+
+    // the register of a local, looking through parentheses
+    fn local(&mut self, expression: &Expression) -> Option<usize> {
+        match expression {
+            Expression::GroupingExpression { exp } => self.local(exp),
+            Expression::Literal { value } if value.kind == TokenType::Identifier => {
+                let symbol = self.unit.symboltable.resolve(value.value.as_ref().unwrap());
+                if symbol.scope != SymbolScope::LOCAL {
+                    return None;
+                }
+                // no instruction reads it, the next one is still on its line
+                self.unit.set_line(value);
+                Some(symbol.index)
+            }
+            _ => None,
+        }
+    }
+
+    // a register holding the value of the variable
+    fn variable(&mut self, name: &Token) -> usize {
+        let symbol = self.unit.symboltable.resolve(name.value.as_ref().unwrap());
+        if symbol.scope == SymbolScope::LOCAL {
+            return symbol.index;
+        }
+        let register = self.alloc();
+        self.get_variable(name, register);
+        register
+    }
+
+    fn get_variable(&mut self, name: &Token, target: usize) {
+        self.unit.set_line(name);
+        let symbol = self.unit.symboltable.resolve(name.value.as_ref().unwrap());
+        match symbol.scope {
+            SymbolScope::LOCAL => self.emit_move(target, symbol.index),
+            SymbolScope::UPVALUE => self.emit(Op::GETUPVAL, target, symbol.index, 0),
+            SymbolScope::GLOBAL => {
+                let index = self.unit.string_constant(&symbol.name);
+                self.emit(Op::GETGLOBAL, target, index, 0);
+            }
+        }
+    }
+
+    /*
+      assigns expr to the variable, returns the register holding the
+      value assigned. a local is computed straight into its register
+      unless the expression writes the target before it is done
+    */
+    fn assign(&mut self, name: &Token, expr: &Expression) -> usize {
+        let symbol = self.unit.symboltable.resolve(name.value.as_ref().unwrap());
+        if symbol.scope == SymbolScope::LOCAL {
+            if is_logical(expr) {
+                let register = self.value(expr);
+                self.unit.set_line(name);
+                self.emit_move(symbol.index, register);
+            } else {
+                self.compile_expression(expr, symbol.index);
+            }
+            return symbol.index;
+        }
+        let register = self.value(expr);
+        self.set_variable(name, register);
+        register
+    }
+
+    fn set_variable(&mut self, name: &Token, register: usize) {
+        self.unit.set_line(name);
+        let symbol = self.unit.symboltable.resolve(name.value.as_ref().unwrap());
+        match symbol.scope {
+            SymbolScope::LOCAL => self.emit_move(symbol.index, register),
+            SymbolScope::UPVALUE => self.emit(Op::SETUPVAL, register, symbol.index, 0),
+            SymbolScope::GLOBAL => {
+                let index = self.unit.string_constant(&symbol.name);
+                self.emit(Op::SETGLOBAL, register, index, 0);
+            }
+        }
+    }
+
+    // i++ and i--, returns the register holding the new value
+    fn increment(&mut self, op: &Token, name: &Token) -> usize {
+        let one = self.unit.add_constant(Object::Number(1.0));
+        let register = self.variable(name);
+        self.unit.set_line(op);
+        match op.kind {
+            TokenType::Increment => self.emit(Op::ADDK, register, register, one),
+            _ => self.emit(Op::SUBK, register, register, one),
+        }
+        if self.unit.symboltable.resolve(name.value.as_ref().unwrap()).scope != SymbolScope::LOCAL {
+            self.set_variable(name, register);
+        }
+        register
+    }
+
+    fn compile_statement(&mut self, statement: &Statement) {
+        self.release();
+        match statement {
+            Statement::ExpressionStatement { expr } => self.compile_effect(expr),
+            Statement::PrintStatement { expr } => {
+                let register = self.value(expr);
+                self.emit(Op::PRINT, register, 0, 0);
+            }
+            Statement::VariableStatement { ident, expr } => {
+                self.unit.set_line(ident);
+                let register = self.alloc();
+                match expr.as_deref() {
+                    Some(Statement::ExpressionStatement { expr }) => self.compile_expression(expr, register),
+                    Some(statement) => {
+                        self.compile_statement(statement);
+                        let nil = self.unit.add_constant(Object::Nil);
+                        self.emit(Op::LOADK, register, nil, 0);
+                    }
+                    None => {
+                        let nil = self.unit.add_constant(Object::Nil);
+                        self.emit(Op::LOADK, register, nil, 0);
+                    }
+                }
+                self.define_variable(ident, register);
+            }
+            Statement::IFStatement {
+                condition,
+                then,
+                _else,
+            } => {
+                let register = self.value(condition);
+                let else_jump = self.emit_jump(Op::JFALSE, register);
+
+                self.compile_statement(then);
+
+                if let Some(_else) = _else {
+                    let end_jump = self.emit_jump(Op::JMP, 0);
+                    self.unit.patch_jump(else_jump);
+                    self.compile_statement(_else);
+                    self.unit.patch_jump(end_jump);
+                } else {
+                    self.unit.patch_jump(else_jump);
+                }
+            }
+            Statement::BlockStatement { statements } => {
+                self.unit.begin_scope();
+                self.compile_statements(statements);
+                self.end_scope();
+            }
+            Statement::WhileStatement { condition, body } => {
+                let loop_start = self.unit.chunk.get_code_length();
+                let register = self.value(condition);
+                let exit_jump = self.emit_jump(Op::JFALSE, register);
+                self.compile_statement(body);
+                self.emit_loop(loop_start);
+                self.unit.patch_jump(exit_jump);
+            }
+            Statement::ForStatement {
+                initializer,
+                condition,
+                increment,
+                body,
+            } => {
+                self.unit.begin_scope();
+                self.compile_statement(initializer);
+                let loop_start = self.unit.chunk.get_code_length();
+                self.release();
+                let register = self.value(condition);
+                let exit_jump = self.emit_jump(Op::JFALSE, register);
+                self.compile_statement(body);
+                self.release();
+                self.compile_effect(increment);
+                self.emit_loop(loop_start);
+                self.unit.patch_jump(exit_jump);
+                self.end_scope();
+            }
+            Statement::FunctionDeclaration { name, args, body } => {
+                self.unit.set_line(name);
+                // a local function has its register before its body, see Compiler::compile_statement
+                if self.unit.scope_depth > 0 {
+                    let register = self.define_local(name.value.as_ref().unwrap().to_owned());
+                    self.compile_function(name, args, body, FunctionKind::Function, register);
+                } else {
+                    let register = self.alloc();
+                    self.compile_function(name, args, body, FunctionKind::Function, register);
+                    self.define_variable(name, register);
+                }
+            }
+            Statement::ReturnStatement { expr } => {
+                let register = match expr {
+                    Some(expr) => self.value(expr),
+                    None => {
+                        let register = self.alloc();
+                        let nil = self.unit.add_constant(Object::Nil);
+                        self.emit(Op::LOADK, register, nil, 0);
+                        register
+                    }
+                };
+                let register = self.compile_finally_blocks(register);
+                if self.unit.kind == FunctionKind::Initializer {
+                    self.emit(Op::RET, 0, 0, 0);
+                } else {
+                    self.emit(Op::RET, register, 0, 0);
+                }
+            }
+            Statement::ClassDeclaration { name, superclass, methods } => {
+                self.unit.set_line(name);
+                let index = self.unit.name_constant(name);
+                let register = self.alloc();
+                self.emit(Op::CLASS, register, index, 0);
+
+                // super is a local the methods capture, see Compiler::compile_statement
+                if let Some(superclass) = superclass {
+                    let value = self.alloc();
+                    self.get_variable(superclass, value);
+                    self.define_variable(name, register);
+                    self.unit.begin_scope();
+                    let parent = self.define_local("super".to_owned());
+                    // a global class leaves its register to super
+                    self.emit_move(parent, value);
+                    let class = self.variable(name);
+                    self.emit(Op::INHERIT, class, parent, 0);
+                } else {
+                    self.define_variable(name, register);
+                }
+
+                self.release();
+                let class = self.variable(name);
+                for method in methods {
+                    if let Statement::FunctionDeclaration { name, args, body } = method {
+                        self.unit.set_line(name);
+                        let register = self.alloc();
+                        self.compile_function(name, args, body, FunctionKind::method(name), register);
+                        let index = self.unit.name_constant(name);
+                        self.emit(Op::METHOD, class, index, register);
+                        self.free = register;
+                    }
+                }
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+            }
+            Statement::ImportStatement { path, alias } => {
+                self.unit.set_line(path);
+                let index = self.unit.name_constant(path);
+                let register = self.alloc();
+                self.emit(Op::IMPORT, register, index, 0);
+                self.define_variable(alias, register);
+            }
+            Statement::FromImportStatement { path, names } => {
+                self.unit.set_line(path);
+                let index = self.unit.name_constant(path);
+                for name in names {
+                    self.release();
+                    let register = self.alloc();
+                    self.emit(Op::IMPORT, register, index, 0);
+                    let name_index = self.unit.name_constant(name);
+                    self.emit(Op::GETPROP, register, register, name_index);
+                    self.define_variable(name, register);
+                }
+            }
+            Statement::ExportStatement { declaration } => self.compile_statement(declaration),
+            Statement::ThrowStatement { keyword, expr } => {
+                let register = self.value(expr);
+                self.unit.set_line(keyword);
+                self.emit(Op::THROW, register, 0, 0);
+            }
+            Statement::TryStatement { body, name, handler, finally } => {
+                self.compile_try(body, name.as_ref(), handler.as_deref(), finally)
+            }
+        }
+    }
+
+    /*
+      the same layout as Compiler::compile_try, with the error caught in
+      the first register above the locals. the handler sees it there as
+      the local name, an error raised in the handler lands in the next
+      register, below the locals of the finally block
+    */
+    fn compile_try(
+        &mut self,
+        body: &Statement,
+        name: Option<&Token>,
+        handler: Option<&Statement>,
+        finally: &Option<Box<Statement>>,
+    ) {
+        let has_finally = finally.is_some();
+        let error = self.unit.symboltable.local_count();
+        let mut rethrow_jumps = vec![];
+        let mut done_jumps = vec![];
+
+        self.reserve(error);
+        let catch_jump = self.emit_jump(Op::TRY, error);
+        self.compile_guarded(TryBlock { handler: true, finally: finally.clone() }, body);
+        self.emit(Op::ENDTRY, 0, 0, 0);
+        done_jumps.push(self.emit_jump(Op::JMP, 0));
+
+        match (name, handler) {
+            (Some(name), Some(handler)) => {
+                self.unit.patch_jump(catch_jump);
+                self.unit.begin_scope();
+                self.define_local(name.value.as_ref().unwrap().to_owned());
+                if has_finally {
+                    self.reserve(error + 1);
+                    rethrow_jumps.push(self.emit_jump(Op::TRY, error + 1));
+                }
+                self.compile_guarded(TryBlock { handler: has_finally, finally: finally.clone() }, handler);
+                if has_finally {
+                    self.emit(Op::ENDTRY, 0, 0, 0);
+                }
+                self.end_scope();
+            }
+            _ => rethrow_jumps.push(catch_jump),
+        }
+
+        for jump in done_jumps {
+            self.unit.patch_jump(jump);
+        }
+        if let Some(finally) = finally {
+            self.compile_statement(finally);
+            let end_jump = self.emit_jump(Op::JMP, 0);
+            for jump in rethrow_jumps {
+                self.unit.patch_jump(jump);
+            }
+            let hidden = if handler.is_some() { 2 } else { 1 };
+            self.unit.begin_scope();
+            for _ in 0..hidden {
+                self.define_local(String::new());
+            }
+            self.compile_statement(finally);
+            self.end_scope();
+            self.emit(Op::THROW, error + hidden - 1, 0, 0);
+            self.unit.patch_jump(end_jump);
+        }
+    }
+
+    /*
+      see Tries::unwind_tries, the return value is kept in a hidden local.
+      returns the register of the value to return
+    */
+    fn compile_finally_blocks(&mut self, register: usize) -> usize {
+        if !self.unit.has_finally() {
+            return register;
+        }
+        self.unit.begin_scope();
+        self.release();
+        let hidden = self.define_local(String::new());
+        self.emit_move(hidden, register);
+        self.unwind_tries();
+        self.unit.forget_scope();
+        hidden
+    }
+
+    fn compile_function(&mut self, name: &Token, args: &[Token], body: &Statement, kind: FunctionKind, target: usize) {
+        let function_name = name.value.as_ref().unwrap().to_owned();
+        let enclosing = self.unit.enter_function(&function_name, kind);
+        let enclosing_free = self.free;
+        let enclosing_registers = self.registers;
+        for arg in args {
+            self.define_local(arg.value.as_ref().unwrap().to_owned());
+        }
+        self.free = self.unit.symboltable.local_count();
+        self.registers = self.free;
+
+        self.compile_statement(body);
+        if kind == FunctionKind::Initializer {
+            self.emit(Op::RET, 0, 0, 0);
+        } else {
+            self.release();
+            let register = self.alloc();
+            let nil = self.unit.add_constant(Object::Nil);
+            self.emit(Op::LOADK, register, nil, 0);
+            self.emit(Op::RET, register, 0, 0);
+        }
+
+        self.unit.chunk.registers = self.registers;
+        let (chunk, upvalues) = self.unit.exit_function(enclosing);
+        self.free = enclosing_free;
+        self.registers = enclosing_registers;
+
+        let function = CompiledFunction::new(function_name, args.len(), upvalues.len(), chunk);
+        let index = self.unit.add_constant(Object::CompiledFunction(Rc::new(function)));
+        self.unit.set_line(name);
+        self.emit(Op::CLOSURE, target, index, 0);
+        for upvalue in upvalues {
+            self.emit_word([upvalue.is_local as u8, upvalue.index as u8, 0, 0]);
+        }
+    }
+
+    // an expression statement, only its side effects are kept
+    fn compile_effect(&mut self, expression: &Expression) {
+        match expression {
+            Expression::VariableAssignment { identifier, expr } => {
+                self.assign(identifier, expr);
+            }
+            Expression::IncrementDecrement { op, identifier } => {
+                self.increment(op, identifier);
+            }
+            _ => {
+                self.value(expression);
+            }
+        }
+    }
+
+    /*
+      a register holding the value of the expression, a local is used
+      where it lives and a call leaves its result where its callee was
+    */
+    fn value(&mut self, expression: &Expression) -> usize {
+        if let Some(register) = self.local(expression) {
+            return register;
+        }
+        if let Expression::FunctionCall { calle, args } = expression {
+            return self.compile_call(calle, args);
+        }
+        self.temporary(expression)
+    }
+
+    // the value of the expression in the next free register, the ones above it are free again
+    fn temporary(&mut self, expression: &Expression) -> usize {
+        let register = self.alloc();
+        self.compile_expression(expression, register);
+        self.free = register + 1;
+        register
+    }
+
+    /*
+      like value, but copies a local when an operand evaluated after it
+      may assign to the local first
+    */
+    fn operand(&mut self, expression: &Expression, later: &Expression) -> usize {
+        if assigns(later) {
+            self.temporary(expression)
+        } else {
+            self.value(expression)
+        }
+    }
+
+    // the callee and arguments go in consecutive registers, returns the one of the result
+    fn compile_call(&mut self, calle: &Expression, args: &[Expression]) -> usize {
+        let callee = self.temporary(calle);
+        for arg in args {
+            self.temporary(arg);
+        }
+        self.emit(Op::CALL, callee, args.len(), 0);
+        self.free = callee + 1;
+        callee
+    }
+
+    fn compile_expression(&mut self, expression: &Expression, target: usize) {
+        match expression {
+            Expression::VariableAssignment { identifier, expr } => {
+                let register = self.assign(identifier, expr);
+                self.emit_move(target, register);
+            }
+            Expression::GroupingExpression { exp } => self.compile_expression(exp, target),
+            Expression::UnaryExpression { op, rhs } => {
+                let register = self.value(rhs);
+                self.unit.set_line(op);
+                match op.kind {
+                    TokenType::Minus => self.emit(Op::NEG, target, register, 0),
+                    _ => self.emit(Op::NOT, target, register, 0),
+                }
+            }
+            Expression::BinaryExpression { lhs, op, rhs } => match op.kind {
+                TokenType::And | TokenType::Or => {
+                    self.compile_expression(lhs, target);
+                    self.unit.set_line(op);
+                    let jump = if op.kind == TokenType::And { Op::JFALSE } else { Op::JTRUE };
+                    let end_jump = self.emit_jump(jump, target);
+                    self.compile_expression(rhs, target);
+                    self.unit.patch_jump(end_jump);
+                }
+                _ => {
+                    let left = self.operand(lhs, rhs);
+                    // adding or subtracting a number needs no register for it
+                    if let (Some(constant_op), Some(number)) = (constant_op(op), number(rhs)) {
+                        let index = self.unit.add_constant(Object::Number(number));
+                        self.unit.set_line(op);
+                        self.emit(constant_op, target, left, index);
+                        return;
+                    }
+                    let right = self.value(rhs);
+                    self.unit.set_line(op);
+                    self.emit(binary_op(op), target, left, right);
+                }
+            },
+            Expression::IncrementDecrement { op, identifier } => {
+                let register = self.increment(op, identifier);
+                self.emit_move(target, register);
+            }
+            Expression::ArrayDeclaration { elements } => {
+                let first = self.free;
+                for element in elements {
+                    self.temporary(element);
+                }
+                self.emit(Op::ARRAY, target, first, elements.len());
+            }
+            Expression::ArrayIndexing { ident, index } => {
+                let array = self.operand(ident, index);
+                let index = self.value(index);
+                self.emit(Op::INDEX, target, array, index);
+            }
+            Expression::FunctionCall { calle, args } => {
+                // a call into the top register needs no move of its result
+                if target + 1 == self.free && target >= self.unit.symboltable.local_count() {
+                    self.free = target;
+                }
+                let register = self.compile_call(calle, args);
+                self.emit_move(target, register);
+            }
+            Expression::GetExpression { identifier, exp } => {
+                let register = self.value(exp);
+                self.unit.set_line(identifier);
+                let index = self.unit.name_constant(identifier);
+                self.emit(Op::GETPROP, target, register, index);
+            }
+            Expression::SuperExpression { keyword, method } => {
+                let location = keyword.get_location();
+                let receiver = self.alloc();
+                let superclass = self.alloc();
+                self.get_variable(&Token::new(TokenType::Identifier, location, Some("self".to_owned())), receiver);
+                self.get_variable(&Token::new(TokenType::Identifier, location, Some("super".to_owned())), superclass);
+                self.unit.set_line(method);
+                let index = self.unit.name_constant(method);
+                self.emit(Op::GETSUPER, target, receiver, index);
+            }
+            Expression::SetExpression { identifier, exp, value } => {
+                let object = self.operand(exp, value);
+                let value = self.value(value);
+                self.unit.set_line(identifier);
+                let index = self.unit.name_constant(identifier);
+                self.emit(Op::SETPROP, object, index, value);
+                self.emit_move(target, value);
+            }
+            Expression::Literal { value } => {
+                self.unit.set_line(value);
+                let constant = match value.kind {
+                    TokenType::Number => Object::Number(value.value.as_ref().unwrap().parse().unwrap_or(0.0)),
+                    TokenType::Identifier => return self.get_variable(value, target),
+                    TokenType::True => Object::Bool(true),
+                    TokenType::False => Object::Bool(false),
+                    TokenType::String => Object::Str(self.unit.interner.intern(value.value.as_ref().unwrap())),
+                    _ => Object::Nil,
+                };
+                let index = self.unit.add_constant(constant);
+                self.emit(Op::LOADK, target, index, 0);
+            }
+        }
+    }
+
+}
+
+fn binary_op(op: &Token) -> Op {
+    match op.kind {
+        TokenType::Plus => Op::ADD,
+        TokenType::Minus => Op::SUB,
+        TokenType::Star => Op::MUL,
+        TokenType::Slash => Op::DIV,
+        TokenType::Modulo => Op::MOD,
+        TokenType::Power => Op::POW,
+        TokenType::IntDivide => Op::IDIV,
+        TokenType::GreaterThan => Op::GT,
+        TokenType::GreaterThanOrEqual => Op::GOE,
+        TokenType::LessThan => Op::LT,
+        TokenType::LessThanOrEqual => Op::LOE,
+        TokenType::Equal => Op::EQ,
+        TokenType::NotEqual => Op::NEQ,
+        _ => Op::IS,
+    }
+}
+
+fn constant_op(op: &Token) -> Option<Op> {
+    match op.kind {
+        TokenType::Plus => Some(Op::ADDK),
+        TokenType::Minus => Some(Op::SUBK),
+        _ => None,
+    }
+}
+
+fn number(expression: &Expression) -> Option<f64> {
+    match expression {
+        Expression::Literal { value } if value.kind == TokenType::Number => {
+            Some(value.value.as_ref().unwrap().parse().unwrap_or(0.0))
+        }
+        _ => None,
+    }
+}
+
+// and, or write their result before the right operand is evaluated
+fn is_logical(expression: &Expression) -> bool {
+    match expression {
+        Expression::GroupingExpression { exp } => is_logical(exp),
+        Expression::BinaryExpression { op, .. } => matches!(op.kind, TokenType::And | TokenType::Or),
+        _ => false,
+    }
+}
+
+/*
+  whether evaluating the expression may assign a local, calls may
+  through the upvalues of a closure
+*/
+fn assigns(expression: &Expression) -> bool {
+    match expression {
+        Expression::VariableAssignment { .. }
+        | Expression::IncrementDecrement { .. }
+        | Expression::FunctionCall { .. } => true,
+        Expression::GroupingExpression { exp } => assigns(exp),
+        Expression::UnaryExpression { rhs, .. } => assigns(rhs),
+        Expression::BinaryExpression { lhs, rhs, .. } => assigns(lhs) || assigns(rhs),
+        Expression::ArrayDeclaration { elements } => elements.iter().any(assigns),
+        Expression::ArrayIndexing { ident, index } => assigns(ident) || assigns(index),
+        Expression::GetExpression { exp, .. } => assigns(exp),
+        Expression::SetExpression { exp, value, .. } => assigns(exp) || assigns(value),
+        Expression::SuperExpression { .. } | Expression::Literal { .. } => false,
+    }
+}
+
+impl Tries for RegisterCompiler {
+    fn unit(&mut self) -> &mut Unit {
+        &mut self.unit
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        self.compile_statement(statement);
+    }
+
+    fn emit_end_try(&mut self) {
+        self.emit(Op::ENDTRY, 0, 0, 0);
+    }
+}
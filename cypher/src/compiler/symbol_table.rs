@@ -1,41 +1,162 @@
-use std::collections::HashMap;
-
-use crate::lexer::token::Token;
 
 
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SymbolScope {
     LOCAL,
-    GLOBAL
+    GLOBAL,
+    UPVALUE,
 }
 
+#[derive(Debug, Clone)]
 pub struct Symbol{
-    name:String,
-    depth:usize,
-    scope:SymbolScope,
-    pub index:usize
+    pub name:String,
+    pub depth:usize,
+    pub scope:SymbolScope,
+    pub index:usize,
+    // set once a closure captures the local, its slot is closed instead of popped
+    pub captured:bool,
+}
+
+/*
+  a captured variable of the function being compiled, either a local
+  slot of the enclosing function or one of its upvalues
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpvalueRef {
+    pub is_local:bool,
+    pub index:usize,
 }
 
+/*
+  locals and upvalues of the function being compiled, next is the
+  table of the enclosing function
+*/
+#[derive(Debug)]
 pub struct SymbolTable{
-    values:HashMap<String,Symbol>,
-    next:Option<Box<SymbolTable>>
+    values:Vec<Symbol>,
+    upvalues:Vec<UpvalueRef>,
+    // the name each upvalue was resolved by
+    upvalue_names:Vec<String>,
+    pub next:Option<Box<SymbolTable>>
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SymbolTable {
     pub fn new()->Self {
+        // slot zero of every frame holds the function being called
+        let callee = Symbol {
+            name: String::new(),
+            depth: 0,
+            scope: SymbolScope::LOCAL,
+            index: 0,
+            captured: false,
+        };
         Self{
-            values:HashMap::new(),
+            values:vec![callee],
+            upvalues:vec![],
+            upvalue_names:vec![],
             next:None
         }
     }
 
-    pub fn define(&mut self,name:String,depth:usize,scope:SymbolScope,index:usize) {
-        let symbol=Symbol{name:name.to_owned(),depth,scope,index};
-       
-        self.values.insert(name, symbol);
+    /*
+      starts the table of a nested function, slot zero is named self
+      for methods so the receiver can be resolved like any local
+    */
+    pub fn enter_function(&mut self, slot_zero: &str) {
+        let enclosing = std::mem::take(self);
+        self.next = Some(Box::new(enclosing));
+        self.values[0].name = slot_zero.to_owned();
+    }
+
+    // restores the enclosing table, returns the upvalues the function captured
+    pub fn exit_function(&mut self) -> Vec<UpvalueRef> {
+        let enclosing = self.next.take().expect("exit_function without enter_function");
+        let function = std::mem::replace(self, *enclosing);
+        function.upvalues
     }
 
-    pub fn resolve(&self,name:&String)->Option<&Symbol> {
-        self.values.get(name)
+    pub fn define(&mut self,name:String,depth:usize) -> usize {
+        let index = self.values.len();
+        let symbol=Symbol{name,depth,scope:SymbolScope::LOCAL,index,captured:false};
+        self.values.push(symbol);
+        index
+    }
+
+    // names of the upvalues captured so far, in the order of their indexes
+    pub fn upvalue_names(&self) -> Vec<String> {
+        self.upvalue_names.clone()
+    }
+
+    pub fn local_count(&self) -> usize {
+        self.values.len()
+    }
+
+    // drops the locals deeper than depth, returns them innermost first
+    pub fn end_scope(&mut self, depth: usize) -> Vec<Symbol> {
+        let mut dropped = vec![];
+        while let Some(symbol) = self.values.last() {
+            if symbol.depth <= depth || symbol.index == 0 {
+                break;
+            }
+            dropped.push(self.values.pop().unwrap());
+        }
+        dropped
     }
-}
\ No newline at end of file
+
+    pub fn resolve(&mut self,name:&String)->Symbol {
+        if let Some(index) = self.resolve_local(name) {
+            return self.values[index].clone();
+        }
+        if let Some(index) = self.resolve_upvalue(name) {
+            return Symbol {
+                name: name.to_owned(),
+                depth: 0,
+                scope: SymbolScope::UPVALUE,
+                index,
+                captured: false,
+            };
+        }
+        Symbol {
+            name: name.to_owned(),
+            depth: 0,
+            scope: SymbolScope::GLOBAL,
+            index: 0,
+            captured: false,
+        }
+    }
+
+    fn resolve_local(&self, name: &String) -> Option<usize> {
+        self.values
+            .iter()
+            .rev()
+            .find(|symbol| !symbol.name.is_empty() && &symbol.name == name)
+            .map(|symbol| symbol.index)
+    }
+
+    fn resolve_upvalue(&mut self, name: &String) -> Option<usize> {
+        let enclosing = self.next.as_mut()?;
+        if let Some(index) = enclosing.resolve_local(name) {
+            enclosing.values[index].captured = true;
+            return Some(self.add_upvalue(true, index, name));
+        }
+        let index = enclosing.resolve_upvalue(name)?;
+        Some(self.add_upvalue(false, index, name))
+    }
+
+    fn add_upvalue(&mut self, is_local: bool, index: usize, name: &str) -> usize {
+        let upvalue = UpvalueRef { is_local, index };
+        if let Some(existing) = self.upvalues.iter().position(|u| *u == upvalue) {
+            return existing;
+        }
+        self.upvalues.push(upvalue);
+        self.upvalue_names.push(name.to_owned());
+        self.upvalues.len() - 1
+    }
+}
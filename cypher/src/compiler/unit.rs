@@ -0,0 +1,236 @@
+use crate::{
+    errors::compile::CompileError,
+    lexer::token::Token,
+    objects::Object,
+    parser::expr::Statement,
+    vm::{chunk::Chunk, interner::Interner},
+};
+
+use super::symbol_table::{SymbolTable, UpvalueRef};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FunctionKind {
+    Script,
+    Function,
+    Method,
+    // init returns self whatever its return statements say
+    Initializer,
+}
+
+impl FunctionKind {
+    // the kind of a method of a class named name
+    pub(super) fn method(name: &Token) -> Self {
+        if name.value.as_deref() == Some("init") {
+            FunctionKind::Initializer
+        } else {
+            FunctionKind::Method
+        }
+    }
+}
+
+/*
+  a try statement enclosing the code being compiled. handler is set
+  while a handler of the statement is active, a return leaving the
+  statement pops it and runs the finally block first
+*/
+#[derive(Clone)]
+pub(super) struct TryBlock {
+    pub(super) handler: bool,
+    pub(super) finally: Option<Box<Statement>>,
+}
+
+/*
+  the function being compiled, kept the same way by the stack and the
+  register compiler. they emit different instructions into chunk but
+  number locals, constants and jump targets alike
+*/
+pub(super) struct Unit {
+    pub(super) chunk: Chunk,
+    pub(super) kind: FunctionKind,
+    pub(super) scope_depth: usize,
+    // try statements of the function being compiled, innermost last
+    pub(super) tries: Vec<TryBlock>,
+    pub(super) symboltable: SymbolTable,
+    // source line of the token compiled last, recorded for every emitted byte
+    pub(super) line: u32,
+    pub(super) interner: Interner,
+    // the first limit the program went past, compiling goes on without it
+    pub(super) error: Option<CompileError>,
+}
+
+// the function a nested one is compiled inside of, given back by exit_function
+pub(super) struct Enclosing {
+    chunk: Chunk,
+    kind: FunctionKind,
+    scope_depth: usize,
+    tries: Vec<TryBlock>,
+}
+
+impl Unit {
+    pub(super) fn new(filename: String, interner: Interner) -> Self {
+        Self {
+            chunk: Chunk::new(filename),
+            kind: FunctionKind::Script,
+            scope_depth: 0,
+            tries: vec![],
+            symboltable: SymbolTable::new(),
+            line: 1,
+            interner,
+            error: None,
+        }
+    }
+
+    // the chunk compiled, or the first error found compiling it
+    pub(super) fn finish(self) -> Result<Chunk, CompileError> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.chunk),
+        }
+    }
+
+    /*
+      operands are single bytes, a function with more than 256 of what
+      they number would have its indexes wrap around
+    */
+    pub(super) fn check_limit(&mut self, count: usize, what: &str) {
+        if count > u8::MAX as usize + 1 {
+            self.fail(format!("too many {} in {}, the limit is 256", what, self.chunk.name));
+        }
+    }
+
+    // keeps the first error, compiling goes on to the end
+    pub(super) fn fail(&mut self, message: String) {
+        if self.error.is_none() {
+            self.error = Some(CompileError {
+                file_name: self.chunk.file.to_owned(),
+                line_no: self.line,
+                message,
+            });
+        }
+    }
+
+    pub(super) fn add_constant(&mut self, obj: Object) -> usize {
+        let index = self.chunk.add_constant(obj);
+        self.check_limit(index + 1, "constants");
+        index
+    }
+
+    pub(super) fn name_constant(&mut self, name: &Token) -> usize {
+        self.string_constant(name.value.as_ref().unwrap())
+    }
+
+    pub(super) fn string_constant(&mut self, s: &str) -> usize {
+        let interned = self.interner.intern(s);
+        self.add_constant(Object::Str(interned))
+    }
+
+    pub(super) fn set_line(&mut self, token: &Token) {
+        self.line = token.get_location().line;
+    }
+
+    // points the jump whose target is at offset to the end of the code
+    pub(super) fn patch_jump(&mut self, offset: usize) {
+        let target = self.chunk.get_code_length();
+        self.patch_jump_to(offset, target);
+    }
+
+    // jump operands are two bytes, a target past them would wrap around
+    pub(super) fn patch_jump_to(&mut self, offset: usize, target: usize) {
+        if target > u16::MAX as usize {
+            self.fail(format!("{} is too long to jump over, the limit is 65536 bytes of code", self.chunk.name));
+        }
+        let bytes = (target as u16).to_be_bytes();
+        self.chunk.code[offset] = bytes[0];
+        self.chunk.code[offset + 1] = bytes[1];
+    }
+
+    pub(super) fn begin_scope(&mut self) {
+        self.scope_depth += 1;
+    }
+
+    // leaves a scope without dropping its locals, for code that never falls out of it
+    pub(super) fn forget_scope(&mut self) {
+        self.scope_depth -= 1;
+        self.symboltable.end_scope(self.scope_depth);
+    }
+
+    pub(super) fn has_finally(&self) -> bool {
+        self.tries.iter().any(|block| block.finally.is_some())
+    }
+
+    /*
+      starts compiling a function named name into a chunk of its own,
+      with self or a hidden local in slot zero. the arguments are for
+      the caller to define
+    */
+    pub(super) fn enter_function(&mut self, name: &str, kind: FunctionKind) -> Enclosing {
+        let chunk = std::mem::replace(&mut self.chunk, Chunk::new(name.to_owned()));
+        self.chunk.file = chunk.file.to_owned();
+        self.chunk.depth = self.scope_depth + 1;
+        let enclosing = Enclosing {
+            chunk,
+            kind: std::mem::replace(&mut self.kind, kind),
+            scope_depth: std::mem::replace(&mut self.scope_depth, 1),
+            tries: std::mem::take(&mut self.tries),
+        };
+
+        let slot_zero = match kind {
+            FunctionKind::Method | FunctionKind::Initializer => "self",
+            _ => "",
+        };
+        self.symboltable.enter_function(slot_zero);
+        self.chunk.begin_local(slot_zero, 0);
+        enclosing
+    }
+
+    // goes back to the enclosing function, returns the chunk compiled and the variables it captures
+    pub(super) fn exit_function(&mut self, enclosing: Enclosing) -> (Chunk, Vec<UpvalueRef>) {
+        self.chunk.end_locals(0);
+        self.chunk.upvalue_names = self.symboltable.upvalue_names();
+        let upvalues = self.symboltable.exit_function();
+        self.check_limit(upvalues.len(), "captured variables");
+        self.kind = enclosing.kind;
+        self.scope_depth = enclosing.scope_depth;
+        self.tries = enclosing.tries;
+        (std::mem::replace(&mut self.chunk, enclosing.chunk), upvalues)
+    }
+}
+
+/*
+  what try statements need from a compiler, so that both keep the try
+  stack and run finally blocks the same way
+*/
+pub(super) trait Tries {
+    fn unit(&mut self) -> &mut Unit;
+
+    fn statement(&mut self, statement: &Statement);
+
+    // leaves the handler pushed last
+    fn emit_end_try(&mut self);
+
+    // compiles statement inside block, a return in it leaves block first
+    fn compile_guarded(&mut self, block: TryBlock, statement: &Statement) {
+        self.unit().tries.push(block);
+        self.statement(statement);
+        self.unit().tries.pop();
+    }
+
+    /*
+      a return inside try statements leaves their handlers and runs
+      their finally blocks, innermost first. a return in a finally block
+      only sees the try statements outside it
+    */
+    fn unwind_tries(&mut self) {
+        let tries = std::mem::take(&mut self.unit().tries);
+        for (index, block) in tries.iter().enumerate().rev() {
+            if block.handler {
+                self.emit_end_try();
+            }
+            if let Some(finally) = &block.finally {
+                self.unit().tries = tries[..index].to_vec();
+                self.statement(finally);
+            }
+        }
+        self.unit().tries = tries;
+    }
+}
@@ -0,0 +1,40 @@
+/*
+  error that stops a bytecode file from being loaded or a chunk from
+  being run
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BytecodeError {
+    // the file does not start with the cyc magic bytes
+    NotBytecode,
+    // written by a version of the format this build cannot read
+    Version { found: u16, expected: u16 },
+    // the file ended while reading what is named
+    Truncated { offset: usize, reading: &'static str },
+    Corrupt { offset: usize, message: String },
+    // code the verifier rejected, offset is into the code of function
+    Invalid { function: String, offset: usize, message: String },
+}
+
+impl std::fmt::Display for BytecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BytecodeError::NotBytecode => write!(f, "not a cypher bytecode file"),
+            BytecodeError::Version { found, expected } => write!(
+                f,
+                "bytecode version {} is not supported, this build reads version {}, compile the source again",
+                found, expected
+            ),
+            BytecodeError::Truncated { offset, reading } => {
+                write!(f, "bytecode file is truncated, ended at byte {} reading {}", offset, reading)
+            }
+            BytecodeError::Corrupt { offset, message } => {
+                write!(f, "bytecode file is corrupt at byte {}: {}", offset, message)
+            }
+            BytecodeError::Invalid { function, offset, message } => {
+                write!(f, "invalid bytecode in {} at {:04}: {}", function, offset, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BytecodeError {}
@@ -0,0 +1,21 @@
+use colored::Colorize;
+
+/*
+  error that stops a parsed program from being compiled, like a
+  function needing more constants or locals than an operand can number
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub file_name: String,
+    pub line_no: u32,
+    pub message: String,
+}
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let _ = writeln!(f, "{}: {}", "Compile Error".red().bold(), self.message.bold());
+        write!(f, "{:4} --> {} at line:{}", "", self.file_name.bright_blue(), self.line_no)
+    }
+}
+
+impl std::error::Error for CompileError {}
@@ -16,7 +16,7 @@ impl std::fmt::Display for LexicalError {
         let col=self.location.col;
         let _=writeln!(f,"{}: {}","Lexical Error".red().bold(),self.message.bold());
         let _=writeln!(f,"{:4} --> {} at line:{}:{}","",self.file_name.bright_blue(),self.location.line,self.location.col-1);
-        let _=writeln!(f,"{:3} {}","","|");
+        let _=writeln!(f,"{:3} |","");
         write!(f,"{:10}  -> {}","",self.line[(col-2) as usize..].underline())
     }
 }
\ No newline at end of file
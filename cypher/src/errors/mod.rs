@@ -1,19 +1,183 @@
+use crate::objects::Object;
 
+use self::{compile::CompileError, lexical::LexicalError, syntax::SyntaxError};
+
+pub mod bytecode;
+
+pub mod compile;
 
 pub mod lexical;
 
 pub mod syntax;
 
-
+/*
+  error that stops a file from being parsed
+*/
 #[derive(Debug)]
+pub enum ParseError {
+    Lexical(LexicalError),
+    Syntax(SyntaxError),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Lexical(err) => write!(f, "{}", err),
+            ParseError::Syntax(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/*
+  broad category of a runtime error, both backends agree on the kind
+  even when their messages differ
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    NameError,
+    TypeError,
+    IndexError,
+    ArityError,
+    AttributeError,
+    ImportError,
+    IOError,
+    // a value of the right type that cannot be used, like malformed json
+    ValueError,
+    // a native or module the sandbox policy does not allow
+    PermissionError,
+    // the vm ran past the instruction or memory limit of its policy
+    BudgetExceeded,
+    StackOverflow,
+    // a value raised with throw that is not an error object
+    Thrown,
+    // a chunk the verifier rejected, raised before any of it runs
+    InvalidBytecode,
+    // raised by exit(code), stops the program with that status
+    Exit(i32),
+}
+
+impl ErrorKind {
+    // try cannot stop a script from exiting or escaping its budget
+    pub fn is_catchable(&self) -> bool {
+        !matches!(self, ErrorKind::Exit(_) | ErrorKind::BudgetExceeded)
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Exit(_) => write!(f, "Exit"),
+            kind => write!(f, "{:?}", kind),
+        }
+    }
+}
+
+/*
+  a call that was running when an error was raised
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    pub function: String,
+    pub file: String,
+    pub line: Option<u32>,
+}
+
+impl std::fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{} [{} line {}]", self.function, self.file, line),
+            None => write!(f, "{} [{}]", self.function, self.file),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct RuntimeError{
-    message:String
+    pub kind: ErrorKind,
+    message:String,
+    // calls active where the error was raised, innermost first
+    pub trace: Vec<TraceFrame>,
+    // what throw raised when it was not an error object
+    value: Option<Box<Object>>,
 }
 
 impl RuntimeError {
-    pub fn new(message:String)->Self{
-        Self { 
-            message
+    pub fn new(kind: ErrorKind, message:String)->Self{
+        Self {
+            kind,
+            message,
+            trace: vec![],
+            value: None,
+        }
+    }
+
+    // the error raised by throw value
+    pub fn thrown(value: Object) -> Self {
+        Self {
+            value: Some(Box::new(value.clone())),
+            ..Self::new(ErrorKind::Thrown, value.to_string())
         }
     }
-}
\ No newline at end of file
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn value(&self) -> Option<&Object> {
+        self.value.as_deref()
+    }
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Runtime Error: {}: {}", self.kind, self.message)?;
+        match self.trace.first() {
+            Some(TraceFrame { file, line: Some(line), .. }) => write!(f, " [{} line {}]", file, line),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/*
+  anything that can go wrong running source through the interpreter
+*/
+#[derive(Debug)]
+pub enum CypherError {
+    Parse(ParseError),
+    Compile(CompileError),
+    Runtime(RuntimeError),
+}
+
+impl std::fmt::Display for CypherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CypherError::Parse(err) => write!(f, "{}", err),
+            CypherError::Compile(err) => write!(f, "{}", err),
+            CypherError::Runtime(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CypherError {}
+
+impl From<ParseError> for CypherError {
+    fn from(err: ParseError) -> Self {
+        CypherError::Parse(err)
+    }
+}
+
+impl From<CompileError> for CypherError {
+    fn from(err: CompileError) -> Self {
+        CypherError::Compile(err)
+    }
+}
+
+impl From<RuntimeError> for CypherError {
+    fn from(err: RuntimeError) -> Self {
+        CypherError::Runtime(err)
+    }
+}
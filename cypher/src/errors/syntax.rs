@@ -26,7 +26,7 @@ impl std::fmt::Display for SyntaxError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let _=writeln!(f,"{}: {}","Syntax Error".red().bold(),self.message.bold());
         let _=writeln!(f,"{:4} --> {} at line:{} col:{}","",self.file_name.bright_blue(),self.line_no,self.col-1);
-        let _=writeln!(f,"{:3} {}","","|");
+        let _=writeln!(f,"{:3} |","");
         write!(f,"{:10}  -> {}","",self.line.underline())
     }
 }
@@ -1,171 +1,233 @@
-use std::{collections::HashMap, fs::write};
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
 
 use crate::{
-    lexer::token::TokenType,
-    objects::{BuiltinFn, Function, Object},
+    errors::{ErrorKind, RuntimeError, TraceFrame},
+    lexer::token::{Token, TokenType},
+    modules::{exported_names, parse_module, ModuleLoader},
+    objects::{
+        class::{is_instance, Class, Instance},
+        methods::MethodTables,
+        module::Module,
+        native::{Caller, NativeRegistry},
+        BoundMethod, Function, Object,
+    },
     parser::expr::{Expression, Program, Statement},
+    policy::Policy,
+    stdlib::string,
 };
 
 use self::symbol_table::SymbolTable;
 
 pub mod symbol_table;
 
+// calls deep, the same limit as the vm
+const CALLS_MAX: usize = 1024;
+
+/*
+  a call evaluates its body on a new stack segment once the native stack
+  has less than STACK_RED_ZONE left, so CALLS_MAX is reached before the
+  process runs out of stack whatever the thread it runs on
+*/
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
 pub struct Eval<'a> {
     program: &'a Program,
-    symbols: SymbolTable,
-    scope_depth: u32,
-    builtins: HashMap<String, Object>,
+    symbols: Rc<RefCell<SymbolTable>>,
+    natives: NativeRegistry,
+    methods: MethodTables,
+    modules: ModuleLoader,
+    // file of the code being evaluated, imports are relative to it
+    filename: String,
+    output: Box<dyn Write>,
+    // line of the token evaluated last, where an error is raised
+    line: u32,
+    // the script, modules and functions running, innermost last
+    calls: Vec<TraceFrame>,
+}
+
+impl Caller for Eval<'_> {
+    fn call(&mut self, callee: Object, args: Vec<Object>) -> Result<Object, RuntimeError> {
+        match self.call_value(callee, args) {
+            Object::Error(err) => Err(err),
+            value => Ok(value),
+        }
+    }
 }
 
 impl<'a> Eval<'a> {
     pub fn new(program: &'a Program) -> Self {
-        let mut funcs: HashMap<String, Object> = HashMap::new();
-        funcs.insert(
-            "len".to_owned(),
-            Object::Builtin(BuiltinFn::new(|objects| {
-                if objects.len() > 1 {
-                    return Object::Error(
-                        format!("required 1 args got {}", objects.len()).to_owned(),
-                    );
-                }
-                let obj = &objects[0];
-                return match obj {
-                    Object::Number(_) => todo!(),
-                    Object::Str(s) => Object::Number(s.len() as f64),
-                    Object::Bool(_) => todo!(),
-                    Object::Nil => todo!(),
-                    Object::Error(_) => todo!(),
-                    Object::Func(_) => todo!(),
-                    Object::Builtin(_) => todo!(),
-                    Object::Tuple(_, _) => todo!(),
-                    Object::Array(arr) => Object::Number(arr.len() as f64),
-                };
-                // Object::Nil
-            })),
-        );
-
-        funcs.insert(
-            "push".to_owned(),
-            Object::Builtin(BuiltinFn::new(|objects| {
-                let obj = &objects[0];
-                let args = &objects[1];
-                let newobj = match obj {
-                    Object::Number(_) => todo!(),
-                    Object::Str(s) => Object::Number(s.len() as f64),
-                    Object::Bool(_) => todo!(),
-                    Object::Nil => todo!(),
-                    Object::Error(_) => todo!(),
-                    Object::Func(_) => todo!(),
-                    Object::Builtin(_) => todo!(),
-                    Object::Tuple(_, _) => todo!(),
-                    Object::Array(arr) => {
-                        let o = match args {
-                            Object::Number(_)
-                            | Object::Str(_)
-                            | Object::Bool(_)
-                            | Object::Nil
-                            | Object::Func(_)
-                            | Object::Tuple(_, _)
-                            | Object::Array(_)
-                            | Object::Builtin(_) => {
-                                let mut new_arr: Vec<Object> = arr.clone();
-                                new_arr.push(args.clone());
-                                Object::Array(new_arr)
-                            }
-                            Object::Error(err) => Object::Error(err.to_owned()),
-                        };
-                        return o;
-                    }
-                };
-                println!("{}", newobj);
-                return newobj;
-            })),
-        );
         Self {
-            scope_depth: 1,
             program,
-            symbols: SymbolTable::new(None),
-            builtins: funcs,
+            symbols: Rc::new(RefCell::new(SymbolTable::new(None))),
+            natives: NativeRegistry::with_builtins(),
+            methods: MethodTables::new(),
+            modules: ModuleLoader::new(),
+            filename: "<eval>".to_owned(),
+            output: Box::new(io::stdout()),
+            line: 1,
+            calls: vec![],
+        }
+    }
+
+    /*
+      natives visible to the program, register host functions here
+      before calling run
+    */
+    pub fn natives(&mut self) -> &mut NativeRegistry {
+        &mut self.natives
+    }
+
+    pub fn methods(&mut self) -> &mut MethodTables {
+        &mut self.methods
+    }
+
+    // search path and cache of imported modules
+    pub fn modules(&mut self) -> &mut ModuleLoader {
+        &mut self.modules
+    }
+
+    /*
+      restricts the natives and modules the program can use. the tree
+      walker counts no instructions or allocations, a policy with an
+      instruction or memory limit is refused rather than run unlimited
+    */
+    pub fn set_policy(&mut self, policy: Policy) -> Result<(), RuntimeError> {
+        if policy.max_instructions.is_some() || policy.max_memory.is_some() {
+            return Err(RuntimeError::new(
+                ErrorKind::ValueError,
+                "instruction and memory limits need the vm or register backend".to_owned(),
+            ));
         }
+        self.modules.set_policy(policy);
+        Ok(())
+    }
+
+    pub fn set_filename(&mut self, filename: String) {
+        self.filename = filename;
     }
-    pub fn run(&mut self) {
+
+    /*
+      redirects the output of print statements, stdout by default
+    */
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    /*
+      runs the program, returns the value of a top level return statement
+      or the error that stopped the program
+    */
+    pub fn run(&mut self) -> Object {
+        self.enter("<script>".to_owned(), self.filename.to_owned());
         let obj: Object = self.evaluate_statements(&self.program.body);
+        let obj = self.traced(obj);
+        self.leave();
+        match obj {
+            Object::Return(value) => *value,
+            Object::Error(_) => obj,
+            _ => Object::Nil,
+        }
+    }
+
+    fn set_line(&mut self, token: &Token) {
+        self.line = token.get_location().line;
+    }
+
+    // starts a call, the caller is left at the current line
+    fn enter(&mut self, function: String, file: String) {
+        if let Some(caller) = self.calls.last_mut() {
+            caller.line = Some(self.line);
+        }
+        self.calls.push(TraceFrame { function, file, line: None });
+    }
+
+    fn leave(&mut self) {
+        self.calls.pop();
+        if let Some(line) = self.calls.last().and_then(|caller| caller.line) {
+            self.line = line;
+        }
+    }
+
+    /*
+      records the running calls in the trace of an error raised since
+      the last call started, like the vm does where it raises it
+    */
+    fn locate(&self, mut err: RuntimeError) -> RuntimeError {
+        if err.trace.is_empty() {
+            err.trace = self.calls.iter().rev().cloned().collect();
+            if let Some(innermost) = err.trace.first_mut() {
+                innermost.line = Some(self.line);
+            }
+        }
+        err
+    }
+
+    fn traced(&self, obj: Object) -> Object {
+        match obj {
+            Object::Error(err) => Object::Error(self.locate(err)),
+            obj => obj,
+        }
     }
 
     fn evaluate_statements(&mut self, statements: &Vec<Statement>) -> Object {
-        let mut obj: Option<Object> = None;
+        let mut obj = Object::Nil;
         for statement in statements {
-            let res = self.evaluate_statement(statement);
-            obj = Some(res);
-            if obj.is_some() {
-                if self.is_error(&obj.as_ref().unwrap()) {
-                    println!("{:?}", obj);
-                    break;
-                }
+            obj = self.evaluate_statement(statement);
+            if self.is_error(&obj) || matches!(obj, Object::Return(_)) {
+                break;
             }
         }
-        if obj.is_none() {
-            return Object::Nil;
-        }
-        obj.unwrap()
+        obj
     }
 
     fn is_error(&self, val: &Object) -> bool {
-        match val {
-            Object::Number(_)
-            | Object::Str(_)
-            | Object::Bool(_)
-            | Object::Func(_)
-            | Object::Nil => false,
-            Object::Error(_) => true,
-            Object::Builtin(_) => false,
-            Object::Tuple(_, _) => todo!(),
-            Object::Array(_) => false,
-        }
+        matches!(val, Object::Error(_))
     }
 
-    fn end_scope(&mut self) {
-        self.symbols = *self.symbols.next.take().unwrap();
+    fn end_scope(&mut self, previous: Rc<RefCell<SymbolTable>>) {
+        self.symbols = previous;
     }
 
-    fn begin_scope(&mut self) {
-        self.symbols = SymbolTable::new(Some(Box::new(self.symbols.to_owned())));
+    fn begin_scope(&mut self, parent: Rc<RefCell<SymbolTable>>) -> Rc<RefCell<SymbolTable>> {
+        let scope = Rc::new(RefCell::new(SymbolTable::new(Some(parent))));
+        std::mem::replace(&mut self.symbols, scope)
     }
 
     fn evaluate_statement(&mut self, statement: &Statement) -> Object {
         match statement {
-            Statement::ExpressionStatement { expr } => {
-                let val = self.eval_expression(expr);
-                val
-            }
+            Statement::ExpressionStatement { expr } => self.eval_expression(expr),
             Statement::PrintStatement { expr } => {
                 let val = self.eval_expression(expr);
-                // if val.is_ok() {
-                println!("{}", val);
-                // }
+                if self.is_error(&val) {
+                    return val;
+                }
+                let _ = writeln!(self.output, "{}", val);
                 Object::Nil
             }
             Statement::VariableStatement { ident, expr } => {
-                if expr.is_some() {
-                    let initializer = self.evaluate_statement(expr.as_ref().unwrap());
+                self.set_line(ident);
+                if let Some(expr) = expr {
+                    let initializer = self.evaluate_statement(expr);
                     if self.is_error(&initializer) {
                         return initializer;
                     }
-                    self.symbols.define(
+                    self.symbols.borrow_mut().define(
                         ident.value.as_ref().unwrap().to_owned(),
                         initializer.clone(),
-                        self.scope_depth,
                     );
 
                     return initializer;
                 }
 
-                self.symbols.define(
-                    ident.value.as_ref().unwrap().to_owned(),
-                    Object::Nil,
-                    self.scope_depth,
-                );
-                return Object::Nil;
+                self.symbols
+                    .borrow_mut()
+                    .define(ident.value.as_ref().unwrap().to_owned(), Object::Nil);
+                Object::Nil
             }
             Statement::IFStatement {
                 condition,
@@ -173,46 +235,26 @@ impl<'a> Eval<'a> {
                 _else,
             } => self.eval_if_statement(condition, then, _else),
             Statement::BlockStatement { statements } => {
-                // self.begin_scope();
-
-                self.symbols = SymbolTable::new(Some(Box::new(self.symbols.to_owned())));
-                self.scope_depth += 1;
-                // let _= write("examples/block.json", self.symbols.to_string());
-
+                let previous = self.begin_scope(self.symbols.clone());
                 let result = self.evaluate_statements(statements);
-                // println!("{:#?}",&self.symbols);
-
-                self.symbols = *self.symbols.next.take().unwrap();
-                self.scope_depth -= 1;
-                // let _= write("examples/block1.json", self.symbols.to_string());
-                // println!("{:#?}",self.symbols);
-                // self.end_scope();
+                self.end_scope(previous);
 
                 result
             }
             Statement::WhileStatement { condition, body } => {
-                let mut condition_expr = self.eval_expression(condition);
-                if self.is_error(&condition_expr) {
-                    return condition_expr;
-                }
-                if self.is_truthy(&condition_expr) {
-                    let mut obj: Option<Object> = None;
-                    // let mut count = 1;
-                    while self.is_truthy(&condition_expr) {
-                        // if count==5{
-                        //     break;
-                        // }
-                        obj = Some(self.evaluate_statement(&body));
-                        condition_expr = self.eval_expression(condition);
-
-                        // println!("condition : {}",&condition_expr);
-                        // let _=fs::write(format!("examples/symbols{}.json",count), self.symbols.to_string());
-                        // println!("{}",self.symbols);
-                        // count+=1;
+                loop {
+                    let condition_expr = self.eval_expression(condition);
+                    if self.is_error(&condition_expr) {
+                        return condition_expr;
+                    }
+                    if !condition_expr.is_truthy() {
+                        break;
+                    }
+                    let obj = self.evaluate_statement(body);
+                    if self.is_error(&obj) || matches!(obj, Object::Return(_)) {
+                        return obj;
                     }
-                    return obj.unwrap_or(Object::Nil);
                 }
-                self.end_scope();
                 Object::Nil
             }
             Statement::ForStatement {
@@ -220,26 +262,242 @@ impl<'a> Eval<'a> {
                 condition,
                 increment,
                 body,
-            } => todo!(),
+            } => {
+                let previous = self.begin_scope(self.symbols.clone());
+                let result = self.eval_for_statement(initializer, condition, increment, body);
+                self.end_scope(previous);
+                result
+            }
             Statement::FunctionDeclaration { name, args, body } => {
-                let func_obj =
-                    Object::Func(Function::new(name.clone(), args.to_vec(), body.clone()));
-                self.symbols.define(
-                    name.to_owned().value.unwrap().to_owned(),
+                self.set_line(name);
+                let func_obj = Object::Func(Rc::new(Function::new(
+                    name.clone(),
+                    args.to_vec(),
+                    Rc::new((**body).clone()),
+                    self.filename.to_owned(),
+                    self.symbols.clone(),
+                )));
+                self.symbols.borrow_mut().define(
+                    name.value.as_ref().unwrap().to_owned(),
                     func_obj.clone(),
-                    self.scope_depth,
                 );
-                return func_obj;
+                func_obj
+            }
+            Statement::ClassDeclaration { name, superclass, methods } => {
+                self.set_line(name);
+                let class = Class::new(name.value.as_ref().unwrap().to_owned());
+                // methods of a subclass see the superclass as super
+                let mut scope = self.symbols.clone();
+                if let Some(superclass) = superclass {
+                    let value = self.eval_expression(&Expression::Literal { value: superclass.clone() });
+                    match value {
+                        Object::Class(superclass) => {
+                            if let Err(err) = class.set_superclass(superclass.clone()) {
+                                return Object::Error(err);
+                            }
+                            scope = Rc::new(RefCell::new(SymbolTable::new(Some(scope))));
+                            scope.borrow_mut().define("super".to_owned(), Object::Class(superclass));
+                        }
+                        Object::Error(_) => return value,
+                        value => {
+                            return Object::error(
+                                ErrorKind::TypeError,
+                                format!("superclass must be a class, got {}", value.type_name()),
+                            )
+                        }
+                    }
+                }
+                for method in methods {
+                    if let Statement::FunctionDeclaration { name, args, body } = method {
+                        let func = Function::new(
+                            name.clone(),
+                            args.to_vec(),
+                            Rc::new((**body).clone()),
+                            self.filename.to_owned(),
+                            scope.clone(),
+                        );
+                        class.add_method(name.value.as_ref().unwrap().to_owned(), Object::Func(Rc::new(func)));
+                    }
+                }
+                let class = Object::Class(Rc::new(class));
+                self.symbols
+                    .borrow_mut()
+                    .define(name.value.as_ref().unwrap().to_owned(), class.clone());
+                class
             }
             Statement::ReturnStatement { expr } => {
-                if expr.is_some() {
-                    return self.eval_expression(expr.as_ref().unwrap());
+                let value = match expr {
+                    Some(expr) => self.eval_expression(expr),
+                    None => Object::Nil,
+                };
+                if self.is_error(&value) {
+                    return value;
+                }
+                Object::Return(Box::new(value))
+            }
+            Statement::ImportStatement { path, alias } => {
+                self.set_line(path);
+                let module = self.import_module(path);
+                if self.is_error(&module) {
+                    return module;
+                }
+                self.symbols
+                    .borrow_mut()
+                    .define(alias.value.as_ref().unwrap().to_owned(), module);
+                Object::Nil
+            }
+            Statement::FromImportStatement { path, names } => {
+                self.set_line(path);
+                let module = self.import_module(path);
+                if self.is_error(&module) {
+                    return module;
+                }
+                for name in names {
+                    let name = name.value.as_ref().unwrap();
+                    match self.methods.get_property(module.clone(), name) {
+                        Ok(value) => self.symbols.borrow_mut().define(name.to_owned(), value),
+                        Err(err) => return Object::Error(err),
+                    }
                 }
                 Object::Nil
             }
+            Statement::ExportStatement { declaration } => self.evaluate_statement(declaration),
+            Statement::ThrowStatement { keyword, expr } => {
+                let value = self.eval_expression(expr);
+                if self.is_error(&value) {
+                    return value;
+                }
+                self.set_line(keyword);
+                match value {
+                    // rethrowing a caught error keeps where it was first raised
+                    Object::Exception(err) => Object::Error((*err).clone()),
+                    value => Object::Error(RuntimeError::thrown(value)),
+                }
+            }
+            Statement::TryStatement { body, name, handler, finally } => {
+                self.eval_try_statement(body, name.as_ref(), handler.as_deref(), finally.as_deref())
+            }
         }
     }
 
+    /*
+      a catchable error raised in body runs handler with the error bound
+      to name. finally runs whatever happened except for errors that
+      cannot be caught, unless it raises or returns itself the outcome
+      of body and handler stands
+    */
+    fn eval_try_statement(
+        &mut self,
+        body: &Statement,
+        name: Option<&Token>,
+        handler: Option<&Statement>,
+        finally: Option<&Statement>,
+    ) -> Object {
+        let mut result = self.evaluate_statement(body);
+        if let (Object::Error(err), Some(name), Some(handler)) = (&result, name, handler) {
+            if err.kind.is_catchable() {
+                let err = self.locate(err.clone());
+                let previous = self.begin_scope(self.symbols.clone());
+                self.symbols
+                    .borrow_mut()
+                    .define(name.value.as_ref().unwrap().to_owned(), Object::Exception(Rc::new(err)));
+                result = self.evaluate_statement(handler);
+                self.end_scope(previous);
+            }
+        }
+        let uncatchable = matches!(&result, Object::Error(err) if !err.kind.is_catchable());
+        if let (Some(finally), false) = (finally, uncatchable) {
+            let outcome = self.evaluate_statement(finally);
+            if self.is_error(&outcome) || matches!(outcome, Object::Return(_)) {
+                return outcome;
+            }
+        }
+        result
+    }
+
+    /*
+      runs the module at path in a fresh global scope unless it already
+      ran, the module object holds the exported bindings of that scope
+    */
+    fn import_module(&mut self, path: &Token) -> Object {
+        let path = path.value.as_ref().unwrap();
+        match self.modules.builtin(path) {
+            Ok(Some(module)) => return module,
+            Ok(None) => {}
+            Err(err) => return Object::Error(err),
+        }
+        let resolved = match self.modules.resolve(path, &self.filename) {
+            Ok(resolved) => resolved,
+            Err(err) => return Object::Error(err),
+        };
+        match self.modules.begin(&resolved) {
+            Ok(Some(module)) => return module,
+            Ok(None) => {}
+            Err(err) => return Object::Error(err),
+        }
+        let program = match parse_module(&resolved) {
+            Ok(program) => program,
+            Err(err) => {
+                self.modules.abort();
+                return Object::Error(err);
+            }
+        };
+
+        let filename = resolved.display().to_string();
+        let scope = Rc::new(RefCell::new(SymbolTable::new(None)));
+        let previous_scope = std::mem::replace(&mut self.symbols, scope.clone());
+        let previous_filename = std::mem::replace(&mut self.filename, filename.to_owned());
+        self.enter("<module>".to_owned(), filename);
+        let result = self.evaluate_statements(&program.body);
+        let result = self.traced(result);
+        self.leave();
+        self.symbols = previous_scope;
+        self.filename = previous_filename;
+        if self.is_error(&result) {
+            self.modules.abort();
+            return result;
+        }
+
+        let exports = exported_names(&program)
+            .into_iter()
+            .filter_map(|name| scope.borrow().get(&name).map(|value| (name, value)))
+            .collect();
+        let module = Object::Module(Rc::new(Module::new(resolved, exports)));
+        self.modules.finish(module.clone());
+        module
+    }
+
+    fn eval_for_statement(
+        &mut self,
+        initializer: &Statement,
+        condition: &Expression,
+        increment: &Expression,
+        body: &Statement,
+    ) -> Object {
+        let init = self.evaluate_statement(initializer);
+        if self.is_error(&init) {
+            return init;
+        }
+        loop {
+            let condition_expr = self.eval_expression(condition);
+            if self.is_error(&condition_expr) {
+                return condition_expr;
+            }
+            if !condition_expr.is_truthy() {
+                break;
+            }
+            let obj = self.evaluate_statement(body);
+            if self.is_error(&obj) || matches!(obj, Object::Return(_)) {
+                return obj;
+            }
+            let step = self.eval_expression(increment);
+            if self.is_error(&step) {
+                return step;
+            }
+        }
+        Object::Nil
+    }
+
     fn eval_if_statement(
         &mut self,
         condition: &Expression,
@@ -250,25 +508,12 @@ impl<'a> Eval<'a> {
         if self.is_error(&condition_expr) {
             return condition_expr;
         }
-        if self.is_truthy(&condition_expr) {
-            return self.evaluate_statement(then);
+        if condition_expr.is_truthy() {
+            self.evaluate_statement(then)
+        } else if let Some(_else) = _else {
+            self.evaluate_statement(_else)
         } else {
-            return self.evaluate_statement(_else.as_ref().unwrap());
-        }
-        // Ok(Object::Nil)
-    }
-
-    fn is_truthy(&self, value: &Object) -> bool {
-        match value {
-            Object::Bool(a) => *a,
-            Object::Number(_) => true,
-            Object::Str(_) => true,
-            Object::Nil => false,
-            Object::Error(_) => todo!(),
-            Object::Func(_) => true,
-            Object::Builtin(_) => true,
-            Object::Tuple(_, _) => todo!(),
-            Object::Array(_) => todo!(),
+            Object::Nil
         }
     }
 
@@ -276,179 +521,214 @@ impl<'a> Eval<'a> {
         match expression {
             Expression::VariableAssignment { identifier, expr } => {
                 let obj = self.eval_expression(expr);
-                self.symbols.assign(
-                    identifier.value.as_ref().unwrap(),
-                    obj.clone(),
-                    self.scope_depth,
-                );
-                obj
+                if self.is_error(&obj) {
+                    return obj;
+                }
+                self.symbols
+                    .borrow_mut()
+                    .assign(identifier.value.as_ref().unwrap(), obj)
             }
-            Expression::GroupingExpression { exp } => {
-                return self.eval_expression(exp);
+            Expression::GroupingExpression { exp } => self.eval_expression(exp),
+            Expression::UnaryExpression { op, rhs } => {
+                let right = self.eval_expression(rhs);
+                if self.is_error(&right) {
+                    return right;
+                }
+                self.set_line(op);
+                match op.kind {
+                    TokenType::Minus => match right {
+                        Object::Number(num) => Object::Number(-num),
+                        _ => Object::error(
+                            ErrorKind::TypeError,
+                            format!("Unknown operation -{}", right.type_name()),
+                        ),
+                    },
+                    _ => Object::Bool(!right.is_truthy()),
+                }
             }
-            Expression::UnaryExpression { op, rhs } => todo!(),
             Expression::BinaryExpression { lhs, op, rhs } => {
                 let left: Object = self.eval_expression(lhs);
                 if self.is_error(&left) {
                     return left;
                 }
+                // logical operators short circuit and yield the deciding operand
+                match op.kind {
+                    TokenType::And if !left.is_truthy() => return left,
+                    TokenType::Or if left.is_truthy() => return left,
+                    TokenType::And | TokenType::Or => return self.eval_expression(rhs),
+                    _ => {}
+                }
                 let right: Object = self.eval_expression(rhs);
 
                 if self.is_error(&right) {
                     return right;
                 }
+                self.set_line(op);
+
+                match op.kind {
+                    TokenType::Equal => return Object::Bool(left == right),
+                    TokenType::NotEqual => return Object::Bool(left != right),
+                    TokenType::Is => {
+                        return match &right {
+                            Object::Class(class) => Object::Bool(is_instance(&left, class)),
+                            _ => Object::error(
+                                ErrorKind::TypeError,
+                                format!("right operand of is must be a class, got {}", right.type_name()),
+                            ),
+                        }
+                    }
+                    _ => {}
+                }
 
-                let res: Object = match (&left, &right) {
+                match (&left, &right) {
                     (Object::Number(a), Object::Number(b)) => match op.kind {
                         TokenType::Plus => Object::Number(a + b),
                         TokenType::Minus => Object::Number(a - b),
                         TokenType::Star => Object::Number(a * b),
                         TokenType::Slash => Object::Number(a / b),
                         TokenType::Modulo => Object::Number(a % b),
+                        TokenType::Power => Object::Number(a.powf(*b)),
+                        TokenType::IntDivide => Object::Number((a / b).floor()),
                         TokenType::GreaterThan => Object::Bool(a > b),
                         TokenType::GreaterThanOrEqual => Object::Bool(a >= b),
                         TokenType::LessThan => Object::Bool(a < b),
                         TokenType::LessThanOrEqual => Object::Bool(a <= b),
-                        TokenType::Equal => Object::Bool(a == b),
-                        TokenType::NotEqual => Object::Bool(a != b),
-                        _ => Object::Error(format!(
-                            "Unknown operation between {} {:?} {}",
-                            &left, op.kind, &right
-                        )),
+                        _ => Object::error(
+                            ErrorKind::TypeError,
+                            format!("Unknown operation between {} {:?} {}", &left, op.kind, &right),
+                        ),
                     },
-                    (Object::Bool(a), Object::Bool(b)) => match op.kind {
-                        TokenType::And => return Object::Bool(*a && *b),
-                        TokenType::Or => return Object::Bool(*a || *b),
-                        _ => Object::Error(format!(
-                            "Unknown operation between {} {:?} {}",
-                            &left, op.kind, &right
-                        )),
+                    (Object::Str(a), Object::Str(b)) => match op.kind {
+                        TokenType::Plus => Object::string(format!("{}{}", a, b)),
+                        TokenType::GreaterThan => Object::Bool(a > b),
+                        TokenType::GreaterThanOrEqual => Object::Bool(a >= b),
+                        TokenType::LessThan => Object::Bool(a < b),
+                        TokenType::LessThanOrEqual => Object::Bool(a <= b),
+                        _ => Object::error(
+                            ErrorKind::TypeError,
+                            format!("Unknown operation between {} {:?} {}", &left, op.kind, &right),
+                        ),
                     },
-                    _ => Object::Error(format!(
-                        "Unknown operation  between {} {:?} {}",
-                        &left, op.kind, &right
-                    )),
-                };
-                res
+                    _ => Object::error(
+                        ErrorKind::TypeError,
+                        format!("Unknown operation between {} {:?} {}", &left, op.kind, &right),
+                    ),
+                }
             }
             Expression::IncrementDecrement { op, identifier } => {
-                let obj = self.symbols.get(identifier.value.as_ref().unwrap());
-
-                // println!("{:?}",obj);
-                if obj.is_none() {
-                    return Object::Error(
-                        format!(
-                            "identifier {} not found",
-                            identifier.value.as_ref().unwrap()
+                self.set_line(op);
+                let obj = self.symbols.borrow().get(identifier.value.as_ref().unwrap());
+
+                let obj = match obj {
+                    Some(obj) => obj,
+                    None => {
+                        return Object::error(
+                            ErrorKind::NameError,
+                            format!("identifier {} not found", identifier.value.as_ref().unwrap()),
                         )
-                        .to_owned(),
-                    );
-                }
+                    }
+                };
 
-                let new_obj: Object = match op.kind {
-                    TokenType::Increment => match &obj.unwrap().object {
-                        Object::Number(num) => Object::Number(num + 1.0),
-                        _ => Object::Error(format!(
-                            "Unknown operation between {} {:?}",
-                            &identifier, op.kind,
-                        )),
-                    },
-                    TokenType::Decrement => match &obj.unwrap().object {
-                        Object::Number(num) => Object::Number(num - 1.0),
-                        _ => Object::Error(format!(
-                            "Unknown operation between {} {:?}",
-                            &identifier, op.kind,
-                        )),
-                    },
-                    _ => Object::Error(format!("Unknown operation  {}", op)),
+                let new_obj: Object = match (op.kind, &obj) {
+                    (TokenType::Increment, Object::Number(num)) => Object::Number(num + 1.0),
+                    (TokenType::Decrement, Object::Number(num)) => Object::Number(num - 1.0),
+                    _ => {
+                        return Object::error(
+                            ErrorKind::TypeError,
+                            format!("Unknown operation between {} {:?}", obj.type_name(), op.kind),
+                        )
+                    }
                 };
 
-                // println!("{}",new_obj);
-                self.symbols.assign(
-                    identifier.value.as_ref().unwrap(),
-                    new_obj.clone(),
-                    self.scope_depth,
-                );
-                new_obj
+                self.symbols
+                    .borrow_mut()
+                    .assign(identifier.value.as_ref().unwrap(), new_obj)
             }
 
             Expression::FunctionCall { calle, args } => {
                 let obj = self.eval_expression(calle);
-
-                // Object::Nil
-                // let func = self.symbols.get(calle.value.as_ref().unwrap());
-                // if func.is_none() {
-                //     let builtin = self.builtins.get(calle.value.as_ref().unwrap());
-                //     if builtin.is_none() {
-                //         return Object::Error(
-                //             format!("Function {}() not found", calle.value.as_ref().unwrap())
-                //                 .to_string(),
-                //         );
-                //     }
-
-                //     match builtin.as_ref().unwrap() {
-                //         Object::Builtin(builtin) => {
-                //             let f=builtin.Fn;
-                //             let mut eval_args: Vec<Object> = vec![];
-
-                //             for expression in args {
-                //                 let obj = self.eval_expression(expression);
-                //                 eval_args.push(obj);
-                //             }
-                //             return f(eval_args);
-                //             // println!("{:?}",);
-                //         },
-                //         _=>todo!()
-                //     }
-
-                //     return  Object::Nil;
-                // }
-
-                let obj = self.eval_function_call(obj, args);
-                obj
-            }
-            Expression::Literal { value } => match value.kind {
-                TokenType::Identifier => {
-                    let obj = self.symbols.get(value.value.as_ref().unwrap());
-                    // println!("{:?}",obj);
-                    if obj.is_none() {
-                        // println!("{:?}",obj);
-                        let builtin = self.builtins.get(value.value.as_ref().unwrap());
-                        if builtin.is_some() {
-                            return builtin.unwrap().clone();
+                if self.is_error(&obj) {
+                    return obj;
+                }
+                self.eval_function_call(obj, args)
+            }
+            Expression::Literal { value } => {
+                self.set_line(value);
+                match value.kind {
+                    TokenType::Identifier => {
+                        let obj = self.symbols.borrow().get(value.value.as_ref().unwrap());
+                        match obj {
+                            Some(obj) => obj,
+                            None => {
+                                if let Some(native) = self.natives.get(value.value.as_ref().unwrap()) {
+                                    return native;
+                                }
+
+                                Object::error(
+                                    ErrorKind::NameError,
+                                    format!("identifier {} not found", value.value.as_ref().unwrap()),
+                                )
+                            }
                         }
-
-                        return Object::Error(
-                            format!("Identifier {} not found", value.value.as_ref().unwrap())
-                                .to_string(),
-                        );
                     }
-                    obj.unwrap().object.clone()
-                }
-                TokenType::Number => {
-                    let number: f64 = value.value.as_ref().unwrap().parse().unwrap_or(0.0);
-                    return Object::Number(number);
+                    TokenType::Number => {
+                        let number: f64 = value.value.as_ref().unwrap().parse().unwrap_or(0.0);
+                        Object::Number(number)
+                    }
+                    TokenType::String => Object::string(value.value.as_ref().unwrap().as_str()),
+                    TokenType::True => Object::Bool(true),
+                    TokenType::False => Object::Bool(false),
+                    _ => Object::Nil,
                 }
-                TokenType::String => return Object::Str(value.value.as_ref().unwrap().to_owned()),
-                TokenType::True => return Object::Bool(true),
-                TokenType::False => return Object::Bool(false),
-                _ => return Object::Nil,
-            },
+            }
             Expression::GetExpression { identifier, exp } => {
                 let obj = self.eval_expression(exp);
-
-                if self
-                    .builtins
-                    .contains_key(identifier.value.as_ref().unwrap())
-                {
-                    let f = self
-                        .builtins
-                        .get(identifier.value.as_ref().unwrap())
-                        .unwrap()
-                        .to_owned();
-                    return Object::Tuple(Box::new(obj), Box::new(f));
+                if self.is_error(&obj) {
+                    return obj;
+                }
+                self.set_line(identifier);
+                match self.methods.get_property(obj, identifier.value.as_ref().unwrap()) {
+                    Ok(value) => value,
+                    Err(err) => Object::Error(err),
+                }
+            }
+            Expression::SuperExpression { method, .. } => {
+                let (superclass, receiver) = {
+                    let symbols = self.symbols.borrow();
+                    (symbols.get("super"), symbols.get("self"))
+                };
+                let (Some(Object::Class(superclass)), Some(receiver)) = (superclass, receiver) else {
+                    return Object::error(ErrorKind::NameError, "super used outside of a subclass".to_owned());
+                };
+                let name = method.value.as_ref().unwrap();
+                match superclass.find_method(name) {
+                    Some(method) => Object::BoundMethod(Rc::new(BoundMethod::new(receiver, method))),
+                    None => Object::error(
+                        ErrorKind::AttributeError,
+                        format!("superclass {} has no method {}", superclass.name, name),
+                    ),
+                }
+            }
+            Expression::SetExpression { identifier, exp, value } => {
+                let obj = self.eval_expression(exp);
+                if self.is_error(&obj) {
+                    return obj;
+                }
+                let value = self.eval_expression(value);
+                if self.is_error(&value) {
+                    return value;
+                }
+                self.set_line(identifier);
+                match obj {
+                    Object::Instance(instance) => {
+                        instance.set_field(identifier.value.as_ref().unwrap().to_owned(), value.clone());
+                        value
+                    }
+                    obj => Object::error(
+                        ErrorKind::TypeError,
+                        format!("cannot set property {} on {}", identifier.value.as_ref().unwrap(), obj.type_name()),
+                    ),
                 }
-                Object::Nil
             }
             Expression::ArrayDeclaration { elements } => {
                 let mut objects: Vec<Object> = vec![];
@@ -459,7 +739,7 @@ impl<'a> Eval<'a> {
                     }
                     objects.push(obj)
                 }
-                Object::Array(objects)
+                Object::array(objects)
             }
             Expression::ArrayIndexing { ident, index } => {
                 let obj = self.eval_expression(ident);
@@ -475,14 +755,36 @@ impl<'a> Eval<'a> {
                 match obj {
                     Object::Array(arr) => match arr_index {
                         Object::Number(index) => {
-                            if let Some(obj) = arr.get(index as usize) {
-                                return obj.clone();
+                            if index >= 0.0 {
+                                if let Some(obj) = arr.get(index as usize) {
+                                    return obj.clone();
+                                }
                             }
-                            return Object::Error("Unable to index ,{arr#?}".to_owned());
+                            Object::error(
+                                ErrorKind::IndexError,
+                                format!("index {} out of range for array of length {}", index, arr.len()),
+                            )
                         }
-                        _ => Object::Error(format!("expected Number as index ").to_owned()),
+                        _ => Object::error(ErrorKind::TypeError, "expected Number as index".to_owned()),
                     },
-                    _ => Object::Error(format!("{}", obj)),
+                    Object::Str(s) => match arr_index {
+                        Object::Number(index) => match string::char_at(&s, index) {
+                            Ok(c) => c,
+                            Err(err) => Object::Error(err),
+                        },
+                        _ => Object::error(ErrorKind::TypeError, "expected Number as index".to_owned()),
+                    },
+                    Object::Map(map) => match arr_index {
+                        Object::Str(key) => match map.get(&*key) {
+                            Some(obj) => obj.clone(),
+                            None => Object::error(ErrorKind::IndexError, format!("key {:?} not found", key)),
+                        },
+                        _ => Object::error(ErrorKind::TypeError, "expected String as key".to_owned()),
+                    },
+                    _ => Object::error(
+                        ErrorKind::TypeError,
+                        format!("{} is not indexable", obj.type_name()),
+                    ),
                 }
             }
         }
@@ -493,57 +795,105 @@ impl<'a> Eval<'a> {
 
         for expression in func_args {
             let obj = self.eval_expression(expression);
+            if self.is_error(&obj) {
+                return obj;
+            }
             eval_args.push(obj);
         }
+        self.call_value(obj, eval_args)
+    }
 
+    fn call_value(&mut self, obj: Object, eval_args: Vec<Object>) -> Object {
         match obj {
             Object::Func(fun_obj) => {
-                if func_args.len() < fun_obj.args.len() {
-                    return Object::Error(
-                        format!(
-                            "Expected {} arguments but got {}",
-                            fun_obj.args.len(),
-                            func_args.len()
-                        )
-                        .to_owned(),
+                if eval_args.len() != fun_obj.args.len() {
+                    return Object::error(
+                        ErrorKind::ArityError,
+                        format!("Expected {} arguments but got {}", fun_obj.args.len(), eval_args.len()),
                     );
                 }
 
-                self.begin_scope();
-                self.scope_depth += 1;
+                if self.calls.len() >= CALLS_MAX {
+                    return Object::error(ErrorKind::StackOverflow, "stack overflow".to_owned());
+                }
+                let previous = self.begin_scope(fun_obj.scope.clone());
 
-                for index in 0..fun_obj.args.len() {
-                    let name = &fun_obj.args[index];
-                    let val = &eval_args[index];
-                    self.symbols.define(
-                        name.value.as_ref().unwrap().to_owned(),
-                        val.to_owned(),
-                        self.scope_depth,
-                    );
+                for (name, val) in fun_obj.args.iter().zip(eval_args) {
+                    self.symbols
+                        .borrow_mut()
+                        .define(name.value.as_ref().unwrap().to_owned(), val);
                 }
 
-                let obj = self.evaluate_statement(&fun_obj.body);
+                self.enter(fun_obj.name.value.clone().unwrap_or_default(), fun_obj.file.to_owned());
+                let obj = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || self.evaluate_statement(&fun_obj.body));
+                let obj = self.traced(obj);
+                self.leave();
 
-                self.end_scope();
-                self.scope_depth -= 1;
+                self.end_scope(previous);
 
-                obj
-            }
-            Object::Builtin(func) => {
-                let function = func.Fn;
-                function(eval_args)
+                match obj {
+                    Object::Return(value) => *value,
+                    Object::Error(_) => obj,
+                    _ => Object::Nil,
+                }
             }
-            Object::Tuple(caller, func) => {
-                // (builtfn.Fn)();
-
-                match *func {
-                    Object::Builtin(func) => {
-                        return (func.Fn)(vec![*caller, Object::Array(eval_args)])
+            Object::Native(native) => match native.call(self, eval_args) {
+                Ok(value) => value,
+                Err(err) => Object::Error(err),
+            },
+            Object::BoundMethod(bound) => match &bound.method {
+                Object::Native(native) => match native.call_method(self, bound.receiver.clone(), eval_args) {
+                    Ok(value) => value,
+                    Err(err) => Object::Error(err),
+                },
+                Object::Func(method) => {
+                    let is_init = method.name.value.as_deref() == Some("init");
+                    let bound_method = self.bind_self(method, bound.receiver.clone());
+                    let result = self.call_value(bound_method, eval_args);
+                    // init always returns the instance
+                    if is_init && !self.is_error(&result) {
+                        return bound.receiver.clone();
                     }
-                    _ => todo!(),
+                    result
                 }
+                method => Object::error(ErrorKind::TypeError, format!("{} is not callable", method.type_name())),
+            },
+            Object::Class(class) => {
+                let instance = Object::Instance(Rc::new(Instance::new(class.clone())));
+                match class.find_method("init") {
+                    Some(Object::Func(init)) => {
+                        let init = self.bind_self(&init, instance.clone());
+                        let result = self.call_value(init, eval_args);
+                        if self.is_error(&result) {
+                            return result;
+                        }
+                    }
+                    _ if !eval_args.is_empty() => {
+                        return Object::error(
+                            ErrorKind::ArityError,
+                            format!("Expected 0 arguments but got {}", eval_args.len()),
+                        )
+                    }
+                    _ => {}
+                }
+                instance
             }
-            _ => Object::Error(format!("is not callable")),
+            _ => Object::error(ErrorKind::TypeError, format!("{} is not callable", obj.type_name())),
         }
     }
+
+    /*
+      copy of a method whose scope has self bound to the receiver
+    */
+    fn bind_self(&self, method: &Function, receiver: Object) -> Object {
+        let scope = Rc::new(RefCell::new(SymbolTable::new(Some(method.scope.clone()))));
+        scope.borrow_mut().define("self".to_owned(), receiver);
+        Object::Func(Rc::new(Function::new(
+            method.name.clone(),
+            method.args.clone(),
+            method.body.clone(),
+            method.file.to_owned(),
+            scope,
+        )))
+    }
 }
@@ -0,0 +1,173 @@
+use std::io::Write;
+
+use crate::{
+    compiler::compiler::Compiler,
+    errors::{CypherError, ErrorKind, RuntimeError},
+    lexer::scanner::Scanner,
+    objects::{
+        convert::{FromValue, IntoValue},
+        native::{Arity, NativeRegistry},
+        Object,
+    },
+    parser::parser::Parser,
+    policy::Policy,
+    vm::{heap::GcStats, vm::VM},
+};
+
+/*
+  entry point for embedding cypher in a rust program, globals defined
+  by one eval_str are visible to the next
+
+    let mut interp = Interpreter::new();
+    interp.eval_str("def add(a, b) { return a + b }")?;
+    let sum: f64 = interp.call_function("add", (1.0, 2.0))?;
+*/
+pub struct Interpreter {
+    vm: VM,
+    filename: String,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            vm: VM::new(),
+            filename: "<eval>".to_owned(),
+        }
+    }
+
+    // name used for the source in error messages
+    pub fn set_filename(&mut self, filename: &str) {
+        self.filename = filename.to_owned();
+    }
+
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.vm.set_output(output);
+    }
+
+    // directory searched for modules not found next to the importing file
+    pub fn add_search_path(&mut self, dir: &str) {
+        self.vm.modules().add_search_path(dir);
+    }
+
+    // sandboxes the scripts run from now on, see Policy
+    pub fn set_policy(&mut self, policy: Policy) {
+        self.vm.set_policy(policy);
+    }
+
+    // collections and live objects of the garbage collector
+    pub fn gc_stats(&self) -> GcStats {
+        self.vm.gc_stats()
+    }
+
+    pub fn collect_garbage(&mut self) {
+        self.vm.collect_garbage();
+    }
+
+    pub fn natives(&mut self) -> &mut NativeRegistry {
+        self.vm.natives()
+    }
+
+    /*
+      registers a rust closure callable from scripts, it may capture
+      host state
+    */
+    pub fn register_fn<F>(&mut self, name: &str, arity: Arity, doc: &str, function: F)
+    where
+        F: Fn(Vec<Object>) -> Result<Object, RuntimeError> + 'static,
+    {
+        self.vm.natives().register(name, arity, doc, function);
+    }
+
+    /*
+      runs source code, returns the value of a top level return statement
+    */
+    pub fn eval_str(&mut self, source: &str) -> Result<Object, CypherError> {
+        let lines: Vec<&str> = source.split('\n').collect();
+        let mut lex = Scanner::new(source, self.filename.to_owned(), &lines);
+        let mut par = Parser::new(&mut lex, &self.filename, &lines);
+        let program = par.try_parse_program()?;
+
+        let chunk = Compiler::with_interner(self.filename.to_owned(), self.vm.interner()).try_compile_program(program)?;
+        match self.vm.run(chunk) {
+            Object::Error(err) => Err(CypherError::Runtime(err)),
+            value => Ok(value),
+        }
+    }
+
+    /*
+      calls a global function or native by name
+    */
+    pub fn call_function<A: IntoArgs, R: FromValue>(
+        &mut self,
+        name: &str,
+        args: A,
+    ) -> Result<R, CypherError> {
+        let callee = match self.vm.get_global(name) {
+            Some(callee) => callee,
+            None => match self.vm.natives().get(name) {
+                Some(native) => native,
+                None => return Err(not_found(name).into()),
+            },
+        };
+        let result = self.vm.call(callee, args.into_args())?;
+        Ok(R::from_value(result)?)
+    }
+
+    pub fn set_global<T: IntoValue>(&mut self, name: &str, value: T) {
+        self.vm.set_global(name, value.into_value());
+    }
+
+    pub fn get_global<T: FromValue>(&self, name: &str) -> Result<T, RuntimeError> {
+        match self.vm.get_global(name) {
+            Some(value) => T::from_value(value),
+            None => Err(not_found(name)),
+        }
+    }
+}
+
+fn not_found(name: &str) -> RuntimeError {
+    RuntimeError::new(ErrorKind::NameError, format!("identifier {} not found", name))
+}
+
+/*
+  argument lists accepted by call_function, a vec of values or a tuple
+  of convertible rust values
+*/
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<Object>;
+}
+
+impl IntoArgs for Vec<Object> {
+    fn into_args(self) -> Vec<Object> {
+        self
+    }
+}
+
+impl IntoArgs for () {
+    fn into_args(self) -> Vec<Object> {
+        vec![]
+    }
+}
+
+macro_rules! tuple_args {
+    ($($name:ident),+) => {
+        impl<$($name: IntoValue),+> IntoArgs for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Vec<Object> {
+                let ($($name,)+) = self;
+                vec![$($name.into_value()),+]
+            }
+        }
+    };
+}
+
+tuple_args!(A);
+tuple_args!(A, B);
+tuple_args!(A, B, C);
+tuple_args!(A, B, C, D);
@@ -5,7 +5,6 @@ use crate::errors::lexical::LexicalError;
 use super::token::{Location, Token, TokenList, TokenType};
 
 pub struct Scanner<'a> {
-    raw: &'a str,
     lines: &'a Vec<&'a str>,
     current_char: Option<char>,
     chars: Peekable<Chars<'a>>,
@@ -40,9 +39,19 @@ impl<'a> Scanner<'a> {
         reserved.insert("false".to_string(), TokenType::False);
         reserved.insert("not".to_string(), TokenType::Not);
         reserved.insert("let".to_string(), TokenType::Let);
+        reserved.insert("class".to_string(), TokenType::Class);
+        reserved.insert("super".to_string(), TokenType::Super);
+        reserved.insert("is".to_string(), TokenType::Is);
+        reserved.insert("import".to_string(), TokenType::Import);
+        reserved.insert("from".to_string(), TokenType::From);
+        reserved.insert("as".to_string(), TokenType::As);
+        reserved.insert("export".to_string(), TokenType::Export);
+        reserved.insert("throw".to_string(), TokenType::Throw);
+        reserved.insert("try".to_string(), TokenType::Try);
+        reserved.insert("catch".to_string(), TokenType::Catch);
+        reserved.insert("finally".to_string(), TokenType::Finally);
 
         Self {
-            raw,
             lines,
             current_char: None,
             chars: raw.chars().peekable(),
@@ -60,6 +69,10 @@ impl<'a> Scanner<'a> {
         self.chars.peek()
     }
 
+    fn peek_next(&self) -> Option<char> {
+        self.chars.clone().nth(1)
+    }
+
     fn advance(&mut self) {
         self.current_char = self.chars.next();
         self.global_location.col += 1;
@@ -103,7 +116,7 @@ impl<'a> Scanner<'a> {
 
     fn scan_operator(&mut self, op: char) -> Result<Token, LexicalError> {
         let operator: TokenType = match op {
-            '*' => TokenType::Star,
+            '*' => self.check_op_return('*', TokenType::Power, TokenType::Star),
             '/' => TokenType::Slash,
             '%' => TokenType::Modulo,
             ';' => TokenType::SemiColon,
@@ -121,6 +134,13 @@ impl<'a> Scanner<'a> {
             '<' => self.check_op_return('=', TokenType::LessThanOrEqual, TokenType::LessThan),
             '!' => self.check_op_return('=', TokenType::NotEqual, TokenType::Bang),
             '=' => self.check_op_return('=', TokenType::Equal, TokenType::Assign),
+            '~' => match self.peek() {
+                Some('/') => {
+                    self.advance();
+                    TokenType::IntDivide
+                }
+                _ => return self.generate_lex_error("expected / after ~".to_string()),
+            },
             _ => return self.generate_lex_error("cannot recognize operator".to_string()),
         };
         self.create_token(operator, None)
@@ -128,10 +148,15 @@ impl<'a> Scanner<'a> {
 
     fn scan_number(&mut self, ch: char) -> Result<Token, LexicalError> {
         let mut num = String::from(ch);
+        let mut seen_dot = false;
         while let Some(c) = self.peek() {
-            if c.is_digit(10) {
+            if c.is_ascii_digit() {
                 num.push(*c);
                 self.advance()
+            } else if *c == '.' && !seen_dot && self.peek_next().is_some_and(|n| n.is_ascii_digit()) {
+                seen_dot = true;
+                num.push('.');
+                self.advance()
             } else {
                 break;
             }
@@ -143,21 +168,39 @@ impl<'a> Scanner<'a> {
     fn scan_string(&mut self) -> Result<Token, LexicalError> {
         let mut string = String::from("");
         loop {
-            let ch = self.peek();
-            if ch.is_none()  {
-                return Err(LexicalError {
-                    file_name: self.file_name.to_owned(),
-                    message: "unterminated string literal".to_owned(),
-                    location: self.global_location,
-                    line: self.lines[(self.global_location.line-1) as usize].to_owned(),
-                });
+            let ch = match self.peek() {
+                Some(ch) => *ch,
+                None => {
+                    return Err(LexicalError {
+                        file_name: self.file_name.to_owned(),
+                        message: "unterminated string literal".to_owned(),
+                        location: self.global_location,
+                        line: self.lines[(self.global_location.line - 1) as usize].to_owned(),
+                    });
+                }
+            };
+            if ch == '"' {
+                break;
             }
-            if ch.unwrap().is_alphanumeric() && *ch.unwrap() != '"' {
-                string.push(*ch.unwrap());
+            if ch == '\\' {
+                self.advance();
+                let escaped = match self.peek() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    _ => return self.generate_lex_error("invalid escape sequence".to_string()),
+                };
+                string.push(escaped);
                 self.advance();
                 continue;
             }
-            break;
+            if ch == '\n' {
+                self.global_location.line += 1;
+                self.global_location.col = 0;
+            }
+            string.push(ch);
+            self.advance();
         }
         self.advance();
         self.create_token(TokenType::String, Some(string))
@@ -166,7 +209,7 @@ impl<'a> Scanner<'a> {
     fn scan_ident_or_keyword(&mut self, ch: char) -> Result<Token, LexicalError> {
         let mut string = String::from(ch);
         while let Some(c) = self.peek() {
-            if c.is_alphabetic() || *c == '_' {
+            if c.is_alphanumeric() || *c == '_' {
                 string.push(*c);
                 self.advance();
                 continue;
@@ -177,7 +220,8 @@ impl<'a> Scanner<'a> {
         match string.as_str() {
             "and" | "print" | "false" | "let" | "true" | "do" | "while" | "for" | "def" | "nil"
             | "or" | "not" | "until" | "if" | "else" | "elseif" | "goto" | "return" | "repeat"
-            | "in" => self.create_token(
+            | "in" | "class" | "super" | "is" | "import" | "from" | "as" | "export" | "throw" | "try"
+            | "catch" | "finally" => self.create_token(
                 *self.reserved_words.get(string.as_str()).unwrap(),
                 Some(string),
             ),
@@ -190,7 +234,7 @@ impl<'a> Scanner<'a> {
     }
 
     fn skip_whitespaces(&mut self) {
-        while let Some(ch) = self.peek() {
+        while let Some(ch) = self.peek().copied() {
             match ch {
                 ' ' | '\t' | '\r' => {
                     self.advance();
@@ -202,6 +246,16 @@ impl<'a> Scanner<'a> {
                     self.advance();
                     continue;
                 }
+                // line comments run until the end of the line
+                '/' if self.peek_next() == Some('/') => {
+                    while let Some(ch) = self.peek() {
+                        if *ch == '\n' {
+                            break;
+                        }
+                        self.advance();
+                    }
+                    continue;
+                }
                 _ => break,
             }
         }
@@ -210,10 +264,10 @@ impl<'a> Scanner<'a> {
     pub fn next_token(&mut self) -> Result<Token, LexicalError> {
         self.skip_whitespaces();
         self.advance();
-        let token: Result<Token, LexicalError> = match self.current_char {
+        match self.current_char {
             Some(ch) => match ch {
                 '+' | '-' | '*' | '/' | '%' | '=' | '>' | '<' | '{' | '}' | '(' | ')' | '['
-                | ']' | ';' | '.' | ',' | '!' => self.scan_operator(ch),
+                | ']' | ';' | '.' | ',' | '!' | '~' => self.scan_operator(ch),
                 '0'..='9' => self.scan_number(ch),
                 '"' => self.scan_string(),
                 _ => {
@@ -225,21 +279,20 @@ impl<'a> Scanner<'a> {
                 }
             },
             None => self.create_token(TokenType::Eof, None),
-        };
-        token
+        }
     }
 
     pub fn generate_token_list(lexer: &mut Scanner) -> TokenList {
         let mut tokens: Vec<Token> = vec![];
         loop {
             let token = lexer.next_token();
-            if token.is_ok() {
-                if token.as_ref().unwrap().kind == TokenType::Eof {
+            if let Ok(token) = token {
+                if token.kind == TokenType::Eof {
                     break;
                 }
-                tokens.push(token.unwrap())
+                tokens.push(token)
             }
         }
-        return TokenList::new(tokens);
+        TokenList::new(tokens)
     }
 }
@@ -13,6 +13,10 @@ pub enum TokenType {
     Star,
     Slash,
     Modulo,
+    // **
+    Power,
+    // ~/
+    IntDivide,
 
     Equal,
     NotEqual,
@@ -57,6 +61,17 @@ pub enum TokenType {
     Function,
     Print,
     Let,
+    Class,
+    Super,
+    Is,
+    Import,
+    From,
+    As,
+    Export,
+    Throw,
+    Try,
+    Catch,
+    Finally,
     Goto,
     True,
     False,
@@ -66,6 +81,48 @@ pub enum TokenType {
     Eof
 }
 
+impl TokenType {
+    // reserved words, these may still name a property after a dot
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            TokenType::Nil
+                | TokenType::Not
+                | TokenType::And
+                | TokenType::Or
+                | TokenType::While
+                | TokenType::For
+                | TokenType::Do
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::Until
+                | TokenType::Return
+                | TokenType::If
+                | TokenType::Elseif
+                | TokenType::Else
+                | TokenType::Repeat
+                | TokenType::In
+                | TokenType::Function
+                | TokenType::Print
+                | TokenType::Let
+                | TokenType::Class
+                | TokenType::Super
+                | TokenType::Is
+                | TokenType::Import
+                | TokenType::From
+                | TokenType::As
+                | TokenType::Export
+                | TokenType::Throw
+                | TokenType::Try
+                | TokenType::Catch
+                | TokenType::Finally
+                | TokenType::Goto
+                | TokenType::True
+                | TokenType::False
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy,DisplayAsJsonPretty,Serialize)]
 pub struct Location {
     pub line: u32,
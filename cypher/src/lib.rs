@@ -5,6 +5,12 @@ pub mod parser;
 pub mod  lexer;
 pub mod compiler;
 pub mod errors;
+pub mod eval;
+pub mod interpreter;
+pub mod modules;
+pub mod objects;
+pub mod policy;
+pub mod stdlib;
 
 
 
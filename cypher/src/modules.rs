@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    errors::{ErrorKind, RuntimeError},
+    lexer::scanner::Scanner,
+    objects::{
+        module::Module,
+        native::{Arity, Native},
+        Object,
+    },
+    parser::{
+        expr::{Program, Statement},
+        parser::Parser,
+    },
+    policy::{Capability, Policy},
+    stdlib,
+};
+
+/*
+  finds, caches and tracks the modules imported by a program. both
+  backends share it, each runs a module once and hands the resulting
+  module object to finish
+*/
+#[derive(Debug)]
+pub struct ModuleLoader {
+    search_path: Vec<PathBuf>,
+    // modules imported by name as registered, see stdlib
+    builtins: HashMap<String, Object>,
+    // what the policy leaves of each builtin, None when it cannot be imported
+    allowed: HashMap<String, Option<Object>>,
+    policy: Policy,
+    // keyed by canonical path so different spellings share a module
+    cache: HashMap<PathBuf, Object>,
+    // modules currently running, innermost last
+    loading: Vec<PathBuf>,
+}
+
+impl Default for ModuleLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModuleLoader {
+    pub fn new() -> Self {
+        let mut loader = Self {
+            search_path: vec![],
+            builtins: HashMap::new(),
+            allowed: HashMap::new(),
+            policy: Policy::permissive(),
+            cache: HashMap::new(),
+            loading: vec![],
+        };
+        for module in stdlib::modules() {
+            loader.add_builtin(module);
+        }
+        loader
+    }
+
+    // makes a module importable by its name, replacing one with the same name
+    pub fn add_builtin(&mut self, module: Object) {
+        if let Object::Module(builtin) = &module {
+            let name = builtin.name();
+            self.allowed.insert(name.to_owned(), restrict(builtin, &self.policy));
+            self.builtins.insert(name, module);
+        }
+    }
+
+    /*
+      limits the builtins and files that can be imported, natives the
+      policy denies are replaced by ones raising PermissionError. set it
+      before the program imports anything
+    */
+    pub fn set_policy(&mut self, policy: Policy) {
+        self.allowed = self
+            .builtins
+            .iter()
+            .map(|(name, module)| match module {
+                Object::Module(module) => (name.to_owned(), restrict(module, &policy)),
+                _ => (name.to_owned(), None),
+            })
+            .collect();
+        self.policy = policy;
+    }
+
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    /*
+      the builtin module called name, builtins take precedence over
+      files. Ok(None) when there is no such builtin
+    */
+    pub fn builtin(&self, name: &str) -> Result<Option<Object>, RuntimeError> {
+        match self.allowed.get(name) {
+            Some(Some(module)) => Ok(Some(module.clone())),
+            Some(None) => Err(RuntimeError::new(
+                ErrorKind::ImportError,
+                format!("module {} is not allowed by the sandbox policy", name),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    // directories searched after the directory of the importing file
+    pub fn add_search_path<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.search_path.push(dir.into());
+    }
+
+    pub fn search_path(&self) -> &[PathBuf] {
+        &self.search_path
+    }
+
+    /*
+      path of the module imported as path from the file importer
+    */
+    pub fn resolve(&self, path: &str, importer: &str) -> Result<PathBuf, RuntimeError> {
+        // reading a script file is file system access
+        if !self.policy.allows(Capability::Fs) {
+            return Err(RuntimeError::new(
+                ErrorKind::ImportError,
+                format!("importing {:?} needs the fs capability, which the sandbox policy denies", path),
+            ));
+        }
+        let relative = Path::new(importer).parent().unwrap_or(Path::new("")).join(path);
+        if relative.is_file() {
+            return Ok(relative);
+        }
+        for dir in &self.search_path {
+            let candidate = dir.join(path);
+            if candidate.is_file() {
+                return Ok(candidate);
+            }
+        }
+        Err(RuntimeError::new(
+            ErrorKind::ImportError,
+            format!("module {:?} not found, imported from {}", path, importer),
+        ))
+    }
+
+    /*
+      starts loading the module at path, gives back the module if it
+      already ran. importing a module that is still running is an error
+    */
+    pub fn begin(&mut self, path: &Path) -> Result<Option<Object>, RuntimeError> {
+        let key = canonical(path);
+        if let Some(module) = self.cache.get(&key) {
+            return Ok(Some(module.clone()));
+        }
+        if let Some(start) = self.loading.iter().position(|loading| *loading == key) {
+            let mut chain: Vec<String> = self.loading[start..]
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            chain.push(key.display().to_string());
+            return Err(RuntimeError::new(
+                ErrorKind::ImportError,
+                format!("circular import {}", chain.join(" -> ")),
+            ));
+        }
+        self.loading.push(key);
+        Ok(None)
+    }
+
+    pub fn finish(&mut self, module: Object) {
+        if let Some(key) = self.loading.pop() {
+            self.cache.insert(key, module);
+        }
+    }
+
+    // the module being loaded failed, it is not cached
+    pub fn abort(&mut self) {
+        self.loading.pop();
+    }
+}
+
+/*
+  the module as the policy allows it, the module itself when nothing is
+  denied and None when nothing is left
+*/
+fn restrict(module: &Rc<Module>, policy: &Policy) -> Option<Object> {
+    let name = module.name();
+    if !policy.allows_module(&name) {
+        return None;
+    }
+    let mut denied = 0;
+    let mut exports = HashMap::new();
+    for (export, value) in &module.exports {
+        let value = match stdlib::capability(&name, export) {
+            Some(capability) if !policy.allows(capability) => {
+                denied += 1;
+                denied_native(&name, export, capability)
+            }
+            _ => value.clone(),
+        };
+        exports.insert(export.to_owned(), value);
+    }
+    if denied == 0 {
+        return Some(Object::Module(module.clone()));
+    }
+    if denied == exports.len() {
+        return None;
+    }
+    Some(Object::Module(Rc::new(Module::new(module.path.clone(), exports))))
+}
+
+fn denied_native(module: &str, name: &str, capability: Capability) -> Object {
+    let message = format!(
+        "{}.{} needs the {} capability, which the sandbox policy denies",
+        module, name, capability
+    );
+    let native = Native::new(
+        name.to_owned(),
+        Arity::Variadic(0),
+        message.to_owned(),
+        Rc::new(move |_, _| Err(RuntimeError::new(ErrorKind::PermissionError, message.to_owned()))),
+    );
+    Object::Native(Rc::new(native))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/*
+  reads and parses a module, syntax errors are reported as import
+  errors that cite the module file
+*/
+pub fn parse_module(path: &Path) -> Result<Program, RuntimeError> {
+    let filename = path.display().to_string();
+    let source = fs::read_to_string(path).map_err(|err| {
+        RuntimeError::new(
+            ErrorKind::ImportError,
+            format!("unable to read {}: {}", filename, err),
+        )
+    })?;
+    let lines: Vec<&str> = source.split('\n').collect();
+    let mut lex = Scanner::new(&source, filename.to_owned(), &lines);
+    let mut par = Parser::new(&mut lex, &filename, &lines);
+    par.try_parse_program()
+        .map_err(|err| RuntimeError::new(ErrorKind::ImportError, err.to_string()))
+}
+
+/*
+  names bound by the top level export statements of a module
+*/
+pub fn exported_names(program: &Program) -> Vec<String> {
+    let mut names = vec![];
+    for statement in &program.body {
+        if let Statement::ExportStatement { declaration } = statement {
+            let name = match declaration.as_ref() {
+                Statement::VariableStatement { ident, .. } => ident,
+                Statement::FunctionDeclaration { name, .. } => name,
+                Statement::ClassDeclaration { name, .. } => name,
+                _ => continue,
+            };
+            names.push(name.value.as_ref().unwrap().to_owned());
+        }
+    }
+    names
+}
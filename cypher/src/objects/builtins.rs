@@ -0,0 +1,47 @@
+use std::rc::Rc;
+
+use crate::errors::{ErrorKind, RuntimeError};
+
+use super::{
+    native::{Arity, NativeRegistry},
+    Object,
+};
+
+/*
+  builtin functions shared by the tree walking interpreter and the vm
+*/
+pub fn register(registry: &mut NativeRegistry) {
+    registry.register(
+        "len",
+        Arity::Fixed(1),
+        "len(value) -> number of characters in a string or elements in an array",
+        |args| match &args[0] {
+            Object::Str(s) => Ok(Object::Number(s.chars().count() as f64)),
+            Object::Array(arr) => Ok(Object::Number(arr.len() as f64)),
+            obj => Err(RuntimeError::new(
+                ErrorKind::TypeError,
+                format!("len() not supported for {}", obj.type_name()),
+            )),
+        },
+    );
+
+    registry.register(
+        "push",
+        Arity::Fixed(2),
+        "push(array, value) -> new array with value appended",
+        |mut args| {
+            let value = args.pop().unwrap();
+            match args.pop().unwrap() {
+                Object::Array(arr) => {
+                    let mut arr = Rc::unwrap_or_clone(arr);
+                    arr.push(value);
+                    Ok(Object::array(arr))
+                }
+                obj => Err(RuntimeError::new(
+                    ErrorKind::TypeError,
+                    format!("push() not supported for {}", obj.type_name()),
+                )),
+            }
+        },
+    );
+}
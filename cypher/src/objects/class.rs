@@ -0,0 +1,98 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::errors::{ErrorKind, RuntimeError};
+
+use super::Object;
+
+/*
+  a class declared by a script, methods are Func values on the tree
+  walker and Closure values on the vm
+*/
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    pub methods: RefCell<HashMap<String, Object>>,
+    // set once right after the class is created
+    pub superclass: RefCell<Option<Rc<Class>>>,
+}
+
+impl Class {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            methods: RefCell::new(HashMap::new()),
+            superclass: RefCell::new(None),
+        }
+    }
+
+    // a chain looping back to the class would make method lookup recurse forever
+    pub fn set_superclass(&self, superclass: Rc<Class>) -> Result<(), RuntimeError> {
+        if superclass.is_subclass_of(self) {
+            return Err(RuntimeError::new(
+                ErrorKind::TypeError,
+                format!("class {} cannot inherit from itself or its subclass {}", self.name, superclass.name),
+            ));
+        }
+        *self.superclass.borrow_mut() = Some(superclass);
+        Ok(())
+    }
+
+    pub fn add_method(&self, name: String, method: Object) {
+        self.methods.borrow_mut().insert(name, method);
+    }
+
+    // looks through the superclass chain, nearest definition wins
+    pub fn find_method(&self, name: &str) -> Option<Object> {
+        if let Some(method) = self.methods.borrow().get(name) {
+            return Some(method.clone());
+        }
+        match &*self.superclass.borrow() {
+            Some(superclass) => superclass.find_method(name),
+            None => None,
+        }
+    }
+
+    // whether the class is other or inherits from it
+    pub fn is_subclass_of(&self, other: &Class) -> bool {
+        if std::ptr::eq(self, other) {
+            return true;
+        }
+        match &*self.superclass.borrow() {
+            Some(superclass) => superclass.is_subclass_of(other),
+            None => false,
+        }
+    }
+}
+
+/*
+  value of `value is class`, only instances belong to a class
+*/
+pub fn is_instance(value: &Object, class: &Class) -> bool {
+    match value {
+        Object::Instance(instance) => instance.class.is_subclass_of(class),
+        _ => false,
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: RefCell<HashMap<String, Object>>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Self {
+            class,
+            fields: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn get_field(&self, name: &str) -> Option<Object> {
+        self.fields.borrow().get(name).cloned()
+    }
+
+    pub fn set_field(&self, name: String, value: Object) {
+        self.fields.borrow_mut().insert(name, value);
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
+
+use crate::errors::{ErrorKind, RuntimeError};
+
+use super::Object;
+
+/*
+  conversions between rust values and script values, used by the
+  embedding api to pass arguments in and results out
+*/
+pub trait IntoValue {
+    fn into_value(self) -> Object;
+}
+
+pub trait FromValue: Sized {
+    fn from_value(value: Object) -> Result<Self, RuntimeError>;
+}
+
+fn mismatch(expected: &str, value: &Object) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::TypeError,
+        format!("expected {} got {}", expected, value.type_name()),
+    )
+}
+
+impl IntoValue for Object {
+    fn into_value(self) -> Object {
+        self
+    }
+}
+
+impl FromValue for Object {
+    fn from_value(value: Object) -> Result<Self, RuntimeError> {
+        Ok(value)
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Object {
+        Object::Nil
+    }
+}
+
+impl FromValue for () {
+    fn from_value(value: Object) -> Result<Self, RuntimeError> {
+        match value {
+            Object::Nil => Ok(()),
+            value => Err(mismatch("nil", &value)),
+        }
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Object {
+        Object::Number(self)
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: Object) -> Result<Self, RuntimeError> {
+        match value {
+            Object::Number(n) => Ok(n),
+            value => Err(mismatch("number", &value)),
+        }
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Object {
+        Object::Bool(self)
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: Object) -> Result<Self, RuntimeError> {
+        match value {
+            Object::Bool(b) => Ok(b),
+            value => Err(mismatch("bool", &value)),
+        }
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Object {
+        Object::string(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Object {
+        Object::string(self.to_owned())
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: Object) -> Result<Self, RuntimeError> {
+        match value {
+            Object::Str(s) => Ok(s.to_string()),
+            value => Err(mismatch("string", &value)),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Object {
+        match self {
+            Some(value) => value.into_value(),
+            None => Object::Nil,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Object) -> Result<Self, RuntimeError> {
+        match value {
+            Object::Nil => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Object {
+        Object::array(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: Object) -> Result<Self, RuntimeError> {
+        match value {
+            Object::Array(arr) => Rc::unwrap_or_clone(arr).into_iter().map(T::from_value).collect(),
+            value => Err(mismatch("array", &value)),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for HashMap<String, T> {
+    fn into_value(self) -> Object {
+        let map: BTreeMap<String, Object> = self
+            .into_iter()
+            .map(|(key, value)| (key, value.into_value()))
+            .collect();
+        Object::map(map)
+    }
+}
+
+impl<T: FromValue> FromValue for HashMap<String, T> {
+    fn from_value(value: Object) -> Result<Self, RuntimeError> {
+        match value {
+            Object::Map(map) => Rc::unwrap_or_clone(map)
+                .into_iter()
+                .map(|(key, value)| Ok((key, T::from_value(value)?)))
+                .collect(),
+            value => Err(mismatch("map", &value)),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::rc::Rc;
+
+use crate::{
+    errors::{ErrorKind, RuntimeError},
+    stdlib::{array, string},
+};
+
+use super::{
+    native::{Arity, NativeRegistry},
+    BoundMethod, Object,
+};
+
+/*
+  methods of the builtin types, `x.name(args)` looks name up in the
+  table for the type of x. method arity does not count the receiver.
+  instances use the fields and methods of their class instead and
+  modules their exports
+*/
+#[derive(Debug, Clone)]
+pub struct MethodTables {
+    pub string: NativeRegistry,
+    pub array: NativeRegistry,
+    pub map: NativeRegistry,
+}
+
+impl Default for MethodTables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MethodTables {
+    pub fn new() -> Self {
+        let mut tables = Self {
+            string: NativeRegistry::new(),
+            array: NativeRegistry::new(),
+            map: NativeRegistry::new(),
+        };
+        string::register(&mut tables.string);
+        array::register(&mut tables.array);
+        register_map(&mut tables.map);
+        tables
+    }
+
+    /*
+      looks up receiver.name, the field of an instance or a method
+      bound to the receiver
+    */
+    pub fn get_property(&self, receiver: Object, name: &str) -> Result<Object, RuntimeError> {
+        if let Object::Instance(instance) = &receiver {
+            if let Some(value) = instance.get_field(name) {
+                return Ok(value);
+            }
+            return match instance.class.find_method(name) {
+                Some(method) => Ok(Object::BoundMethod(Rc::new(BoundMethod::new(receiver, method)))),
+                None => Err(RuntimeError::new(
+                    ErrorKind::AttributeError,
+                    format!("{} instance has no property {}", instance.class.name, name),
+                )),
+            };
+        }
+        if let Object::Module(module) = &receiver {
+            return match module.exports.get(name) {
+                Some(value) => Ok(value.clone()),
+                None => Err(RuntimeError::new(
+                    ErrorKind::AttributeError,
+                    format!("module {} has no export {}", module.name(), name),
+                )),
+            };
+        }
+        if let Object::Exception(err) = &receiver {
+            return error_property(err, name);
+        }
+        let table = match &receiver {
+            Object::Str(_) => Some(&self.string),
+            Object::Array(_) => Some(&self.array),
+            Object::Map(_) => Some(&self.map),
+            _ => None,
+        };
+        match table.and_then(|table| table.lookup(name)) {
+            Some(method) => Ok(Object::BoundMethod(Rc::new(BoundMethod::new(
+                receiver,
+                Object::Native(method),
+            )))),
+            None => Err(RuntimeError::new(
+                ErrorKind::AttributeError,
+                format!("{} has no method {}", receiver.type_name(), name),
+            )),
+        }
+    }
+}
+
+/*
+  the properties of a caught error, stack lists the calls that were
+  running innermost first
+*/
+fn error_property(err: &RuntimeError, name: &str) -> Result<Object, RuntimeError> {
+    match name {
+        "message" => Ok(Object::string(err.message().to_owned())),
+        "kind" => Ok(Object::string(err.kind.to_string())),
+        "stack" => Ok(Object::array(
+            err.trace.iter().map(|frame| Object::string(frame.to_string())).collect(),
+        )),
+        "value" => Ok(err.value().cloned().unwrap_or(Object::Nil)),
+        _ => Err(RuntimeError::new(
+            ErrorKind::AttributeError,
+            format!("error has no property {}", name),
+        )),
+    }
+}
+
+fn register_map(table: &mut NativeRegistry) {
+    table.register("len", Arity::Fixed(0), "m.len() -> number of entries", |args| {
+        match &args[0] {
+            Object::Map(map) => Ok(Object::Number(map.len() as f64)),
+            obj => Err(receiver_error("map", obj)),
+        }
+    });
+    table.register("keys", Arity::Fixed(0), "m.keys() -> array of keys in order", |args| {
+        match &args[0] {
+            Object::Map(map) => Ok(Object::array(
+                map.keys().map(|key| Object::string(key.to_owned())).collect(),
+            )),
+            obj => Err(receiver_error("map", obj)),
+        }
+    });
+    table.register("values", Arity::Fixed(0), "m.values() -> array of values in key order", |args| {
+        match &args[0] {
+            Object::Map(map) => Ok(Object::array(map.values().cloned().collect())),
+            obj => Err(receiver_error("map", obj)),
+        }
+    });
+    table.register("contains", Arity::Fixed(1), "m.contains(key) -> whether key is present", |args| {
+        match (&args[0], &args[1]) {
+            (Object::Map(map), Object::Str(key)) => Ok(Object::Bool(map.contains_key(&**key))),
+            (Object::Map(_), key) => Err(RuntimeError::new(
+                ErrorKind::TypeError,
+                format!("expected string key got {}", key.type_name()),
+            )),
+            (obj, _) => Err(receiver_error("map", obj)),
+        }
+    });
+}
+
+fn receiver_error(expected: &str, obj: &Object) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::TypeError,
+        format!("expected {} receiver got {}", expected, obj.type_name()),
+    )
+}
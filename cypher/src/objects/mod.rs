@@ -1,53 +1,189 @@
-use std::fmt::Display;
+use std::{cell::RefCell, collections::BTreeMap, fmt::Display, rc::Rc};
 
 use display_json::DisplayAsJsonPretty;
 use serde::Serialize;
 
-use crate::{lexer::token::Token, parser::expr::Statement};
+use crate::{
+    errors::{ErrorKind, RuntimeError},
+    eval::symbol_table::SymbolTable,
+    lexer::token::Token,
+    parser::expr::Statement,
+    vm::object::{Closure, CompiledFunction},
+};
 
-type BuiltinFunction = fn(args: Vec<Object>) -> Object;
+use self::{
+    class::{Class, Instance},
+    module::Module,
+    native::Native,
+};
 
-#[derive(Debug, Clone)]
-pub struct BuiltinFn {
-    pub Fn: BuiltinFunction,
-}
+pub mod builtins;
+pub mod class;
+pub mod convert;
+pub mod methods;
+pub mod module;
+pub mod native;
 
-#[derive(Debug, Clone, Serialize, DisplayAsJsonPretty)]
+#[derive(Clone, Serialize, DisplayAsJsonPretty)]
 pub struct Function {
     pub name: Token,
     pub args: Vec<Token>,
-    pub body: Box<Statement>,
-    // scope:SymbolTable
+    // shared with the methods bound from it
+    pub body: Rc<Statement>,
+    // file the function was declared in, for stack traces
+    pub file: String,
+    #[serde(skip)]
+    pub scope: Rc<RefCell<SymbolTable>>,
 }
 
-impl BuiltinFn {
-    pub fn new(function: BuiltinFunction) -> Self {
-        Self { Fn: function }
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the captured scope usually contains the function itself
+        f.debug_struct("Function")
+            .field("name", &self.name.value)
+            .field("args", &self.args.len())
+            .finish()
+    }
+}
+
+/*
+  a method looked up on a value together with its receiver. method is
+  a Native taking the receiver as its first argument, or a Func or
+  Closure of a class that sees the receiver as self
+*/
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Object,
+    pub method: Object,
+}
+
+impl BoundMethod {
+    pub fn new(receiver: Object, method: Object) -> Self {
+        Self { receiver, method }
+    }
+
+    pub fn name(&self) -> String {
+        match &self.method {
+            Object::Native(native) => native.name.to_owned(),
+            Object::Func(func) => func.name.value.clone().unwrap_or_default(),
+            Object::Closure(closure) => closure.function.name.to_owned(),
+            method => method.to_string(),
+        }
     }
 }
 
 impl Function {
-    pub fn new(name: Token, args: Vec<Token>, body: Box<Statement>) -> Self {
-        Self { name, args, body }
+    pub fn new(
+        name: Token,
+        args: Vec<Token>,
+        body: Rc<Statement>,
+        file: String,
+        scope: Rc<RefCell<SymbolTable>>,
+    ) -> Self {
+        Self {
+            name,
+            args,
+            body,
+            file,
+            scope,
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum Object {
     Number(f64),
-    Str(String),
+    Str(Rc<str>),
     Bool(bool),
     Nil,
-    Error(String),
-    Func(Function),
-    Tuple(Box<Object>,Box<Object>),
-    Array(Vec<Object>),
+    // an error being raised, it unwinds until a catch turns it into an Exception
+    Error(RuntimeError),
+    // a caught error, an ordinary value with message, kind, stack and value
+    Exception(Rc<RuntimeError>),
+    Func(Rc<Function>),
+    // arrays and maps cannot be changed in place, copies share them
+    Array(Rc<Vec<Object>>),
+    // string keyed, kept sorted so printing is deterministic
+    Map(Rc<BTreeMap<String, Object>>),
     // Property(Box<Object>),
-    Builtin(BuiltinFn),
+    Native(Rc<Native>),
+    BoundMethod(Rc<BoundMethod>),
+    Class(Rc<Class>),
+    Instance(Rc<Instance>),
+    Module(Rc<Module>),
+    Return(Box<Object>),
+    CompiledFunction(Rc<CompiledFunction>),
+    Closure(Rc<Closure>),
 }
 
 impl Object {
-    
+    pub fn error(kind: ErrorKind, message: String) -> Self {
+        Object::Error(RuntimeError::new(kind, message))
+    }
+
+    // a string value that is not interned, see Interner for the ones that are
+    pub fn string(s: impl Into<Rc<str>>) -> Self {
+        Object::Str(s.into())
+    }
+
+    pub fn array(elements: Vec<Object>) -> Self {
+        Object::Array(Rc::new(elements))
+    }
+
+    pub fn map(entries: BTreeMap<String, Object>) -> Self {
+        Object::Map(Rc::new(entries))
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Object::Nil | Object::Bool(false))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Number(_) => "number",
+            Object::Str(_) => "string",
+            Object::Bool(_) => "bool",
+            Object::Nil => "nil",
+            Object::Error(_) | Object::Exception(_) => "error",
+            Object::Func(_)
+            | Object::Native(_)
+            | Object::BoundMethod(_)
+            | Object::CompiledFunction(_)
+            | Object::Closure(_) => "function",
+            Object::Array(_) => "array",
+            Object::Map(_) => "map",
+            Object::Class(_) => "class",
+            Object::Instance(_) => "instance",
+            Object::Module(_) => "module",
+            Object::Return(value) => value.type_name(),
+        }
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Number(a), Object::Number(b)) => a == b,
+            // interned strings are usually the same string, others compare by contents
+            (Object::Str(a), Object::Str(b)) => Rc::ptr_eq(a, b) || a == b,
+            (Object::Bool(a), Object::Bool(b)) => a == b,
+            (Object::Nil, Object::Nil) => true,
+            (Object::Array(a), Object::Array(b)) => a == b,
+            (Object::Map(a), Object::Map(b)) => a == b,
+            (Object::Func(a), Object::Func(b)) => Rc::ptr_eq(&a.scope, &b.scope) && a.name.value == b.name.value,
+            (Object::Native(a), Object::Native(b)) => Rc::ptr_eq(a, b),
+            (Object::BoundMethod(a), Object::BoundMethod(b)) => {
+                a.method == b.method && a.receiver == b.receiver
+            }
+            (Object::Class(a), Object::Class(b)) => Rc::ptr_eq(a, b),
+            (Object::Instance(a), Object::Instance(b)) => Rc::ptr_eq(a, b),
+            (Object::Module(a), Object::Module(b)) => Rc::ptr_eq(a, b),
+            (Object::Exception(a), Object::Exception(b)) => Rc::ptr_eq(a, b),
+            (Object::CompiledFunction(a), Object::CompiledFunction(b)) => Rc::ptr_eq(a, b),
+            (Object::Closure(a), Object::Closure(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
 }
 
 impl Display for Object {
@@ -57,21 +193,45 @@ impl Display for Object {
             Object::Str(a) => write!(f, "{}", a),
             Object::Bool(a) => write!(f, "{}", a),
             Object::Nil => write!(f, "nil"),
-            Object::Error(message) => write!(f, "{}", message),
+            Object::Error(err) => write!(f, "{}", err.message()),
+            Object::Exception(err) => write!(f, "{}: {}", err.kind, err.message()),
             Object::Func(func) => {
                 write!(f, "function({})", &func.name.value.as_ref().unwrap())
             }
-            Object::Builtin(fun) => {
-                write!(f,"native fn<{:?}>",&fun.Fn)
-            },
-            Object::Tuple(_, _) => todo!(),
+            Object::Native(native) => write!(f, "native fn<{}>", native.name),
+            Object::BoundMethod(bound) => {
+                let owner = match &bound.receiver {
+                    Object::Instance(instance) => instance.class.name.to_owned(),
+                    receiver => receiver.type_name().to_owned(),
+                };
+                write!(f, "method({}.{})", owner, bound.name())
+            }
+            Object::Class(class) => write!(f, "class({})", class.name),
+            Object::Instance(instance) => write!(f, "instance({})", instance.class.name),
+            Object::Module(module) => write!(f, "module({})", module.name()),
             Object::Array(array) => {
                 let _=write!(f,"[");
-                for obj in array{
-                    let _=write!(f,"{},",obj);
+                for (index, obj) in array.iter().enumerate() {
+                    if index > 0 {
+                        let _ = write!(f, ", ");
+                    }
+                    let _=write!(f,"{}",obj);
                 }
                 write!(f,"]")
             },
+            Object::Map(map) => {
+                let _=write!(f,"{{");
+                for (index, (key, obj)) in map.iter().enumerate() {
+                    if index > 0 {
+                        let _ = write!(f, ", ");
+                    }
+                    let _=write!(f,"{:?}: {}",key,obj);
+                }
+                write!(f,"}}")
+            },
+            Object::Return(value) => write!(f, "{}", value),
+            Object::CompiledFunction(func) => write!(f, "function({})", func.name),
+            Object::Closure(closure) => write!(f, "function({})", closure.function.name),
         }
     }
 }
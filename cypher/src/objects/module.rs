@@ -0,0 +1,26 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use super::Object;
+
+/*
+  an imported module, only its exported top level bindings are visible
+  to the importer
+*/
+#[derive(Debug)]
+pub struct Module {
+    pub path: PathBuf,
+    pub exports: HashMap<String, Object>,
+}
+
+impl Module {
+    pub fn new(path: PathBuf, exports: HashMap<String, Object>) -> Self {
+        Self { path, exports }
+    }
+
+    pub fn name(&self) -> String {
+        match self.path.file_stem() {
+            Some(stem) => stem.to_string_lossy().to_string(),
+            None => self.path.display().to_string(),
+        }
+    }
+}
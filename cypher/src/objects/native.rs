@@ -0,0 +1,204 @@
+use std::{collections::HashMap, fmt::Display, rc::Rc};
+
+use crate::errors::{ErrorKind, RuntimeError};
+
+use super::Object;
+
+pub type NativeFunction = Rc<dyn Fn(&mut dyn Caller, Vec<Object>) -> Result<Object, RuntimeError>>;
+
+/*
+  the interpreter running a native, natives taking callbacks use it to
+  call script functions. both Eval and the VM implement it
+*/
+pub trait Caller {
+    fn call(&mut self, callee: Object, args: Vec<Object>) -> Result<Object, RuntimeError>;
+
+    /*
+      counts bytes a native is about to allocate against the memory
+      limit, so that an allocation too big for it is refused before it
+      is made. the result is charged once the native returns, less what
+      it reserved
+    */
+    fn reserve(&mut self, _bytes: usize) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+}
+
+/*
+  number of arguments a native accepts, checked before the native runs
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Fixed(usize),
+    // inclusive bounds
+    Range(usize, usize),
+    // at least this many
+    Variadic(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, argc: usize) -> bool {
+        match *self {
+            Arity::Fixed(n) => argc == n,
+            Arity::Range(min, max) => argc >= min && argc <= max,
+            Arity::Variadic(min) => argc >= min,
+        }
+    }
+
+    // the same arity with one more leading argument
+    pub fn with_receiver(&self) -> Arity {
+        match *self {
+            Arity::Fixed(n) => Arity::Fixed(n + 1),
+            Arity::Range(min, max) => Arity::Range(min + 1, max + 1),
+            Arity::Variadic(min) => Arity::Variadic(min + 1),
+        }
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arity::Fixed(n) => write!(f, "{}", n),
+            Arity::Range(min, max) => write!(f, "{} to {}", min, max),
+            Arity::Variadic(min) => write!(f, "at least {}", min),
+        }
+    }
+}
+
+/*
+  a function implemented in rust, callable from scripts on both backends
+*/
+pub struct Native {
+    pub name: String,
+    pub arity: Arity,
+    pub doc: String,
+    function: NativeFunction,
+}
+
+impl std::fmt::Debug for Native {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Native")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+impl Native {
+    pub fn new(name: String, arity: Arity, doc: String, function: NativeFunction) -> Self {
+        Self {
+            name,
+            arity,
+            doc,
+            function,
+        }
+    }
+
+    pub fn call(&self, caller: &mut dyn Caller, args: Vec<Object>) -> Result<Object, RuntimeError> {
+        self.check_arity(args.len())?;
+        (self.function)(caller, args)
+    }
+
+    /*
+      calls a method native, the arity does not count the receiver
+      which is passed as the first argument
+    */
+    pub fn call_method(
+        &self,
+        caller: &mut dyn Caller,
+        receiver: Object,
+        mut args: Vec<Object>,
+    ) -> Result<Object, RuntimeError> {
+        self.check_arity(args.len())?;
+        args.insert(0, receiver);
+        (self.function)(caller, args)
+    }
+
+    // a method native as a function taking the receiver as its first argument
+    pub fn as_function(&self) -> Native {
+        Native::new(
+            self.name.to_owned(),
+            self.arity.with_receiver(),
+            self.doc.to_owned(),
+            self.function.clone(),
+        )
+    }
+
+    fn check_arity(&self, argc: usize) -> Result<(), RuntimeError> {
+        if self.arity.accepts(argc) {
+            return Ok(());
+        }
+        Err(RuntimeError::new(
+            ErrorKind::ArityError,
+            format!("{}() expected {} arguments but got {}", self.name, self.arity, argc),
+        ))
+    }
+}
+
+/*
+  natives visible to a program by name, globals shadow them
+*/
+#[derive(Debug, Default, Clone)]
+pub struct NativeRegistry {
+    natives: HashMap<String, Rc<Native>>,
+}
+
+impl NativeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // registry holding the builtins every program starts with
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        super::builtins::register(&mut registry);
+        registry
+    }
+
+    /*
+      registers a native, replacing any previous one with the same name
+    */
+    pub fn register<F>(&mut self, name: &str, arity: Arity, doc: &str, function: F)
+    where
+        F: Fn(Vec<Object>) -> Result<Object, RuntimeError> + 'static,
+    {
+        let function = move |_: &mut dyn Caller, args| function(args);
+        let native = Native::new(name.to_owned(), arity, doc.to_owned(), Rc::new(function));
+        self.natives.insert(name.to_owned(), Rc::new(native));
+    }
+
+    /*
+      registers a native that calls back into the running interpreter,
+      such as one taking a script function as an argument
+    */
+    pub fn register_with_caller<F>(&mut self, name: &str, arity: Arity, doc: &str, function: F)
+    where
+        F: Fn(&mut dyn Caller, Vec<Object>) -> Result<Object, RuntimeError> + 'static,
+    {
+        let native = Native::new(name.to_owned(), arity, doc.to_owned(), Rc::new(function));
+        self.natives.insert(name.to_owned(), Rc::new(native));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Object> {
+        self.natives.get(name).map(|native| Object::Native(native.clone()))
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Rc<Native>> {
+        self.natives.get(name).cloned()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.natives.contains_key(name)
+    }
+
+    pub fn doc(&self, name: &str) -> Option<&str> {
+        self.natives.get(name).map(|native| native.doc.as_str())
+    }
+
+    // registered names in sorted order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.natives.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
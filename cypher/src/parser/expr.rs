@@ -51,6 +51,37 @@ pub enum Statement {
     ReturnStatement {
         expr: Option<Expression>,
     },
+    ClassDeclaration {
+        name: Token,
+        superclass: Option<Token>,
+        // FunctionDeclarations
+        methods: Vec<Statement>,
+    },
+    // import "path" as alias
+    ImportStatement {
+        path: Token,
+        alias: Token,
+    },
+    // from "path" import a, b
+    FromImportStatement {
+        path: Token,
+        names: Vec<Token>,
+    },
+    ExportStatement {
+        declaration: Box<Statement>,
+    },
+    // throw expr, keyword gives the line
+    ThrowStatement {
+        keyword: Token,
+        expr: Expression,
+    },
+    // try { } catch name { } finally { }, catch or finally may be left out
+    TryStatement {
+        body: Box<Statement>,
+        name: Option<Token>,
+        handler: Option<Box<Statement>>,
+        finally: Option<Box<Statement>>,
+    },
 }
 
 #[derive(Debug, Serialize, DisplayAsJsonPretty,Clone)]
@@ -90,6 +121,15 @@ pub enum Expression {
         identifier:Token,
         exp:Box<Expression>
     },
+    SuperExpression{
+        keyword:Token,
+        method:Token
+    },
+    SetExpression{
+        identifier:Token,
+        exp:Box<Expression>,
+        value:Box<Expression>
+    },
     Literal {
         value: Token,
     },
@@ -1,4 +1,5 @@
 
 
+#[allow(clippy::module_inception)]
 pub mod parser;
 pub mod expr;
\ No newline at end of file
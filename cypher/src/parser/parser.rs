@@ -1,7 +1,7 @@
 use std::vec;
 
 use crate::{
-    errors::syntax::SyntaxError,
+    errors::{lexical::LexicalError, syntax::SyntaxError, ParseError},
     lexer::{
         scanner::Scanner,
         token::{Token, TokenType},
@@ -16,6 +16,10 @@ pub struct Parser<'a> {
     current_token: Option<Token>,
     next_token: Option<Token>,
     lines: &'a Vec<&'a str>,
+    // first lexical error, parsing stops at it as if the file ended there
+    lexical_error: Option<LexicalError>,
+    // nesting of the block being parsed, exports only happen at zero
+    block_depth: usize,
 }
 
 impl<'a> Parser<'a> {
@@ -30,6 +34,8 @@ impl<'a> Parser<'a> {
             current_token: None,
             next_token: None,
             lines,
+            lexical_error: None,
+            block_depth: 0,
         }
     }
 
@@ -40,8 +46,12 @@ impl<'a> Parser<'a> {
                 self.next_token = Some(token);
             }
             Err(err) => {
-                println!("{}", err);
-                std::process::exit(1);
+                let eof = Token::new(TokenType::Eof, err.location, None);
+                self.current_token = self.next_token.take();
+                self.next_token = Some(eof);
+                if self.lexical_error.is_none() {
+                    self.lexical_error = Some(err);
+                }
             }
         }
     }
@@ -67,8 +77,12 @@ impl<'a> Parser<'a> {
         false
     }
 
+    // skips to just after the next token of the type, or up to the end of the input
     fn consume(&mut self, token_type: TokenType) {
         while let Some(token) = self.current_token.as_ref() {
+            if token.kind == TokenType::Eof {
+                break;
+            }
             if token.kind == token_type {
                 self.advance();
                 break;
@@ -79,7 +93,7 @@ impl<'a> Parser<'a> {
 
     fn generate_syntax_error(&self, message: String) -> Result<Expression, SyntaxError> {
         let location = self.lexer.get_location();
-        let line = self.lines[(location.line - 1) as usize];
+        let line = self.source_line(location.line);
 
         Err(SyntaxError::new(
             self.file_name.clone(),
@@ -90,19 +104,31 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn generate_syntax_error_for_statements(
-        &self,
-        message: String,
-    ) -> Result<Statement, SyntaxError> {
-        let location = self.current_token.as_ref().unwrap().get_location();
-        let line = self.lines[location.line as usize - 1];
-        Err(SyntaxError::new(
+    fn syntax_error(&self, message: String) -> SyntaxError {
+        // a token taken at the end of the input leaves none, the lexer stopped there
+        let location = match self.current_token.as_ref() {
+            Some(token) => token.get_location(),
+            None => self.lexer.get_location(),
+        };
+        let line = self.source_line(location.line);
+        SyntaxError::new(
             self.file_name.clone(),
             location.line,
             location.col,
             message,
             line.to_owned(),
-        ))
+        )
+    }
+
+    fn source_line(&self, line: u32) -> &str {
+        line.checked_sub(1).and_then(|index| self.lines.get(index as usize)).copied().unwrap_or_default()
+    }
+
+    fn generate_syntax_error_for_statements(
+        &self,
+        message: String,
+    ) -> Result<Statement, SyntaxError> {
+        Err(self.syntax_error(message))
     }
 
     pub fn generate_json(statements: Vec<Statement>) -> String {
@@ -114,7 +140,20 @@ impl<'a> Parser<'a> {
         json
     }
 
+    /*
+      parses the whole file, printing the first error and exiting
+    */
     pub fn parse_program(&mut self) -> Program {
+        match self.try_parse_program() {
+            Ok(program) => program,
+            Err(err) => {
+                println!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pub fn try_parse_program(&mut self) -> Result<Program, ParseError> {
         let mut statements = vec![];
         self.advance();
         self.advance();
@@ -123,33 +162,50 @@ impl<'a> Parser<'a> {
                 if token.kind == TokenType::Eof {
                     break;
                 }
-                let statement = self.declaration();
-                if statement.is_ok() {
-                    statements.push(statement.unwrap());
-                } else {
-                    let err = statement.err();
-                    println!("{}", err.unwrap());
-                    std::process::exit(1);
+                if token.kind == TokenType::SemiColon {
+                    self.advance();
+                    continue;
+                }
+                match self.declaration() {
+                    Ok(statement) => statements.push(statement),
+                    Err(err) => {
+                        if let Some(err) = self.lexical_error.take() {
+                            return Err(ParseError::Lexical(err));
+                        }
+                        return Err(ParseError::Syntax(err));
+                    }
                 }
             }
         }
 
-        Program::new(statements)
+        if let Some(err) = self.lexical_error.take() {
+            return Err(ParseError::Lexical(err));
+        }
+        Ok(Program::new(statements))
     }
 
     fn declaration(&mut self) -> Result<Statement, SyntaxError> {
         /*
 
-         declaration    → varDecl | statement |function-declaration;
+         declaration    → varDecl | statement |function-declaration | classDecl
+                        | import | fromImport | export;
 
         */
 
         match self.current_token.clone() {
-            Some(token) => {
+            Some(_) => {
                 if self.check_token(TokenType::Let) {
                     self.parse_variable_declaration()
                 } else if self.check_token(TokenType::Function) {
                     self.parse_function_declaration()
+                } else if self.check_token(TokenType::Class) {
+                    self.parse_class_declaration()
+                } else if self.check_token(TokenType::Import) {
+                    self.parse_import()
+                } else if self.check_token(TokenType::From) {
+                    self.parse_from_import()
+                } else if self.check_token(TokenType::Export) {
+                    self.parse_export()
                 } else {
                     self.parse_statements()
                 }
@@ -167,6 +223,8 @@ impl<'a> Parser<'a> {
                 TokenType::While => self.parse_while_statements(),
                 TokenType::For => self.parse_for_statements(),
                 TokenType::Return => self.parse_return_statement(),
+                TokenType::Throw => self.parse_throw_statement(),
+                TokenType::Try => self.parse_try_statement(),
                 TokenType::LBrace => self.parse_block(),
                 _ => self.expression_statement(),
             },
@@ -176,15 +234,64 @@ impl<'a> Parser<'a> {
 
     fn parse_return_statement(&mut self) -> Result<Statement, SyntaxError> {
         self.advance();
-        let expr = self.parse_expression();
+        if self.check_token(TokenType::RBrace)
+            || self.check_token(TokenType::SemiColon)
+            || self.check_token(TokenType::Eof)
+        {
+            return Ok(Statement::ReturnStatement { expr: None });
+        }
+        let expr = self.parse_expression()?;
+
+        Ok(Statement::ReturnStatement { expr: Some(expr) })
+    }
+
+    fn parse_throw_statement(&mut self) -> Result<Statement, SyntaxError> {
+        /*
+          throwStatement -> "throw" expression
+        */
+        let keyword = self.current_token.clone().unwrap();
+        self.advance();
+        let expr = self.parse_expression()?;
+        Ok(Statement::ThrowStatement { keyword, expr })
+    }
+
+    fn parse_try_statement(&mut self) -> Result<Statement, SyntaxError> {
+        /*
+          tryStatement -> "try" block ( "catch" IDENTIFIER block )? ( "finally" block )?
+        */
+        self.advance();
+        if !self.check_token(TokenType::LBrace) {
+            return self.generate_syntax_error_for_statements("expected { after try".to_owned());
+        }
+        let body = self.parse_block()?;
 
-        if expr.is_err() {
-            println!("{}", expr.unwrap());
-            std::process::exit(1);
+        let (mut name, mut handler) = (None, None);
+        if self.check_token(TokenType::Catch) {
+            self.advance();
+            name = Some(self.parse_import_name()?);
+            if !self.check_token(TokenType::LBrace) {
+                return self.generate_syntax_error_for_statements("expected { after catch".to_owned());
+            }
+            handler = Some(Box::new(self.parse_block()?));
         }
 
-        Ok(Statement::ReturnStatement {
-            expr: Some(expr.unwrap()),
+        let mut finally = None;
+        if self.check_token(TokenType::Finally) {
+            self.advance();
+            if !self.check_token(TokenType::LBrace) {
+                return self.generate_syntax_error_for_statements("expected { after finally".to_owned());
+            }
+            finally = Some(Box::new(self.parse_block()?));
+        }
+
+        if handler.is_none() && finally.is_none() {
+            return self.generate_syntax_error_for_statements("expected catch or finally after try".to_owned());
+        }
+        Ok(Statement::TryStatement {
+            body: Box::new(body),
+            name,
+            handler,
+            finally,
         })
     }
 
@@ -196,6 +303,9 @@ impl<'a> Parser<'a> {
                 self.advance();
                 continue;
             }
+            if self.check_token(TokenType::LBrace) || self.check_token(TokenType::Eof) {
+                break;
+            }
             args.push(self.current_token.clone().unwrap());
             self.advance();
         }
@@ -215,43 +325,26 @@ impl<'a> Parser<'a> {
                         .generate_syntax_error_for_statements("expected identifier".to_owned());
                 }
                 let ident = token;
-                let mut args: Vec<Token> = vec![];
                 self.advance();
                 if self.check_token(TokenType::LParen) {
                     self.advance();
 
-                    loop {
-                        if self.check_token(TokenType::RParen) {
-                            break;
-                        }
-                        if self.check_token(TokenType::LBrace) {
-                            return self
-                                .generate_syntax_error_for_statements("expected )".to_string());
-                        }
-                        if self.check_token(TokenType::Comma) {
-                            self.advance();
-                            continue;
-                        }
-                        args.push(self.current_token.clone().unwrap());
-                        self.advance();
-                    }
-                    args = self.parse_function_args();
+                    let args = self.parse_function_args();
 
                     self.advance();
 
                     if self.check_token(TokenType::LBrace) {
                         let body = self.parse_block();
 
-                        if body.is_err() {
-                            println!("{}", body.err().unwrap());
-                            std::process::exit(1)
+                        if let Err(err) = body {
+                            return Err(err);
                         }
 
-                        return Ok(Statement::FunctionDeclaration {
+                        Ok(Statement::FunctionDeclaration {
                             name: ident,
                             args,
                             body: Box::new(body.unwrap()),
-                        });
+                        })
                     } else {
                         self.generate_syntax_error_for_statements("expected {".to_string())
                     }
@@ -263,59 +356,169 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn parse_class_declaration(&mut self) -> Result<Statement, SyntaxError> {
+        /*
+          classDecl -> "class" IDENTIFIER ( "<" IDENTIFIER )? "{" <function>* "}"
+        */
+        self.advance();
+        let name = match self.current_token.clone() {
+            Some(token) if token.kind == TokenType::Identifier => token,
+            _ => {
+                return self
+                    .generate_syntax_error_for_statements("expected class name".to_owned())
+            }
+        };
+        self.advance();
+        let mut superclass = None;
+        if self.check_token(TokenType::LessThan) {
+            self.advance();
+            if !self.check_token(TokenType::Identifier) {
+                return self
+                    .generate_syntax_error_for_statements("expected superclass name".to_owned());
+            }
+            superclass = self.current_token.clone();
+            self.advance();
+        }
+        if !self.check_token(TokenType::LBrace) {
+            return self.generate_syntax_error_for_statements("expected {".to_string());
+        }
+        self.advance();
+
+        let mut methods = vec![];
+        while !self.check_token(TokenType::RBrace) {
+            if self.check_token(TokenType::SemiColon) {
+                self.advance();
+                continue;
+            }
+            if !self.check_token(TokenType::Function) {
+                return self.generate_syntax_error_for_statements(
+                    "expected method declaration or }".to_owned(),
+                );
+            }
+            methods.push(self.parse_function_declaration()?);
+        }
+        self.advance();
+        Ok(Statement::ClassDeclaration {
+            name,
+            superclass,
+            methods,
+        })
+    }
+
+    fn parse_module_path(&mut self) -> Result<Token, SyntaxError> {
+        match self.current_token.clone() {
+            Some(token) if token.kind == TokenType::String => {
+                self.advance();
+                Ok(token)
+            }
+            _ => Err(self.syntax_error("expected module path string".to_owned())),
+        }
+    }
+
+    fn parse_import_name(&mut self) -> Result<Token, SyntaxError> {
+        match self.current_token.clone() {
+            Some(token) if token.kind == TokenType::Identifier => {
+                self.advance();
+                Ok(token)
+            }
+            _ => Err(self.syntax_error("expected identifier".to_owned())),
+        }
+    }
+
+    fn parse_import(&mut self) -> Result<Statement, SyntaxError> {
+        /*
+          import -> "import" STRING "as" IDENTIFIER
+        */
+        self.advance();
+        let path = self.parse_module_path()?;
+        if !self.check_token(TokenType::As) {
+            return self.generate_syntax_error_for_statements("expected as".to_owned());
+        }
+        self.advance();
+        let alias = self.parse_import_name()?;
+        Ok(Statement::ImportStatement { path, alias })
+    }
+
+    fn parse_from_import(&mut self) -> Result<Statement, SyntaxError> {
+        /*
+          fromImport -> "from" STRING "import" IDENTIFIER ( "," IDENTIFIER )*
+        */
+        self.advance();
+        let path = self.parse_module_path()?;
+        if !self.check_token(TokenType::Import) {
+            return self.generate_syntax_error_for_statements("expected import".to_owned());
+        }
+        self.advance();
+        let mut names = vec![self.parse_import_name()?];
+        while self.check_token(TokenType::Comma) {
+            self.advance();
+            names.push(self.parse_import_name()?);
+        }
+        Ok(Statement::FromImportStatement { path, names })
+    }
+
+    fn parse_export(&mut self) -> Result<Statement, SyntaxError> {
+        /*
+          export -> "export" ( varDecl | function | classDecl )
+        */
+        if self.block_depth > 0 {
+            return self.generate_syntax_error_for_statements(
+                "export is only allowed at the top level of a module".to_owned(),
+            );
+        }
+        self.advance();
+        let declaration = if self.check_token(TokenType::Let) {
+            self.parse_variable_declaration()?
+        } else if self.check_token(TokenType::Function) {
+            self.parse_function_declaration()?
+        } else if self.check_token(TokenType::Class) {
+            self.parse_class_declaration()?
+        } else {
+            return self.generate_syntax_error_for_statements(
+                "expected let, def or class after export".to_owned(),
+            );
+        };
+        Ok(Statement::ExportStatement {
+            declaration: Box::new(declaration),
+        })
+    }
+
     fn parse_for_statements(&mut self) -> Result<Statement, SyntaxError> {
+        /*
+          forStatement -> "for" <varDecl> ";" <expression> ";" <expression> <block>
+        */
         self.advance();
         match self.current_token.clone() {
-            Some(token) => {
-                let mut increment: Option<Expression> = None;
-                let mut initializer: Option<Statement> = None;
-                if self.check_token(TokenType::Let) {
-                    match self.parse_variable_declaration() {
-                        Ok(statement) => {
-                            initializer = Some(statement);
-                        }
-                        Err(err) => {
-                            println!("{}", err);
-                            std::process::exit(1)
-                        }
-                    }
+            Some(_) => {
+                let initializer = if self.check_token(TokenType::Let) {
+                    self.parse_variable_declaration()?
                 } else {
                     return self.generate_syntax_error_for_statements(
                         "loop variable should be initialized".to_owned(),
                     );
-                }
+                };
 
                 self.consume(TokenType::SemiColon);
 
-                let end = self.parse_expression();
-
-                if end.is_err() {
-                    println!("{}", end.unwrap());
-                    std::process::exit(1)
-                }
+                let end = self.parse_expression()?;
 
-                if self.check_token(TokenType::SemiColon) {
-                    self.advance();
-
-                    let expr = self.parse_expression();
-                    if expr.is_ok() {
-                        increment = Some(expr.unwrap());
-                    } else {
-                        println!("{}", expr.unwrap());
-                    }
+                if !self.check_token(TokenType::SemiColon) {
+                    return self.generate_syntax_error_for_statements("expected ;".to_string());
                 }
+                self.advance();
+                let increment = self.parse_expression()?;
 
                 if self.check_token(TokenType::LBrace) {
-                    let body = self.parse_block();
+                    let body = self.parse_block()?;
 
                     Ok(Statement::ForStatement {
-                        initializer: Box::new(initializer.unwrap()),
-                        condition: end.unwrap(),
-                        increment: increment.unwrap(),
-                        body: Box::new(body.unwrap()),
+                        initializer: Box::new(initializer),
+                        condition: end,
+                        increment,
+                        body: Box::new(body),
                     })
                 } else {
-                    self.generate_syntax_error_for_statements("".to_string())
+                    self.generate_syntax_error_for_statements("expected {".to_string())
                 }
             }
             None => self.generate_syntax_error_for_statements("".to_string()),
@@ -325,19 +528,19 @@ impl<'a> Parser<'a> {
     fn parse_while_statements(&mut self) -> Result<Statement, SyntaxError> {
         self.advance();
         match self.current_token.clone() {
-            Some(token) => {
+            Some(_) => {
                 if self.check_token(TokenType::LBrace) {
                     return self
                         .generate_syntax_error_for_statements("expected condition".to_owned());
                 }
-                let expr = self.parse_expression();
+                let expr = self.parse_expression()?;
 
                 if self.check_token(TokenType::LBrace) {
-                    let body = self.parse_block();
+                    let body = self.parse_block()?;
 
                     Ok(Statement::WhileStatement {
-                        condition: expr.unwrap(),
-                        body: Box::new(body.unwrap()),
+                        condition: expr,
+                        body: Box::new(body),
                     })
                 } else {
                     self.generate_syntax_error_for_statements("expected do keyword".to_string())
@@ -351,18 +554,28 @@ impl<'a> Parser<'a> {
         self.advance();
         let mut statements: Vec<Statement> = vec![];
 
+        self.block_depth += 1;
         while !self.check_token(TokenType::RBrace) {
-            let stmt = self.declaration();
-            if stmt.is_ok() {
-                statements.push(stmt.unwrap());
+            if self.check_token(TokenType::Eof) {
+                break;
+            }
+            if self.check_token(TokenType::SemiColon) {
+                self.advance();
+                continue;
+            }
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(err) => {
+                    self.block_depth -= 1;
+                    return Err(err);
+                }
             }
         }
+        self.block_depth -= 1;
 
         if self.check_token(TokenType::RBrace) {
             self.advance();
-            return Ok(Statement::BlockStatement {
-                statements: statements,
-            });
+            return Ok(Statement::BlockStatement { statements });
         }
 
         self.generate_syntax_error_for_statements("expected }".to_string())
@@ -374,54 +587,35 @@ impl<'a> Parser<'a> {
         */
 
         match self.current_token.clone() {
-            Some(token) => {
+            Some(_) => {
                 self.advance();
                 if self.check_token(TokenType::LBrace) {
                     return self
                         .generate_syntax_error_for_statements("expected condition".to_owned());
                 }
-                let expr = self.parse_expression();
-                // self.advance();
-                if expr.is_err() {
-                    return self.generate_syntax_error_for_statements(expr.unwrap().to_string());
-                }
-                let mut else_statement: Option<Statement> = None;
-                let mut then_statement: Option<Statement> = None;
-
-                if self.check_token(TokenType::LBrace) {
-                    let stmt = self.parse_block();
-                    if stmt.is_err() {
-                        return stmt;
-                    }
+                let expr = self.parse_expression()?;
 
-                    then_statement = Some(stmt.unwrap());
+                if !self.check_token(TokenType::LBrace) {
+                    return self.generate_syntax_error_for_statements("expected {".to_owned());
                 }
+                let then_statement = self.parse_block()?;
 
-                // self.advance();
-
+                let mut else_statement: Option<Statement> = None;
                 if self.check_token(TokenType::Else) {
                     self.advance();
-                    if self.check_token(TokenType::LBrace) {
-                        let stmt = self.parse_block();
-                        if stmt.is_ok() {
-                            else_statement = Some(stmt.unwrap())
-                        }
+                    if self.check_token(TokenType::If) {
+                        else_statement = Some(self.parse_if_statment()?);
+                    } else if self.check_token(TokenType::LBrace) {
+                        else_statement = Some(self.parse_block()?);
+                    } else {
+                        return self.generate_syntax_error_for_statements("expected {".to_owned());
                     }
-                    // self.advance();
-                }
-
-                if else_statement.is_some() {
-                    return Ok(Statement::IFStatement {
-                        condition: expr.unwrap(),
-                        then: Box::new(then_statement.unwrap()),
-                        _else: Some(Box::new(else_statement.unwrap())),
-                    });
                 }
 
                 Ok(Statement::IFStatement {
-                    condition: expr.unwrap(),
-                    then: Box::new(then_statement.unwrap()),
-                    _else: None,
+                    condition: expr,
+                    then: Box::new(then_statement),
+                    _else: else_statement.map(Box::new),
                 })
             }
             None => self.generate_syntax_error_for_statements("end of file error".to_string()),
@@ -435,6 +629,11 @@ impl<'a> Parser<'a> {
         self.advance();
         match self.current_token.clone() {
             Some(token) => {
+                if token.kind != TokenType::Identifier {
+                    return self.generate_syntax_error_for_statements(
+                        "expected identifier after let".to_owned(),
+                    );
+                }
                 let identifier = token;
                 self.advance();
 
@@ -452,28 +651,21 @@ impl<'a> Parser<'a> {
                                     "Expected Function Body".to_owned(),
                                 );
                             }
-                            let function_body = self.parse_block();
-                            if function_body.is_err() {
-                                return function_body;
-                            }
+                            let function_body = self.parse_block()?;
                             return Ok(Statement::FunctionDeclaration {
                                 name: identifier,
                                 args,
-                                body: Box::new(function_body.unwrap()),
+                                body: Box::new(function_body),
                             });
                         }
                     } else {
                         let exp = self.parse_expression();
-                        if exp.is_ok() {
-                            expr = Some(Box::new(Statement::ExpressionStatement {
-                                expr: exp.unwrap(),
-                            }))
+                        if let Ok(exp) = exp {
+                            expr = Some(Box::new(Statement::ExpressionStatement { expr: exp }))
                         } else {
-                            let err = self.generate_syntax_error_for_statements(
+                            return self.generate_syntax_error_for_statements(
                                 "expected assignment expression".to_string(),
                             );
-                            println!("{}", err.unwrap());
-                            std::process::exit(1);
                         }
                     }
                 }
@@ -493,81 +685,76 @@ impl<'a> Parser<'a> {
             Some(token) => {
                 if token.kind == TokenType::LParen {
                     let expr = self.parse_expression();
-                    if expr.is_ok() {
-                        return Ok(Statement::PrintStatement {
-                            expr: expr.unwrap(),
-                        });
+                    if let Ok(expr) = expr {
+                        return Ok(Statement::PrintStatement { expr });
                     } else {
                         return self.generate_syntax_error_for_statements(
                             "expected 1 or more arguments,found 0".to_string(),
                         );
                     }
                 }
-                println!("unexpected token");
-                std::process::exit(1)
+                self.generate_syntax_error_for_statements("unexpected token".to_string())
             }
             None => self.generate_syntax_error_for_statements("end of file".to_string()),
         }
     }
 
     fn expression_statement(&mut self) -> Result<Statement, SyntaxError> {
-        let expr = self.parse_expression();
-        if expr.is_ok() {
-            return Ok(Statement::ExpressionStatement {
-                expr: expr.unwrap(),
-            });
-        }
-
-        self.generate_syntax_error_for_statements("expected expression".to_owned())
+        let expr = self.parse_expression()?;
+        Ok(Statement::ExpressionStatement { expr })
     }
 
     pub fn parse_expression(&mut self) -> Result<Expression, SyntaxError> {
-        if self.check_token(TokenType::LSqBracket){
-            let mut elements: Vec<Expression> =vec![];
-            self.advance();
-            while !self.check_token(TokenType::RSqBracket) {
-                if self.check_token(TokenType::Comma){
-                    self.advance();
-                    continue;
-                }
-                let element=self.parse_expression();
-                if element.is_err() {
-                    return element;
-                }
-                elements.push(element.unwrap())
+        self.parse_variable_reassignment()
+    }
+
+    fn parse_array_declaration(&mut self) -> Result<Expression, SyntaxError> {
+        /*
+          array → "[" ( <expression> ( "," <expression> )* )? "]"
+        */
+        let mut elements: Vec<Expression> =vec![];
+        self.advance();
+        while !self.check_token(TokenType::RSqBracket) {
+            if self.check_token(TokenType::Comma){
+                self.advance();
+                continue;
             }
-            self.advance();
-           return  Ok(Expression::ArrayDeclaration { elements })
+            if self.check_token(TokenType::Eof) {
+                return self.generate_syntax_error("expected ]".to_owned());
+            }
+            let element = self.parse_expression()?;
+            elements.push(element)
         }
-        self.parse_variable_reassignment()
+        self.advance();
+        Ok(Expression::ArrayDeclaration { elements })
     }
 
     fn parse_variable_reassignment(&mut self) -> Result<Expression, SyntaxError> {
-        if self.check_token(TokenType::Identifier) {
-            match self.current_token.clone() {
-                Some(token) => {
-                    if self.check_next_token(TokenType::Assign) {
-                        let ident = token;
-                        self.advance();
-                        self.advance();
-                        let expr = self.parse_variable_reassignment();
-                        if expr.is_ok() {
-                            return Ok(Expression::VariableAssignment {
-                                identifier: ident,
-                                expr: Box::new(expr.unwrap()),
-                            });
-                        } else {
-                            println!("{}", expr.unwrap());
-                            std::process::exit(1)
-                        }
-                    } else {
-                        return self.equality();
-                    }
-                }
-                _ => return self.generate_syntax_error("eof error".to_string()),
+        if self.check_token(TokenType::Identifier) && self.check_next_token(TokenType::Assign) {
+            let ident = self.current_token.clone().unwrap();
+            self.advance();
+            self.advance();
+            let expr = self.parse_expression()?;
+            return Ok(Expression::VariableAssignment {
+                identifier: ident,
+                expr: Box::new(expr),
+            });
+        }
+        let expr = self.parse_logical_or()?;
+        if self.check_token(TokenType::Assign) {
+            // obj.field = value
+            if let Expression::GetExpression { identifier, exp } = expr {
+                self.advance();
+                let value = self.parse_expression()?;
+                return Ok(Expression::SetExpression {
+                    identifier,
+                    exp,
+                    value: Box::new(value),
+                });
             }
+            return self.generate_syntax_error("invalid assignment target".to_owned());
         }
-        self.parse_logical_or()
+        Ok(expr)
     }
 
     fn parse_logical_or(&mut self) -> Result<Expression, SyntaxError> {
@@ -577,9 +764,9 @@ impl<'a> Parser<'a> {
             self.advance();
             let rhs = self.parse_logical_and();
             lhs = Ok(Expression::BinaryExpression {
-                lhs: Box::new(lhs.unwrap()),
+                lhs: Box::new(lhs?),
                 op,
-                rhs: Box::new(rhs.unwrap()),
+                rhs: Box::new(rhs?),
             })
         }
         lhs
@@ -592,9 +779,9 @@ impl<'a> Parser<'a> {
             self.advance();
             let rhs = self.equality();
             lhs = Ok(Expression::BinaryExpression {
-                lhs: Box::new(lhs.unwrap()),
+                lhs: Box::new(lhs?),
                 op,
-                rhs: Box::new(rhs.unwrap()),
+                rhs: Box::new(rhs?),
             })
         }
 
@@ -603,18 +790,21 @@ impl<'a> Parser<'a> {
 
     fn equality(&mut self) -> Result<Expression, SyntaxError> {
         /*
-        equality       → comparison ( ( "!=" | "==" ) comparison )* ;
+        equality       → comparison ( ( "!=" | "==" | "is" ) comparison )* ;
         */
         let mut lhs = self.comparison();
 
-        while self.check_token(TokenType::NotEqual) || self.check_token(TokenType::Equal) {
+        while self.check_token(TokenType::NotEqual)
+            || self.check_token(TokenType::Equal)
+            || self.check_token(TokenType::Is)
+        {
             let op = self.current_token.take().unwrap();
             self.advance();
             let rhs = self.comparison();
             lhs = Ok(Expression::BinaryExpression {
-                lhs: Box::new(lhs.unwrap()),
+                lhs: Box::new(lhs?),
                 op,
-                rhs: Box::new(rhs.unwrap()),
+                rhs: Box::new(rhs?),
             })
         }
 
@@ -636,9 +826,9 @@ impl<'a> Parser<'a> {
             self.advance();
             let rhs = self.term();
             lhs = Ok(Expression::BinaryExpression {
-                lhs: Box::new(lhs.unwrap()),
+                lhs: Box::new(lhs?),
                 op,
-                rhs: Box::new(rhs.unwrap()),
+                rhs: Box::new(rhs?),
             })
         }
 
@@ -660,9 +850,9 @@ impl<'a> Parser<'a> {
             //     return self.generate_syntax_error("expression expected".to_owned());
             // }
             lhs = Ok(Expression::BinaryExpression {
-                lhs: Box::new(lhs.unwrap()),
+                lhs: Box::new(lhs?),
                 op,
-                rhs: Box::new(rhs.unwrap()),
+                rhs: Box::new(rhs?),
             })
         }
 
@@ -671,57 +861,69 @@ impl<'a> Parser<'a> {
 
     fn factor(&mut self) -> Result<Expression, SyntaxError> {
         /*
-        factor  → unary ( ( "/" | "*" ) unary )* ;
+        factor  → unary ( ( "/" | "*" | "%" | "~/" ) unary )* ;
          */
-        let mut lhs = self.unary();
+        let mut lhs = self.unary()?;
 
         while self.check_token(TokenType::Modulo)
             || self.check_token(TokenType::Slash)
             || self.check_token(TokenType::Star)
+            || self.check_token(TokenType::IntDivide)
         {
             let op = self.current_token.clone().unwrap();
             self.advance();
-            let rhs = self.unary();
-            lhs = Ok(Expression::BinaryExpression {
-                lhs: Box::new(lhs.unwrap()),
+            let rhs = self.unary()?;
+            lhs = Expression::BinaryExpression {
+                lhs: Box::new(lhs),
                 op,
-                rhs: Box::new(rhs.unwrap()),
-            })
+                rhs: Box::new(rhs),
+            }
         }
 
-        lhs
+        Ok(lhs)
     }
 
     fn unary(&mut self) -> Result<Expression, SyntaxError> {
         match self.current_token.clone() {
             Some(token) => {
-                if self.check_token(TokenType::Not) || self.check_token(TokenType::Minus) {
+                if self.check_token(TokenType::Not)
+                    || self.check_token(TokenType::Bang)
+                    || self.check_token(TokenType::Minus)
+                {
                     let op = token;
                     self.advance();
-                    let expr = self.unary();
-                    if expr.is_ok() {
-                        Ok(Expression::UnaryExpression {
-                            op: op.clone(),
-                            rhs: Box::new(expr.unwrap()),
-                        })
-                    } else {
-                        println!("{}", expr.unwrap());
-                        std::process::exit(1);
-                    }
+                    let expr = self.unary()?;
+                    Ok(Expression::UnaryExpression {
+                        op,
+                        rhs: Box::new(expr),
+                    })
                 } else {
-                    let expr = self.parse_increment_decrement();
-                    if expr.is_ok() {
-                        return expr;
-                    } else {
-                        println!("{}", expr.unwrap());
-                        std::process::exit(1);
-                    }
+                    self.power()
                 }
             }
             None => self.generate_syntax_error("Eof parsing error".to_string()),
         }
     }
 
+    fn power(&mut self) -> Result<Expression, SyntaxError> {
+        /*
+        power  → increment ( "**" unary )? ;
+        right associative and tighter than a unary minus on its left
+         */
+        let base = self.parse_increment_decrement()?;
+        if !self.check_token(TokenType::Power) {
+            return Ok(base);
+        }
+        let op = self.current_token.clone().unwrap();
+        self.advance();
+        let exponent = self.unary()?;
+        Ok(Expression::BinaryExpression {
+            lhs: Box::new(base),
+            op,
+            rhs: Box::new(exponent),
+        })
+    }
+
     fn parse_increment_decrement(&mut self) -> Result<Expression, SyntaxError> {
         match self.current_token.clone() {
             Some(token) => {
@@ -734,17 +936,15 @@ impl<'a> Parser<'a> {
 
                     let operator = self.current_token.clone().unwrap();
                     self.advance();
-                    return Ok(Expression::IncrementDecrement {
+                    Ok(Expression::IncrementDecrement {
                         op: operator,
                         identifier: ident,
-                    });
+                    })
                 } else {
                     let expr = self.parse_array_index();
 
-                    if expr.is_err() {
-                        let err = expr.err().unwrap();
-                        println!("{}", err);
-                        std::process::exit(1)
+                    if let Err(err) = expr {
+                        return Err(err);
                     }
 
                     expr
@@ -755,26 +955,22 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_array_index(&mut self) ->Result<Expression,SyntaxError>{
-        let expr=self.parse_property_access();
-        if expr.is_err() {
-            return expr;
-        }
+        let mut expr=self.parse_property_access()?;
 
-        if self.check_token(TokenType::LSqBracket){
+        while self.check_token(TokenType::LSqBracket){
             self.advance();
-            let index=self.parse_property_access();
-            if index.is_err(){
-                return index;
+            let index=self.parse_expression()?;
+            if !self.check_token(TokenType::RSqBracket) {
+                return self.generate_syntax_error("expected ]".to_owned());
             }
             self.advance();
-            return Ok(Expression::ArrayIndexing { ident: Box::new(expr.unwrap()), index: Box::new(index.unwrap()) })
+            expr = Expression::ArrayIndexing { ident: Box::new(expr), index: Box::new(index) };
         }
-        expr
+        Ok(expr)
     }
 
     fn parse_property_access(&mut self) -> Result<Expression, SyntaxError> {
-        let current_token = self.current_token.clone();
-        let mut expr = self.primary();
+        let mut expr = self.primary()?;
         // self.advance();
         loop {
             if self.check_token(TokenType::LParen) {
@@ -783,44 +979,44 @@ impl<'a> Parser<'a> {
                 while !self.check_token(TokenType::RParen) {
                     if self.check_token(TokenType::Comma) {
                         self.advance();
+                        continue;
                     }
-                    let expression = self.parse_expression();
-                    if expression.is_err() {
-                        println!("{}", expression.unwrap());
-                        std::process::exit(1);
+                    if self.check_token(TokenType::Eof) {
+                        return self.generate_syntax_error("expected )".to_owned());
+                    }
+                    match self.parse_expression() {
+                        Ok(expression) => args.push(expression),
+                        Err(err) => return Err(err),
                     }
-
-                    args.push(expression.unwrap());
                 }
                 self.advance();
-                expr=Ok(Expression::FunctionCall {
-                    calle: Box::new(expr.unwrap()),
+                expr=Expression::FunctionCall {
+                    calle: Box::new(expr),
                     args,
-                });
-            }
-            if self.check_token(TokenType::Dot) {
+                };
+            } else if self.check_token(TokenType::Dot) {
                 self.advance();
                 
-                let mut ident: Option<&Token> =None;
-                if self.check_token(TokenType::Identifier){
-                    ident=self.current_token.as_ref();
-                }
-                
-                if ident.is_none(){
-                    return self.generate_syntax_error("expected identifier after .".to_owned())
-                }
-                
-                expr= Ok(Expression::GetExpression {
-                    identifier: ident.unwrap().clone(),
-                    exp: Box::new(expr.unwrap()),
-                });
+                // keywords are fine as property names, as in s.repeat(2)
+                let ident = match self.current_token.as_ref() {
+                    Some(token) if token.kind == TokenType::Identifier => token.clone(),
+                    Some(token) if token.kind.is_keyword() => {
+                        Token::new(TokenType::Identifier, token.get_location(), token.value.clone())
+                    }
+                    _ => return self.generate_syntax_error("expected identifier after .".to_owned()),
+                };
+
+                expr=Expression::GetExpression {
+                    identifier: ident,
+                    exp: Box::new(expr),
+                };
 
                 self.advance();
             } else {
                 break;
             }
         }
-        expr
+        Ok(expr)
     }
 
     // fn parse_function_call(&mut self) -> Result<Expression, SyntaxError> {
@@ -856,6 +1052,7 @@ impl<'a> Parser<'a> {
     fn primary(&mut self) -> Result<Expression, SyntaxError> {
         /*
         primary        → NUMBER | STRING | "true" | "false" | "nil"
+              | "super" "." IDENTIFIER
               | "(" expression ")" ;
         */
         // println!("{:?}",self.current_token);
@@ -870,17 +1067,39 @@ impl<'a> Parser<'a> {
                     self.advance();
                     Ok(Expression::Literal { value: token })
                 }
+                TokenType::LSqBracket => {
+                    self.current_token = Some(token);
+                    self.parse_array_declaration()
+                }
+                TokenType::Super => {
+                    self.advance();
+                    if !self.check_token(TokenType::Dot) {
+                        return self.generate_syntax_error("expected . after super".to_owned());
+                    }
+                    self.advance();
+                    if !self.check_token(TokenType::Identifier) {
+                        return self.generate_syntax_error("expected method name after super.".to_owned());
+                    }
+                    let method = self.current_token.clone().unwrap();
+                    self.advance();
+                    Ok(Expression::SuperExpression { keyword: token, method })
+                }
                 TokenType::LParen => {
                     self.advance();
-                    let expr = self.parse_expression();
-                    // self.consume(TokenType::RParen);
+                    let expr = self.parse_expression()?;
+                    if !self.check_token(TokenType::RParen) {
+                        return self.generate_syntax_error("expected )".to_owned());
+                    }
                     self.advance();
 
-                    return Ok(Expression::GroupingExpression {
-                        exp: Box::new(expr.unwrap()),
-                    });
+                    Ok(Expression::GroupingExpression {
+                        exp: Box::new(expr),
+                    })
+                }
+                _ => {
+                    self.current_token = Some(token);
+                    self.generate_syntax_error("Invalid Syntax".to_string())
                 }
-                _ => self.generate_syntax_error("Invalid Syntax".to_string()),
             },
             None => self.generate_syntax_error("EOF Error".to_string()),
         }
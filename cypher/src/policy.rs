@@ -0,0 +1,129 @@
+use std::{collections::HashSet, fmt::Display};
+
+/*
+  groups of natives that reach outside the interpreter, a policy turns
+  each group on or off
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    // the fs module and importing script files
+    Fs,
+    // io.env
+    Env,
+    // io.args, io.read_line and io.exit
+    Process,
+    // the time module
+    Time,
+    // math.random, math.random_int and math.seed
+    Random,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 5] = [
+        Capability::Fs,
+        Capability::Env,
+        Capability::Process,
+        Capability::Time,
+        Capability::Random,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Capability> {
+        Capability::ALL.into_iter().find(|capability| capability.to_string() == name)
+    }
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Capability::Fs => "fs",
+            Capability::Env => "env",
+            Capability::Process => "process",
+            Capability::Time => "time",
+            Capability::Random => "random",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/*
+  what a program may do. the default allows everything, sandboxed
+  starts with nothing allowed for running untrusted scripts:
+
+    let policy = Policy::sandboxed()
+        .allow(Capability::Time)
+        .with_instruction_limit(1_000_000)
+        .with_memory_limit(16 << 20);
+
+  the limits are enforced by the vm only
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    allowed: HashSet<Capability>,
+    denied_modules: HashSet<String>,
+    // instructions a single run may execute
+    pub max_instructions: Option<u64>,
+    /*
+      bytes a single run may allocate for strings, arrays, maps and
+      objects. a running total, memory freed again is not given back so
+      a long loop can go over it while using little at a time
+    */
+    pub max_memory: Option<usize>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::permissive()
+    }
+}
+
+impl Policy {
+    pub fn permissive() -> Self {
+        Self {
+            allowed: Capability::ALL.into_iter().collect(),
+            denied_modules: HashSet::new(),
+            max_instructions: None,
+            max_memory: None,
+        }
+    }
+
+    pub fn sandboxed() -> Self {
+        Self {
+            allowed: HashSet::new(),
+            ..Self::permissive()
+        }
+    }
+
+    pub fn allow(mut self, capability: Capability) -> Self {
+        self.allowed.insert(capability);
+        self
+    }
+
+    pub fn deny(mut self, capability: Capability) -> Self {
+        self.allowed.remove(&capability);
+        self
+    }
+
+    // rejects importing the builtin module name whatever it contains
+    pub fn deny_module(mut self, name: &str) -> Self {
+        self.denied_modules.insert(name.to_owned());
+        self
+    }
+
+    pub fn with_instruction_limit(mut self, limit: u64) -> Self {
+        self.max_instructions = Some(limit);
+        self
+    }
+
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.allowed.contains(&capability)
+    }
+
+    pub fn allows_module(&self, name: &str) -> bool {
+        !self.denied_modules.contains(name)
+    }
+}
@@ -0,0 +1,310 @@
+use std::{cmp::Ordering, rc::Rc};
+
+use crate::{
+    errors::{ErrorKind, RuntimeError},
+    objects::{
+        native::{Arity, Caller, NativeRegistry},
+        Object,
+    },
+};
+
+use super::{argument_error, clamp, index_arg};
+
+/*
+  array methods, also exported as the array module. arrays are values,
+  methods that change an array return the changed copy:
+
+    a = a.push(4).insert(0, 1)
+
+  the higher order methods call their callback through the interpreter
+  running them
+*/
+pub fn register(table: &mut NativeRegistry) {
+    table.register("len", Arity::Fixed(0), "a.len() -> number of elements", |args| {
+        Ok(Object::Number(receiver(&args)?.len() as f64))
+    });
+    table.register(
+        "push",
+        Arity::Fixed(1),
+        "a.push(value) -> new array with value appended",
+        |args| {
+            let (mut arr, mut rest) = into_receiver(args)?;
+            arr.push(rest.remove(0));
+            Ok(Object::array(arr))
+        },
+    );
+    table.register("pop", Arity::Fixed(0), "a.pop() -> new array without the last element", |args| {
+        let (mut arr, _) = into_receiver(args)?;
+        if arr.pop().is_none() {
+            return Err(RuntimeError::new(ErrorKind::IndexError, "pop() on empty array".to_owned()));
+        }
+        Ok(Object::array(arr))
+    });
+    table.register(
+        "insert",
+        Arity::Fixed(2),
+        "a.insert(index, value) -> new array with value at index",
+        |args| {
+            let index = index_arg(&args, 1, "insert")?;
+            let (mut arr, mut rest) = into_receiver(args)?;
+            if index < 0 || index as usize > arr.len() {
+                return Err(out_of_range(index, arr.len()));
+            }
+            arr.insert(index as usize, rest.remove(1));
+            Ok(Object::array(arr))
+        },
+    );
+    table.register(
+        "remove",
+        Arity::Fixed(1),
+        "a.remove(index) -> new array without the element at index",
+        |args| {
+            let index = index_arg(&args, 1, "remove")?;
+            let (mut arr, _) = into_receiver(args)?;
+            if index < 0 || index as usize >= arr.len() {
+                return Err(out_of_range(index, arr.len()));
+            }
+            arr.remove(index as usize);
+            Ok(Object::array(arr))
+        },
+    );
+    table.register(
+        "slice",
+        Arity::Range(1, 2),
+        "a.slice(start, end?) -> elements from start up to end, negative indices count from the end",
+        |args| {
+            let arr = receiver(&args)?;
+            let start = clamp(index_arg(&args, 1, "slice")?, arr.len());
+            let end = match args.get(2) {
+                Some(_) => clamp(index_arg(&args, 2, "slice")?, arr.len()),
+                None => arr.len(),
+            };
+            Ok(Object::array(arr[start..end.max(start)].to_vec()))
+        },
+    );
+    table.register(
+        "concat",
+        Arity::Fixed(1),
+        "a.concat(other) -> new array with the elements of a then other",
+        |args| {
+            let (mut arr, mut rest) = into_receiver(args)?;
+            match rest.remove(0) {
+                Object::Array(other) => {
+                    arr.extend(other.iter().cloned());
+                    Ok(Object::array(arr))
+                }
+                obj => Err(argument_error("concat", "array", &obj)),
+            }
+        },
+    );
+    table.register("reverse", Arity::Fixed(0), "a.reverse() -> new array in reverse order", |args| {
+        let (mut arr, _) = into_receiver(args)?;
+        arr.reverse();
+        Ok(Object::array(arr))
+    });
+    table.register(
+        "index_of",
+        Arity::Fixed(1),
+        "a.index_of(value) -> index of the first element equal to value, -1 if missing",
+        |args| {
+            let arr = receiver(&args)?;
+            let index = arr.iter().position(|obj| *obj == args[1]);
+            Ok(Object::Number(index.map_or(-1.0, |index| index as f64)))
+        },
+    );
+    table.register(
+        "contains",
+        Arity::Fixed(1),
+        "a.contains(value) -> whether an element equals value",
+        |args| {
+            let arr = receiver(&args)?;
+            Ok(Object::Bool(arr.contains(&args[1])))
+        },
+    );
+    table.register_with_caller(
+        "sort",
+        Arity::Range(0, 1),
+        "a.sort(compare?) -> new sorted array, compare(x, y) returns a negative number when x goes first",
+        |caller, args| {
+            let (mut arr, mut rest) = into_receiver(args)?;
+            let compare = rest.pop();
+            // sort_by cannot stop early, the first error is kept and returned
+            let mut error = None;
+            arr.sort_by(|a, b| {
+                if error.is_some() {
+                    return Ordering::Equal;
+                }
+                let ordering = match &compare {
+                    Some(compare) => compare_with(caller, compare, a, b),
+                    None => compare_values(a, b),
+                };
+                ordering.unwrap_or_else(|err| {
+                    error = Some(err);
+                    Ordering::Equal
+                })
+            });
+            match error {
+                Some(err) => Err(err),
+                None => Ok(Object::array(arr)),
+            }
+        },
+    );
+    table.register_with_caller(
+        "map",
+        Arity::Fixed(1),
+        "a.map(f) -> new array of f(element) for each element",
+        |caller, args| {
+            let (arr, mut rest) = into_receiver(args)?;
+            let f = rest.remove(0);
+            let mut mapped = Vec::with_capacity(arr.len());
+            for obj in arr {
+                mapped.push(caller.call(f.clone(), vec![obj])?);
+            }
+            Ok(Object::array(mapped))
+        },
+    );
+    table.register_with_caller(
+        "filter",
+        Arity::Fixed(1),
+        "a.filter(f) -> new array of the elements for which f(element) is truthy",
+        |caller, args| {
+            let (arr, mut rest) = into_receiver(args)?;
+            let f = rest.remove(0);
+            let mut kept = vec![];
+            for obj in arr {
+                if caller.call(f.clone(), vec![obj.clone()])?.is_truthy() {
+                    kept.push(obj);
+                }
+            }
+            Ok(Object::array(kept))
+        },
+    );
+    table.register_with_caller(
+        "reduce",
+        Arity::Range(1, 2),
+        "a.reduce(f, initial?) -> f(f(initial, a[0]), a[1])..., initial defaults to the first element",
+        |caller, args| {
+            let (arr, mut rest) = into_receiver(args)?;
+            let f = rest.remove(0);
+            let mut elements = arr.into_iter();
+            let mut acc = match rest.pop() {
+                Some(initial) => initial,
+                None => elements.next().ok_or_else(|| {
+                    RuntimeError::new(
+                        ErrorKind::IndexError,
+                        "reduce() of empty array with no initial value".to_owned(),
+                    )
+                })?,
+            };
+            for obj in elements {
+                acc = caller.call(f.clone(), vec![acc, obj])?;
+            }
+            Ok(acc)
+        },
+    );
+    table.register_with_caller(
+        "any",
+        Arity::Fixed(1),
+        "a.any(f) -> whether f(element) is truthy for some element",
+        |caller, args| {
+            let (arr, mut rest) = into_receiver(args)?;
+            let f = rest.remove(0);
+            for obj in arr {
+                if caller.call(f.clone(), vec![obj])?.is_truthy() {
+                    return Ok(Object::Bool(true));
+                }
+            }
+            Ok(Object::Bool(false))
+        },
+    );
+    table.register_with_caller(
+        "all",
+        Arity::Fixed(1),
+        "a.all(f) -> whether f(element) is truthy for every element",
+        |caller, args| {
+            let (arr, mut rest) = into_receiver(args)?;
+            let f = rest.remove(0);
+            for obj in arr {
+                if !caller.call(f.clone(), vec![obj])?.is_truthy() {
+                    return Ok(Object::Bool(false));
+                }
+            }
+            Ok(Object::Bool(true))
+        },
+    );
+    table.register_with_caller(
+        "each",
+        Arity::Fixed(1),
+        "a.each(f) -> nil, calls f(element) for each element in order",
+        |caller, args| {
+            let (arr, mut rest) = into_receiver(args)?;
+            let f = rest.remove(0);
+            for obj in arr {
+                caller.call(f.clone(), vec![obj])?;
+            }
+            Ok(Object::Nil)
+        },
+    );
+}
+
+pub fn module() -> Object {
+    let mut table = NativeRegistry::new();
+    register(&mut table);
+    super::method_module("array", &table)
+}
+
+// order used by sort without a comparator, numbers or strings only
+fn compare_values(a: &Object, b: &Object) -> Result<Ordering, RuntimeError> {
+    match (a, b) {
+        (Object::Number(a), Object::Number(b)) => Ok(a.partial_cmp(b).unwrap_or(Ordering::Equal)),
+        (Object::Str(a), Object::Str(b)) => Ok(a.cmp(b)),
+        _ => Err(RuntimeError::new(
+            ErrorKind::TypeError,
+            format!("sort() cannot compare {} and {}", a.type_name(), b.type_name()),
+        )),
+    }
+}
+
+fn compare_with(
+    caller: &mut dyn Caller,
+    compare: &Object,
+    a: &Object,
+    b: &Object,
+) -> Result<Ordering, RuntimeError> {
+    match caller.call(compare.clone(), vec![a.clone(), b.clone()])? {
+        Object::Number(n) => Ok(n.partial_cmp(&0.0).unwrap_or(Ordering::Equal)),
+        obj => Err(RuntimeError::new(
+            ErrorKind::TypeError,
+            format!("sort() comparator must return a number got {}", obj.type_name()),
+        )),
+    }
+}
+
+fn receiver(args: &[Object]) -> Result<&Vec<Object>, RuntimeError> {
+    match &args[0] {
+        Object::Array(arr) => Ok(arr),
+        obj => Err(receiver_error(obj)),
+    }
+}
+
+// takes the receiver array out of the arguments, with the other arguments
+fn into_receiver(mut args: Vec<Object>) -> Result<(Vec<Object>, Vec<Object>), RuntimeError> {
+    match args.remove(0) {
+        Object::Array(arr) => Ok((Rc::unwrap_or_clone(arr), args)),
+        obj => Err(receiver_error(&obj)),
+    }
+}
+
+fn receiver_error(obj: &Object) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::TypeError,
+        format!("expected array receiver got {}", obj.type_name()),
+    )
+}
+
+fn out_of_range(index: i64, len: usize) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::IndexError,
+        format!("index {} out of range for array of length {}", index, len),
+    )
+}
@@ -0,0 +1,132 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    errors::{ErrorKind, RuntimeError},
+    objects::{
+        module::Module,
+        native::{Arity, NativeRegistry},
+        Object,
+    },
+};
+
+use super::{argument_error, exports};
+
+/*
+  the fs module, relative paths are relative to the working directory
+  of the process. failures are IOError runtime errors
+*/
+pub fn module() -> Object {
+    let mut table = NativeRegistry::new();
+    register(&mut table);
+    Object::Module(Rc::new(Module::new(PathBuf::from("fs"), exports(&table))))
+}
+
+fn register(table: &mut NativeRegistry) {
+    table.register_with_caller("read_file", Arity::Fixed(1), "read_file(path) -> contents of the file", |caller, args| {
+        let path = path_arg(&args, "read_file")?;
+        // the memory limit is checked against the size of the file before reading it
+        let size = fs::metadata(path).map_err(|err| io_error("read_file", path, err))?.len();
+        caller.reserve(usize::try_from(size).unwrap_or(usize::MAX))?;
+        fs::read_to_string(path)
+            .map(Object::string)
+            .map_err(|err| io_error("read_file", path, err))
+    });
+    table.register(
+        "write_file",
+        Arity::Fixed(2),
+        "write_file(path, text) -> nil, replaces the file with text",
+        |args| {
+            let path = path_arg(&args, "write_file")?;
+            let text = text_arg(&args, "write_file")?;
+            fs::write(path, text)
+                .map(|_| Object::Nil)
+                .map_err(|err| io_error("write_file", path, err))
+        },
+    );
+    table.register(
+        "append_file",
+        Arity::Fixed(2),
+        "append_file(path, text) -> nil, adds text to the end of the file, creating it if needed",
+        |args| {
+            let path = path_arg(&args, "append_file")?;
+            let text = text_arg(&args, "append_file")?;
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(text.as_bytes()))
+                .map(|_| Object::Nil)
+                .map_err(|err| io_error("append_file", path, err))
+        },
+    );
+    table.register("exists", Arity::Fixed(1), "exists(path) -> whether a file or directory is at path", |args| {
+        let path = path_arg(&args, "exists")?;
+        Ok(Object::Bool(Path::new(path).exists()))
+    });
+    table.register(
+        "list_dir",
+        Arity::Fixed(1),
+        "list_dir(path) -> sorted array of the names in the directory",
+        |args| {
+            let path = path_arg(&args, "list_dir")?;
+            let entries = fs::read_dir(path).map_err(|err| io_error("list_dir", path, err))?;
+            let mut names = vec![];
+            for entry in entries {
+                let entry = entry.map_err(|err| io_error("list_dir", path, err))?;
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+            names.sort();
+            Ok(Object::array(names.into_iter().map(Object::string).collect()))
+        },
+    );
+    table.register(
+        "mkdir",
+        Arity::Fixed(1),
+        "mkdir(path) -> nil, creates the directory and any missing parents",
+        |args| {
+            let path = path_arg(&args, "mkdir")?;
+            fs::create_dir_all(path)
+                .map(|_| Object::Nil)
+                .map_err(|err| io_error("mkdir", path, err))
+        },
+    );
+    table.register(
+        "remove",
+        Arity::Fixed(1),
+        "remove(path) -> nil, deletes a file or an empty directory",
+        |args| {
+            let path = path_arg(&args, "remove")?;
+            let removed = if Path::new(path).is_dir() {
+                fs::remove_dir(path)
+            } else {
+                fs::remove_file(path)
+            };
+            removed
+                .map(|_| Object::Nil)
+                .map_err(|err| io_error("remove", path, err))
+        },
+    );
+}
+
+fn path_arg<'a>(args: &'a [Object], name: &str) -> Result<&'a str, RuntimeError> {
+    match &args[0] {
+        Object::Str(path) => Ok(path),
+        obj => Err(argument_error(name, "string path", obj)),
+    }
+}
+
+fn text_arg<'a>(args: &'a [Object], name: &str) -> Result<&'a str, RuntimeError> {
+    match &args[1] {
+        Object::Str(text) => Ok(text),
+        obj => Err(argument_error(name, "string", obj)),
+    }
+}
+
+fn io_error(name: &str, path: &str, err: std::io::Error) -> RuntimeError {
+    RuntimeError::new(ErrorKind::IOError, format!("{}({:?}): {}", name, path, err))
+}
@@ -0,0 +1,60 @@
+use std::{env, io, path::PathBuf, rc::Rc};
+
+use crate::{
+    errors::{ErrorKind, RuntimeError},
+    objects::{
+        module::Module,
+        native::{Arity, NativeRegistry},
+        Object,
+    },
+};
+
+use super::{argument_error, exports, index_arg};
+
+/*
+  the io module, args are the arguments given to the script by the
+  host, the cli passes the ones after the script name
+*/
+pub fn module(args: Vec<String>) -> Object {
+    let mut table = NativeRegistry::new();
+    register(&mut table, args);
+    Object::Module(Rc::new(Module::new(PathBuf::from("io"), exports(&table))))
+}
+
+fn register(table: &mut NativeRegistry, args: Vec<String>) {
+    table.register(
+        "read_line",
+        Arity::Fixed(0),
+        "read_line() -> next line of stdin without its line ending, nil at the end",
+        |_| {
+            let mut line = String::new();
+            match io::stdin().read_line(&mut line) {
+                Ok(0) => Ok(Object::Nil),
+                Ok(_) => {
+                    let trimmed = line.strip_suffix('\n').unwrap_or(&line);
+                    Ok(Object::string(trimmed.strip_suffix('\r').unwrap_or(trimmed).to_owned()))
+                }
+                Err(err) => Err(RuntimeError::new(ErrorKind::IOError, format!("read_line(): {}", err))),
+            }
+        },
+    );
+    table.register("args", Arity::Fixed(0), "args() -> array of the script arguments", move |_| {
+        Ok(Object::array(args.iter().cloned().map(Object::string).collect()))
+    });
+    table.register(
+        "env",
+        Arity::Fixed(1),
+        "env(name) -> value of the environment variable, nil when unset",
+        |args| match &args[0] {
+            Object::Str(name) => Ok(env::var(&**name).map_or(Object::Nil, Object::string)),
+            obj => Err(argument_error("env", "string", obj)),
+        },
+    );
+    table.register("exit", Arity::Range(0, 1), "exit(code?) -> stops the program with status code", |args| {
+        let code = match args.first() {
+            Some(_) => index_arg(&args, 0, "exit")? as i32,
+            None => 0,
+        };
+        Err(RuntimeError::new(ErrorKind::Exit(code), format!("exit({})", code)))
+    });
+}
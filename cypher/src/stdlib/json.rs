@@ -0,0 +1,216 @@
+use std::{collections::BTreeMap, io, path::PathBuf, rc::Rc};
+
+use serde::Serialize;
+use serde_json::{ser::PrettyFormatter, Map, Number, Serializer, Value};
+
+use crate::{
+    errors::{ErrorKind, RuntimeError},
+    objects::{
+        class::Instance,
+        module::Module,
+        native::{Arity, Caller, NativeRegistry},
+        Object,
+    },
+};
+
+use super::{argument_error, exports, index_arg};
+
+// arrays, maps and instances nested deeper are refused, the same limit parse has
+const DEPTH_MAX: usize = 128;
+// wider indents are written with this many spaces
+const INDENT_MAX: usize = 16;
+
+/*
+  the json module. objects become maps, whole numbers are written
+  without a fraction and instances are written as an object of their
+  fields:
+
+    let config = json.parse(fs.read_file("config.json"))
+    print(json.stringify(config, 2))
+*/
+pub fn module() -> Object {
+    let mut table = NativeRegistry::new();
+    table.register("parse", Arity::Fixed(1), "parse(text) -> the value the json text describes", |args| {
+        match &args[0] {
+            Object::Str(text) => parse(text),
+            obj => Err(argument_error("parse", "string", obj)),
+        }
+    });
+    table.register_with_caller(
+        "stringify",
+        Arity::Range(1, 2),
+        "stringify(value, indent?) -> json text for value, indented by indent spaces per level when given",
+        |caller, args| {
+            let indent = match args.get(1) {
+                Some(Object::Nil) | None => 0,
+                Some(_) => index_arg(&args, 1, "stringify")?,
+            };
+            if indent < 0 {
+                return Err(RuntimeError::new(
+                    ErrorKind::ValueError,
+                    format!("stringify() indent {} is negative", indent),
+                ));
+            }
+            let value = to_json(&args[0], &mut String::from("$"), &mut vec![], 0)?;
+            Ok(Object::string(write(&value, (indent as usize).min(INDENT_MAX), caller)?))
+        },
+    );
+    Object::Module(Rc::new(Module::new(PathBuf::from("json"), exports(&table))))
+}
+
+fn parse(text: &str) -> Result<Object, RuntimeError> {
+    match serde_json::from_str::<Value>(text) {
+        Ok(value) => Ok(from_json(value)),
+        Err(err) => Err(RuntimeError::new(
+            ErrorKind::ValueError,
+            format!("parse() invalid json: {}", err),
+        )),
+    }
+}
+
+fn from_json(value: Value) -> Object {
+    match value {
+        Value::Null => Object::Nil,
+        Value::Bool(b) => Object::Bool(b),
+        Value::Number(n) => Object::Number(n.as_f64().unwrap_or(f64::NAN)),
+        Value::String(s) => Object::string(s),
+        Value::Array(values) => Object::array(values.into_iter().map(from_json).collect()),
+        Value::Object(entries) => Object::map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key, from_json(value)))
+                .collect::<BTreeMap<String, Object>>(),
+        ),
+    }
+}
+
+/*
+  converts obj found at path, the jsonpath of it from the value passed
+  to stringify, depth arrays, maps and instances deep. instances being
+  written are kept in open so that a cycle through their fields is
+  reported instead of recursing forever
+*/
+fn to_json(obj: &Object, path: &mut String, open: &mut Vec<*const Instance>, depth: usize) -> Result<Value, RuntimeError> {
+    if matches!(obj, Object::Array(_) | Object::Map(_) | Object::Instance(_)) && depth >= DEPTH_MAX {
+        return Err(RuntimeError::new(
+            ErrorKind::ValueError,
+            format!("stringify() nesting at {} is deeper than {}", path, DEPTH_MAX),
+        ));
+    }
+    let value = match obj {
+        Object::Nil => Value::Null,
+        Object::Bool(b) => Value::Bool(*b),
+        Object::Str(s) => Value::String(s.to_string()),
+        Object::Number(n) => number(*n).ok_or_else(|| {
+            RuntimeError::new(
+                ErrorKind::ValueError,
+                format!("stringify() cannot write {} at {}", n, path),
+            )
+        })?,
+        Object::Array(elements) => {
+            let mut values = Vec::with_capacity(elements.len());
+            for (index, element) in elements.iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", index));
+                values.push(to_json(element, path, open, depth + 1)?);
+                path.truncate(len);
+            }
+            Value::Array(values)
+        }
+        Object::Map(entries) => object(entries.iter(), path, open, depth + 1)?,
+        Object::Instance(instance) => {
+            let pointer = Rc::as_ptr(instance);
+            if open.contains(&pointer) {
+                return Err(RuntimeError::new(
+                    ErrorKind::ValueError,
+                    format!("stringify() cycle at {}, {} instance contains itself", path, instance.class.name),
+                ));
+            }
+            open.push(pointer);
+            // sorted like maps so the output does not depend on hashing
+            let fields: BTreeMap<String, Object> = instance.fields.borrow().clone().into_iter().collect();
+            let value = object(fields.iter(), path, open, depth + 1);
+            open.pop();
+            value?
+        }
+        obj => {
+            return Err(RuntimeError::new(
+                ErrorKind::TypeError,
+                format!("stringify() cannot write {} at {}", obj.type_name(), path),
+            ))
+        }
+    };
+    Ok(value)
+}
+
+fn object<'a>(
+    entries: impl Iterator<Item = (&'a String, &'a Object)>,
+    path: &mut String,
+    open: &mut Vec<*const Instance>,
+    depth: usize,
+) -> Result<Value, RuntimeError> {
+    let mut values = Map::new();
+    for (key, obj) in entries {
+        let len = path.len();
+        if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            path.push_str(&format!(".{}", key));
+        } else {
+            path.push_str(&format!("[{:?}]", key));
+        }
+        values.insert(key.to_owned(), to_json(obj, path, open, depth)?);
+        path.truncate(len);
+    }
+    Ok(Value::Object(values))
+}
+
+// whole numbers as integers so 3 is not written as 3.0, None for nan and the infinities
+fn number(n: f64) -> Option<Value> {
+    if n.fract() == 0.0 && n.abs() < 2f64.powi(53) {
+        return Some(Value::Number(Number::from(n as i64)));
+    }
+    Number::from_f64(n).map(Value::Number)
+}
+
+// the text is charged against the memory limit as it is written, before it grows past it
+fn write(value: &Value, indent: usize, caller: &mut dyn Caller) -> Result<String, RuntimeError> {
+    caller.reserve(indent)?;
+    let spaces = " ".repeat(indent);
+    let mut out = Budgeted {
+        caller,
+        text: vec![],
+        error: None,
+    };
+    let written = if indent == 0 {
+        serde_json::to_writer(&mut out, value)
+    } else {
+        value.serialize(&mut Serializer::with_formatter(&mut out, PrettyFormatter::with_indent(spaces.as_bytes())))
+    };
+    match written {
+        Ok(()) => Ok(String::from_utf8(out.text).unwrap()),
+        // writing a Value only fails when the buffer refuses to grow
+        Err(err) => Err(out
+            .error
+            .unwrap_or_else(|| RuntimeError::new(ErrorKind::ValueError, format!("stringify() {}", err)))),
+    }
+}
+
+struct Budgeted<'a> {
+    caller: &'a mut dyn Caller,
+    text: Vec<u8>,
+    error: Option<RuntimeError>,
+}
+
+impl io::Write for Budgeted<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Err(err) = self.caller.reserve(buf.len()) {
+            self.error = Some(err);
+            return Err(io::Error::other("memory limit exceeded"));
+        }
+        self.text.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
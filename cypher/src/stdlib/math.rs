@@ -0,0 +1,162 @@
+use std::{
+    cell::Cell,
+    path::PathBuf,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    errors::{ErrorKind, RuntimeError},
+    objects::{
+        module::Module,
+        native::{Arity, NativeRegistry},
+        Object,
+    },
+};
+
+use super::{argument_error, exports, index_arg};
+
+/*
+  the math module, numbers are f64 so nan and the infinities follow
+  ieee rules instead of raising errors
+*/
+pub fn module() -> Object {
+    let mut table = NativeRegistry::new();
+    register(&mut table);
+    let mut exports = exports(&table);
+    exports.insert("pi".to_owned(), Object::Number(std::f64::consts::PI));
+    exports.insert("e".to_owned(), Object::Number(std::f64::consts::E));
+    exports.insert("inf".to_owned(), Object::Number(f64::INFINITY));
+    exports.insert("nan".to_owned(), Object::Number(f64::NAN));
+    Object::Module(Rc::new(Module::new(PathBuf::from("math"), exports)))
+}
+
+fn register(table: &mut NativeRegistry) {
+    unary(table, "floor", "floor(x) -> largest whole number not above x", f64::floor);
+    unary(table, "ceil", "ceil(x) -> smallest whole number not below x", f64::ceil);
+    unary(table, "round", "round(x) -> nearest whole number, halves away from zero", f64::round);
+    unary(table, "trunc", "trunc(x) -> x without its fractional part", f64::trunc);
+    unary(table, "abs", "abs(x) -> absolute value", f64::abs);
+    unary(table, "sqrt", "sqrt(x) -> square root, nan below zero", f64::sqrt);
+    unary(table, "exp", "exp(x) -> e to the power x", f64::exp);
+    unary(table, "log2", "log2(x) -> base 2 logarithm", f64::log2);
+    unary(table, "log10", "log10(x) -> base 10 logarithm", f64::log10);
+    unary(table, "sin", "sin(x) -> sine of x radians", f64::sin);
+    unary(table, "cos", "cos(x) -> cosine of x radians", f64::cos);
+    unary(table, "tan", "tan(x) -> tangent of x radians", f64::tan);
+    unary(table, "asin", "asin(x) -> arcsine in radians", f64::asin);
+    unary(table, "acos", "acos(x) -> arccosine in radians", f64::acos);
+    unary(table, "atan", "atan(x) -> arctangent in radians", f64::atan);
+
+    table.register("pow", Arity::Fixed(2), "pow(x, y) -> x to the power y", |args| {
+        Ok(Object::Number(number(&args, 0, "pow")?.powf(number(&args, 1, "pow")?)))
+    });
+    table.register("atan2", Arity::Fixed(2), "atan2(y, x) -> angle of the point (x, y) in radians", |args| {
+        Ok(Object::Number(number(&args, 0, "atan2")?.atan2(number(&args, 1, "atan2")?)))
+    });
+    table.register("log", Arity::Range(1, 2), "log(x, base?) -> logarithm of x, natural without base", |args| {
+        let x = number(&args, 0, "log")?;
+        Ok(Object::Number(match args.get(1) {
+            Some(_) => x.log(number(&args, 1, "log")?),
+            None => x.ln(),
+        }))
+    });
+    table.register("min", Arity::Variadic(1), "min(x, ...) -> smallest argument", |args| {
+        fold(&args, "min", f64::min)
+    });
+    table.register("max", Arity::Variadic(1), "max(x, ...) -> largest argument", |args| {
+        fold(&args, "max", f64::max)
+    });
+    table.register("is_nan", Arity::Fixed(1), "is_nan(x) -> whether x is nan", |args| {
+        Ok(Object::Bool(number(&args, 0, "is_nan")?.is_nan()))
+    });
+
+    // all random functions share one generator so that seed makes runs repeatable
+    let random = Rc::new(Random::new(clock_seed()));
+    let state = random.clone();
+    table.register("seed", Arity::Fixed(1), "seed(n) -> nil, restarts random from seed n", move |args| {
+        state.seed(number(&args, 0, "seed")?.to_bits());
+        Ok(Object::Nil)
+    });
+    let state = random.clone();
+    table.register("random", Arity::Fixed(0), "random() -> number in [0, 1)", move |_| {
+        Ok(Object::Number(state.next_f64()))
+    });
+    table.register(
+        "random_int",
+        Arity::Fixed(2),
+        "random_int(lo, hi) -> whole number in [lo, hi]",
+        move |args| {
+            let lo = index_arg(&args, 0, "random_int")?;
+            let hi = index_arg(&args, 1, "random_int")?;
+            if lo > hi {
+                return Err(RuntimeError::new(
+                    ErrorKind::IndexError,
+                    format!("random_int() empty range {} to {}", lo, hi),
+                ));
+            }
+            // the span of the widest range is one past u64::MAX
+            let span = (hi as i128 - lo as i128) as u128 + 1;
+            Ok(Object::Number((lo as i128 + (random.next_u64() as u128 % span) as i128) as f64))
+        },
+    );
+}
+
+fn unary(table: &mut NativeRegistry, name: &'static str, doc: &str, f: fn(f64) -> f64) {
+    table.register(name, Arity::Fixed(1), doc, move |args| {
+        Ok(Object::Number(f(number(&args, 0, name)?)))
+    });
+}
+
+fn fold(args: &[Object], name: &str, f: fn(f64, f64) -> f64) -> Result<Object, RuntimeError> {
+    let mut acc = number(args, 0, name)?;
+    for index in 1..args.len() {
+        acc = f(acc, number(args, index, name)?);
+    }
+    Ok(Object::Number(acc))
+}
+
+fn number(args: &[Object], index: usize, name: &str) -> Result<f64, RuntimeError> {
+    match &args[index] {
+        Object::Number(n) => Ok(*n),
+        obj => Err(argument_error(name, "number", obj)),
+    }
+}
+
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+/*
+  splitmix64, small and fast with a full period. not for anything
+  that needs to be unpredictable
+*/
+struct Random {
+    state: Cell<u64>,
+}
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        Self { state: Cell::new(seed) }
+    }
+
+    fn seed(&self, seed: u64) {
+        self.state.set(seed);
+    }
+
+    fn next_u64(&self) -> u64 {
+        let state = self.state.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+        self.state.set(state);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // the top 53 bits scaled into [0, 1)
+    fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
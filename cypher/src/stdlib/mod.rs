@@ -0,0 +1,96 @@
+use std::{collections::HashMap, path::PathBuf, rc::Rc};
+
+use crate::{
+    errors::{ErrorKind, RuntimeError},
+    objects::{module::Module, native::NativeRegistry, Object},
+    policy::Capability,
+};
+
+pub mod array;
+pub mod fs;
+pub mod io;
+pub mod json;
+pub mod math;
+pub mod string;
+pub mod time;
+
+/*
+  modules built into the interpreter, imported by name instead of by
+  path: import "string" as string
+*/
+pub fn modules() -> Vec<Object> {
+    vec![
+        string::module(),
+        array::module(),
+        math::module(),
+        fs::module(),
+        io::module(vec![]),
+        time::module(),
+        json::module(),
+    ]
+}
+
+/*
+  the capability a builtin module export needs, exports without one
+  are always available
+*/
+pub fn capability(module: &str, export: &str) -> Option<Capability> {
+    match (module, export) {
+        ("fs", _) => Some(Capability::Fs),
+        ("time", _) => Some(Capability::Time),
+        ("io", "env") => Some(Capability::Env),
+        ("io", _) => Some(Capability::Process),
+        ("math", "random" | "random_int" | "seed") => Some(Capability::Random),
+        _ => None,
+    }
+}
+
+/*
+  module exporting the methods of a builtin type as plain functions,
+  the receiver becomes the first argument
+*/
+fn method_module(name: &str, methods: &NativeRegistry) -> Object {
+    let exports: HashMap<String, Object> = methods
+        .names()
+        .into_iter()
+        .filter_map(|method| methods.lookup(method))
+        .map(|method| {
+            let function = method.as_function();
+            (function.name.to_owned(), Object::Native(Rc::new(function)))
+        })
+        .collect();
+    Object::Module(Rc::new(Module::new(PathBuf::from(name), exports)))
+}
+
+// every native of a registry by name, the exports of a module of functions
+fn exports(table: &NativeRegistry) -> HashMap<String, Object> {
+    table
+        .names()
+        .into_iter()
+        .filter_map(|name| table.get(name).map(|native| (name.to_owned(), native)))
+        .collect()
+}
+
+// a whole number argument, may be negative
+fn index_arg(args: &[Object], index: usize, name: &str) -> Result<i64, RuntimeError> {
+    match &args[index] {
+        Object::Number(n) if n.fract() == 0.0 => Ok(*n as i64),
+        obj => Err(argument_error(name, "whole number", obj)),
+    }
+}
+
+fn argument_error(name: &str, expected: &str, obj: &Object) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::TypeError,
+        format!("{}() expected {} argument got {}", name, expected, obj.type_name()),
+    )
+}
+
+// index into a sequence of length len, negative counts from the end
+fn clamp(index: i64, len: usize) -> usize {
+    if index < 0 {
+        (len as i64 + index).max(0) as usize
+    } else {
+        (index as usize).min(len)
+    }
+}
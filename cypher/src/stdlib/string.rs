@@ -0,0 +1,301 @@
+use crate::{
+    errors::{ErrorKind, RuntimeError},
+    objects::{
+        native::{Arity, NativeRegistry},
+        Object,
+    },
+};
+
+use super::{argument_error, clamp, index_arg};
+
+/*
+  string methods, also exported as the string module. indices and
+  lengths count unicode characters, not bytes
+*/
+pub fn register(table: &mut NativeRegistry) {
+    table.register("len", Arity::Fixed(0), "s.len() -> number of characters", |args| {
+        let s = receiver(&args)?;
+        Ok(Object::Number(s.chars().count() as f64))
+    });
+    table.register("upper", Arity::Fixed(0), "s.upper() -> s in upper case", |args| {
+        Ok(Object::string(receiver(&args)?.to_uppercase()))
+    });
+    table.register("lower", Arity::Fixed(0), "s.lower() -> s in lower case", |args| {
+        Ok(Object::string(receiver(&args)?.to_lowercase()))
+    });
+    table.register("trim", Arity::Fixed(0), "s.trim() -> s without surrounding whitespace", |args| {
+        Ok(Object::string(receiver(&args)?.trim().to_owned()))
+    });
+    table.register("trim_start", Arity::Fixed(0), "s.trim_start() -> s without leading whitespace", |args| {
+        Ok(Object::string(receiver(&args)?.trim_start().to_owned()))
+    });
+    table.register("trim_end", Arity::Fixed(0), "s.trim_end() -> s without trailing whitespace", |args| {
+        Ok(Object::string(receiver(&args)?.trim_end().to_owned()))
+    });
+    table.register(
+        "split",
+        Arity::Range(0, 1),
+        "s.split(sep?) -> array of the parts between sep, whitespace when sep is missing",
+        |args| {
+            let s = receiver(&args)?;
+            let parts: Vec<Object> = match args.get(1) {
+                None => s.split_whitespace().map(|part| Object::string(part.to_owned())).collect(),
+                Some(_) => {
+                    let sep = string_arg(&args, 1, "split")?;
+                    if sep.is_empty() {
+                        s.chars().map(|c| Object::string(c.to_string())).collect()
+                    } else {
+                        s.split(sep).map(|part| Object::string(part.to_owned())).collect()
+                    }
+                }
+            };
+            Ok(Object::array(parts))
+        },
+    );
+    table.register(
+        "join",
+        Arity::Fixed(1),
+        "s.join(array) -> the elements of array separated by s",
+        |args| {
+            let sep = receiver(&args)?;
+            match &args[1] {
+                Object::Array(items) => Ok(Object::string(
+                    items.iter().map(|item| item.to_string()).collect::<Vec<String>>().join(sep),
+                )),
+                obj => Err(argument_error("join", "array", obj)),
+            }
+        },
+    );
+    table.register(
+        "replace",
+        Arity::Fixed(2),
+        "s.replace(from, to) -> s with every from replaced by to",
+        |args| {
+            let s = receiver(&args)?;
+            let from = string_arg(&args, 1, "replace")?;
+            let to = string_arg(&args, 2, "replace")?;
+            if from.is_empty() {
+                return Err(RuntimeError::new(
+                    ErrorKind::TypeError,
+                    "replace() pattern must not be empty".to_owned(),
+                ));
+            }
+            Ok(Object::string(s.replace(from, to)))
+        },
+    );
+    table.register(
+        "find",
+        Arity::Range(1, 2),
+        "s.find(sub, start?) -> index of the first sub at or after start, -1 if missing",
+        |args| {
+            let s = receiver(&args)?;
+            let sub = string_arg(&args, 1, "find")?;
+            let start = match args.get(2) {
+                Some(_) => clamp(index_arg(&args, 2, "find")?, s.chars().count()),
+                None => 0,
+            };
+            let offset = byte_offset(s, start);
+            let found = s[offset..]
+                .find(sub)
+                .map(|byte| (start + s[offset..offset + byte].chars().count()) as f64);
+            Ok(Object::Number(found.unwrap_or(-1.0)))
+        },
+    );
+    table.register(
+        "contains",
+        Arity::Fixed(1),
+        "s.contains(sub) -> whether sub occurs in s",
+        |args| {
+            let s = receiver(&args)?;
+            Ok(Object::Bool(s.contains(string_arg(&args, 1, "contains")?)))
+        },
+    );
+    table.register(
+        "starts_with",
+        Arity::Fixed(1),
+        "s.starts_with(prefix) -> whether s begins with prefix",
+        |args| {
+            let s = receiver(&args)?;
+            Ok(Object::Bool(s.starts_with(string_arg(&args, 1, "starts_with")?)))
+        },
+    );
+    table.register(
+        "ends_with",
+        Arity::Fixed(1),
+        "s.ends_with(suffix) -> whether s ends with suffix",
+        |args| {
+            let s = receiver(&args)?;
+            Ok(Object::Bool(s.ends_with(string_arg(&args, 1, "ends_with")?)))
+        },
+    );
+    table.register(
+        "slice",
+        Arity::Range(1, 2),
+        "s.slice(start, end?) -> characters from start up to end, negative indices count from the end",
+        |args| {
+            let s = receiver(&args)?;
+            let len = s.chars().count();
+            let start = clamp(index_arg(&args, 1, "slice")?, len);
+            let end = match args.get(2) {
+                Some(_) => clamp(index_arg(&args, 2, "slice")?, len),
+                None => len,
+            };
+            Ok(Object::string(s.chars().skip(start).take(end.saturating_sub(start)).collect::<String>()))
+        },
+    );
+    table.register(
+        "substr",
+        Arity::Range(1, 2),
+        "s.substr(start, length?) -> length characters from start, negative start counts from the end",
+        |args| {
+            let s = receiver(&args)?;
+            let start = clamp(index_arg(&args, 1, "substr")?, s.chars().count());
+            let chars = s.chars().skip(start);
+            let sub: String = match args.get(2) {
+                Some(_) => {
+                    let length = index_arg(&args, 2, "substr")?;
+                    if length < 0 {
+                        return Err(RuntimeError::new(
+                            ErrorKind::IndexError,
+                            format!("substr() length {} is negative", length),
+                        ));
+                    }
+                    chars.take(length as usize).collect()
+                }
+                None => chars.collect(),
+            };
+            Ok(Object::string(sub))
+        },
+    );
+    table.register_with_caller("repeat", Arity::Fixed(1), "s.repeat(n) -> s repeated n times", |caller, args| {
+        let s = receiver(&args)?;
+        let times = index_arg(&args, 1, "repeat")?;
+        if times < 0 {
+            return Err(RuntimeError::new(
+                ErrorKind::IndexError,
+                format!("repeat() count {} is negative", times),
+            ));
+        }
+        // the size is known up front, checked before allocating it
+        let too_large = || RuntimeError::new(ErrorKind::ValueError, format!("repeat() of {} times is too large", times));
+        let size = s.len().checked_mul(times as usize).ok_or_else(too_large)?;
+        caller.reserve(size)?;
+        let mut repeated = String::new();
+        repeated.try_reserve_exact(size).map_err(|_| too_large())?;
+        for _ in 0..times {
+            repeated.push_str(s);
+        }
+        Ok(Object::string(repeated))
+    });
+    table.register("chars", Arity::Fixed(0), "s.chars() -> array of the characters of s", |args| {
+        let s = receiver(&args)?;
+        Ok(Object::array(s.chars().map(|c| Object::string(c.to_string())).collect()))
+    });
+    table.register(
+        "format",
+        Arity::Variadic(0),
+        "s.format(args...) -> s with each {} replaced by the next argument and {n} by argument n",
+        |args| {
+            let template = receiver(&args)?;
+            format(template, &args[1..])
+        },
+    );
+}
+
+pub fn module() -> Object {
+    let mut table = NativeRegistry::new();
+    register(&mut table);
+    super::method_module("string", &table)
+}
+
+/*
+  character at index of a string, used by s[index]
+*/
+pub fn char_at(s: &str, index: f64) -> Result<Object, RuntimeError> {
+    if index >= 0.0 && index.fract() == 0.0 {
+        if let Some(c) = s.chars().nth(index as usize) {
+            return Ok(Object::string(c.to_string()));
+        }
+    }
+    Err(RuntimeError::new(
+        ErrorKind::IndexError,
+        format!("index {} out of range for string of length {}", index, s.chars().count()),
+    ))
+}
+
+fn format(template: &str, args: &[Object]) -> Result<Object, RuntimeError> {
+    let mut out = String::new();
+    let mut next = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut position = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_ascii_digit() => position.push(c),
+                        _ => {
+                            return Err(RuntimeError::new(
+                                ErrorKind::TypeError,
+                                format!("format() invalid placeholder in {:?}", template),
+                            ))
+                        }
+                    }
+                }
+                let index = if position.is_empty() {
+                    next += 1;
+                    next - 1
+                } else {
+                    position.parse::<usize>().unwrap_or(usize::MAX)
+                };
+                match args.get(index) {
+                    Some(arg) => out.push_str(&arg.to_string()),
+                    None => {
+                        return Err(RuntimeError::new(
+                            ErrorKind::IndexError,
+                            format!("format() has no argument {} for {:?}", index, template),
+                        ))
+                    }
+                }
+            }
+            '}' => {
+                return Err(RuntimeError::new(
+                    ErrorKind::TypeError,
+                    format!("format() unmatched }} in {:?}", template),
+                ))
+            }
+            c => out.push(c),
+        }
+    }
+    Ok(Object::string(out))
+}
+
+fn receiver(args: &[Object]) -> Result<&str, RuntimeError> {
+    match &args[0] {
+        Object::Str(s) => Ok(s),
+        obj => Err(RuntimeError::new(
+            ErrorKind::TypeError,
+            format!("expected string receiver got {}", obj.type_name()),
+        )),
+    }
+}
+
+fn string_arg<'a>(args: &'a [Object], index: usize, name: &str) -> Result<&'a str, RuntimeError> {
+    match &args[index] {
+        Object::Str(s) => Ok(s),
+        obj => Err(argument_error(name, "string", obj)),
+    }
+}
+
+fn byte_offset(s: &str, chars: usize) -> usize {
+    s.char_indices().nth(chars).map_or(s.len(), |(offset, _)| offset)
+}
@@ -0,0 +1,34 @@
+use std::{
+    path::PathBuf,
+    rc::Rc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::objects::{
+    module::Module,
+    native::{Arity, NativeRegistry},
+    Object,
+};
+
+use super::exports;
+
+/*
+  the time module, times are seconds as numbers
+*/
+pub fn module() -> Object {
+    let mut table = NativeRegistry::new();
+    table.register("now", Arity::Fixed(0), "now() -> seconds since the unix epoch", |_| {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |elapsed| elapsed.as_secs_f64());
+        Ok(Object::Number(elapsed))
+    });
+    let start = Instant::now();
+    table.register(
+        "clock",
+        Arity::Fixed(0),
+        "clock() -> seconds since the interpreter started, for timing code",
+        move |_| Ok(Object::Number(start.elapsed().as_secs_f64())),
+    );
+    Object::Module(Rc::new(Module::new(PathBuf::from("time"), exports(&table))))
+}
@@ -1,40 +1,67 @@
-use super::{chunk::Chunk, object::Object};
+use std::rc::Rc;
 
+use crate::objects::Object;
 
+use super::object::Closure;
+
+
+
+/*
+  a try block being executed, an error raised inside it resumes at ip
+  with the stack cut back to sp and the error pushed
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct Handler {
+    pub ip: usize,
+    pub sp: usize,
+}
 
 pub struct CallFrame{
-    name:String,
-    pub code:Vec<u8>,
+    pub closure:Rc<Closure>,
     pub ip:usize,
-    args:Vec<Object>,
-    constants:Vec<Object>
+    // index of the callee in the vm stack, locals are addressed from here
+    pub base:usize,
+    // try blocks entered and not yet left, innermost last
+    pub handlers:Vec<Handler>,
+    // the window of a frame of register code, 0 for stack code
+    pub registers:usize,
 }
 
 impl CallFrame {
-    pub fn new(name:String,code:Vec<u8>,constants:Vec<Object>,args:Vec<Object>) ->Self{
+    pub fn new(closure:Rc<Closure>,base:usize) ->Self{
+        let registers=closure.function.chunk.registers;
         Self{
-            name,
-            code,
+            closure,
             ip:0,
-            args,
-            constants
+            base,
+            handlers:vec![],
+            registers,
         }
     }
 
- 
+    pub fn name(&self)->&str {
+        &self.closure.function.name
+    }
+
     pub fn get_code(&self)->&Vec<u8> {
-        self.get_code()
+        &self.closure.function.chunk.code
     }
 
     pub fn get_code_length(&self)->usize {
-        self.code.len()
+        self.closure.function.chunk.code.len()
     }
 
     pub fn read_byte(&self)->u8 {
-        self.code[self.ip]
+        self.closure.function.chunk.code[self.ip]
     }
-    pub fn get_constant(&mut self,index:usize)->&Object{
-        self.constants.get(index).unwrap()
+
+    pub fn get_constant(&self,index:usize)->&Object{
+        self.closure.function.chunk.constants.get(index).unwrap()
+    }
+
+    // line of the instruction that is currently executing
+    pub fn current_line(&self)->u32 {
+        self.closure.function.chunk.get_line(self.ip.saturating_sub(1))
     }
     
-}
\ No newline at end of file
+}
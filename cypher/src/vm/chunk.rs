@@ -1,29 +1,101 @@
-use super::object::Object;
+use crate::objects::Object;
 
 
 
 pub struct Chunk{
     pub name:String,
+    // source file the chunk was compiled from, for errors and imports
+    pub file:String,
     pub code:Vec<u8>,
     pub constants:Vec<Object>,
-    pub depth:usize
+    // source line of every byte in code
+    pub lines:Vec<u32>,
+    pub depth:usize,
+    // registers a frame running the chunk needs, 0 for stack code, see vm::register
+    pub registers:usize,
+    // names for debuggers, the vm runs without them
+    pub locals:Vec<LocalName>,
+    pub upvalue_names:Vec<String>,
+}
+
+/*
+  a local variable of the function, held in slot (or register) while
+  the code from start up to end runs
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalName {
+    pub name:String,
+    pub slot:usize,
+    pub start:usize,
+    pub end:usize,
+}
+
+impl std::fmt::Debug for Chunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Chunk")
+            .field("name", &self.name)
+            .field("code", &self.code.len())
+            .field("constants", &self.constants.len())
+            .finish()
+    }
 }
 
 impl Chunk {
     pub fn new(name:String)->Self {
         Self{
+            file:name.to_owned(),
             name,
             code:vec![],
             constants:vec![],
+            lines:vec![],
             depth:0,
+            registers:0,
+            locals:vec![],
+            upvalue_names:vec![],
+        }
+    }
+
+    // the local name lives in slot from the next instruction on
+    pub fn begin_local(&mut self,name:&str,slot:usize) {
+        if name.is_empty() {
+            return;
+        }
+        self.locals.push(LocalName {
+            name:name.to_owned(),
+            slot,
+            start:self.code.len(),
+            end:usize::MAX,
+        });
+    }
+
+    // the locals in slot and above go out of scope here
+    pub fn end_locals(&mut self,slot:usize) {
+        let end=self.code.len();
+        for local in self.locals.iter_mut().filter(|local| local.end==usize::MAX && local.slot>=slot) {
+            local.end=end;
         }
     }
 
-    pub fn write_byte(&mut self,byte:u8) {
-        self.code.push(byte)
+    // the locals in scope at offset, innermost last
+    pub fn locals_at(&self,offset:usize)->Vec<&LocalName> {
+        let mut locals:Vec<&LocalName>=self.locals.iter().filter(|local| local.start<=offset && offset<local.end).collect();
+        locals.sort_by_key(|local| local.slot);
+        locals
     }
 
+    pub fn write_byte(&mut self,byte:u8,line:u32) {
+        self.code.push(byte);
+        self.lines.push(line);
+    }
+
+    /*
+      adds obj to the constant pool, numbers, strings, bools and nil
+      already in it are reused instead of being added again
+    */
     pub fn add_constant(&mut self,obj:Object) ->usize{
+        if let Some(index) = self.constants.iter().position(|constant| same_constant(constant, &obj)) {
+            return index;
+        }
         self.constants.push(obj);
         self.constants.len()-1
     }
@@ -35,5 +107,24 @@ impl Chunk {
     pub fn get_code_length(&self)->usize {
         self.code.len()
     }
+
+    pub fn get_line(&self,offset:usize)->u32 {
+        self.lines.get(offset).copied().unwrap_or(0)
+    }
+
+    pub fn read_u16(&self,offset:usize)->u16 {
+        u16::from_be_bytes([self.code[offset],self.code[offset+1]])
+    }
     
-}
\ No newline at end of file
+}
+
+// functions are never shared, -0 and 0 are different constants
+fn same_constant(a: &Object, b: &Object) -> bool {
+    match (a, b) {
+        (Object::Number(a), Object::Number(b)) => a.to_bits() == b.to_bits(),
+        (Object::Str(a), Object::Str(b)) => a == b,
+        (Object::Bool(a), Object::Bool(b)) => a == b,
+        (Object::Nil, Object::Nil) => true,
+        _ => false,
+    }
+}
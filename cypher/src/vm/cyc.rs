@@ -0,0 +1,262 @@
+use std::rc::Rc;
+
+use crate::{errors::bytecode::BytecodeError, objects::Object};
+
+use super::{chunk::Chunk, interner::Interner, object::CompiledFunction};
+
+/*
+  the .cyc bytecode file format, a compiled program that runs without
+  its source. numbers are big endian like jump targets in code
+
+    magic        "CYC\0"
+    version      u16, VERSION of the build that wrote it
+    source hash  u64, fnv-1a of the source text
+    chunk        the top level chunk
+
+  chunk
+    name, file   str
+    depth        u32
+    code         u32 length, then the bytes
+    lines        u32 run count, then (line u32, bytes u32) runs covering code
+    constants    u32 count, then one tagged constant each
+
+  constant
+    0 nil, 1 false, 2 true
+    3 number     f64 bits as u64
+    4 string     str
+    5 function   name str, arity u32, upvalue count u32, chunk
+
+  str is a u32 byte length followed by utf-8
+*/
+pub const MAGIC: &[u8; 4] = b"CYC\0";
+// bump whenever the layout or the meaning of an opcode changes
+pub const VERSION: u16 = 1;
+
+// functions nested deeper than this are rejected instead of recursing
+const MAX_NESTING: usize = 255;
+
+const TAG_NIL: u8 = 0;
+const TAG_FALSE: u8 = 1;
+const TAG_TRUE: u8 = 2;
+const TAG_NUMBER: u8 = 3;
+const TAG_STRING: u8 = 4;
+const TAG_FUNCTION: u8 = 5;
+
+/*
+  a loaded bytecode file, source_hash tells whether it was compiled
+  from a given source, see hash_source
+*/
+#[derive(Debug)]
+pub struct BytecodeFile {
+    pub source_hash: u64,
+    pub chunk: Chunk,
+}
+
+// fnv-1a, stable across builds and platforms unlike the std hasher
+pub fn hash_source(source: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in source.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// the bytes of a .cyc file holding chunk, compiled from source. only stack code is
+// stored, without the names of locals and upvalues debuggers show
+pub fn write(chunk: &Chunk, source: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(chunk.code.len() * 2 + 64);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_be_bytes());
+    out.extend_from_slice(&hash_source(source).to_be_bytes());
+    write_chunk(&mut out, chunk);
+    out
+}
+
+fn write_u32(out: &mut Vec<u8>, value: usize) {
+    out.extend_from_slice(&(value as u32).to_be_bytes());
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_u32(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_chunk(out: &mut Vec<u8>, chunk: &Chunk) {
+    write_str(out, &chunk.name);
+    write_str(out, &chunk.file);
+    write_u32(out, chunk.depth);
+    write_u32(out, chunk.code.len());
+    out.extend_from_slice(&chunk.code);
+
+    let mut runs: Vec<(u32, usize)> = vec![];
+    for &line in &chunk.lines {
+        match runs.last_mut() {
+            Some((last, count)) if *last == line => *count += 1,
+            _ => runs.push((line, 1)),
+        }
+    }
+    write_u32(out, runs.len());
+    for (line, count) in runs {
+        write_u32(out, line as usize);
+        write_u32(out, count);
+    }
+
+    write_u32(out, chunk.constants.len());
+    for constant in &chunk.constants {
+        match constant {
+            Object::Bool(false) => out.push(TAG_FALSE),
+            Object::Bool(true) => out.push(TAG_TRUE),
+            Object::Number(n) => {
+                out.push(TAG_NUMBER);
+                out.extend_from_slice(&n.to_bits().to_be_bytes());
+            }
+            Object::Str(s) => {
+                out.push(TAG_STRING);
+                write_str(out, s);
+            }
+            Object::CompiledFunction(function) => {
+                out.push(TAG_FUNCTION);
+                write_str(out, &function.name);
+                write_u32(out, function.arity);
+                write_u32(out, function.upvalue_count);
+                write_chunk(out, &function.chunk);
+            }
+            // the compiler puts nothing else in the pool
+            _ => out.push(TAG_NIL),
+        }
+    }
+}
+
+/*
+  loads a .cyc file, strings are interned in interner. anything but a
+  complete file of the current version is an error
+*/
+pub fn read(bytes: &[u8], interner: &Interner) -> Result<BytecodeFile, BytecodeError> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(BytecodeError::NotBytecode);
+    }
+    let mut reader = Reader {
+        bytes,
+        offset: MAGIC.len(),
+        interner,
+    };
+    let version = u16::from_be_bytes(reader.take(2, "the version")?.try_into().unwrap());
+    if version != VERSION {
+        return Err(BytecodeError::Version {
+            found: version,
+            expected: VERSION,
+        });
+    }
+    let source_hash = u64::from_be_bytes(reader.take(8, "the source hash")?.try_into().unwrap());
+    let chunk = reader.chunk(0)?;
+    if reader.offset != bytes.len() {
+        return Err(reader.corrupt(format!("{} bytes after the end of the program", bytes.len() - reader.offset)));
+    }
+    Ok(BytecodeFile { source_hash, chunk })
+}
+
+// whether bytes look like a .cyc file, whatever its version
+pub fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    interner: &'a Interner,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize, reading: &'static str) -> Result<&'a [u8], BytecodeError> {
+        if self.bytes.len() - self.offset < len {
+            return Err(BytecodeError::Truncated {
+                offset: self.bytes.len(),
+                reading,
+            });
+        }
+        let taken = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(taken)
+    }
+
+    fn corrupt(&self, message: String) -> BytecodeError {
+        BytecodeError::Corrupt {
+            offset: self.offset,
+            message,
+        }
+    }
+
+    fn u8(&mut self, reading: &'static str) -> Result<u8, BytecodeError> {
+        Ok(self.take(1, reading)?[0])
+    }
+
+    fn u32(&mut self, reading: &'static str) -> Result<usize, BytecodeError> {
+        Ok(u32::from_be_bytes(self.take(4, reading)?.try_into().unwrap()) as usize)
+    }
+
+    fn str(&mut self, reading: &'static str) -> Result<&'a str, BytecodeError> {
+        let len = self.u32(reading)?;
+        let start = self.offset;
+        let bytes = self.take(len, reading)?;
+        std::str::from_utf8(bytes).map_err(|_| BytecodeError::Corrupt {
+            offset: start,
+            message: format!("{} is not utf-8", reading),
+        })
+    }
+
+    fn chunk(&mut self, nesting: usize) -> Result<Chunk, BytecodeError> {
+        if nesting > MAX_NESTING {
+            return Err(self.corrupt(format!("functions nested more than {} deep", MAX_NESTING)));
+        }
+        let mut chunk = Chunk::new(self.str("a chunk name")?.to_owned());
+        chunk.file = self.str("a file name")?.to_owned();
+        chunk.depth = self.u32("a chunk depth")?;
+        let len = self.u32("the code length")?;
+        chunk.code = self.take(len, "code")?.to_vec();
+
+        let runs = self.u32("the line table")?;
+        for _ in 0..runs {
+            let line = self.u32("the line table")? as u32;
+            let count = self.u32("the line table")?;
+            if chunk.lines.len() + count > len {
+                return Err(self.corrupt(format!("line table covers more than the {} bytes of code", len)));
+            }
+            chunk.lines.extend(std::iter::repeat_n(line, count));
+        }
+        if chunk.lines.len() != len {
+            return Err(self.corrupt(format!(
+                "line table covers {} of the {} bytes of code",
+                chunk.lines.len(),
+                len
+            )));
+        }
+
+        let count = self.u32("the constant count")?;
+        for _ in 0..count {
+            let constant = match self.u8("a constant tag")? {
+                TAG_NIL => Object::Nil,
+                TAG_FALSE => Object::Bool(false),
+                TAG_TRUE => Object::Bool(true),
+                TAG_NUMBER => {
+                    let bits = u64::from_be_bytes(self.take(8, "a number")?.try_into().unwrap());
+                    Object::Number(f64::from_bits(bits))
+                }
+                TAG_STRING => Object::Str(self.interner.intern(self.str("a string")?)),
+                TAG_FUNCTION => {
+                    let name = self.str("a function name")?.to_owned();
+                    let arity = self.u32("a function arity")?;
+                    let upvalue_count = self.u32("an upvalue count")?;
+                    let function_chunk = self.chunk(nesting + 1)?;
+                    Object::CompiledFunction(Rc::new(CompiledFunction::new(name, arity, upvalue_count, function_chunk)))
+                }
+                tag => {
+                    self.offset -= 1;
+                    return Err(self.corrupt(format!("unknown constant tag {}", tag)));
+                }
+            };
+            chunk.constants.push(constant);
+        }
+        Ok(chunk)
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{
+    errors::{CypherError, ErrorKind, RuntimeError},
+    lexer::scanner::Scanner,
+    objects::Object,
+    parser::{expr::Program, parser::Parser},
+};
+
+use super::{
+    chunk::Chunk,
+    object::{Closure, CompiledFunction, Upvalue},
+    vm::VM,
+};
+
+/*
+  called by the vm before every instruction while it is set, see
+  VM::set_hook. the hook is taken out of the vm during the call, code it
+  runs through the vm, like eval_in_frame, is not hooked. an error stops
+  the program as if the instruction had raised it
+*/
+pub trait Hook {
+    fn before_instruction(&mut self, vm: &mut VM) -> Result<(), RuntimeError>;
+}
+
+// where the running frame is, cheap enough to ask before every instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location<'a> {
+    // frames on the stack, 1 for the script
+    pub depth: usize,
+    pub function: &'a str,
+    pub file: &'a str,
+    pub line: u32,
+    pub ip: usize,
+}
+
+// a frame of the call stack with the variables it can see
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameInfo {
+    pub function: String,
+    pub file: String,
+    // the line running, for callers the line of the call
+    pub line: u32,
+    pub ip: usize,
+    pub locals: Vec<(String, Object)>,
+    pub upvalues: Vec<(String, Object)>,
+}
+
+impl VM {
+    pub fn set_hook(&mut self, hook: Option<Box<dyn Hook>>) {
+        self.hook = hook;
+    }
+
+    pub fn location(&self) -> Option<Location<'_>> {
+        let frame = self.frames[..self.fp].last()?;
+        let chunk = &frame.closure.function.chunk;
+        Some(Location {
+            depth: self.fp,
+            function: frame.name(),
+            file: &chunk.file,
+            line: chunk.get_line(frame.ip),
+            ip: frame.ip,
+        })
+    }
+
+    // the frames of the call stack, innermost first
+    pub fn backtrace(&self) -> Vec<FrameInfo> {
+        (0..self.fp).filter_map(|frame| self.frame_info(frame)).collect()
+    }
+
+    // frame 0 is the innermost one
+    pub fn frame_info(&self, frame: usize) -> Option<FrameInfo> {
+        let index = self.frame_index(frame)?;
+        let call = &self.frames[index];
+        let chunk = &call.closure.function.chunk;
+        let ip = self.paused_ip(index);
+        let locals = chunk
+            .locals_at(ip)
+            .into_iter()
+            .filter_map(|local| Some((local.name.to_owned(), self.stack.get(call.base + local.slot)?.clone())))
+            .collect();
+        let upvalues = call
+            .closure
+            .upvalues
+            .iter()
+            .enumerate()
+            .map(|(index, upvalue)| {
+                let value = match &*upvalue.borrow() {
+                    Upvalue::Open(slot) => self.stack.get(*slot).cloned().unwrap_or(Object::Nil),
+                    Upvalue::Closed(value) => value.clone(),
+                };
+                (upvalue_name(chunk, index), value)
+            })
+            .collect();
+        Some(FrameInfo {
+            function: call.name().to_owned(),
+            file: chunk.file.to_owned(),
+            line: chunk.get_line(ip),
+            ip,
+            locals,
+            upvalues,
+        })
+    }
+
+    // the globals of the module the frame's function was declared in, by name
+    pub fn frame_globals(&self, frame: usize) -> Vec<(String, Object)> {
+        let Some(index) = self.frame_index(frame) else {
+            return vec![];
+        };
+        let mut globals: Vec<(String, Object)> = self.frames[index]
+            .closure
+            .globals
+            .borrow()
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        globals.sort_by(|a, b| a.0.cmp(&b.0));
+        globals
+    }
+
+    /*
+      runs source as if it were written in the frame: its locals, upvalues
+      and globals can be read and assigned. an expression gives its value,
+      statements give the value of a return. variables the code declares
+      at the top become globals of the frame's module
+    */
+    pub fn eval_in_frame(&mut self, frame: usize, source: &str) -> Result<Object, CypherError> {
+        let Some(index) = self.frame_index(frame) else {
+            return Err(RuntimeError::new(ErrorKind::NameError, format!("there is no frame {}", frame)).into());
+        };
+        let info = self.frame_info(frame).unwrap();
+        let program = parse_eval(&info.file, source)?;
+        let module = self.frames[index].closure.globals.clone();
+
+        // the frame's variables become globals of the code, locals shadow the rest
+        let mut scope: HashMap<Rc<str>, Object> = module.borrow().clone();
+        for (name, value) in info.upvalues.iter().chain(&info.locals) {
+            scope.insert(self.interner().intern(name), value.clone());
+        }
+        let before = scope.clone();
+        let scope = Rc::new(RefCell::new(scope));
+        let chunk = self.compile(info.file.to_owned(), program)?;
+        let function = Rc::new(CompiledFunction::new("<eval>".to_owned(), 0, 0, chunk));
+        let closure = Closure::new(function, vec![], scope.clone());
+        let result = self.call(Object::Closure(Rc::new(closure)), vec![])?;
+
+        /*
+          assignments go back to where the variable lives. only variables
+          the code changed are written, the rest of the copy may be stale
+          since functions it called assigned the originals
+        */
+        let scope = scope.borrow();
+        let assigned = |name: &str| scope.get(name).filter(|value| before.get(name) != Some(*value));
+        let call = &self.frames[index];
+        let chunk = &call.closure.function.chunk;
+        let mut writes = vec![];
+        for local in chunk.locals_at(self.paused_ip(index)) {
+            if let Some(value) = assigned(&local.name) {
+                writes.push((call.base + local.slot, value.clone()));
+            }
+        }
+        for (slot, upvalue) in call.closure.upvalues.clone().iter().enumerate() {
+            let Some(value) = assigned(&upvalue_name(chunk, slot)) else {
+                continue;
+            };
+            match &mut *upvalue.borrow_mut() {
+                Upvalue::Open(slot) => writes.push((*slot, value.clone())),
+                Upvalue::Closed(closed) => *closed = value.clone(),
+            }
+        }
+        let variables: Vec<&str> = info.locals.iter().chain(&info.upvalues).map(|(name, _)| name.as_str()).collect();
+        let mut module = module.borrow_mut();
+        for (name, _) in scope.iter().filter(|(name, _)| !variables.contains(&&***name)) {
+            if let Some(value) = assigned(name) {
+                module.insert(name.clone(), value.clone());
+            }
+        }
+        for (slot, value) in writes {
+            if slot < self.stack.len() {
+                self.stack[slot] = value;
+            }
+        }
+        Ok(result)
+    }
+
+    fn frame_index(&self, frame: usize) -> Option<usize> {
+        self.fp.checked_sub(frame + 1)
+    }
+
+    // callers stopped after their CALL, the innermost frame before its next instruction
+    fn paused_ip(&self, index: usize) -> usize {
+        let ip = self.frames[index].ip;
+        if index + 1 < self.fp {
+            ip.saturating_sub(1)
+        } else {
+            ip
+        }
+    }
+}
+
+fn upvalue_name(chunk: &Chunk, index: usize) -> String {
+    chunk.upvalue_names.get(index).cloned().unwrap_or_else(|| format!("upvalue {}", index))
+}
+
+// an expression is returned, anything else runs as statements
+fn parse_eval(file: &str, source: &str) -> Result<Program, CypherError> {
+    let file = file.to_owned();
+    let parse = |code: &str| {
+        let lines: Vec<&str> = code.split('\n').collect();
+        let mut lex = Scanner::new(code, file.to_owned(), &lines);
+        let mut par = Parser::new(&mut lex, &file, &lines);
+        par.try_parse_program()
+    };
+    match parse(&format!("return {}", source)) {
+        Ok(program) => Ok(program),
+        Err(_) => Ok(parse(source)?),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Breakpoint {
+    pub file: String,
+    pub line: u32,
+}
+
+impl Breakpoint {
+    // file:line, a bare line is in default_file
+    pub fn parse(text: &str, default_file: &str) -> Option<Breakpoint> {
+        let (file, line) = match text.rsplit_once(':') {
+            Some((file, line)) => (file, line),
+            None => (default_file, text),
+        };
+        Some(Breakpoint {
+            file: file.to_owned(),
+            line: line.trim().parse().ok()?,
+        })
+    }
+
+    // the file may be given by a path ending in the one the code was compiled from
+    fn matches(&self, file: &str, line: u32) -> bool {
+        self.line == line && (self.file == file || Path::new(file).ends_with(&self.file))
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+// the lines that have code in the chunk or the functions in it
+pub fn code_lines(chunk: &Chunk) -> BTreeSet<u32> {
+    let mut lines: BTreeSet<u32> = chunk.lines.iter().copied().collect();
+    for constant in &chunk.constants {
+        if let Object::CompiledFunction(function) = constant {
+            lines.extend(code_lines(&function.chunk));
+        }
+    }
+    lines
+}
+
+// the first line at or after line with code, where a breakpoint set on line would stop
+pub fn breakable_line(chunk: &Chunk, line: u32) -> Option<u32> {
+    code_lines(chunk).range(line..).next().copied()
+}
+
+// how a paused program goes on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    // until a breakpoint
+    Continue,
+    // to the next line, in a function it calls as well
+    StepInto,
+    // to the next line of this frame or a caller
+    StepOver,
+    // to the next line once this frame returned
+    StepOut,
+    // stops the program with an Exit error
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint,
+    Step,
+}
+
+/*
+  the user interface of a Debugger, told about every stop. vm can be
+  inspected and evaluated in and breakpoints changed before it answers
+*/
+pub trait Console {
+    fn paused(&mut self, vm: &mut VM, breakpoints: &mut Vec<Breakpoint>, reason: StopReason) -> Resume;
+}
+
+/*
+  a hook stopping the program at breakpoints and after steps, always
+  before the first instruction of a line. it starts stepping into the
+  program, so the console is asked before the first line runs
+*/
+pub struct Debugger {
+    console: Box<dyn Console>,
+    breakpoints: Vec<Breakpoint>,
+    resume: Resume,
+    // the depth resume was chosen at
+    depth: usize,
+    // the line each frame of the call stack was last on
+    lines: Vec<u32>,
+}
+
+impl Debugger {
+    pub fn new(console: Box<dyn Console>) -> Self {
+        Self {
+            console,
+            breakpoints: vec![],
+            resume: Resume::StepInto,
+            depth: 0,
+            lines: vec![],
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    // runs to the first breakpoint instead of stopping at the first line
+    pub fn set_resume(&mut self, resume: Resume) {
+        self.resume = resume;
+    }
+}
+
+impl Hook for Debugger {
+    fn before_instruction(&mut self, vm: &mut VM) -> Result<(), RuntimeError> {
+        let Some(location) = vm.location() else {
+            return Ok(());
+        };
+        let depth = location.depth;
+        // a new frame starts at 0 even when one of the same depth just returned
+        let new_line = location.ip == 0 || self.lines.get(depth - 1) != Some(&location.line);
+        self.lines.resize(depth, 0);
+        self.lines[depth - 1] = location.line;
+        if !new_line {
+            return Ok(());
+        }
+        let step = match self.resume {
+            Resume::StepInto => true,
+            Resume::StepOver => depth <= self.depth,
+            Resume::StepOut => depth < self.depth,
+            Resume::Continue | Resume::Quit => false,
+        };
+        let reason = if step {
+            StopReason::Step
+        } else if self.breakpoints.iter().any(|breakpoint| breakpoint.matches(location.file, location.line)) {
+            StopReason::Breakpoint
+        } else {
+            return Ok(());
+        };
+
+        self.resume = self.console.paused(vm, &mut self.breakpoints, reason);
+        self.depth = depth;
+        if self.resume == Resume::Quit {
+            return Err(RuntimeError::new(ErrorKind::Exit(0), "quit the debugger".to_owned()));
+        }
+        Ok(())
+    }
+}
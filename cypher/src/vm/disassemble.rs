@@ -1,4 +1,8 @@
-use super::{chunk::Chunk, opcode::Opcode};
+use std::fmt::Write;
+
+use crate::objects::Object;
+
+use super::{chunk::Chunk, opcode::Opcode, register};
 
 pub struct Disassembler<'a> {
     chunk: &'a Chunk,
@@ -11,98 +15,91 @@ impl<'a> Disassembler<'a> {
     }
 
     pub fn run(&mut self) {
+        print!("{}", self.render());
+    }
+
+    /*
+      disassembles the whole chunk followed by the chunks of the
+      functions in its constant pool
+    */
+    pub fn render(&mut self) -> String {
+        let mut out = String::new();
         let len = self.chunk.get_code_length();
-        // println!("{:?}:", &self.chunk.code);
-        println!("{}:", &self.chunk.name);
+        let _ = writeln!(out, "{}:", &self.chunk.name);
+        self.ip = 0;
         while self.ip < len {
-            let ins = self.decode();
-            self.disassemble_ins(ins)
+            let (text, next) = Self::instruction(self.chunk, self.ip);
+            let _ = writeln!(out, "{:04} {:>4} {}", self.ip, self.chunk.get_line(self.ip), text);
+            self.ip = next;
         }
+        for constant in &self.chunk.constants {
+            if let Object::CompiledFunction(function) = constant {
+                out.push('\n');
+                out.push_str(&Disassembler::new(&function.chunk).render());
+            }
+        }
+        out
     }
 
-    fn decode(&mut self) -> Opcode {
-        let opcode = self.chunk.code[self.ip];
-        self.ip += 1;
-        Opcode::from(opcode)
-    }
-
-    fn disassemble_ins(&mut self, ins: Opcode) {
+    /*
+      text of the instruction at offset and the offset of the next one
+    */
+    pub fn instruction(chunk: &Chunk, offset: usize) -> (String, usize) {
+        if chunk.registers > 0 {
+            return register::instruction(chunk, offset);
+        }
+        let ins = Opcode::from(chunk.code[offset]);
+        let name = format!("{:?}", ins).to_lowercase();
         match ins {
-            Opcode::ADD => {
-                println!("{:5} add", " ");
-            }
-            Opcode::PUSH => {
-                println!("push")
-            }
-            Opcode::LC => {
-                self.disassemble_load_ins();
-            }
-            Opcode::POP => {
-                println!("pop")
+            Opcode::LC
+            | Opcode::GETGLOBAL
+            | Opcode::SETGLOBAL
+            | Opcode::DEFGLOBAL
+            | Opcode::GETPROP
+            | Opcode::SETPROP
+            | Opcode::CLASS
+            | Opcode::METHOD
+            | Opcode::GETSUPER
+            | Opcode::IMPORT => {
+                let index = chunk.code[offset + 1] as usize;
+                let constant = match chunk.constants.get(index) {
+                    Some(Object::Str(s)) if ins == Opcode::LC => format!("{:?}", s),
+                    Some(constant) => constant.to_string(),
+                    None => "<invalid>".to_owned(),
+                };
+                (format!("{:<10} {:3} {}", name, index, constant), offset + 2)
             }
-            Opcode::SUB => {
-                println!("sub")
+            Opcode::GETLOCAL
+            | Opcode::SETLOCAL
+            | Opcode::GETUPVAL
+            | Opcode::SETUPVAL
+            | Opcode::CALL
+            | Opcode::ARRAY
+            | Opcode::INCLOCAL => {
+                let operand = chunk.code[offset + 1];
+                (format!("{:<10} {:3}", name, operand), offset + 2)
             }
-            Opcode::MUL => {
-                println!("{:5} mul", " ");
+            _ if ins.is_jump() => {
+                let target = chunk.read_u16(offset + 1);
+                (format!("{:<10} {:3}", name, target), offset + 3)
             }
-            Opcode::DIV => {
-                println!("div")
+            Opcode::CLOSURE => {
+                let index = chunk.code[offset + 1] as usize;
+                let mut next = offset + 2;
+                let mut text = match chunk.constants.get(index) {
+                    Some(constant) => format!("{:<10} {:3} {}", name, index, constant),
+                    None => format!("{:<10} {:3} <invalid>", name, index),
+                };
+                if let Some(Object::CompiledFunction(function)) = chunk.constants.get(index) {
+                    for _ in 0..function.upvalue_count {
+                        let kind = if chunk.code[next] == 1 { "local" } else { "upvalue" };
+                        let _ = write!(text, " ({} {})", kind, chunk.code[next + 1]);
+                        next += 2;
+                    }
+                }
+                (text, next)
             }
-            Opcode::MOD => {
-                println!("mod")
-            }
-            Opcode::PRINT => {
-                println!("{:5} print", " ")
-            }
-
-            Opcode::JMP => {
-                let index = self.chunk.code[self.ip];
-                // self.ip+=1;
-                let ip = &self.chunk.code[self.ip];
-                self.ip += 1;
-                print!("{:5} jmp", " ");
-                println!("{:3} {}", " ", ip);
-            }
-            Opcode::JNE => self.disassemble_jne_ins(),
-            Opcode::LT => {
-                println!("{:5} le", " ");
-            }
-            Opcode::GT => {
-                println!("{:5} ge", " ");
-            }
-            Opcode::GOE => todo!(),
-            Opcode::LOE => todo!(),
-            Opcode::NOP => {
-                println!("nop")
-            }
-            Opcode::GETGLOBAL => {
-                let index = self.chunk.code[self.ip];
-                let constant = &self.chunk.constants[index as usize];
-                self.ip += 1;
-                print!("{:5} get", " ");
-                println!("{:3} {}", " ", constant);
-            }
-            Opcode::SETGLOBAL => todo!(),
-            Opcode::REASSIGN => todo!(),
-            Opcode::GETLOCAL => todo!(),
-            Opcode::SETLOCAL => todo!(),
+            _ => (name, offset + 1),
         }
     }
-
-    fn disassemble_load_ins(&mut self) {
-        let index = self.chunk.code[self.ip];
-        let constant = &self.chunk.constants[index as usize];
-        self.ip += 1;
-        print!("{:5} lc", " ");
-        println!("{:4} {}", " ", constant);
-    }
-
-    fn disassemble_jne_ins(&mut self) {
-        let index = self.chunk.code[self.ip];
-        let ip = &self.chunk.code[self.ip];
-        self.ip += 1;
-        print!("{:5} jne", " ");
-        println!("{:3} {}", " ", ip);
-    }
 }
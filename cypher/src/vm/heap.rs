@@ -0,0 +1,381 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    errors::RuntimeError,
+    objects::{
+        class::{Class, Instance},
+        BoundMethod, Object,
+    },
+};
+
+use super::object::{Closure, Globals, Upvalue};
+
+// bytes of tracked objects allocated before the first collection
+const FIRST_COLLECTION: usize = 1 << 20;
+
+/*
+  the objects of the vm shared through Rc. strings, arrays and maps
+  cannot be changed once made so only the others can refer back to
+  themselves, every cycle passes through one of those
+*/
+#[derive(Clone)]
+pub enum Node {
+    Str(Rc<str>),
+    Array(Rc<Vec<Object>>),
+    Map(Rc<BTreeMap<String, Object>>),
+    Instance(Rc<Instance>),
+    Class(Rc<Class>),
+    Closure(Rc<Closure>),
+    Upvalue(Rc<RefCell<Upvalue>>),
+    Globals(Globals),
+    BoundMethod(Rc<BoundMethod>),
+    Exception(Rc<RuntimeError>),
+}
+
+enum WeakNode {
+    Str(Weak<str>),
+    Array(Weak<Vec<Object>>),
+    Map(Weak<BTreeMap<String, Object>>),
+    Instance(Weak<Instance>),
+    Class(Weak<Class>),
+    Closure(Weak<Closure>),
+    Upvalue(Weak<RefCell<Upvalue>>),
+    Globals(Weak<RefCell<HashMap<Rc<str>, Object>>>),
+    BoundMethod(Weak<BoundMethod>),
+    Exception(Weak<RuntimeError>),
+}
+
+impl Node {
+    // the node an object refers to, None for values
+    pub fn of(obj: &Object) -> Option<Node> {
+        match obj {
+            Object::Str(s) => Some(Node::Str(s.clone())),
+            Object::Array(arr) => Some(Node::Array(arr.clone())),
+            Object::Map(map) => Some(Node::Map(map.clone())),
+            Object::Instance(instance) => Some(Node::Instance(instance.clone())),
+            Object::Class(class) => Some(Node::Class(class.clone())),
+            Object::Closure(closure) => Some(Node::Closure(closure.clone())),
+            Object::BoundMethod(bound) => Some(Node::BoundMethod(bound.clone())),
+            Object::Exception(err) => Some(Node::Exception(err.clone())),
+            _ => None,
+        }
+    }
+
+    fn address(&self) -> usize {
+        match self {
+            Node::Str(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Array(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Map(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Instance(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Class(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Closure(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Upvalue(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Globals(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::BoundMethod(rc) => Rc::as_ptr(rc) as *const () as usize,
+            Node::Exception(rc) => Rc::as_ptr(rc) as *const () as usize,
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Node::Str(rc) => Rc::strong_count(rc),
+            Node::Array(rc) => Rc::strong_count(rc),
+            Node::Map(rc) => Rc::strong_count(rc),
+            Node::Instance(rc) => Rc::strong_count(rc),
+            Node::Class(rc) => Rc::strong_count(rc),
+            Node::Closure(rc) => Rc::strong_count(rc),
+            Node::Upvalue(rc) => Rc::strong_count(rc),
+            Node::Globals(rc) => Rc::strong_count(rc),
+            Node::BoundMethod(rc) => Rc::strong_count(rc),
+            Node::Exception(rc) => Rc::strong_count(rc),
+        }
+    }
+
+    fn downgrade(&self) -> WeakNode {
+        match self {
+            Node::Str(rc) => WeakNode::Str(Rc::downgrade(rc)),
+            Node::Array(rc) => WeakNode::Array(Rc::downgrade(rc)),
+            Node::Map(rc) => WeakNode::Map(Rc::downgrade(rc)),
+            Node::Instance(rc) => WeakNode::Instance(Rc::downgrade(rc)),
+            Node::Class(rc) => WeakNode::Class(Rc::downgrade(rc)),
+            Node::Closure(rc) => WeakNode::Closure(Rc::downgrade(rc)),
+            Node::Upvalue(rc) => WeakNode::Upvalue(Rc::downgrade(rc)),
+            Node::Globals(rc) => WeakNode::Globals(Rc::downgrade(rc)),
+            Node::BoundMethod(rc) => WeakNode::BoundMethod(Rc::downgrade(rc)),
+            Node::Exception(rc) => WeakNode::Exception(Rc::downgrade(rc)),
+        }
+    }
+
+    /*
+      calls visit with every node this one holds a reference to. modules
+      and natives are not followed so whatever they hold counts as
+      referenced from outside the heap
+    */
+    fn references(&self, visit: &mut impl FnMut(Node)) {
+        match self {
+            Node::Str(_) => {}
+            Node::Array(elements) => {
+                for element in elements.iter() {
+                    value_references(element, visit);
+                }
+            }
+            Node::Map(entries) => {
+                for entry in entries.values() {
+                    value_references(entry, visit);
+                }
+            }
+            Node::Instance(instance) => {
+                visit(Node::Class(instance.class.clone()));
+                for value in instance.fields.borrow().values() {
+                    value_references(value, visit);
+                }
+            }
+            Node::Class(class) => {
+                for method in class.methods.borrow().values() {
+                    value_references(method, visit);
+                }
+                if let Some(superclass) = &*class.superclass.borrow() {
+                    visit(Node::Class(superclass.clone()));
+                }
+            }
+            Node::Closure(closure) => {
+                for upvalue in &closure.upvalues {
+                    visit(Node::Upvalue(upvalue.clone()));
+                }
+                visit(Node::Globals(closure.globals.clone()));
+            }
+            // an open upvalue points into the stack, which is a root anyway
+            Node::Upvalue(upvalue) => {
+                if let Upvalue::Closed(value) = &*upvalue.borrow() {
+                    value_references(value, visit);
+                }
+            }
+            Node::Globals(globals) => {
+                for value in globals.borrow().values() {
+                    value_references(value, visit);
+                }
+            }
+            Node::BoundMethod(bound) => {
+                value_references(&bound.receiver, visit);
+                value_references(&bound.method, visit);
+            }
+            Node::Exception(err) => {
+                if let Some(value) = err.value() {
+                    value_references(value, visit);
+                }
+            }
+        }
+    }
+
+    /*
+      drops everything the node refers to. only done to garbage, every
+      cycle passes through one of the nodes holding a RefCell
+    */
+    fn clear(&self) {
+        // taken out first so that nothing is dropped while borrowed
+        match self {
+            Node::Instance(instance) => drop(std::mem::take(&mut *instance.fields.borrow_mut())),
+            Node::Class(class) => {
+                drop(std::mem::take(&mut *class.methods.borrow_mut()));
+                drop(class.superclass.borrow_mut().take());
+            }
+            Node::Upvalue(upvalue) => drop(upvalue.replace(Upvalue::Closed(Object::Nil))),
+            Node::Globals(globals) => drop(std::mem::take(&mut *globals.borrow_mut())),
+            Node::Str(_)
+            | Node::Array(_)
+            | Node::Map(_)
+            | Node::Closure(_)
+            | Node::BoundMethod(_)
+            | Node::Exception(_) => {}
+        }
+    }
+}
+
+impl WeakNode {
+    fn upgrade(&self) -> Option<Node> {
+        match self {
+            WeakNode::Str(weak) => weak.upgrade().map(Node::Str),
+            WeakNode::Array(weak) => weak.upgrade().map(Node::Array),
+            WeakNode::Map(weak) => weak.upgrade().map(Node::Map),
+            WeakNode::Instance(weak) => weak.upgrade().map(Node::Instance),
+            WeakNode::Class(weak) => weak.upgrade().map(Node::Class),
+            WeakNode::Closure(weak) => weak.upgrade().map(Node::Closure),
+            WeakNode::Upvalue(weak) => weak.upgrade().map(Node::Upvalue),
+            WeakNode::Globals(weak) => weak.upgrade().map(Node::Globals),
+            WeakNode::BoundMethod(weak) => weak.upgrade().map(Node::BoundMethod),
+            WeakNode::Exception(weak) => weak.upgrade().map(Node::Exception),
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        match self {
+            WeakNode::Str(weak) => weak.strong_count() > 0,
+            WeakNode::Array(weak) => weak.strong_count() > 0,
+            WeakNode::Map(weak) => weak.strong_count() > 0,
+            WeakNode::Instance(weak) => weak.strong_count() > 0,
+            WeakNode::Class(weak) => weak.strong_count() > 0,
+            WeakNode::Closure(weak) => weak.strong_count() > 0,
+            WeakNode::Upvalue(weak) => weak.strong_count() > 0,
+            WeakNode::Globals(weak) => weak.strong_count() > 0,
+            WeakNode::BoundMethod(weak) => weak.strong_count() > 0,
+            WeakNode::Exception(weak) => weak.strong_count() > 0,
+        }
+    }
+}
+
+fn value_references(value: &Object, visit: &mut impl FnMut(Node)) {
+    match value {
+        Object::Return(value) => value_references(value, visit),
+        Object::Error(err) => {
+            if let Some(value) = err.value() {
+                value_references(value, visit);
+            }
+        }
+        obj => {
+            if let Some(node) = Node::of(obj) {
+                visit(node);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStats {
+    pub collections: u64,
+    // objects handed to the heap since the vm was created
+    pub allocated: u64,
+    // objects the collector freed by breaking the cycles they were in
+    pub freed: u64,
+    // objects and their estimated bytes that are still alive, cyclic garbage
+    // stays counted until the next collection
+    pub live: usize,
+    pub live_bytes: usize,
+}
+
+/*
+  keeps track of every object the vm allocates and frees the ones only
+  kept alive by cycles. most objects are freed as soon as their last
+  reference goes away, a collection looks for the rest:
+
+    1. every tracked object starts with the number of references to it,
+       minus the ones coming from other tracked objects. what is left
+       comes from outside the heap, the vm stack, a native or the host
+    2. those objects and the roots are marked, then everything they
+       refer to
+    3. unmarked objects are garbage, their fields, methods and captured
+       variables are cleared which breaks their cycles and frees them
+*/
+pub struct Heap {
+    objects: Vec<(WeakNode, usize)>,
+    // estimated bytes of tracked objects since the last collection
+    bytes: usize,
+    next_collection: usize,
+    // collect on every allocation, for testing the collector
+    stress: bool,
+    collections: u64,
+    allocated: u64,
+    freed: u64,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            objects: vec![],
+            bytes: 0,
+            next_collection: FIRST_COLLECTION,
+            stress: false,
+            collections: 0,
+            allocated: 0,
+            freed: 0,
+        }
+    }
+
+    pub fn set_stress(&mut self, stress: bool) {
+        self.stress = stress;
+    }
+
+    /*
+      starts tracking an object of about size bytes, returns whether a
+      collection is due
+    */
+    pub fn track(&mut self, node: &Node, size: usize) -> bool {
+        self.objects.push((node.downgrade(), size));
+        self.bytes += size;
+        self.allocated += 1;
+        self.stress || self.bytes > self.next_collection
+    }
+
+    pub fn stats(&self) -> GcStats {
+        let live = self.objects.iter().filter(|(node, _)| node.is_alive());
+        let (live, live_bytes) = live.fold((0, 0), |(count, bytes), (_, size)| (count + 1, bytes + size));
+        GcStats {
+            collections: self.collections,
+            allocated: self.allocated,
+            freed: self.freed,
+            live,
+            live_bytes,
+        }
+    }
+
+    /*
+      frees the garbage cycles. the values on the stack and the roots are
+      what the vm can reach without going through another object
+    */
+    pub fn collect(&mut self, stack: &[Object], mut roots: Vec<Node>) {
+        self.collections += 1;
+        let (nodes, sizes): (Vec<Node>, Vec<usize>) = self
+            .objects
+            .drain(..)
+            .filter_map(|(weak, size)| weak.upgrade().map(|node| (node, size)))
+            .unzip();
+        let index: HashMap<usize, usize> = nodes.iter().enumerate().map(|(i, node)| (node.address(), i)).collect();
+
+        // the reference held by nodes itself does not count
+        let mut outside: Vec<usize> = nodes.iter().map(|node| node.strong_count() - 1).collect();
+        for node in &nodes {
+            node.references(&mut |child| {
+                if let Some(&i) = index.get(&child.address()) {
+                    outside[i] = outside[i].saturating_sub(1);
+                }
+            });
+        }
+
+        for value in stack {
+            value_references(value, &mut |node| roots.push(node));
+        }
+        let mut pending = roots;
+        for (i, node) in nodes.iter().enumerate() {
+            if outside[i] > 0 {
+                pending.push(node.clone());
+            }
+        }
+        let mut marked = HashSet::new();
+        while let Some(node) = pending.pop() {
+            if marked.insert(node.address()) {
+                node.references(&mut |child| pending.push(child));
+            }
+        }
+
+        self.bytes = 0;
+        for (node, size) in nodes.iter().zip(sizes) {
+            if marked.contains(&node.address()) {
+                self.objects.push((node.downgrade(), size));
+                self.bytes += size;
+            } else {
+                node.clear();
+                self.freed += 1;
+            }
+        }
+        self.next_collection = FIRST_COLLECTION.max(self.bytes * 2);
+    }
+}
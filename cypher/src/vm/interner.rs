@@ -0,0 +1,54 @@
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
+
+// strings interned before the first prune
+const FIRST_PRUNE: usize = 1024;
+
+/*
+  keeps one copy of every name and string literal. the compiler puts
+  interned strings in the constant pool and the vm compiles imported
+  modules and names the host sets with the same interner, so equal
+  interned strings are one Rc and comparing them is a pointer comparison.
+  clones share the same strings.
+
+  strings nothing else holds anymore are dropped once the set has grown
+  to twice what was left after the last prune, so a host compiling one
+  program after another does not keep the strings of all of them
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    strings: Rc<RefCell<Strings>>,
+}
+
+#[derive(Debug, Default)]
+struct Strings {
+    set: HashSet<Rc<str>>,
+    next_prune: usize,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&self, s: &str) -> Rc<str> {
+        if let Some(interned) = self.strings.borrow().set.get(s) {
+            return interned.clone();
+        }
+        let mut strings = self.strings.borrow_mut();
+        if strings.set.len() >= strings.next_prune.max(FIRST_PRUNE) {
+            strings.set.retain(|interned| Rc::strong_count(interned) > 1);
+            strings.next_prune = strings.set.len() * 2;
+        }
+        let interned: Rc<str> = Rc::from(s);
+        strings.set.insert(interned.clone());
+        interned
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.borrow().set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -1,8 +1,18 @@
 
 
 pub mod opcode;
+#[allow(clippy::module_inception)]
 pub mod  vm;
 pub mod chunk;
+pub mod cyc;
 pub mod object;
 pub mod disassemble;
 pub mod callframe;
+pub mod heap;
+pub mod interner;
+pub mod verify;
+pub mod peephole;
+pub mod register;
+pub mod profile;
+pub mod trace;
+pub mod debug;
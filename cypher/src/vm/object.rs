@@ -1,20 +1,71 @@
-use std::fmt::Display;
-
-#[derive(Debug,Clone)]
-pub enum Object{
-    Number(f64),
-    Nil,
-    Boolean(bool),
-    Str(String)
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::objects::Object;
+
+use super::chunk::Chunk;
+
+/*
+  a function produced by the compiler, lives in the constant pool of
+  the enclosing chunk until a CLOSURE instruction wraps it
+*/
+#[derive(Debug)]
+pub struct CompiledFunction {
+    pub name: String,
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+}
+
+impl CompiledFunction {
+    pub fn new(name: String, arity: usize, upvalue_count: usize, chunk: Chunk) -> Self {
+        Self {
+            name,
+            arity,
+            upvalue_count,
+            chunk,
+        }
+    }
+}
+
+/*
+  a captured variable, open while the variable still lives on the
+  vm stack and closed over once its scope ends
+*/
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Object),
+}
+
+// global variables of one module, keyed by the interned name
+pub type Globals = Rc<RefCell<HashMap<Rc<str>, Object>>>;
+
+/*
+  a function together with its captured variables and the globals of
+  the module it was declared in
+*/
+pub struct Closure {
+    pub function: Rc<CompiledFunction>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    pub globals: Globals,
 }
 
-impl Display for Object {
+impl std::fmt::Debug for Closure {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Object::Number(a) => write!(f, "{}", a),
-            Object::Nil => write!(f,"{}",self),
-            Object::Boolean(b) => write!(f,"{}",b),
-            Object::Str(s) => write!(f,"{}",s),
+        // globals usually contain the closure itself
+        f.debug_struct("Closure")
+            .field("function", &self.function.name)
+            .field("upvalues", &self.upvalues.len())
+            .finish()
+    }
+}
+
+impl Closure {
+    pub fn new(function: Rc<CompiledFunction>, upvalues: Vec<Rc<RefCell<Upvalue>>>, globals: Globals) -> Self {
+        Self {
+            function,
+            upvalues,
+            globals,
         }
     }
-}
\ No newline at end of file
+}
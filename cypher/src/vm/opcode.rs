@@ -1,4 +1,4 @@
-
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opcode {
     ADD,
     PUSH,
@@ -21,33 +21,194 @@ pub enum Opcode {
     REASSIGN,
     GETLOCAL,
     SETLOCAL,
-    NOP
+    NOP,
+
+    DEFGLOBAL,
+    EQ,
+    NEQ,
+    NEG,
+    NOT,
+    AND,
+    OR,
+    CALL,
+    RET,
+    CLOSURE,
+    GETUPVAL,
+    SETUPVAL,
+    CLOSEUPVAL,
+    ARRAY,
+    INDEX,
+    GETPROP,
+    SETPROP,
+    CLASS,
+    METHOD,
+    INHERIT,
+    GETSUPER,
+    IS,
+    IMPORT,
+    POW,
+    IDIV,
+    // pushes a handler jumping to its u16 operand when an error is raised
+    TRY,
+    // pops the handler of the innermost try block
+    ENDTRY,
+    THROW,
+
+    /*
+      superinstructions written by the peephole pass, never by the
+      compiler. INCLOCAL adds 1 to a local and pushes the result, the
+      jumps pop two values and jump to their u16 operand when comparing
+      them yields false, like LT followed by JNE
+    */
+    INCLOCAL,
+    JLT,
+    JGT,
+    JLOE,
+    JGOE,
+    JEQ,
+    JNEQ,
 }
 
 impl From<u8> for Opcode {
     fn from(value: u8) -> Self {
-        match value {
-            0=>Opcode::ADD,
-            1=>Opcode::PUSH,
-            2=>Opcode::LC,
-            3=>Opcode::POP,
-            4=>Opcode::SUB,
-            5=>Opcode::MUL,
-            6=>Opcode::DIV,
-            7=>Opcode::MOD,
-            8=>Opcode::PRINT,
-            9=>Opcode::JMP,
-            10=>Opcode::JNE,
-            11=>Opcode::LT,
-            12=>Opcode::GT,
-            13=>Opcode::GOE,
-            14=>Opcode::LOE,
-            15=>Opcode::GETGLOBAL,
-            16=>Opcode::SETGLOBAL,
-            17=>Opcode::REASSIGN,
-            18=>Opcode::GETLOCAL,
-            19=>Opcode::SETLOCAL,
-            _=>Opcode::NOP
+        Opcode::decode(value).unwrap_or(Opcode::NOP)
+    }
+}
+
+impl Opcode {
+    // the opcode a byte encodes, None for bytes that are not an opcode
+    pub fn decode(byte: u8) -> Option<Opcode> {
+        match byte {
+            0 => Some(Opcode::ADD),
+            1 => Some(Opcode::PUSH),
+            2 => Some(Opcode::LC),
+            3 => Some(Opcode::POP),
+            4 => Some(Opcode::SUB),
+            5 => Some(Opcode::MUL),
+            6 => Some(Opcode::DIV),
+            7 => Some(Opcode::MOD),
+            8 => Some(Opcode::PRINT),
+            9 => Some(Opcode::JMP),
+            10 => Some(Opcode::JNE),
+            11 => Some(Opcode::LT),
+            12 => Some(Opcode::GT),
+            13 => Some(Opcode::GOE),
+            14 => Some(Opcode::LOE),
+            15 => Some(Opcode::GETGLOBAL),
+            16 => Some(Opcode::SETGLOBAL),
+            17 => Some(Opcode::REASSIGN),
+            18 => Some(Opcode::GETLOCAL),
+            19 => Some(Opcode::SETLOCAL),
+            20 => Some(Opcode::NOP),
+            21 => Some(Opcode::DEFGLOBAL),
+            22 => Some(Opcode::EQ),
+            23 => Some(Opcode::NEQ),
+            24 => Some(Opcode::NEG),
+            25 => Some(Opcode::NOT),
+            26 => Some(Opcode::AND),
+            27 => Some(Opcode::OR),
+            28 => Some(Opcode::CALL),
+            29 => Some(Opcode::RET),
+            30 => Some(Opcode::CLOSURE),
+            31 => Some(Opcode::GETUPVAL),
+            32 => Some(Opcode::SETUPVAL),
+            33 => Some(Opcode::CLOSEUPVAL),
+            34 => Some(Opcode::ARRAY),
+            35 => Some(Opcode::INDEX),
+            36 => Some(Opcode::GETPROP),
+            37 => Some(Opcode::SETPROP),
+            38 => Some(Opcode::CLASS),
+            39 => Some(Opcode::METHOD),
+            40 => Some(Opcode::INHERIT),
+            41 => Some(Opcode::GETSUPER),
+            42 => Some(Opcode::IS),
+            43 => Some(Opcode::IMPORT),
+            44 => Some(Opcode::POW),
+            45 => Some(Opcode::IDIV),
+            46 => Some(Opcode::TRY),
+            47 => Some(Opcode::ENDTRY),
+            48 => Some(Opcode::THROW),
+            49 => Some(Opcode::INCLOCAL),
+            50 => Some(Opcode::JLT),
+            51 => Some(Opcode::JGT),
+            52 => Some(Opcode::JLOE),
+            53 => Some(Opcode::JGOE),
+            54 => Some(Opcode::JEQ),
+            55 => Some(Opcode::JNEQ),
+            _ => None,
+        }
+    }
+
+    /*
+      bytes of operands following the opcode. CLOSURE is followed by
+      two more bytes for each upvalue of its function
+    */
+    pub fn operand_len(self) -> usize {
+        match self {
+            Opcode::LC
+            | Opcode::GETGLOBAL
+            | Opcode::SETGLOBAL
+            | Opcode::DEFGLOBAL
+            | Opcode::GETPROP
+            | Opcode::SETPROP
+            | Opcode::CLASS
+            | Opcode::METHOD
+            | Opcode::GETSUPER
+            | Opcode::IMPORT
+            | Opcode::GETLOCAL
+            | Opcode::SETLOCAL
+            | Opcode::GETUPVAL
+            | Opcode::SETUPVAL
+            | Opcode::CALL
+            | Opcode::ARRAY
+            | Opcode::CLOSURE
+            | Opcode::INCLOCAL => 1,
+            _ if self.is_jump() => 2,
+            _ => 0,
         }
     }
-}
\ No newline at end of file
+
+    // whether the operand is a u16 offset into the code to jump to
+    pub fn is_jump(self) -> bool {
+        matches!(
+            self,
+            Opcode::JMP
+                | Opcode::JNE
+                | Opcode::AND
+                | Opcode::OR
+                | Opcode::TRY
+                | Opcode::JLT
+                | Opcode::JGT
+                | Opcode::JLOE
+                | Opcode::JGOE
+                | Opcode::JEQ
+                | Opcode::JNEQ
+        )
+    }
+
+    // the comparison a fused compare and branch makes, JLT compares with LT
+    pub fn fused_comparison(self) -> Option<Opcode> {
+        match self {
+            Opcode::JLT => Some(Opcode::LT),
+            Opcode::JGT => Some(Opcode::GT),
+            Opcode::JLOE => Some(Opcode::LOE),
+            Opcode::JGOE => Some(Opcode::GOE),
+            Opcode::JEQ => Some(Opcode::EQ),
+            Opcode::JNEQ => Some(Opcode::NEQ),
+            _ => None,
+        }
+    }
+
+    // the fused compare and branch replacing this comparison followed by JNE
+    pub fn fused_branch(self) -> Option<Opcode> {
+        match self {
+            Opcode::LT => Some(Opcode::JLT),
+            Opcode::GT => Some(Opcode::JGT),
+            Opcode::LOE => Some(Opcode::JLOE),
+            Opcode::GOE => Some(Opcode::JGOE),
+            Opcode::EQ => Some(Opcode::JEQ),
+            Opcode::NEQ => Some(Opcode::JNEQ),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::{collections::HashSet, rc::Rc};
+
+use crate::objects::Object;
+
+use super::{chunk::Chunk, opcode::Opcode};
+
+/*
+  rewrites short runs of instructions in a compiled chunk and the
+  functions in its constant pool into fewer ones that do the same:
+
+    LC k; POP and GETLOCAL x; POP          removed
+    GETLOCAL x; LC 1; ADD; SETLOCAL x      INCLOCAL x
+    LT; JNE t (and GT, LOE, GOE, EQ, NEQ)  JLT t
+    a jump to a JMP                        a jump to where that JMP goes
+    JMP to the next instruction            removed
+    code after RET, THROW or JMP that      removed
+    nothing jumps to
+
+  runs are never merged across the start of an instruction something
+  jumps to. the chunk is rewritten until nothing changes, jump targets
+  are moved to where their instruction ends up and every instruction
+  keeps the line of the code it came from, a fused instruction the line
+  of the one in the run that can fail
+*/
+pub fn optimize(chunk: &mut Chunk) {
+    while rewrite(chunk) {}
+    chunk.constants = std::mem::take(&mut chunk.constants)
+        .into_iter()
+        .map(|constant| match constant {
+            // a freshly compiled function is not shared yet
+            Object::CompiledFunction(function) => match Rc::try_unwrap(function) {
+                Ok(mut function) => {
+                    optimize(&mut function.chunk);
+                    Object::CompiledFunction(Rc::new(function))
+                }
+                Err(function) => Object::CompiledFunction(function),
+            },
+            constant => constant,
+        })
+        .collect();
+}
+
+#[derive(Clone)]
+struct Instruction {
+    // where the instruction starts in the code being rewritten
+    offset: usize,
+    opcode: Opcode,
+    // operands after the opcode, a jump target is kept in target instead
+    operands: Vec<u8>,
+    target: Option<usize>,
+    line: u32,
+}
+
+fn decode(chunk: &Chunk) -> Vec<Instruction> {
+    let code = &chunk.code;
+    let mut instructions = vec![];
+    let mut offset = 0;
+    while offset < code.len() {
+        let opcode = Opcode::from(code[offset]);
+        let mut len = 1 + opcode.operand_len();
+        if opcode == Opcode::CLOSURE {
+            if let Some(Object::CompiledFunction(function)) = chunk.constants.get(code[offset + 1] as usize) {
+                len += 2 * function.upvalue_count;
+            }
+        }
+        let (operands, target) = if opcode.is_jump() {
+            (vec![], Some(chunk.read_u16(offset + 1) as usize))
+        } else {
+            (code[offset + 1..offset + len].to_vec(), None)
+        };
+        instructions.push(Instruction {
+            offset,
+            opcode,
+            operands,
+            target,
+            line: chunk.get_line(offset),
+        });
+        offset += len;
+    }
+    instructions
+}
+
+// one pass over the chunk, returns whether anything was rewritten
+fn rewrite(chunk: &mut Chunk) -> bool {
+    let instructions = decode(chunk);
+    let targets: HashSet<usize> = instructions.iter().filter_map(|ins| ins.target).collect();
+    // the run starting at index may be merged into one instruction if nothing jumps into it
+    let mergeable = |index: usize, len: usize| {
+        index + len <= instructions.len() && (index + 1..index + len).all(|i| !targets.contains(&instructions[i].offset))
+    };
+    let is_one = |ins: &Instruction| {
+        matches!(chunk.constants.get(ins.operands[0] as usize), Some(Object::Number(n)) if *n == 1.0)
+    };
+
+    let mut out: Vec<Instruction> = vec![];
+    let mut changed = false;
+    let mut index = 0;
+    while index < instructions.len() {
+        let ins = &instructions[index];
+        let next = |n: usize| instructions.get(index + n).map(|ins| ins.opcode);
+
+        if matches!(ins.opcode, Opcode::LC | Opcode::GETLOCAL) && next(1) == Some(Opcode::POP) && mergeable(index, 2) {
+            index += 2;
+            changed = true;
+            continue;
+        }
+        if ins.opcode == Opcode::GETLOCAL
+            && mergeable(index, 4)
+            && next(1) == Some(Opcode::LC)
+            && is_one(&instructions[index + 1])
+            && next(2) == Some(Opcode::ADD)
+            && next(3) == Some(Opcode::SETLOCAL)
+            && instructions[index + 3].operands == ins.operands
+        {
+            out.push(Instruction {
+                opcode: Opcode::INCLOCAL,
+                line: instructions[index + 2].line,
+                ..ins.clone()
+            });
+            index += 4;
+            changed = true;
+            continue;
+        }
+        if let Some(fused) = ins.opcode.fused_branch() {
+            if next(1) == Some(Opcode::JNE) && mergeable(index, 2) {
+                out.push(Instruction {
+                    opcode: fused,
+                    target: instructions[index + 1].target,
+                    ..ins.clone()
+                });
+                index += 2;
+                changed = true;
+                continue;
+            }
+        }
+
+        let mut ins = ins.clone();
+        if let Some(target) = ins.target {
+            let threaded = follow_jumps(&instructions, target);
+            if threaded != target {
+                ins.target = Some(threaded);
+                changed = true;
+            }
+        }
+        let end = instructions.get(index + 1).map_or(chunk.code.len(), |next| next.offset);
+        if ins.opcode == Opcode::JMP && ins.target == Some(end) {
+            index += 1;
+            changed = true;
+            continue;
+        }
+        let ends = matches!(ins.opcode, Opcode::RET | Opcode::THROW | Opcode::JMP);
+        out.push(ins);
+        index += 1;
+        if ends {
+            while index < instructions.len() && !targets.contains(&instructions[index].offset) {
+                index += 1;
+                changed = true;
+            }
+        }
+    }
+
+    changed && encode(chunk, &out)
+}
+
+// where a jump to target ends up after any JMPs it lands on
+fn follow_jumps(instructions: &[Instruction], target: usize) -> usize {
+    let mut seen = vec![target];
+    let mut at = target;
+    while let Ok(index) = instructions.binary_search_by_key(&at, |ins| ins.offset) {
+        if instructions[index].opcode != Opcode::JMP {
+            break;
+        }
+        at = instructions[index].target.unwrap();
+        // a loop of jumps never ends wherever it is entered, leave it alone
+        if seen.contains(&at) {
+            return target;
+        }
+        seen.push(at);
+    }
+    at
+}
+
+/*
+  writes out the rewritten instructions, a jump to an instruction that
+  was removed goes to the one that followed it. the chunk is left as it
+  was when a target does not fit the two bytes of a jump
+*/
+fn encode(chunk: &mut Chunk, instructions: &[Instruction]) -> bool {
+    let mut starts = Vec::with_capacity(instructions.len());
+    let mut len = 0;
+    for ins in instructions {
+        starts.push(len);
+        len += 1 + ins.operands.len() + if ins.target.is_some() { 2 } else { 0 };
+    }
+    let moved = |target: usize| {
+        let index = instructions.partition_point(|ins| ins.offset < target);
+        starts.get(index).copied().unwrap_or(len)
+    };
+
+    let mut code = Vec::with_capacity(len);
+    let mut lines = Vec::with_capacity(len);
+    for ins in instructions {
+        code.push(ins.opcode as u8);
+        code.extend_from_slice(&ins.operands);
+        if let Some(target) = ins.target {
+            let Ok(target) = u16::try_from(moved(target)) else {
+                return false;
+            };
+            code.extend_from_slice(&target.to_be_bytes());
+        }
+        lines.resize(code.len(), ins.line);
+    }
+    for local in &mut chunk.locals {
+        local.start = moved(local.start);
+        if local.end != usize::MAX {
+            local.end = moved(local.end);
+        }
+    }
+    chunk.code = code;
+    chunk.lines = lines;
+    true
+}
@@ -0,0 +1,242 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use super::{callframe::CallFrame, object::CompiledFunction, opcode::Opcode, register::Op};
+
+// longest bar of the opcode histogram
+const BAR_WIDTH: usize = 40;
+// how many rows of functions and lines are printed
+const ROWS: usize = 20;
+
+/*
+  samples the instruction pointer of the running frame before every
+  instruction the vm dispatches. the time until the next sample is
+  charged to the function and line of the instruction, so a CALL of a
+  native or an IMPORT is charged with the time spent inside it
+*/
+pub struct Profiler {
+    // keyed by the address of the function, which the entry keeps alive
+    functions: HashMap<*const CompiledFunction, FunctionStats>,
+    // dispatches of every stack and register opcode
+    opcodes: [[u64; 256]; 2],
+    last: Option<(*const CompiledFunction, u32)>,
+    clock: Instant,
+    started: Instant,
+}
+
+struct FunctionStats {
+    function: Rc<CompiledFunction>,
+    samples: u64,
+    time: Duration,
+    lines: HashMap<u32, (u64, Duration)>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            functions: HashMap::new(),
+            opcodes: [[0; 256]; 2],
+            last: None,
+            clock: now,
+            started: now,
+        }
+    }
+
+    pub fn sample(&mut self, frame: &CallFrame) {
+        let now = Instant::now();
+        self.charge(now);
+        let function = &frame.closure.function;
+        let key = Rc::as_ptr(function);
+        let line = function.chunk.lines.get(frame.ip).copied().unwrap_or(0);
+        let stats = self.functions.entry(key).or_insert_with(|| FunctionStats {
+            function: function.clone(),
+            samples: 0,
+            time: Duration::ZERO,
+            lines: HashMap::new(),
+        });
+        stats.samples += 1;
+        stats.lines.entry(line).or_default().0 += 1;
+        if let Some(&byte) = function.chunk.code.get(frame.ip) {
+            self.opcodes[(frame.registers > 0) as usize][byte as usize] += 1;
+        }
+        self.last = Some((key, line));
+        self.clock = now;
+    }
+
+    // a run is over, the time until the next one is not charged
+    pub fn pause(&mut self) {
+        self.charge(Instant::now());
+    }
+
+    // the time since the last sample goes to the instruction it saw
+    fn charge(&mut self, now: Instant) {
+        if let Some((key, line)) = self.last.take() {
+            let elapsed = now - self.clock;
+            if let Some(stats) = self.functions.get_mut(&key) {
+                stats.time += elapsed;
+                stats.lines.entry(line).or_default().1 += elapsed;
+            }
+        }
+    }
+
+    // what was sampled so far, the last instruction is charged up to now
+    pub fn report(&mut self) -> Profile {
+        let now = Instant::now();
+        self.charge(now);
+        self.clock = now;
+        let mut functions = vec![];
+        let mut lines = vec![];
+        for stats in self.functions.values() {
+            let chunk = &stats.function.chunk;
+            functions.push(FunctionProfile {
+                name: stats.function.name.to_owned(),
+                file: chunk.file.to_owned(),
+                samples: stats.samples,
+                time: stats.time,
+            });
+            for (&line, &(samples, time)) in &stats.lines {
+                lines.push(LineProfile {
+                    function: stats.function.name.to_owned(),
+                    file: chunk.file.to_owned(),
+                    line,
+                    samples,
+                    time,
+                });
+            }
+        }
+        functions.sort_by(|a, b| b.time.cmp(&a.time).then(b.samples.cmp(&a.samples)).then(a.name.cmp(&b.name)));
+        lines.sort_by(|a, b| b.time.cmp(&a.time).then(b.samples.cmp(&a.samples)).then(a.line.cmp(&b.line)));
+
+        let mut opcodes = vec![];
+        for (machine, counts) in self.opcodes.iter().enumerate() {
+            for (byte, &count) in counts.iter().enumerate().filter(|(_, &count)| count > 0) {
+                let name = if machine == 1 {
+                    Op::decode(byte as u8).map(|op| format!("{:?} (register)", op))
+                } else {
+                    Opcode::decode(byte as u8).map(|opcode| format!("{:?}", opcode))
+                };
+                let name = name.unwrap_or_else(|| format!("unknown {}", byte)).to_lowercase();
+                opcodes.push(OpcodeCount { name, count });
+            }
+        }
+        opcodes.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+
+        Profile {
+            samples: functions.iter().map(|function| function.samples).sum(),
+            elapsed: now - self.started,
+            functions,
+            lines,
+            opcodes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionProfile {
+    pub name: String,
+    pub file: String,
+    // instructions sampled while it was the running frame
+    pub samples: u64,
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineProfile {
+    pub function: String,
+    pub file: String,
+    pub line: u32,
+    pub samples: u64,
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpcodeCount {
+    pub name: String,
+    pub count: u64,
+}
+
+/*
+  where a program spent its time, functions and lines sorted by time and
+  opcodes by how often they were dispatched. printing it gives tables of
+  the hottest functions and lines and a histogram of the opcodes
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub samples: u64,
+    // from enabling the profiler to the report, time spent outside runs included
+    pub elapsed: Duration,
+    pub functions: Vec<FunctionProfile>,
+    pub lines: Vec<LineProfile>,
+    pub opcodes: Vec<OpcodeCount>,
+}
+
+impl Profile {
+    pub fn function(&self, name: &str) -> Option<&FunctionProfile> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn opcode(&self, name: &str) -> u64 {
+        self.opcodes.iter().find(|opcode| opcode.name == name).map_or(0, |opcode| opcode.count)
+    }
+
+    fn share(&self, time: Duration) -> f64 {
+        let total: Duration = self.functions.iter().map(|function| function.time).sum();
+        if total.is_zero() {
+            return 0.0;
+        }
+        100.0 * time.as_secs_f64() / total.as_secs_f64()
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "profile: {} instructions in {:.3} ms", self.samples, ms(self.elapsed))?;
+        writeln!(f, "\n{:>10} {:>7} {:>12}  function", "ms", "time", "instructions")?;
+        for function in self.functions.iter().take(ROWS) {
+            writeln!(
+                f,
+                "{:>10.3} {:>6.1}% {:>12}  {} ({})",
+                ms(function.time),
+                self.share(function.time),
+                function.samples,
+                function.name,
+                function.file
+            )?;
+        }
+        writeln!(f, "\n{:>10} {:>7} {:>12}  line", "ms", "time", "instructions")?;
+        for line in self.lines.iter().take(ROWS) {
+            writeln!(
+                f,
+                "{:>10.3} {:>6.1}% {:>12}  {}:{} in {}",
+                ms(line.time),
+                self.share(line.time),
+                line.samples,
+                line.file,
+                line.line,
+                line.function
+            )?;
+        }
+        writeln!(f, "\n{:>12}  opcode", "count")?;
+        let most = self.opcodes.first().map_or(1, |opcode| opcode.count.max(1));
+        for opcode in &self.opcodes {
+            let bar = (opcode.count as u128 * BAR_WIDTH as u128).div_ceil(most as u128) as usize;
+            writeln!(f, "{:>12}  {:<20} {}", opcode.count, opcode.name, "#".repeat(bar))?;
+        }
+        Ok(())
+    }
+}
+
+fn ms(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,186 @@
+use std::{io::Write, rc::Rc};
+
+use crate::{
+    errors::{ErrorKind, RuntimeError},
+    objects::Object,
+};
+
+use super::{
+    super::{callframe::Handler, vm::{add_method, VM}},
+    Op, WIDTH,
+};
+
+impl VM {
+    /*
+      executes the register instruction at the ip of the running frame,
+      yields a value once the outermost frame returns. R[x] is the slot
+      base + x of the vm stack, which holds the whole window of the frame
+    */
+    pub(in crate::vm) fn execute_register(&mut self) -> Result<Option<Object>, RuntimeError> {
+        let frame = &mut self.frames[self.fp - 1];
+        let code = &frame.closure.function.chunk.code;
+        let ip = frame.ip;
+        let (byte, a, b, c) = (code[ip], code[ip + 1] as usize, code[ip + 2] as usize, code[ip + 3] as usize);
+        frame.ip = ip + WIDTH;
+        let base = frame.base;
+        let bx = (b << 8) | c;
+        let Some(op) = Op::decode(byte) else {
+            return Err(self.error(ErrorKind::InvalidBytecode, format!("unknown register op {}", byte)));
+        };
+
+        match op {
+            Op::MOVE => self.stack[base + a] = self.stack[base + b].clone(),
+            Op::LOADK => self.stack[base + a] = self.constant(b),
+            Op::GETGLOBAL => {
+                let name = self.name(b);
+                self.stack[base + a] = self.global(name)?;
+            }
+            Op::SETGLOBAL => {
+                let name = self.name(b);
+                self.assign_global(name, self.stack[base + a].clone())?;
+            }
+            Op::DEFGLOBAL => {
+                let name = self.name(b);
+                self.define_global(name, self.stack[base + a].clone());
+            }
+            Op::GETUPVAL => self.stack[base + a] = self.upvalue(b)?,
+            Op::SETUPVAL => self.set_upvalue(b, self.stack[base + a].clone())?,
+            Op::ADD
+            | Op::SUB
+            | Op::MUL
+            | Op::DIV
+            | Op::MOD
+            | Op::POW
+            | Op::IDIV
+            | Op::LT
+            | Op::GT
+            | Op::LOE
+            | Op::GOE => {
+                let value = match (&self.stack[base + b], &self.stack[base + c]) {
+                    // the common case without cloning the operands
+                    (Object::Number(left), Object::Number(right)) if op == Op::ADD => Object::Number(left + right),
+                    (Object::Number(left), Object::Number(right)) if op == Op::LT => Object::Bool(left < right),
+                    (left, right) => {
+                        let (left, right) = (left.clone(), right.clone());
+                        self.binary_value(op.binary().unwrap(), &left, &right)?
+                    }
+                };
+                self.stack[base + a] = value;
+            }
+            Op::ADDK | Op::SUBK => {
+                let value = match (&self.stack[base + b], self.frames[self.fp - 1].get_constant(c)) {
+                    (Object::Number(left), Object::Number(right)) if op == Op::ADDK => Object::Number(left + right),
+                    (Object::Number(left), Object::Number(right)) => Object::Number(left - right),
+                    (left, right) => {
+                        let (left, right) = (left.clone(), right.clone());
+                        self.binary_value(op.binary().unwrap(), &left, &right)?
+                    }
+                };
+                self.stack[base + a] = value;
+            }
+            Op::EQ => self.stack[base + a] = Object::Bool(self.stack[base + b] == self.stack[base + c]),
+            Op::NEQ => self.stack[base + a] = Object::Bool(self.stack[base + b] != self.stack[base + c]),
+            Op::IS => {
+                let value = self.stack[base + b].clone();
+                self.stack[base + a] = self.is_value(&value, self.stack[base + c].clone())?;
+            }
+            Op::NEG => self.stack[base + a] = self.negate(self.stack[base + b].clone())?,
+            Op::NOT => self.stack[base + a] = Object::Bool(!self.stack[base + b].is_truthy()),
+            Op::JMP => self.get_current_frame().ip = bx,
+            Op::JFALSE => {
+                if !self.stack[base + a].is_truthy() {
+                    self.get_current_frame().ip = bx;
+                }
+            }
+            Op::JTRUE => {
+                if self.stack[base + a].is_truthy() {
+                    self.get_current_frame().ip = bx;
+                }
+            }
+            Op::CALL => {
+                // the callee and its arguments end up on top of the stack like for CALL
+                let top = base + a + b + 1;
+                self.stack.truncate(top);
+                self.sp = top;
+                let depth = self.fp;
+                self.call_value(b)?;
+                // natives finish inside call_value
+                if self.fp == depth {
+                    self.fit_frame();
+                }
+            }
+            Op::RET => {
+                let result = self.stack[base + a].clone();
+                return self.return_value(result);
+            }
+            Op::CLOSURE => {
+                let function = self.function_constant(b)?;
+                let mut upvalues = Vec::with_capacity(function.upvalue_count);
+                for word in 0..function.upvalue_count {
+                    let at = ip + WIDTH * (word + 1);
+                    let code = &self.frames[self.fp - 1].closure.function.chunk.code;
+                    let (is_local, index) = (code[at] == 1, code[at + 1] as usize);
+                    upvalues.push(self.capture(is_local, index));
+                }
+                self.get_current_frame().ip = ip + WIDTH * (function.upvalue_count + 1);
+                self.stack[base + a] = self.closure(function, upvalues)?;
+            }
+            Op::CLOSE => self.close_upvalues(base + a)?,
+            Op::ARRAY => {
+                let elements = self.stack[base + b..base + b + c].to_vec();
+                self.stack[base + a] = self.allocate(Object::array(elements), 0)?;
+            }
+            Op::INDEX => {
+                let (obj, index) = (self.stack[base + b].clone(), self.stack[base + c].clone());
+                self.stack[base + a] = self.index_value(obj, index)?;
+            }
+            Op::GETPROP => {
+                let name = self.name(c);
+                self.stack[base + a] = self.get_property(self.stack[base + b].clone(), &name)?;
+            }
+            Op::SETPROP => {
+                let name = self.name(b);
+                self.set_property(self.stack[base + a].clone(), &name, self.stack[base + c].clone())?;
+            }
+            Op::CLASS => {
+                let name = self.name(b);
+                self.stack[base + a] = self.new_class(&name)?;
+            }
+            Op::INHERIT => self.inherit(self.stack[base + a].clone(), self.stack[base + b].clone())?,
+            Op::METHOD => {
+                let name = self.name(b);
+                add_method(&self.stack[base + a], &name, self.stack[base + c].clone());
+            }
+            Op::GETSUPER => {
+                let name = self.name(c);
+                let (receiver, superclass) = (self.stack[base + b].clone(), self.stack[base + b + 1].clone());
+                self.stack[base + a] = self.super_method(receiver, superclass, &name)?;
+            }
+            Op::IMPORT => {
+                let path = self.name(b);
+                self.stack[base + a] = self.import(&path)?;
+            }
+            // the error is pushed onto the stack cut back to R[A]
+            Op::TRY => self.get_current_frame().handlers.push(Handler { ip: bx, sp: base + a }),
+            Op::ENDTRY => {
+                self.get_current_frame().handlers.pop();
+            }
+            Op::THROW => return Err(self.throw(self.stack[base + a].clone())),
+            Op::PRINT => {
+                let _ = writeln!(self.output, "{}", self.stack[base + a]);
+            }
+        }
+        Ok(None)
+    }
+
+    fn constant(&self, index: usize) -> Object {
+        self.frames[self.fp - 1].get_constant(index).clone()
+    }
+
+    fn name(&self, index: usize) -> Rc<str> {
+        match self.frames[self.fp - 1].get_constant(index) {
+            Object::Str(name) => name.clone(),
+            obj => obj.to_string().into(),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::objects::Object;
+
+use super::{chunk::Chunk, opcode::Opcode};
+
+mod execute;
+pub mod verify;
+
+/*
+  instructions of the register machine, compiled by
+  compiler::register and run by the same VM as stack code. a frame of
+  register code owns chunk.registers slots of the vm stack starting at
+  its callee, R[0] is the callee (self in methods), R[1..] the
+  arguments, then the locals in the order they are declared and above
+  them temporaries. K[x] is constant x of the chunk
+
+  every instruction is WIDTH bytes, the opcode and three operands
+
+    op A B C       registers or constant indices, one byte each
+    op A Bx        Bx is a u16 offset into the code, big endian
+
+  CLOSURE is followed by one more word per upvalue of its function,
+  holding is_local and index like the operands of the stack CLOSURE
+*/
+pub const WIDTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    // R[A] = R[B]
+    MOVE,
+    // R[A] = K[B]
+    LOADK,
+    // R[A] = the global named K[B]
+    GETGLOBAL,
+    // the existing global named K[B] = R[A]
+    SETGLOBAL,
+    // defines the global named K[B] as R[A]
+    DEFGLOBAL,
+    // R[A] = upvalue B
+    GETUPVAL,
+    // upvalue B = R[A]
+    SETUPVAL,
+    // R[A] = R[B] op R[C]
+    ADD,
+    SUB,
+    MUL,
+    DIV,
+    MOD,
+    POW,
+    IDIV,
+    LT,
+    GT,
+    LOE,
+    GOE,
+    EQ,
+    NEQ,
+    IS,
+    // R[A] = R[B] + K[C], R[A] = R[B] - K[C]
+    ADDK,
+    SUBK,
+    // R[A] = -R[B], R[A] = !R[B]
+    NEG,
+    NOT,
+    // jump to Bx
+    JMP,
+    // jump to Bx when R[A] is falsy, or truthy
+    JFALSE,
+    JTRUE,
+    // calls R[A] with the B arguments above it, the result goes to R[A]
+    // and every register above A is left undefined
+    CALL,
+    // returns R[A]
+    RET,
+    // R[A] = a closure of the function K[B]
+    CLOSURE,
+    // closes the upvalues of registers A and above, their locals went out of scope
+    CLOSE,
+    // R[A] = [R[B], ..., R[B+C-1]]
+    ARRAY,
+    // R[A] = R[B][R[C]]
+    INDEX,
+    // R[A] = R[B].K[C]
+    GETPROP,
+    // R[A].K[B] = R[C]
+    SETPROP,
+    // R[A] = a class named K[B]
+    CLASS,
+    // class R[A] inherits from R[B]
+    INHERIT,
+    // adds R[C] to class R[A] as method K[B]
+    METHOD,
+    // R[A] = method K[C] of the superclass R[B+1] bound to R[B]
+    GETSUPER,
+    // R[A] = the module at path K[B]
+    IMPORT,
+    // pushes a handler jumping to Bx with the error in R[A]
+    TRY,
+    ENDTRY,
+    // raises R[A]
+    THROW,
+    PRINT,
+}
+
+const OPS: [Op; 45] = [
+    Op::MOVE,
+    Op::LOADK,
+    Op::GETGLOBAL,
+    Op::SETGLOBAL,
+    Op::DEFGLOBAL,
+    Op::GETUPVAL,
+    Op::SETUPVAL,
+    Op::ADD,
+    Op::SUB,
+    Op::MUL,
+    Op::DIV,
+    Op::MOD,
+    Op::POW,
+    Op::IDIV,
+    Op::LT,
+    Op::GT,
+    Op::LOE,
+    Op::GOE,
+    Op::EQ,
+    Op::NEQ,
+    Op::IS,
+    Op::ADDK,
+    Op::SUBK,
+    Op::NEG,
+    Op::NOT,
+    Op::JMP,
+    Op::JFALSE,
+    Op::JTRUE,
+    Op::CALL,
+    Op::RET,
+    Op::CLOSURE,
+    Op::CLOSE,
+    Op::ARRAY,
+    Op::INDEX,
+    Op::GETPROP,
+    Op::SETPROP,
+    Op::CLASS,
+    Op::INHERIT,
+    Op::METHOD,
+    Op::GETSUPER,
+    Op::IMPORT,
+    Op::TRY,
+    Op::ENDTRY,
+    Op::THROW,
+    Op::PRINT,
+];
+
+impl Op {
+    // the op a byte encodes, None for bytes that are not one
+    pub fn decode(byte: u8) -> Option<Op> {
+        OPS.get(byte as usize).copied()
+    }
+
+    // whether Bx is an offset into the code to jump to
+    pub fn is_jump(self) -> bool {
+        matches!(self, Op::JMP | Op::JFALSE | Op::JTRUE | Op::TRY)
+    }
+
+    /*
+      the stack opcode computing the same binary operation, errors name
+      the operation the same way on both machines
+    */
+    pub fn binary(self) -> Option<Opcode> {
+        match self {
+            Op::ADD | Op::ADDK => Some(Opcode::ADD),
+            Op::SUB | Op::SUBK => Some(Opcode::SUB),
+            Op::MUL => Some(Opcode::MUL),
+            Op::DIV => Some(Opcode::DIV),
+            Op::MOD => Some(Opcode::MOD),
+            Op::POW => Some(Opcode::POW),
+            Op::IDIV => Some(Opcode::IDIV),
+            Op::LT => Some(Opcode::LT),
+            Op::GT => Some(Opcode::GT),
+            Op::LOE => Some(Opcode::LOE),
+            Op::GOE => Some(Opcode::GOE),
+            _ => None,
+        }
+    }
+}
+
+/*
+  text of the register instruction at offset and the offset of the
+  next one, see Disassembler
+*/
+pub fn instruction(chunk: &Chunk, offset: usize) -> (String, usize) {
+    let code = &chunk.code;
+    if offset + WIDTH > code.len() {
+        return ("<truncated>".to_owned(), code.len());
+    }
+    let [byte, a, b, c] = [code[offset], code[offset + 1], code[offset + 2], code[offset + 3]];
+    let Some(op) = Op::decode(byte) else {
+        return (format!("<unknown {}>", byte), offset + WIDTH);
+    };
+    let name = format!("{:?}", op).to_lowercase();
+    let constant = |index: u8| match chunk.constants.get(index as usize) {
+        Some(Object::Str(s)) => format!("{:?}", s),
+        Some(constant) => constant.to_string(),
+        None => "<invalid>".to_owned(),
+    };
+    let text = match op {
+        _ if op.is_jump() => format!("{:<10} r{} {}", name, a, u16::from_be_bytes([b, c])),
+        Op::LOADK | Op::GETGLOBAL | Op::SETGLOBAL | Op::DEFGLOBAL | Op::CLASS | Op::IMPORT => {
+            format!("{:<10} r{} {}", name, a, constant(b))
+        }
+        Op::GETUPVAL | Op::SETUPVAL => format!("{:<10} r{} u{}", name, a, b),
+        Op::ADDK | Op::SUBK | Op::GETPROP | Op::GETSUPER => {
+            format!("{:<10} r{} r{} {}", name, a, b, constant(c))
+        }
+        Op::SETPROP | Op::METHOD => format!("{:<10} r{} {} r{}", name, a, constant(b), c),
+        Op::CALL => format!("{:<10} r{} {}", name, a, b),
+        Op::ARRAY => format!("{:<10} r{} r{} {}", name, a, b, c),
+        Op::RET | Op::CLOSE | Op::THROW | Op::PRINT => format!("{:<10} r{}", name, a),
+        Op::ENDTRY => name,
+        Op::MOVE | Op::NEG | Op::NOT | Op::INHERIT => format!("{:<10} r{} r{}", name, a, b),
+        Op::CLOSURE => {
+            let mut text = format!("{:<10} r{} {}", name, a, constant(b));
+            let mut next = offset + WIDTH;
+            if let Some(Object::CompiledFunction(function)) = chunk.constants.get(b as usize) {
+                for _ in 0..function.upvalue_count {
+                    if next + 1 < code.len() {
+                        let kind = if code[next] == 1 { "local" } else { "upvalue" };
+                        text.push_str(&format!(" ({} {})", kind, code[next + 1]));
+                    }
+                    next += WIDTH;
+                }
+            }
+            return (text, next.min(code.len()));
+        }
+        _ => format!("{:<10} r{} r{} r{}", name, a, b, c),
+    };
+    (text, offset + WIDTH)
+}
@@ -0,0 +1,212 @@
+use crate::{errors::bytecode::BytecodeError, objects::Object};
+
+use super::{
+    super::{chunk::Chunk, verify::verify_function as verify_nested},
+    Op, WIDTH,
+};
+
+/*
+  checks register code the way verify checks stack code:
+
+    - every op is known and the code is made of whole instructions
+    - registers lie inside the window of the function, which holds at
+      least the callee and the arguments
+    - constants used as names are strings and CLOSURE wraps a function
+    - jumps land on an instruction, not inside the words of a CLOSURE
+    - upvalues exist where they are used
+    - the last instruction does not run on past the end of the code
+*/
+pub fn verify_function(chunk: &Chunk, name: &str, arity: usize, upvalue_count: usize) -> Result<(), BytecodeError> {
+    let verifier = Verifier {
+        chunk,
+        name,
+        upvalue_count,
+    };
+    if chunk.registers <= arity || chunk.registers > 256 {
+        return Err(verifier.invalid(
+            0,
+            format!("{} registers cannot hold the callee and {} arguments", chunk.registers, arity),
+        ));
+    }
+    let (starts, last) = verifier.decode()?;
+    for offset in (0..chunk.code.len()).filter(|&offset| starts[offset]) {
+        let op = Op::decode(chunk.code[offset]).unwrap();
+        if op.is_jump() {
+            let target = chunk.read_u16(offset + 2) as usize;
+            if target >= chunk.code.len() || !starts[target] {
+                return Err(verifier.invalid(offset, format!("jump to {:04} is not the start of an instruction", target)));
+            }
+        }
+    }
+    match last {
+        Some((_, Op::RET | Op::JMP | Op::THROW)) => {}
+        Some((offset, _)) => return Err(verifier.invalid(offset, "execution runs past the end of the code".to_owned())),
+        None => return Err(verifier.invalid(0, "execution runs past the end of the code".to_owned())),
+    }
+    for constant in &chunk.constants {
+        if let Object::CompiledFunction(function) = constant {
+            verify_nested(&function.chunk, &function.name, function.arity, function.upvalue_count)?;
+        }
+    }
+    Ok(())
+}
+
+// which offsets start an instruction and the last instruction
+type Decoded = (Vec<bool>, Option<(usize, Op)>);
+
+struct Verifier<'a> {
+    chunk: &'a Chunk,
+    name: &'a str,
+    upvalue_count: usize,
+}
+
+impl Verifier<'_> {
+    fn invalid(&self, offset: usize, message: String) -> BytecodeError {
+        BytecodeError::Invalid {
+            function: self.name.to_owned(),
+            offset,
+            message,
+        }
+    }
+
+    // walks the instructions in order checking each on its own
+    fn decode(&self) -> Result<Decoded, BytecodeError> {
+        let code = &self.chunk.code;
+        let mut starts = vec![false; code.len()];
+        let mut last = None;
+        let mut offset = 0;
+        while offset < code.len() {
+            if offset + WIDTH > code.len() {
+                return Err(self.invalid(offset, format!("an instruction needs {} bytes", WIDTH)));
+            }
+            let op = Op::decode(code[offset]).ok_or_else(|| self.invalid(offset, format!("unknown register op {}", code[offset])))?;
+            starts[offset] = true;
+            last = Some((offset, op));
+            offset += self.check(offset, op)?;
+        }
+        Ok((starts, last))
+    }
+
+    // checks the operands of the instruction at offset, returns its length
+    fn check(&self, offset: usize, op: Op) -> Result<usize, BytecodeError> {
+        let code = &self.chunk.code;
+        let (a, b, c) = (code[offset + 1] as usize, code[offset + 2] as usize, code[offset + 3] as usize);
+        let registers = |list: &[usize]| -> Result<(), BytecodeError> {
+            match list.iter().find(|&&register| register >= self.chunk.registers) {
+                Some(register) => Err(self.invalid(
+                    offset,
+                    format!("register {} is outside the {} registers of the function", register, self.chunk.registers),
+                )),
+                None => Ok(()),
+            }
+        };
+        match op {
+            Op::MOVE | Op::NEG | Op::NOT | Op::INHERIT => registers(&[a, b])?,
+            Op::LOADK => {
+                registers(&[a])?;
+                self.constant(offset, b)?;
+            }
+            Op::GETGLOBAL | Op::SETGLOBAL | Op::DEFGLOBAL | Op::CLASS | Op::IMPORT => {
+                registers(&[a])?;
+                self.name_constant(offset, op, b)?;
+            }
+            Op::GETUPVAL | Op::SETUPVAL => {
+                registers(&[a])?;
+                self.upvalue(offset, b)?;
+            }
+            Op::ADD
+            | Op::SUB
+            | Op::MUL
+            | Op::DIV
+            | Op::MOD
+            | Op::POW
+            | Op::IDIV
+            | Op::LT
+            | Op::GT
+            | Op::LOE
+            | Op::GOE
+            | Op::EQ
+            | Op::NEQ
+            | Op::IS
+            | Op::INDEX => registers(&[a, b, c])?,
+            Op::ADDK | Op::SUBK => {
+                registers(&[a, b])?;
+                self.constant(offset, c)?;
+            }
+            Op::JMP | Op::ENDTRY => {}
+            Op::JFALSE | Op::JTRUE | Op::TRY | Op::RET | Op::CLOSE | Op::THROW | Op::PRINT => registers(&[a])?,
+            // the callee, its arguments and its result
+            Op::CALL => registers(&[a, a + b])?,
+            Op::ARRAY => {
+                registers(&[a])?;
+                if b + c > self.chunk.registers {
+                    return Err(self.invalid(offset, format!("array of registers {} to {} outside the function", b, b + c)));
+                }
+            }
+            Op::GETPROP => {
+                registers(&[a, b])?;
+                self.name_constant(offset, op, c)?;
+            }
+            Op::SETPROP | Op::METHOD => {
+                registers(&[a, c])?;
+                self.name_constant(offset, op, b)?;
+            }
+            Op::GETSUPER => {
+                registers(&[a, b, b + 1])?;
+                self.name_constant(offset, op, c)?;
+            }
+            Op::CLOSURE => {
+                registers(&[a])?;
+                let count = match self.constant(offset, b)? {
+                    Object::CompiledFunction(function) => function.upvalue_count,
+                    constant => {
+                        return Err(self.invalid(offset, format!("CLOSURE of {} which is not a function", constant.type_name())))
+                    }
+                };
+                let len = WIDTH * (count + 1);
+                if offset + len > code.len() {
+                    return Err(self.invalid(offset, format!("CLOSURE needs {} bytes of upvalues", len - WIDTH)));
+                }
+                for word in 1..=count {
+                    let at = offset + WIDTH * word;
+                    match (code[at], code[at + 1] as usize) {
+                        (1, index) => registers(&[index])?,
+                        (0, index) => self.upvalue(offset, index)?,
+                        (flag, _) => return Err(self.invalid(offset, format!("upvalue flag {} is neither 0 nor 1", flag))),
+                    }
+                }
+                return Ok(len);
+            }
+        }
+        Ok(WIDTH)
+    }
+
+    fn constant(&self, offset: usize, index: usize) -> Result<&Object, BytecodeError> {
+        self.chunk.constants.get(index).ok_or_else(|| {
+            self.invalid(
+                offset,
+                format!("constant {} out of range, the pool holds {}", index, self.chunk.constants.len()),
+            )
+        })
+    }
+
+    fn name_constant(&self, offset: usize, op: Op, index: usize) -> Result<(), BytecodeError> {
+        match self.constant(offset, index)? {
+            Object::Str(_) => Ok(()),
+            constant => Err(self.invalid(
+                offset,
+                format!("{:?} names a {} constant, not a string", op, constant.type_name()),
+            )),
+        }
+    }
+
+    fn upvalue(&self, offset: usize, index: usize) -> Result<(), BytecodeError> {
+        if index >= self.upvalue_count {
+            return Err(self.invalid(
+                offset,
+                format!("upvalue {} out of range, the function captures {}", index, self.upvalue_count),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use std::io::Write;
+
+use serde_json::json;
+
+use crate::objects::Object;
+
+use super::{callframe::CallFrame, disassemble::Disassembler};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    // one line of text per instruction
+    Text,
+    // one json object per line, for tools reading the trace
+    Json,
+}
+
+/*
+  writes every instruction before the vm executes it, disassembled the
+  way Disassembler prints it, with the frame running it, its source line
+  and the stack slots of the frame from the callee up
+*/
+pub struct Tracer {
+    output: Box<dyn Write>,
+    format: TraceFormat,
+    // instructions traced so far
+    step: u64,
+}
+
+impl Tracer {
+    pub fn new(output: Box<dyn Write>, format: TraceFormat) -> Self {
+        Self { output, format, step: 0 }
+    }
+
+    // frames are those of the vm, the running one last
+    pub fn trace(&mut self, frames: &[CallFrame], stack: &[Object]) {
+        let Some(frame) = frames.last() else {
+            return;
+        };
+        self.step += 1;
+        let chunk = &frame.closure.function.chunk;
+        let (text, _) = Disassembler::instruction(chunk, frame.ip);
+        let line = chunk.get_line(frame.ip);
+        let slots = stack.get(frame.base..).unwrap_or_default();
+        let _ = match self.format {
+            TraceFormat::Text => {
+                let location = format!("[{}] {} {}:{}", frames.len(), frame.name(), chunk.file, line);
+                writeln!(
+                    self.output,
+                    "{:<32} {:04} {:<28} [{}]",
+                    location,
+                    frame.ip,
+                    text,
+                    slots.iter().map(value).collect::<Vec<_>>().join(", ")
+                )
+            }
+            TraceFormat::Json => {
+                let record = json!({
+                    "step": self.step,
+                    "depth": frames.len(),
+                    "function": frame.name(),
+                    "file": chunk.file,
+                    "line": line,
+                    "ip": frame.ip,
+                    "instruction": text.split_whitespace().collect::<Vec<_>>().join(" "),
+                    "base": frame.base,
+                    "stack": slots.iter().map(value).collect::<Vec<_>>(),
+                });
+                writeln!(self.output, "{}", record)
+            }
+        };
+    }
+}
+
+// strings are quoted so that "1" and 1 tell apart
+fn value(obj: &Object) -> String {
+    match obj {
+        Object::Str(s) => format!("{:?}", s),
+        obj => obj.to_string(),
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::BTreeSet;
+
+use crate::{errors::bytecode::BytecodeError, objects::Object};
+
+use super::{chunk::Chunk, opcode::Opcode, register};
+
+/*
+  checks that a chunk and the functions in its constant pool are safe
+  to run, so that bytecode loaded from a file cannot make the vm read
+  past its code, constants or stack:
+
+    - every opcode is known and its operands are inside the code
+    - constants used as names are strings and CLOSURE wraps a function
+    - jumps land on the start of an instruction
+    - locals and upvalues exist where they are used
+    - the stack never drops into the slot of the running function and
+      has the same depth whichever path reaches an instruction
+    - a local captured by an upvalue is only dropped by CLOSEUPVAL
+    - no path runs past the end of the code
+
+  register code is checked by register::verify
+*/
+pub fn verify(chunk: &Chunk) -> Result<(), BytecodeError> {
+    // the script closure sits in slot 0 of the top level chunk
+    verify_function(chunk, "<script>", 0, 0)
+}
+
+pub(super) fn verify_function(chunk: &Chunk, name: &str, arity: usize, upvalue_count: usize) -> Result<(), BytecodeError> {
+    if chunk.registers > 0 {
+        return register::verify::verify_function(chunk, name, arity, upvalue_count);
+    }
+    let verifier = Verifier {
+        chunk,
+        name,
+        upvalue_count,
+    };
+    let starts = verifier.decode()?;
+    verifier.check_depths(&starts, arity + 1)?;
+    for constant in &chunk.constants {
+        if let Object::CompiledFunction(function) = constant {
+            verify_function(&function.chunk, &function.name, function.arity, function.upvalue_count)?;
+        }
+    }
+    Ok(())
+}
+
+struct Verifier<'a> {
+    chunk: &'a Chunk,
+    name: &'a str,
+    upvalue_count: usize,
+}
+
+impl Verifier<'_> {
+    fn invalid(&self, offset: usize, message: String) -> BytecodeError {
+        BytecodeError::Invalid {
+            function: self.name.to_owned(),
+            offset,
+            message,
+        }
+    }
+
+    /*
+      walks the instructions in order checking each on its own, returns
+      which offsets start an instruction
+    */
+    fn decode(&self) -> Result<Vec<bool>, BytecodeError> {
+        let code = &self.chunk.code;
+        let mut starts = vec![false; code.len()];
+        let mut offset = 0;
+        while offset < code.len() {
+            starts[offset] = true;
+            let opcode = self.opcode(offset)?;
+            let len = self.instruction_len(offset, opcode)?;
+            self.check_operands(offset, opcode)?;
+            offset += len;
+        }
+        for offset in (0..code.len()).filter(|&offset| starts[offset]) {
+            if let Some(target) = self.jump_target(offset) {
+                if target >= code.len() || !starts[target] {
+                    return Err(self.invalid(offset, format!("jump to {:04} is not the start of an instruction", target)));
+                }
+            }
+        }
+        Ok(starts)
+    }
+
+    fn opcode(&self, offset: usize) -> Result<Opcode, BytecodeError> {
+        let byte = self.chunk.code[offset];
+        Opcode::decode(byte).ok_or_else(|| self.invalid(offset, format!("unknown opcode {}", byte)))
+    }
+
+    fn instruction_len(&self, offset: usize, opcode: Opcode) -> Result<usize, BytecodeError> {
+        let code = &self.chunk.code;
+        let mut len = 1 + opcode.operand_len();
+        if opcode == Opcode::CLOSURE && offset + 1 < code.len() {
+            len += 2 * self.function_at(offset)?.1;
+        }
+        if offset + len > code.len() {
+            return Err(self.invalid(offset, format!("{:?} needs {} bytes of operands", opcode, len - 1)));
+        }
+        Ok(len)
+    }
+
+    fn constant(&self, offset: usize) -> Result<&Object, BytecodeError> {
+        let index = self.chunk.code[offset + 1] as usize;
+        self.chunk.constants.get(index).ok_or_else(|| {
+            self.invalid(
+                offset,
+                format!("constant {} out of range, the pool holds {}", index, self.chunk.constants.len()),
+            )
+        })
+    }
+
+    // arity and upvalue count of the function a CLOSURE wraps
+    fn function_at(&self, offset: usize) -> Result<(usize, usize), BytecodeError> {
+        match self.constant(offset)? {
+            Object::CompiledFunction(function) => Ok((function.arity, function.upvalue_count)),
+            constant => Err(self.invalid(offset, format!("CLOSURE of {} which is not a function", constant.type_name()))),
+        }
+    }
+
+    fn check_operands(&self, offset: usize, opcode: Opcode) -> Result<(), BytecodeError> {
+        let code = &self.chunk.code;
+        match opcode {
+            Opcode::LC => {
+                self.constant(offset)?;
+            }
+            Opcode::GETGLOBAL
+            | Opcode::SETGLOBAL
+            | Opcode::DEFGLOBAL
+            | Opcode::GETPROP
+            | Opcode::SETPROP
+            | Opcode::CLASS
+            | Opcode::METHOD
+            | Opcode::GETSUPER
+            | Opcode::IMPORT => match self.constant(offset)? {
+                Object::Str(_) => {}
+                constant => {
+                    return Err(self.invalid(
+                        offset,
+                        format!("{:?} names a {} constant, not a string", opcode, constant.type_name()),
+                    ))
+                }
+            },
+            Opcode::GETUPVAL | Opcode::SETUPVAL => {
+                let index = code[offset + 1] as usize;
+                if index >= self.upvalue_count {
+                    return Err(self.invalid(
+                        offset,
+                        format!("upvalue {} out of range, the function captures {}", index, self.upvalue_count),
+                    ));
+                }
+            }
+            Opcode::CLOSURE => {
+                let (_, count) = self.function_at(offset)?;
+                for pair in 0..count {
+                    let at = offset + 2 + pair * 2;
+                    match code[at] {
+                        0 if (code[at + 1] as usize) >= self.upvalue_count => {
+                            return Err(self.invalid(
+                                offset,
+                                format!("captures upvalue {} of {} upvalues", code[at + 1], self.upvalue_count),
+                            ))
+                        }
+                        0 | 1 => {}
+                        flag => return Err(self.invalid(offset, format!("upvalue flag {} is neither 0 nor 1", flag))),
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn jump_target(&self, offset: usize) -> Option<usize> {
+        match Opcode::from(self.chunk.code[offset]) {
+            opcode if opcode.is_jump() => Some(self.chunk.read_u16(offset + 1) as usize),
+            _ => None,
+        }
+    }
+
+    /*
+      values an instruction pops and pushes, the stack must hold more
+      than it pops
+    */
+    fn stack_effect(&self, offset: usize, opcode: Opcode) -> (usize, usize) {
+        let operand = || self.chunk.code[offset + 1] as usize;
+        match opcode {
+            Opcode::ADD
+            | Opcode::SUB
+            | Opcode::MUL
+            | Opcode::DIV
+            | Opcode::MOD
+            | Opcode::POW
+            | Opcode::IDIV
+            | Opcode::LT
+            | Opcode::GT
+            | Opcode::GOE
+            | Opcode::LOE
+            | Opcode::EQ
+            | Opcode::NEQ
+            | Opcode::INDEX
+            | Opcode::IS
+            | Opcode::GETSUPER
+            | Opcode::SETPROP => (2, 1),
+            Opcode::JLT | Opcode::JGT | Opcode::JLOE | Opcode::JGOE | Opcode::JEQ | Opcode::JNEQ => (2, 0),
+            Opcode::INCLOCAL => (0, 1),
+            Opcode::NEG | Opcode::NOT | Opcode::GETPROP => (1, 1),
+            Opcode::SETGLOBAL | Opcode::SETLOCAL | Opcode::SETUPVAL => (1, 1),
+            Opcode::LC | Opcode::GETGLOBAL | Opcode::GETLOCAL | Opcode::GETUPVAL => (0, 1),
+            Opcode::CLOSURE | Opcode::CLASS | Opcode::IMPORT => (0, 1),
+            Opcode::POP
+            | Opcode::PRINT
+            | Opcode::DEFGLOBAL
+            | Opcode::CLOSEUPVAL
+            | Opcode::JNE
+            | Opcode::METHOD
+            | Opcode::RET
+            | Opcode::THROW => (1, 0),
+            // the class stays under the method
+            Opcode::INHERIT => (2, 0),
+            Opcode::CALL => (operand() + 1, 1),
+            Opcode::ARRAY => (operand(), 1),
+            // these only pop when they do not jump, see successors
+            Opcode::AND | Opcode::OR => (1, 1),
+            Opcode::JMP | Opcode::TRY | Opcode::ENDTRY | Opcode::PUSH | Opcode::REASSIGN | Opcode::NOP => (0, 0),
+        }
+    }
+
+    /*
+      follows every path from the start of the chunk keeping track of
+      how deep the stack is, entering with the callee and its arguments,
+      and which local slots open upvalues point at. paths meeting with
+      different captured slots go on with all of them
+    */
+    fn check_depths(&self, starts: &[bool], entry: usize) -> Result<(), BytecodeError> {
+        let code = &self.chunk.code;
+        let mut depths: Vec<Option<usize>> = vec![None; code.len()];
+        let mut captures: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); code.len()];
+        let mut pending = vec![(0, entry, BTreeSet::new())];
+        while let Some((offset, depth, mut captured)) = pending.pop() {
+            if offset >= code.len() {
+                return Err(self.invalid(offset, "execution runs past the end of the code".to_owned()));
+            }
+            debug_assert!(starts[offset]);
+            match depths[offset] {
+                Some(known) if known == depth && captured.is_subset(&captures[offset]) => continue,
+                Some(known) if known == depth => {
+                    captures[offset].extend(captured);
+                    captured = captures[offset].clone();
+                }
+                Some(known) => {
+                    return Err(self.invalid(
+                        offset,
+                        format!("stack depth is {} on one path and {} on another", known, depth),
+                    ))
+                }
+                None => {
+                    depths[offset] = Some(depth);
+                    captures[offset] = captured.clone();
+                }
+            }
+
+            let opcode = Opcode::from(code[offset]);
+            let (pops, pushes) = self.stack_effect(offset, opcode);
+            // slot 0 holds the running function and is never popped
+            if pops >= depth {
+                return Err(self.invalid(
+                    offset,
+                    format!("{:?} pops {} values from a stack of {}", opcode, pops, depth - 1),
+                ));
+            }
+            if matches!(opcode, Opcode::GETLOCAL | Opcode::SETLOCAL | Opcode::INCLOCAL) && code[offset + 1] as usize >= depth {
+                return Err(self.invalid(
+                    offset,
+                    format!("local slot {} is past the top of a stack of {}", code[offset + 1], depth),
+                ));
+            }
+            // the vm would read the slot of an upvalue after it was popped
+            if opcode == Opcode::CLOSEUPVAL {
+                captured.remove(&(depth - 1));
+            } else if let Some(slot) = captured.range(depth - pops..).next() {
+                return Err(self.invalid(
+                    offset,
+                    format!("{:?} drops local slot {} while an upvalue captures it", opcode, slot),
+                ));
+            }
+            // a local function captures the slot its own closure is pushed to
+            if opcode == Opcode::CLOSURE {
+                for pair in (offset + 2..offset + self.instruction_len(offset, opcode)?).step_by(2) {
+                    if code[pair] == 1 && code[pair + 1] as usize > depth {
+                        return Err(self.invalid(
+                            offset,
+                            format!("captures local slot {} past the top of a stack of {}", code[pair + 1], depth),
+                        ));
+                    }
+                    if code[pair] == 1 {
+                        captured.insert(code[pair + 1] as usize);
+                    }
+                }
+            }
+            let next = offset + self.instruction_len(offset, opcode)?;
+            let after = depth - pops + pushes;
+            match opcode {
+                Opcode::RET | Opcode::THROW => {}
+                Opcode::JMP => pending.push((self.jump_target(offset).unwrap(), depth, captured)),
+                _ if opcode == Opcode::JNE || opcode.fused_comparison().is_some() => {
+                    pending.push((self.jump_target(offset).unwrap(), after, captured.clone()));
+                    pending.push((next, after, captured));
+                }
+                // the operand stays when jumping and is popped otherwise
+                Opcode::AND | Opcode::OR => {
+                    pending.push((self.jump_target(offset).unwrap(), depth, captured.clone()));
+                    pending.push((next, depth - 1, captured));
+                }
+                // the handler resumes with the error on top of the stack as it was here
+                Opcode::TRY => {
+                    let kept = captured.range(..depth).copied().collect();
+                    pending.push((self.jump_target(offset).unwrap(), depth + 1, kept));
+                    pending.push((next, depth, captured));
+                }
+                _ => pending.push((next, after, captured)),
+            }
+        }
+        Ok(())
+    }
+}
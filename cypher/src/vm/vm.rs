@@ -1,10 +1,110 @@
-use super::{callframe::CallFrame, chunk::Chunk, object::{self, Object}, opcode::Opcode};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use crate::{
+    errors::{compile::CompileError, ErrorKind, RuntimeError, TraceFrame},
+    objects::{
+        class::{is_instance, Class, Instance},
+        methods::MethodTables,
+        module::Module,
+        native::{Caller, Native, NativeRegistry},
+        BoundMethod, Object,
+    },
+    compiler::{compiler::Compiler, register::RegisterCompiler},
+    parser::expr::Program,
+    modules::{exported_names, parse_module, ModuleLoader},
+    policy::Policy,
+    stdlib::string,
+};
+
+use super::{
+    callframe::{CallFrame, Handler},
+    chunk::Chunk,
+    heap::{GcStats, Heap, Node},
+    interner::Interner,
+    profile::{Profile, Profiler},
+    trace::Tracer,
+    debug::Hook,
+    object::{Closure, CompiledFunction, Globals, Upvalue},
+    opcode::Opcode,
+    verify::verify,
+};
+
+const FRAMES_MAX: usize = 1024;
+/*
+  each call back from a native runs a nested execute on the native stack.
+  the nested run gets a new stack segment once less than STACK_RED_ZONE
+  is left, and past CALLBACKS_MAX nested runs the call is a StackOverflow
+*/
+const CALLBACKS_MAX: usize = 256;
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+/*
+  the instruction set programs are compiled to. both run on the same vm
+  with the same values and natives and can call each other's functions
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Machine {
+    #[default]
+    Stack,
+    // see vm::register
+    Register,
+}
 
 pub struct VM {
-    stack: Vec<Object>,
-    frames:Vec<CallFrame>,
-    fp:usize,
-    sp:usize,
+    pub(super) stack: Vec<Object>,
+    pub(super) frames:Vec<CallFrame>,
+    pub(super) fp:usize,
+    pub(super) sp:usize,
+    // globals of the main program, modules get their own
+    globals: Globals,
+    modules: ModuleLoader,
+    natives: NativeRegistry,
+    methods: MethodTables,
+    // upvalues still pointing into the stack, closed when their slot is popped
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    // execute returns once a RET brings the frame count back down to this
+    exit_depth: usize,
+    // executes nested in natives calling back into the vm, see CALLBACKS_MAX
+    callbacks: usize,
+    pub(super) output: Box<dyn Write>,
+    // budget of the current run, see Policy
+    instructions: u64,
+    allocated: usize,
+    // bytes the running native reserved for its result, see Caller::reserve
+    reserved: usize,
+    max_instructions: Option<u64>,
+    max_memory: Option<usize>,
+    heap: Heap,
+    interner: Interner,
+    // what compile and imports compile to
+    machine: Machine,
+    profiler: Option<Profiler>,
+    tracer: Option<Tracer>,
+    // see debug::Hook
+    pub(super) hook: Option<Box<dyn Hook>>,
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Caller for VM {
+    fn call(&mut self, callee: Object, args: Vec<Object>) -> Result<Object, RuntimeError> {
+        VM::call(self, callee, args)
+    }
+
+    fn reserve(&mut self, bytes: usize) -> Result<(), RuntimeError> {
+        self.reserved = self.reserved.saturating_add(bytes);
+        self.charge(bytes)
+    }
 }
 
 impl VM {
@@ -12,20 +112,364 @@ impl VM {
         Self {
             sp:0,
             fp:0,
-            frames:Vec::with_capacity(1024),
+            frames:Vec::with_capacity(FRAMES_MAX),
             stack: Vec::with_capacity(256),
+            globals: Rc::new(RefCell::new(HashMap::new())),
+            modules: ModuleLoader::new(),
+            natives: NativeRegistry::with_builtins(),
+            methods: MethodTables::new(),
+            open_upvalues: vec![],
+            exit_depth: 0,
+            callbacks: 0,
+            output: Box::new(io::stdout()),
+            instructions: 0,
+            allocated: 0,
+            reserved: 0,
+            max_instructions: None,
+            max_memory: None,
+            heap: Heap::new(),
+            interner: Interner::new(),
+            machine: Machine::Stack,
+            profiler: None,
+            tracer: None,
+            hook: None,
+        }
+    }
+
+    /*
+      restricts the natives and modules the program can use and how
+      many instructions and bytes of memory each run may take, see
+      Policy
+    */
+    pub fn set_policy(&mut self, policy: Policy) {
+        self.max_instructions = policy.max_instructions;
+        self.max_memory = policy.max_memory;
+        self.modules.set_policy(policy);
+    }
+
+    // instructions executed by the last run or host call
+    pub fn instruction_count(&self) -> u64 {
+        self.instructions
+    }
+
+    /*
+      collects garbage on every allocation instead of once enough was
+      allocated, slow but finds objects the collector frees too early
+    */
+    pub fn set_gc_stress(&mut self, stress: bool) {
+        self.heap.set_stress(stress);
+    }
+
+    pub fn gc_stats(&self) -> GcStats {
+        self.heap.stats()
+    }
+
+    /*
+      frees objects that are only kept alive by referring to each other,
+      the vm does this by itself as it allocates
+    */
+    pub fn collect_garbage(&mut self) {
+        let mut roots = vec![Node::Globals(self.globals.clone())];
+        roots.extend(self.frames.iter().map(|frame| Node::Closure(frame.closure.clone())));
+        roots.extend(self.open_upvalues.iter().map(|upvalue| Node::Upvalue(upvalue.clone())));
+        self.heap.collect(&self.stack, roots);
+    }
+
+    // hands a new object to the heap, collecting when that is due
+    pub(super) fn track(&mut self, node: Node, size: usize) {
+        if self.heap.track(&node, size) {
+            self.collect_garbage();
+        }
+    }
+
+    /*
+      charges a string, array or map the program made and tracks it when
+      nothing else holds it yet, one handed back by a native may be shared.
+      reserved bytes were charged before it was made
+    */
+    pub(super) fn allocate(&mut self, value: Object, reserved: usize) -> Result<Object, RuntimeError> {
+        let size = allocation(&value);
+        self.charge(size.saturating_sub(reserved))?;
+        let fresh = match &value {
+            Object::Str(s) => Rc::strong_count(s) == 1,
+            Object::Array(arr) => Rc::strong_count(arr) == 1,
+            Object::Map(map) => Rc::strong_count(map) == 1,
+            _ => false,
+        };
+        if let Some(node) = Node::of(&value).filter(|_| fresh) {
+            self.track(node, size);
+        }
+        Ok(value)
+    }
+
+    /*
+      natives visible to the program, register host functions here
+      before calling run
+    */
+    pub fn natives(&mut self) -> &mut NativeRegistry {
+        &mut self.natives
+    }
+
+    // search path and cache of imported modules
+    pub fn modules(&mut self) -> &mut ModuleLoader {
+        &mut self.modules
+    }
+
+    pub fn methods(&mut self) -> &mut MethodTables {
+        &mut self.methods
+    }
+
+    /*
+      redirects the output of print statements, stdout by default
+    */
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    /*
+      runs the chunk, returns the value of a top level return statement
+      or the error that stopped the program. the chunk is verified first,
+      nothing of it runs when that fails
+    */
+    pub fn run(&mut self,code: Chunk) -> Object {
+        if let Err(err) = verify(&code) {
+            return Object::error(ErrorKind::InvalidBytecode, err.to_string());
+        }
+        self.reset_budget();
+        let function = Rc::new(CompiledFunction::new("<script>".to_owned(), 0, 0, code));
+        let closure = Rc::new(Closure::new(function, vec![], self.globals.clone()));
+        self.push(Object::Closure(closure.clone()));
+        self.add_frame(CallFrame::new(closure, 0));
+        self.fit_frame();
+
+        let result = self.execute();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.pause();
+        }
+        match result {
+            Ok(value) => value,
+            Err(err) => {
+                self.reset();
+                Object::Error(err)
+            }
+        }
+    }
+
+    /*
+      calls a function value with the given arguments and runs it to
+      completion, can be used from the host between runs or from a
+      native while the vm is running
+    */
+    pub fn call(&mut self, callee: Object, args: Vec<Object>) -> Result<Object, RuntimeError> {
+        let depth = self.fp;
+        let base = self.sp;
+        let argc = args.len();
+        if depth == 0 {
+            self.reset_budget();
+        }
+        self.push(callee);
+        for arg in args {
+            self.push(arg);
+        }
+        let result = match self.call_value(argc) {
+            // natives finish inside call_value
+            Ok(()) if self.fp == depth => Ok(self.pop()),
+            Ok(()) if self.callbacks >= CALLBACKS_MAX => {
+                Err(self.error(ErrorKind::StackOverflow, "stack overflow".to_owned()))
+            }
+            Ok(()) => {
+                let previous = std::mem::replace(&mut self.exit_depth, depth);
+                self.callbacks += 1;
+                let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || self.execute());
+                self.callbacks -= 1;
+                self.exit_depth = previous;
+                result
+            }
+            Err(err) => Err(err),
+        };
+        if depth == 0 {
+            if let Some(profiler) = &mut self.profiler {
+                profiler.pause();
+            }
+        }
+        if result.is_err() {
+            if depth == 0 {
+                self.reset();
+            } else {
+                self.unwind(depth, base);
+            }
+        }
+        result
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        self.globals.borrow().get(name).cloned()
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Object) {
+        let name = self.interner.intern(name);
+        self.globals.borrow_mut().insert(name, value);
+    }
+
+    /*
+      strings shared by the code this vm runs, compile with
+      Compiler::with_interner(file, vm.interner()) to use them
+    */
+    pub fn interner(&self) -> Interner {
+        self.interner.clone()
+    }
+
+    /*
+      samples every instruction of the following runs and host calls
+      until turned off, see profile. off by default, turning it on again
+      starts over
+    */
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiler = enabled.then(Profiler::new);
+    }
+
+    // where the runs since profiling was turned on spent their time
+    pub fn profile(&mut self) -> Option<Profile> {
+        self.profiler.as_mut().map(Profiler::report)
+    }
+
+    // writes every instruction before it runs, None turns tracing off
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        self.tracer = tracer;
+    }
+
+    // selects the compiler used by compile and by imports
+    pub fn set_machine(&mut self, machine: Machine) {
+        self.machine = machine;
+    }
+
+    pub fn machine(&self) -> Machine {
+        self.machine
+    }
+
+    // compiles a program for the machine of this vm, sharing its strings
+    pub fn compile(&self, filename: String, program: Program) -> Result<Chunk, CompileError> {
+        match self.machine {
+            Machine::Stack => Compiler::with_interner(filename, self.interner()).try_compile_program(program),
+            Machine::Register => RegisterCompiler::with_interner(filename, self.interner()).try_compile_program(program),
+        }
+    }
+
+    fn execute(&mut self) -> Result<Object, RuntimeError> {
+        loop {
+            self.instructions += 1;
+            if let Some(limit) = self.max_instructions {
+                if self.instructions > limit {
+                    let message = format!("instruction limit of {} exceeded", limit);
+                    return Err(self.error(ErrorKind::BudgetExceeded, message));
+                }
+            }
+            if let Some(profiler) = &mut self.profiler {
+                profiler.sample(&self.frames[self.fp-1]);
+            }
+            if let Some(tracer) = &mut self.tracer {
+                tracer.trace(&self.frames[..self.fp], &self.stack);
+            }
+            if let Some(mut hook) = self.hook.take() {
+                let result = hook.before_instruction(self);
+                // unless the hook set another one
+                if self.hook.is_none() {
+                    self.hook = Some(hook);
+                }
+                if let Err(err) = result {
+                    self.catch(err)?;
+                    continue;
+                }
+            }
+            let result = if self.frames[self.fp-1].registers > 0 {
+                self.execute_register()
+            } else {
+                let opcode = self.decode_opcode();
+                self.execute_instruction(opcode)
+            };
+            match result {
+                Ok(Some(value)) => return Ok(value),
+                Ok(None) => {}
+                Err(err) => self.catch(err)?,
+            }
         }
     }
 
-    pub fn run(&mut self,code: Chunk) {
-        let frame=CallFrame::new("main".to_owned(),code.code,code.constants,vec![]);
-        self.add_frame(frame);
-        while self.get_current_frame().ip < self.get_current_frame().get_code_length() {
-            let opcode = self.decode_opcode();
-            self.execute_instruction(opcode)
+    /*
+      resumes at the innermost try block entered by this execute, the
+      frames and stack above it are dropped. returns the error when
+      nothing catches it
+    */
+    fn catch(&mut self, err: RuntimeError) -> Result<(), RuntimeError> {
+        if !err.kind.is_catchable() {
+            return Err(err);
         }
+        let Some(depth) = (self.exit_depth..self.fp).rev().find(|&depth| !self.frames[depth].handlers.is_empty()) else {
+            return Err(err);
+        };
+        let handler = self.frames[depth].handlers.pop().unwrap();
+        self.close_upvalues(handler.sp)?;
+        self.frames.truncate(depth + 1);
+        self.fp = depth + 1;
+        self.stack.truncate(handler.sp);
+        self.sp = handler.sp;
+        self.frames[depth].ip = handler.ip;
+        let err = Rc::new(err);
+        self.push(Object::Exception(err.clone()));
+        self.fit_frame();
+        self.track(Node::Exception(err), size_of::<RuntimeError>());
+        Ok(())
+    }
 
-        // println!("{:?}",self.stack)
+    /*
+      gives a frame of register code all of its registers back, the
+      stack above them was cut to call a function or catch an error
+    */
+    pub(super) fn fit_frame(&mut self) {
+        let frame = &self.frames[self.fp-1];
+        if frame.registers > 0 {
+            let top = frame.base + frame.registers;
+            self.stack.resize(top, Object::Nil);
+            self.sp = top;
+        }
+    }
+
+    fn reset_budget(&mut self) {
+        self.instructions = 0;
+        self.allocated = 0;
+    }
+
+    /*
+      counts bytes allocated by the running program against the memory
+      limit. this is a running total, memory freed again is not given back
+    */
+    pub(super) fn charge(&mut self, bytes: usize) -> Result<(), RuntimeError> {
+        self.allocated = self.allocated.saturating_add(bytes);
+        match self.max_memory {
+            Some(limit) if self.allocated > limit => {
+                let message = format!("memory limit of {} bytes exceeded", limit);
+                Err(self.error(ErrorKind::BudgetExceeded, message))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.stack.clear();
+        self.frames.clear();
+        self.open_upvalues.clear();
+        self.sp = 0;
+        self.fp = 0;
+    }
+
+    // drops the frames and stack of a failed call made while running
+    fn unwind(&mut self, depth: usize, base: usize) {
+        // the error is on its way out already, an upvalue past the stack is dropped
+        let _ = self.close_upvalues(base);
+        self.frames.truncate(depth);
+        self.fp = depth;
+        self.stack.truncate(base);
+        self.sp = base;
     }
 
     fn add_frame(&mut self,frame:CallFrame) {
@@ -33,77 +477,739 @@ impl VM {
         self.fp+=1;
     }
 
-    fn is_truthy(&self,object:&Object)->bool{
-        match object {
-            Object::Number(_) => true,
-            Object::Nil => false,
-            Object::Boolean(b) => *b,
-            Object::Str(_) => true,
+    pub(super) fn error(&mut self, kind: ErrorKind, message: String) -> RuntimeError {
+        self.locate(RuntimeError::new(kind, message))
+    }
+
+    /*
+      records the running calls in the trace of an error raised here,
+      errors passed up from a nested call keep the trace they have
+    */
+    pub(super) fn locate(&self, mut err: RuntimeError) -> RuntimeError {
+        if err.trace.is_empty() {
+            err.trace = self.frames[..self.fp]
+                .iter()
+                .rev()
+                .map(|frame| TraceFrame {
+                    function: frame.name().to_owned(),
+                    file: frame.closure.function.chunk.file.to_owned(),
+                    line: Some(frame.current_line()),
+                })
+                .collect();
         }
+        err
     }
 
-    fn execute_instruction(&mut self, opcode: Opcode) {
+    /*
+      executes a single instruction, yields a value once the outermost
+      frame returns
+    */
+    fn execute_instruction(&mut self, opcode: Opcode) -> Result<Option<Object>, RuntimeError> {
         match opcode {
-            Opcode::ADD => {
+            Opcode::ADD
+            | Opcode::SUB
+            | Opcode::MUL
+            | Opcode::DIV
+            | Opcode::MOD
+            | Opcode::POW
+            | Opcode::IDIV
+            | Opcode::LT
+            | Opcode::GT
+            | Opcode::GOE
+            | Opcode::LOE => self.binary_op(opcode)?,
+            Opcode::EQ => {
+                let right=self.pop();
                 let left=self.pop();
+                self.push(Object::Bool(left == right));
+            }
+            Opcode::NEQ => {
                 let right=self.pop();
-                match (left,right) {
-                    (Object::Number(a), Object::Number(b)) => self.push(Object::Number(a+b)),
-                    _=>todo!()
+                let left=self.pop();
+                self.push(Object::Bool(left != right));
+            }
+            Opcode::NEG => {
+                let value=self.pop();
+                let value=self.negate(value)?;
+                self.push(value);
+            }
+            Opcode::NOT => {
+                let value=self.pop();
+                self.push(Object::Bool(!value.is_truthy()));
+            }
+            Opcode::JLT | Opcode::JGT | Opcode::JLOE | Opcode::JGOE | Opcode::JEQ | Opcode::JNEQ => {
+                let index=self.read_u16();
+                let right=self.pop();
+                let left=self.pop();
+                let holds = match opcode.fused_comparison().unwrap() {
+                    Opcode::EQ => left == right,
+                    Opcode::NEQ => left != right,
+                    comparison => self.binary_value(comparison, &left, &right)?.is_truthy(),
+                };
+                if !holds {
+                    self.get_current_frame().ip=index as usize;
                 }
             }
-            Opcode::PUSH => {
-                
+            Opcode::INCLOCAL => {
+                let slot=self.read_byte() as usize;
+                let base=self.get_current_frame().base;
+                let value = match &self.stack[base+slot] {
+                    Object::Number(n) => Object::Number(n + 1.0),
+                    left => {
+                        let left = left.clone();
+                        return Err(self.unknown_operation(&left, Opcode::ADD, &Object::Number(1.0)));
+                    }
+                };
+                self.stack[base+slot]=value.clone();
+                self.push(value);
             }
+            Opcode::PUSH | Opcode::REASSIGN | Opcode::NOP => {}
             Opcode::LC=>{
-                let index=self.read_next_8bytes();
+                let index=self.read_byte();
                 let obj=self.get_current_frame().get_constant(index as usize).to_owned();
                 self.push(obj);
             },
-            Opcode::POP => todo!(),
-            Opcode::SUB => todo!(),
-            Opcode::MUL => todo!(),
-            Opcode::DIV => todo!(),
-            Opcode::MOD => todo!(),
+            Opcode::POP => {
+                self.pop();
+            }
             Opcode::PRINT => {
-                println!("{}",self.pop());
+                let value=self.pop();
+                let _ = writeln!(self.output, "{}", value);
             },
-            Opcode::NOP => todo!(),
             Opcode::JMP => {
-                let index=self.read_next_8bytes();
+                let index=self.read_u16();
                 self.get_current_frame().ip=index as usize;
             },
             Opcode::JNE => {
+                let index=self.read_u16();
                 let condition=self.pop();
-                if !self.is_truthy(&condition){
-                    self.get_current_frame().ip+=1;
+                if !condition.is_truthy(){
+                    self.get_current_frame().ip=index as usize;
                 }
-                else{
-                    let index=self.read_next_8bytes();
+            },
+            Opcode::AND => {
+                let index=self.read_u16();
+                if !self.peek(0).is_truthy() {
                     self.get_current_frame().ip=index as usize;
+                } else {
+                    self.pop();
                 }
-              
+            }
+            Opcode::OR => {
+                let index=self.read_u16();
+                if self.peek(0).is_truthy() {
+                    self.get_current_frame().ip=index as usize;
+                } else {
+                    self.pop();
+                }
+            }
+            Opcode::DEFGLOBAL => {
+                let name=self.read_name();
+                let value=self.pop();
+                self.define_global(name, value);
+            }
+            Opcode::GETGLOBAL => {
+                let name=self.read_name();
+                let value=self.global(name)?;
+                self.push(value);
             },
-            Opcode::LT => {
-                let left=self.pop();
-                let right=self.pop();
-                match (left,right) {
-                    (Object::Number(a), Object::Number(b)) => self.push(Object::Boolean(a<b)),
-                    _=>todo!()
+            Opcode::SETGLOBAL => {
+                let name=self.read_name();
+                let value=self.peek(0).clone();
+                self.assign_global(name, value)?;
+            }
+            Opcode::GETLOCAL => {
+                let slot=self.read_byte() as usize;
+                let base=self.get_current_frame().base;
+                let value=self.stack[base+slot].clone();
+                self.push(value);
+            }
+            Opcode::SETLOCAL => {
+                let slot=self.read_byte() as usize;
+                let base=self.get_current_frame().base;
+                self.stack[base+slot]=self.peek(0).clone();
+            }
+            Opcode::GETUPVAL => {
+                let index=self.read_byte() as usize;
+                let value=self.upvalue(index)?;
+                self.push(value);
+            }
+            Opcode::SETUPVAL => {
+                let index=self.read_byte() as usize;
+                let value=self.peek(0).clone();
+                self.set_upvalue(index, value)?;
+            }
+            Opcode::CLOSEUPVAL => {
+                self.close_upvalues(self.sp-1)?;
+                self.pop();
+            }
+            Opcode::CLOSURE => {
+                let index=self.read_byte() as usize;
+                let function=self.function_constant(index)?;
+                let mut upvalues = Vec::with_capacity(function.upvalue_count);
+                for _ in 0..function.upvalue_count {
+                    let is_local=self.read_byte()==1;
+                    let index=self.read_byte() as usize;
+                    upvalues.push(self.capture(is_local, index));
+                }
+                let closure=self.closure(function, upvalues)?;
+                self.push(closure);
+            }
+            Opcode::CALL => {
+                let argc=self.read_byte() as usize;
+                self.call_value(argc)?;
+            }
+            Opcode::RET => {
+                let result=self.pop();
+                return self.return_value(result);
+            }
+            Opcode::ARRAY => {
+                let count=self.read_byte() as usize;
+                let elements=self.stack.split_off(self.sp-count);
+                self.sp-=count;
+                let array=self.allocate(Object::array(elements), 0)?;
+                self.push(array);
+            }
+            Opcode::GETPROP => {
+                let name=self.read_name();
+                let receiver=self.pop();
+                let value=self.get_property(receiver, &name)?;
+                self.push(value);
+            }
+            Opcode::SETPROP => {
+                let name=self.read_name();
+                let value=self.pop();
+                let obj=self.pop();
+                self.set_property(obj, &name, value.clone())?;
+                self.push(value);
+            }
+            Opcode::CLASS => {
+                let name=self.read_name();
+                let class=self.new_class(&name)?;
+                self.push(class);
+            }
+            Opcode::METHOD => {
+                let name=self.read_name();
+                let method=self.pop();
+                add_method(self.peek(0), &name, method);
+            }
+            Opcode::IMPORT => {
+                let path=self.read_name();
+                let module=self.import(&path)?;
+                self.push(module);
+            }
+            Opcode::TRY => {
+                let ip=self.read_u16() as usize;
+                let sp=self.sp;
+                self.get_current_frame().handlers.push(Handler { ip, sp });
+            }
+            Opcode::ENDTRY => {
+                self.get_current_frame().handlers.pop();
+            }
+            Opcode::THROW => {
+                let value=self.pop();
+                return Err(self.throw(value));
+            }
+            Opcode::INHERIT => {
+                let superclass=self.pop();
+                let class=self.pop();
+                self.inherit(class, superclass)?;
+            }
+            Opcode::GETSUPER => {
+                let name=self.read_name();
+                let superclass=self.pop();
+                let receiver=self.pop();
+                let method=self.super_method(receiver, superclass, &name)?;
+                self.push(method);
+            }
+            Opcode::IS => {
+                let class=self.pop();
+                let value=self.pop();
+                let result=self.is_value(&value, class)?;
+                self.push(result);
+            }
+            Opcode::INDEX => {
+                let index=self.pop();
+                let obj=self.pop();
+                let value = self.index_value(obj, index)?;
+                self.push(value);
+            }
+        }
+        Ok(None)
+    }
+
+    /*
+      the operations below are shared by the stack and the register
+      machine, they only differ in where operands come from
+    */
+
+    pub(super) fn negate(&mut self, value: Object) -> Result<Object, RuntimeError> {
+        match value {
+            Object::Number(num) => Ok(Object::Number(-num)),
+            _ => Err(self.error(
+                ErrorKind::TypeError,
+                format!("Unknown operation -{}", value.type_name()),
+            )),
+        }
+    }
+
+    pub(super) fn is_value(&mut self, value: &Object, class: Object) -> Result<Object, RuntimeError> {
+        match class {
+            Object::Class(class) => Ok(Object::Bool(is_instance(value, &class))),
+            class => Err(self.error(
+                ErrorKind::TypeError,
+                format!("right operand of is must be a class, got {}", class.type_name()),
+            )),
+        }
+    }
+
+    pub(super) fn define_global(&mut self, name: Rc<str>, value: Object) {
+        self.get_current_frame().closure.globals.borrow_mut().insert(name, value);
+    }
+
+    // a global of the running module, or a native of that name
+    pub(super) fn global(&mut self, name: Rc<str>) -> Result<Object, RuntimeError> {
+        let global = self.get_current_frame().closure.globals.borrow().get(&name).cloned();
+        match global {
+            Some(value) => Ok(value),
+            None => match self.natives.get(&name) {
+                Some(native) => Ok(native),
+                None => Err(self.error(
+                    ErrorKind::NameError,
+                    format!("identifier {} not found", name),
+                )),
+            },
+        }
+    }
+
+    pub(super) fn assign_global(&mut self, name: Rc<str>, value: Object) -> Result<(), RuntimeError> {
+        let globals=self.get_current_frame().closure.globals.clone();
+        let found = match globals.borrow_mut().get_mut(&name) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        };
+        if !found {
+            return Err(self.error(
+                ErrorKind::NameError,
+                format!("identifier {} not found", name),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(super) fn upvalue(&mut self, index: usize) -> Result<Object, RuntimeError> {
+        let upvalue=self.get_current_frame().closure.upvalues[index].clone();
+        let value = match &*upvalue.borrow() {
+            Upvalue::Open(slot) => match self.stack.get(*slot) {
+                Some(value) => value.clone(),
+                None => return Err(self.dangling_upvalue(*slot)),
+            },
+            Upvalue::Closed(value) => value.clone(),
+        };
+        Ok(value)
+    }
+
+    pub(super) fn set_upvalue(&mut self, index: usize, value: Object) -> Result<(), RuntimeError> {
+        let upvalue=self.get_current_frame().closure.upvalues[index].clone();
+        match &mut *upvalue.borrow_mut() {
+            Upvalue::Open(slot) if *slot < self.stack.len() => self.stack[*slot] = value,
+            Upvalue::Open(slot) => return Err(self.dangling_upvalue(*slot)),
+            Upvalue::Closed(closed) => *closed = value,
+        };
+        Ok(())
+    }
+
+    // an open upvalue whose slot was popped without closing it, only bad bytecode does that
+    fn dangling_upvalue(&mut self, slot: usize) -> RuntimeError {
+        self.error(ErrorKind::InvalidBytecode, format!("upvalue of stack slot {} outlived the slot", slot))
+    }
+
+    pub(super) fn function_constant(&mut self, index: usize) -> Result<Rc<CompiledFunction>, RuntimeError> {
+        match self.get_current_frame().get_constant(index) {
+            Object::CompiledFunction(function) => Ok(function.clone()),
+            obj => {
+                let message = format!("{} is not a function", obj);
+                Err(self.error(ErrorKind::TypeError, message))
+            }
+        }
+    }
+
+    // an upvalue for a new closure, a local of the running frame or one of its upvalues
+    pub(super) fn capture(&mut self, is_local: bool, index: usize) -> Rc<RefCell<Upvalue>> {
+        if is_local {
+            let base=self.get_current_frame().base;
+            self.capture_upvalue(base+index)
+        } else {
+            self.get_current_frame().closure.upvalues[index].clone()
+        }
+    }
+
+    pub(super) fn closure(&mut self, function: Rc<CompiledFunction>, upvalues: Vec<Rc<RefCell<Upvalue>>>) -> Result<Object, RuntimeError> {
+        let globals=self.get_current_frame().closure.globals.clone();
+        self.charge(size_of::<Closure>())?;
+        let closure=Rc::new(Closure::new(function, upvalues, globals));
+        self.track(Node::Closure(closure.clone()), size_of::<Closure>());
+        Ok(Object::Closure(closure))
+    }
+
+    /*
+      pops the running frame, returns the result when that ends the
+      execute, otherwise hands it to the caller in the slot of the callee
+    */
+    pub(super) fn return_value(&mut self, result: Object) -> Result<Option<Object>, RuntimeError> {
+        self.close_upvalues(self.frames[self.fp-1].base)?;
+        let frame=self.frames.pop().unwrap();
+        self.fp-=1;
+        self.stack.truncate(frame.base);
+        self.sp=frame.base;
+        if self.fp==self.exit_depth {
+            return Ok(Some(result));
+        }
+        self.push(result);
+        self.fit_frame();
+        Ok(None)
+    }
+
+    pub(super) fn get_property(&mut self, receiver: Object, name: &str) -> Result<Object, RuntimeError> {
+        match self.methods.get_property(receiver, name) {
+            Ok(value) => {
+                if let Object::BoundMethod(bound) = &value {
+                    self.track(Node::BoundMethod(bound.clone()), size_of::<BoundMethod>());
+                }
+                Ok(value)
+            }
+            Err(err) => Err(self.locate(err)),
+        }
+    }
+
+    pub(super) fn set_property(&mut self, obj: Object, name: &str, value: Object) -> Result<(), RuntimeError> {
+        match obj {
+            Object::Instance(instance) => {
+                self.charge(name.len() + size_of::<Object>())?;
+                instance.set_field(name.to_string(), value);
+                Ok(())
+            }
+            obj => Err(self.error(
+                ErrorKind::TypeError,
+                format!("cannot set property {} on {}", name, obj.type_name()),
+            )),
+        }
+    }
+
+    pub(super) fn new_class(&mut self, name: &str) -> Result<Object, RuntimeError> {
+        self.charge(size_of::<Class>())?;
+        let class=Rc::new(Class::new(name.to_string()));
+        self.track(Node::Class(class.clone()), size_of::<Class>());
+        Ok(Object::Class(class))
+    }
+
+    pub(super) fn inherit(&mut self, class: Object, superclass: Object) -> Result<(), RuntimeError> {
+        match (class, superclass) {
+            (Object::Class(class), Object::Class(superclass)) => {
+                class.set_superclass(superclass).map_err(|err| self.locate(err))
+            }
+            (_, superclass) => Err(self.error(
+                ErrorKind::TypeError,
+                format!("superclass must be a class, got {}", superclass.type_name()),
+            )),
+        }
+    }
+
+    // method name of superclass bound to receiver, for super.name
+    pub(super) fn super_method(&mut self, receiver: Object, superclass: Object, name: &str) -> Result<Object, RuntimeError> {
+        let Object::Class(superclass) = superclass else {
+            return Err(self.error(ErrorKind::NameError, "super used outside of a subclass".to_owned()));
+        };
+        match superclass.find_method(name) {
+            Some(method) => {
+                let bound=Rc::new(BoundMethod::new(receiver, method));
+                self.track(Node::BoundMethod(bound.clone()), size_of::<BoundMethod>());
+                Ok(Object::BoundMethod(bound))
+            }
+            None => Err(self.error(
+                ErrorKind::AttributeError,
+                format!("superclass {} has no method {}", superclass.name, name),
+            )),
+        }
+    }
+
+    pub(super) fn throw(&mut self, value: Object) -> RuntimeError {
+        let err = match value {
+            // rethrowing a caught error keeps where it was first raised
+            Object::Exception(err) => (*err).clone(),
+            value => RuntimeError::thrown(value),
+        };
+        self.locate(err)
+    }
+
+    fn binary_op(&mut self, opcode: Opcode) -> Result<(), RuntimeError> {
+        let right=self.pop();
+        let left=self.pop();
+        let result = self.binary_value(opcode, &left, &right)?;
+        self.push(result);
+        Ok(())
+    }
+
+    pub(super) fn binary_value(&mut self, opcode: Opcode, left: &Object, right: &Object) -> Result<Object, RuntimeError> {
+        let result = match (left, right) {
+            (Object::Number(a), Object::Number(b)) => match opcode {
+                Opcode::ADD => Object::Number(a + b),
+                Opcode::SUB => Object::Number(a - b),
+                Opcode::MUL => Object::Number(a * b),
+                Opcode::DIV => Object::Number(a / b),
+                Opcode::MOD => Object::Number(a % b),
+                Opcode::POW => Object::Number(a.powf(*b)),
+                Opcode::IDIV => Object::Number((a / b).floor()),
+                Opcode::LT => Object::Bool(a < b),
+                Opcode::GT => Object::Bool(a > b),
+                Opcode::GOE => Object::Bool(a >= b),
+                _ => Object::Bool(a <= b),
+            },
+            (Object::Str(a), Object::Str(b)) => match opcode {
+                Opcode::ADD => self.allocate(Object::string(format!("{}{}", a, b)), 0)?,
+                Opcode::LT => Object::Bool(a < b),
+                Opcode::GT => Object::Bool(a > b),
+                Opcode::GOE => Object::Bool(a >= b),
+                Opcode::LOE => Object::Bool(a <= b),
+                _ => return Err(self.unknown_operation(left, opcode, right)),
+            },
+            _ => return Err(self.unknown_operation(left, opcode, right)),
+        };
+        Ok(result)
+    }
+
+    pub(super) fn unknown_operation(&mut self, left: &Object, opcode: Opcode, right: &Object) -> RuntimeError {
+        self.error(
+            ErrorKind::TypeError,
+            format!("Unknown operation between {} {:?} {}", left, opcode, right),
+        )
+    }
+
+    pub(super) fn index_value(&mut self, obj: Object, index: Object) -> Result<Object, RuntimeError> {
+        match obj {
+            Object::Array(arr) => match index {
+                Object::Number(index) => {
+                    if index >= 0.0 {
+                        if let Some(obj) = arr.get(index as usize) {
+                            return Ok(obj.clone());
+                        }
+                    }
+                    Err(self.error(
+                        ErrorKind::IndexError,
+                        format!("index {} out of range for array of length {}", index, arr.len()),
+                    ))
                 }
+                _ => Err(self.error(ErrorKind::TypeError, "expected Number as index".to_owned())),
             },
-            Opcode::GT => todo!(),
-            Opcode::GOE => todo!(),
-            Opcode::LOE => todo!(),
-            Opcode::GETGLOBAL => {
-                let index=self.read_next_8bytes();
-                let obj=self.get_current_frame().get_constant(index as usize).to_owned();
-                self.push(obj);
+            Object::Str(s) => match index {
+                Object::Number(index) => {
+                    string::char_at(&s, index).map_err(|err| self.locate(err))
+                }
+                _ => Err(self.error(ErrorKind::TypeError, "expected Number as index".to_owned())),
+            },
+            Object::Map(map) => match index {
+                Object::Str(key) => match map.get(&*key) {
+                    Some(obj) => Ok(obj.clone()),
+                    None => Err(self.error(ErrorKind::IndexError, format!("key {:?} not found", key))),
+                },
+                _ => Err(self.error(ErrorKind::TypeError, "expected String as key".to_owned())),
+            },
+            _ => Err(self.error(
+                ErrorKind::TypeError,
+                format!("{} is not indexable", obj.type_name()),
+            )),
+        }
+    }
+
+    // runs a native, the budget it reserved is kept apart from that of natives it calls
+    fn call_native(&mut self, native: &Native, receiver: Option<Object>, args: Vec<Object>) -> Result<Object, RuntimeError> {
+        let outer = std::mem::take(&mut self.reserved);
+        let result = match receiver {
+            Some(receiver) => native.call_method(self, receiver, args),
+            None => native.call(self, args),
+        };
+        let reserved = std::mem::replace(&mut self.reserved, outer);
+        match result {
+            Ok(result) => self.allocate(result, reserved),
+            Err(err) => Err(self.locate(err)),
+        }
+    }
+
+    pub(super) fn call_value(&mut self, argc: usize) -> Result<(), RuntimeError> {
+        let callee=self.peek(argc).clone();
+        match callee {
+            Object::Closure(closure) => self.call_closure(closure, argc)?,
+            Object::Native(native) => {
+                let args=self.stack.split_off(self.sp-argc);
+                self.sp-=argc;
+                self.pop();
+                let result=self.call_native(&native, None, args)?;
+                self.push(result);
+            }
+            Object::BoundMethod(bound) => match &bound.method {
+                Object::Closure(closure) => {
+                    // the receiver takes the callee slot, where self resolves to
+                    let slot=self.sp-argc-1;
+                    self.stack[slot]=bound.receiver.clone();
+                    self.call_closure(closure.clone(), argc)?;
+                }
+                Object::Native(native) => {
+                    let args=self.stack.split_off(self.sp-argc);
+                    self.sp-=argc;
+                    self.pop();
+                    let result=self.call_native(native, Some(bound.receiver.clone()), args)?;
+                    self.push(result);
+                }
+                method => {
+                    return Err(self.error(
+                        ErrorKind::TypeError,
+                        format!("{} is not callable", method.type_name()),
+                    ))
+                }
             },
-            Opcode::SETGLOBAL => todo!(),
-            Opcode::REASSIGN => todo!(),
-            Opcode::GETLOCAL => todo!(),
-            Opcode::SETLOCAL => todo!(),
+            Object::Class(class) => {
+                let slot=self.sp-argc-1;
+                self.charge(size_of::<Instance>())?;
+                let instance=Rc::new(Instance::new(class.clone()));
+                self.stack[slot]=Object::Instance(instance.clone());
+                self.track(Node::Instance(instance), size_of::<Instance>());
+                match class.find_method("init") {
+                    Some(Object::Closure(init)) => self.call_closure(init, argc)?,
+                    _ if argc != 0 => {
+                        return Err(self.error(
+                            ErrorKind::ArityError,
+                            format!("Expected 0 arguments but got {}", argc),
+                        ))
+                    }
+                    _ => {}
+                }
+            }
+            _ => {
+                return Err(self.error(
+                    ErrorKind::TypeError,
+                    format!("{} is not callable", callee.type_name()),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /*
+      runs the module at path in its own globals unless it already ran,
+      paths are relative to the file of the running code
+    */
+    pub(super) fn import(&mut self, path: &str) -> Result<Object, RuntimeError> {
+        match self.modules.builtin(path) {
+            Ok(Some(module)) => return Ok(module),
+            Ok(None) => {}
+            Err(err) => return Err(self.locate(err)),
+        }
+        let importer = self.get_current_frame().closure.function.chunk.file.to_owned();
+        let resolved = match self.modules.resolve(path, &importer) {
+            Ok(resolved) => resolved,
+            Err(err) => return Err(self.locate(err)),
+        };
+        match self.modules.begin(&resolved) {
+            Ok(Some(module)) => return Ok(module),
+            Ok(None) => {}
+            Err(err) => return Err(self.locate(err)),
+        }
+
+        let program = match parse_module(&resolved) {
+            Ok(program) => program,
+            Err(err) => {
+                self.modules.abort();
+                return Err(self.locate(err));
+            }
+        };
+        let exports = exported_names(&program);
+        let filename = resolved.display().to_string();
+        let chunk = match self.compile(filename.to_owned(), program) {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                self.modules.abort();
+                return Err(self.error(ErrorKind::ImportError, err.to_string()));
+            }
+        };
+        if let Err(err) = verify(&chunk) {
+            self.modules.abort();
+            return Err(self.error(ErrorKind::InvalidBytecode, err.to_string()));
+        }
+        let function = Rc::new(CompiledFunction::new("<module>".to_owned(), 0, 0, chunk));
+        let globals: Globals = Rc::new(RefCell::new(HashMap::new()));
+        self.track(Node::Globals(globals.clone()), size_of::<HashMap<Rc<str>, Object>>());
+        let closure = Closure::new(function, vec![], globals.clone());
+        if let Err(err) = self.call(Object::Closure(Rc::new(closure)), vec![]) {
+            self.modules.abort();
+            return Err(err);
+        }
+
+        let globals = globals.borrow();
+        let exports = exports
+            .into_iter()
+            .filter_map(|name| globals.get(name.as_str()).map(|value| (name, value.clone())))
+            .collect();
+        let module = Object::Module(Rc::new(Module::new(resolved, exports)));
+        self.modules.finish(module.clone());
+        Ok(module)
+    }
+
+    fn call_closure(&mut self, closure: Rc<Closure>, argc: usize) -> Result<(), RuntimeError> {
+        if argc != closure.function.arity {
+            return Err(self.error(
+                ErrorKind::ArityError,
+                format!("Expected {} arguments but got {}", closure.function.arity, argc),
+            ));
+        }
+        if self.fp >= FRAMES_MAX {
+            return Err(self.error(ErrorKind::StackOverflow, "stack overflow".to_owned()));
+        }
+        let base=self.sp-argc-1;
+        self.add_frame(CallFrame::new(closure, base));
+        self.fit_frame();
+        Ok(())
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        for upvalue in &self.open_upvalues {
+            if let Upvalue::Open(open) = &*upvalue.borrow() {
+                if *open == slot {
+                    return upvalue.clone();
+                }
+            }
+        }
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        self.open_upvalues.push(upvalue.clone());
+        self.track(Node::Upvalue(upvalue.clone()), size_of::<RefCell<Upvalue>>());
+        upvalue
+    }
+
+    /*
+      closes every open upvalue pointing at or above the given stack slot,
+      fails when one points past the top of the stack
+    */
+    pub(super) fn close_upvalues(&mut self, last: usize) -> Result<(), RuntimeError> {
+        let stack = &self.stack;
+        let mut dangling = None;
+        self.open_upvalues.retain(|upvalue| {
+            let slot = match &*upvalue.borrow() {
+                Upvalue::Open(slot) => *slot,
+                Upvalue::Closed(_) => return false,
+            };
+            if slot >= last {
+                match stack.get(slot) {
+                    Some(value) => *upvalue.borrow_mut() = Upvalue::Closed(value.clone()),
+                    None => dangling = Some(slot),
+                }
+                return false;
+            }
+            true
+        });
+        match dangling {
+            Some(slot) => Err(self.dangling_upvalue(slot)),
+            None => Ok(()),
         }
     }
 
@@ -112,15 +1218,29 @@ impl VM {
         self.get_current_frame().ip += 1;
         Opcode::from(byte)
     }
-    
 
-    fn read_next_8bytes(&mut self) -> u8 {
+    fn read_byte(&mut self) -> u8 {
         let byte = self.get_current_frame().read_byte();
         self.get_current_frame().ip += 1;
         byte
     }
 
-    fn push(&mut self,val:Object) {
+    fn read_u16(&mut self) -> u16 {
+        let frame = self.get_current_frame();
+        let value = frame.closure.function.chunk.read_u16(frame.ip);
+        frame.ip += 2;
+        value
+    }
+
+    fn read_name(&mut self) -> Rc<str> {
+        let index = self.read_byte();
+        match self.get_current_frame().get_constant(index as usize) {
+            Object::Str(name) => name.clone(),
+            obj => obj.to_string().into(),
+        }
+    }
+
+    pub(super) fn push(&mut self,val:Object) {
         self.stack.push(val);
         self.sp+=1;
     }
@@ -130,8 +1250,39 @@ impl VM {
         self.stack.pop().unwrap_or(Object::Nil)
     }
 
-    fn get_current_frame(&mut self)->&mut CallFrame{
+    fn peek(&self, distance: usize) -> &Object {
+        &self.stack[self.sp - 1 - distance]
+    }
+
+    pub(super) fn get_current_frame(&mut self)->&mut CallFrame{
         &mut self.frames[self.fp-1]
     }
 
 }
+
+impl Drop for VM {
+    // the globals refer to the functions declared in them, break that up first
+    fn drop(&mut self) {
+        self.reset();
+        self.globals.borrow_mut().clear();
+        self.modules = ModuleLoader::new();
+        self.collect_garbage();
+    }
+}
+
+// adds a method to the class being declared
+pub(super) fn add_method(class: &Object, name: &str, method: Object) {
+    if let Object::Class(class) = class {
+        class.add_method(name.to_string(), method);
+    }
+}
+
+// bytes a native result owns on its own, elements are not counted again
+fn allocation(obj: &Object) -> usize {
+    match obj {
+        Object::Str(s) => s.len(),
+        Object::Array(arr) => arr.len() * size_of::<Object>(),
+        Object::Map(map) => map.keys().map(|key| key.len() + size_of::<Object>()).sum(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,59 @@
+/*
+  helpers the tests share, a test file takes them with mod common. each
+  test is its own crate so a helper one of them does not use is not
+  dead code
+*/
+#![allow(dead_code)]
+
+use cypher::{
+    compiler::{compiler::Compiler, register::RegisterCompiler},
+    eval::Eval,
+    lexer::scanner::Scanner,
+    objects::Object,
+    parser::{expr::Program, parser::Parser},
+    vm::{chunk::Chunk, interner::Interner, vm::VM},
+};
+
+pub const FILENAME: &str = "test.cy";
+
+pub fn parse(code: &str) -> Program {
+    let filename = FILENAME.to_owned();
+    let lines: Vec<&str> = code.split('\n').collect();
+    let mut lex = Scanner::new(code, filename.to_owned(), &lines);
+    let mut par = Parser::new(&mut lex, &filename, &lines);
+    par.parse_program()
+}
+
+// stack code for the vm
+pub fn compile(code: &str) -> Chunk {
+    Compiler::new(FILENAME.to_owned()).compile_program(parse(code))
+}
+
+// stack code with its strings taken from interner
+pub fn compile_with(code: &str, interner: Interner) -> Chunk {
+    Compiler::with_interner(FILENAME.to_owned(), interner).compile_program(parse(code))
+}
+
+// register code for the vm
+pub fn compile_register(code: &str) -> Chunk {
+    RegisterCompiler::new(FILENAME.to_owned()).compile_program(parse(code))
+}
+
+// the result of code on the tree walking interpreter and on the vm
+pub fn run_both(code: &str) -> [Object; 2] {
+    run_both_with(code, |_| {}, |_| {})
+}
+
+// see run_both, setting up each backend before it runs
+pub fn run_both_with(code: &str, setup_eval: impl FnOnce(&mut Eval), setup_vm: impl FnOnce(&mut VM)) -> [Object; 2] {
+    let program = parse(code);
+
+    let mut eval = Eval::new(&program);
+    setup_eval(&mut eval);
+    let tree = eval.run();
+
+    let mut vm = VM::new();
+    setup_vm(&mut vm);
+    let chunk = Compiler::with_interner(FILENAME.to_owned(), vm.interner()).compile_program(program);
+    [tree, vm.run(chunk)]
+}
@@ -0,0 +1,75 @@
+use std::rc::Rc;
+
+use cypher::{
+    interpreter::Interpreter,
+    objects::Object,
+    vm::{chunk::Chunk, interner::Interner},
+};
+
+mod common;
+use common::compile_with;
+
+fn string_constant(chunk: &Chunk, value: &str) -> Rc<str> {
+    chunk
+        .constants
+        .iter()
+        .find_map(|constant| match constant {
+            Object::Str(s) if &**s == value => Some(s.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no constant {:?}", value))
+}
+
+#[test]
+fn repeated_constants_are_added_once() {
+    let code = "let a = 1\nlet b = 1\nwhile a < 300 {\n    print(\"x\")\n    print(\"x\")\n    a = a + 1\n}\nreturn a == 300";
+    let chunk = compile_with(code, Interner::new());
+    let strings = chunk.constants.iter().filter(|c| matches!(c, Object::Str(_))).count();
+    let numbers = chunk.constants.iter().filter(|c| matches!(c, Object::Number(_))).count();
+    // a, b and x, then 1 and 300
+    assert_eq!(strings, 3, "{:?}", chunk.constants);
+    assert_eq!(numbers, 2, "{:?}", chunk.constants);
+
+    let chunk = compile_with("let x = 0\nlet y = 0.0\nlet z = true\nlet w = true", Interner::new());
+    assert_eq!(chunk.constants.iter().filter(|c| matches!(c, Object::Number(_))).count(), 1);
+    assert_eq!(chunk.constants.iter().filter(|c| matches!(c, Object::Bool(_))).count(), 1);
+}
+
+#[test]
+fn strings_are_shared_between_functions() {
+    let interner = Interner::new();
+    let chunk = compile_with("let name = \"x\"\ndef f() {\n    return name + \"x\"\n}", interner.clone());
+    let function = chunk
+        .constants
+        .iter()
+        .find_map(|constant| match constant {
+            Object::CompiledFunction(function) => Some(function.clone()),
+            _ => None,
+        })
+        .unwrap();
+    assert!(Rc::ptr_eq(&string_constant(&chunk, "name"), &string_constant(&function.chunk, "name")));
+    assert!(Rc::ptr_eq(&string_constant(&chunk, "x"), &interner.intern("x")));
+
+    // a second program compiled with the same interner gets the same strings
+    let other = compile_with("print(\"x\")", interner.clone());
+    assert!(Rc::ptr_eq(&string_constant(&chunk, "x"), &string_constant(&other, "x")));
+}
+
+#[test]
+fn interned_and_built_strings_compare_by_content() {
+    let mut interp = Interpreter::new();
+    assert_eq!(interp.eval_str("return \"ab\" == \"ab\"").unwrap(), Object::Bool(true));
+    assert_eq!(interp.eval_str("return \"a\" + \"b\" == \"ab\"").unwrap(), Object::Bool(true));
+    assert_eq!(interp.eval_str("return \"ab\" != \"ba\"").unwrap(), Object::Bool(true));
+}
+
+#[test]
+fn strings_no_longer_held_are_dropped() {
+    let interner = Interner::new();
+    let kept = compile_with("print(\"kept\")", interner.clone());
+    for i in 0..5000 {
+        compile_with(&format!("print(\"string {}\")", i), interner.clone());
+    }
+    assert!(interner.len() < 3000, "{} strings interned", interner.len());
+    assert!(Rc::ptr_eq(&string_constant(&kept, "kept"), &interner.intern("kept")));
+}
@@ -0,0 +1,103 @@
+use cypher::{
+    errors::bytecode::BytecodeError,
+    objects::Object,
+    vm::{cyc, disassemble::Disassembler, interner::Interner, vm::VM},
+};
+
+mod common;
+use common::compile;
+
+const PROGRAM: &str = "class Counter {
+    def init(start) {
+        self.count = start
+    }
+    def next() {
+        self.count = self.count + 1
+        return self.count
+    }
+}
+def adder(n) {
+    def add(x) {
+        return x + n
+    }
+    return add
+}
+let c = Counter(0.5)
+c.next()
+let words = [\"a\", \"b\"]
+return [adder(2)(c.next()), words[1], nil, true, false, -0]";
+
+#[test]
+fn round_trip_keeps_the_program() {
+    let chunk = compile(PROGRAM);
+    let bytes = cyc::write(&chunk, PROGRAM);
+    let file = cyc::read(&bytes, &Interner::new()).unwrap();
+
+    assert_eq!(file.source_hash, cyc::hash_source(PROGRAM));
+    assert_eq!(file.chunk.file, "test.cy");
+    assert_eq!(file.chunk.lines, chunk.lines);
+    assert_eq!(
+        Disassembler::new(&file.chunk).render(),
+        Disassembler::new(&chunk).render()
+    );
+    assert_eq!(cyc::write(&file.chunk, PROGRAM), bytes);
+
+    let expected = VM::new().run(compile(PROGRAM));
+    assert_eq!(VM::new().run(file.chunk), expected);
+    assert_eq!(expected.to_string(), "[4.5, b, nil, true, false, -0]");
+}
+
+#[test]
+fn loaded_strings_are_interned() {
+    let bytes = cyc::write(&compile("return \"hello\""), "");
+    let interner = Interner::new();
+    let file = cyc::read(&bytes, &interner).unwrap();
+    let Some(Object::Str(hello)) = file.chunk.constants.iter().find(|c| matches!(c, Object::Str(_))) else {
+        panic!("no string constant");
+    };
+    assert!(std::rc::Rc::ptr_eq(hello, &interner.intern("hello")));
+}
+
+#[test]
+fn truncated_files_are_rejected() {
+    let bytes = cyc::write(&compile(PROGRAM), PROGRAM);
+    for len in 0..bytes.len() {
+        match cyc::read(&bytes[..len], &Interner::new()) {
+            Err(BytecodeError::Truncated { .. }) => {}
+            Err(BytecodeError::NotBytecode) if len < cyc::MAGIC.len() => {}
+            result => panic!("{} of {} bytes loaded as {:?}", len, bytes.len(), result.map(|_| ())),
+        }
+    }
+}
+
+#[test]
+fn corrupt_files_are_rejected() {
+    let bytes = cyc::write(&compile("return 1"), "return 1");
+
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[0] = b'X';
+    assert_eq!(cyc::read(&wrong_magic, &Interner::new()).unwrap_err(), BytecodeError::NotBytecode);
+
+    let mut newer = bytes.clone();
+    newer[4..6].copy_from_slice(&(cyc::VERSION + 1).to_be_bytes());
+    let err = cyc::read(&newer, &Interner::new()).unwrap_err();
+    assert_eq!(
+        err,
+        BytecodeError::Version {
+            found: cyc::VERSION + 1,
+            expected: cyc::VERSION
+        }
+    );
+    assert!(err.to_string().contains("compile the source again"));
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    let err = cyc::read(&trailing, &Interner::new()).unwrap_err();
+    assert!(err.to_string().contains("1 bytes after the end of the program"), "{}", err);
+
+    // the tag of the last constant, the nil returned at the end
+    let mut bad_tag = bytes.clone();
+    *bad_tag.last_mut().unwrap() = 9;
+    let err = cyc::read(&bad_tag, &Interner::new()).unwrap_err();
+    assert!(err.to_string().contains("unknown constant tag 9"), "{}", err);
+}
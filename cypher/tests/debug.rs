@@ -0,0 +1,244 @@
+use std::{cell::RefCell, rc::Rc};
+
+use cypher::{
+    errors::ErrorKind,
+    objects::Object,
+    vm::{
+        chunk::Chunk,
+        debug::{breakable_line, Breakpoint, Console, Debugger, Resume, StopReason},
+        peephole,
+        vm::VM,
+    },
+};
+
+mod common;
+use common::{compile, compile_register};
+
+const CODE: &str = "def add(a, b) {
+    let sum = a + b
+    return sum
+}
+let total = 0
+for let i = 0; i < 3; i++ {
+    total = add(total, i)
+}
+def counter() {
+    let count = 10
+    def next() {
+        count = count + 1
+        return count
+    }
+    return next
+}
+let next = counter()
+next()
+return total";
+
+// looks at the vm at the nth stop
+type Check = Box<dyn FnMut(&mut VM, usize)>;
+
+/*
+  answers every stop with the next resume of a script and writes down
+  where it stopped, after running a check on the paused vm
+*/
+struct Script {
+    resumes: Vec<Resume>,
+    stops: Rc<RefCell<Vec<String>>>,
+    check: Check,
+}
+
+impl Console for Script {
+    fn paused(&mut self, vm: &mut VM, _: &mut Vec<Breakpoint>, reason: StopReason) -> Resume {
+        let location = vm.location().unwrap();
+        let stop = format!("{:?} {}:{} {}", reason, location.function, location.line, location.depth);
+        let mut stops = self.stops.borrow_mut();
+        stops.push(stop);
+        (self.check)(vm, stops.len() - 1);
+        self.resumes.get(stops.len() - 1).copied().unwrap_or(Resume::Continue)
+    }
+}
+
+fn debug(chunk: Chunk, breakpoints: &[u32], resumes: Vec<Resume>, check: impl FnMut(&mut VM, usize) + 'static) -> (Object, Vec<String>) {
+    let stops = Rc::new(RefCell::new(vec![]));
+    let mut debugger = Debugger::new(Box::new(Script {
+        resumes,
+        stops: stops.clone(),
+        check: Box::new(check),
+    }));
+    for &line in breakpoints {
+        debugger.add_breakpoint(Breakpoint::parse(&line.to_string(), "test.cy").unwrap());
+    }
+    if !breakpoints.is_empty() {
+        debugger.set_resume(Resume::Continue);
+    }
+    let mut vm = VM::new();
+    vm.set_hook(Some(Box::new(debugger)));
+    let result = vm.run(chunk);
+    let stops = stops.borrow().clone();
+    (result, stops)
+}
+
+#[test]
+fn steps_into_over_and_out() {
+    use Resume::*;
+    let resumes = vec![StepInto, StepOver, StepInto, StepInto, StepOver, StepOut, StepOver, Continue];
+    let (result, stops) = debug(compile(CODE), &[], resumes, |_, _| {});
+    assert_eq!(result, Object::Number(3.0));
+    assert_eq!(
+        stops,
+        [
+            "Step <script>:1 1",
+            "Step <script>:5 1",
+            "Step <script>:6 1",
+            "Step <script>:7 1",
+            "Step add:2 2",
+            "Step add:3 2",
+            // back in the loop after the call
+            "Step <script>:6 1",
+            "Step <script>:7 1",
+        ]
+    );
+}
+
+#[test]
+fn breakpoints_stop_every_time_the_line_is_reached() {
+    let (result, stops) = debug(compile(CODE), &[3, 13], vec![], |_, _| {});
+    assert_eq!(result, Object::Number(3.0));
+    assert_eq!(
+        stops,
+        ["Breakpoint add:3 2", "Breakpoint add:3 2", "Breakpoint add:3 2", "Breakpoint next:13 2"]
+    );
+}
+
+#[test]
+fn frames_show_their_variables() {
+    let seen = Rc::new(RefCell::new(vec![]));
+    let record = seen.clone();
+    debug(compile(CODE), &[3, 13], vec![], move |vm, stop| {
+        let mut seen = record.borrow_mut();
+        let frames = vm.backtrace();
+        match stop {
+            1 => {
+                assert_eq!(frames.len(), 2);
+                assert_eq!(frames[0].function, "add");
+                assert_eq!(frames[0].line, 3);
+                seen.push(format!("{:?}", frames[0].locals));
+                // the caller is on the line of the call, with the loop variable in scope
+                assert_eq!((frames[1].function.as_str(), frames[1].line), ("<script>", 7));
+                seen.push(format!("{:?}", frames[1].locals));
+                let total = vm.frame_globals(1).into_iter().find(|(name, _)| name == "total").unwrap();
+                assert_eq!(total.1, Object::Number(0.0));
+            }
+            3 => {
+                assert_eq!(frames[0].upvalues, [("count".to_owned(), Object::Number(11.0))]);
+                seen.push("upvalue".to_owned());
+            }
+            _ => {}
+        }
+    });
+    assert_eq!(
+        *seen.borrow(),
+        [
+            "[(\"a\", Number(0.0)), (\"b\", Number(1.0)), (\"sum\", Number(1.0))]",
+            "[(\"i\", Number(1.0))]",
+            "upvalue",
+        ]
+    );
+}
+
+#[test]
+fn expressions_are_evaluated_in_the_paused_frame() {
+    let (result, _) = debug(compile(CODE), &[3, 13], vec![], |vm, stop| match stop {
+        0 => {
+            assert_eq!(vm.eval_in_frame(0, "a + b * 10").unwrap(), Object::Number(0.0));
+            assert_eq!(vm.eval_in_frame(1, "i").unwrap(), Object::Number(0.0));
+            assert_eq!(vm.eval_in_frame(1, "add(40, 2)").unwrap(), Object::Number(42.0));
+            // assigned back to the local the frame returns
+            assert_eq!(vm.eval_in_frame(0, "sum = 100").unwrap(), Object::Number(100.0));
+        }
+        3 => {
+            vm.eval_in_frame(0, "count = count * 2").unwrap();
+            assert!(vm.eval_in_frame(0, "nope").is_err());
+            assert!(vm.eval_in_frame(0, "1 +").is_err());
+            assert!(vm.eval_in_frame(5, "1").is_err());
+            // a global of the module
+            vm.eval_in_frame(0, "total = total + 1000").unwrap();
+        }
+        _ => {}
+    });
+    // 100 + 1 + 2 instead of 0 + 1 + 2, then the assignment during next()
+    assert_eq!(result, Object::Number(1103.0));
+}
+
+#[test]
+fn variables_assigned_by_called_functions_are_kept() {
+    let code = "let count = 0
+def bump() {
+    count = count + 1
+    return count
+}
+bump()
+return count";
+    let (result, _) = debug(compile(code), &[6], vec![], |vm, _| {
+        assert_eq!(vm.eval_in_frame(0, "bump()").unwrap(), Object::Number(1.0));
+        assert_eq!(vm.eval_in_frame(0, "count").unwrap(), Object::Number(1.0));
+    });
+    assert_eq!(result, Object::Number(2.0));
+
+    // the same for an upvalue assigned by the closure owning it
+    debug(compile(CODE), &[13], vec![], |vm, _| {
+        assert_eq!(vm.eval_in_frame(0, "next()").unwrap(), Object::Number(12.0));
+        assert_eq!(vm.eval_in_frame(0, "count").unwrap(), Object::Number(12.0));
+    });
+}
+
+#[test]
+fn register_code_and_the_peephole_pass_keep_their_names() {
+    let check = |vm: &mut VM, stop: usize| {
+        if stop == 0 {
+            let frames = vm.backtrace();
+            assert_eq!(
+                frames[0].locals,
+                [
+                    ("a".to_owned(), Object::Number(0.0)),
+                    ("b".to_owned(), Object::Number(0.0)),
+                    ("sum".to_owned(), Object::Number(0.0))
+                ]
+            );
+            assert_eq!(frames[1].locals, [("i".to_owned(), Object::Number(0.0))]);
+            assert_eq!(vm.eval_in_frame(0, "sum = 7").unwrap(), Object::Number(7.0));
+        }
+    };
+    let (result, stops) = debug(compile_register(CODE), &[3], vec![], check);
+    assert_eq!(result, Object::Number(10.0));
+    assert_eq!(stops.len(), 3);
+
+    let mut chunk = compile(CODE);
+    peephole::optimize(&mut chunk);
+    let (result, _) = debug(chunk, &[3], vec![], check);
+    assert_eq!(result, Object::Number(10.0));
+}
+
+#[test]
+fn quitting_stops_the_program() {
+    let (result, stops) = debug(compile(CODE), &[2], vec![Resume::Quit], |_, _| {});
+    assert_eq!(stops.len(), 1);
+    match result {
+        Object::Error(err) => assert_eq!(err.kind, ErrorKind::Exit(0)),
+        result => panic!("expected an exit, got {}", result),
+    }
+}
+
+#[test]
+fn breakpoints_move_to_lines_with_code() {
+    let chunk = compile(CODE);
+    assert_eq!(breakable_line(&chunk, 4), Some(5));
+    assert_eq!(breakable_line(&chunk, 12), Some(12));
+    assert_eq!(breakable_line(&chunk, 40), None);
+    assert_eq!(
+        Breakpoint::parse("dir/test.cy:12", "main.cy"),
+        Some(Breakpoint { file: "dir/test.cy".to_owned(), line: 12 })
+    );
+    assert_eq!(Breakpoint::parse("12", "main.cy").unwrap().to_string(), "main.cy:12");
+    assert_eq!(Breakpoint::parse("main.cy:x", "main.cy"), None);
+}
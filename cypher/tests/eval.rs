@@ -0,0 +1,94 @@
+use cypher::{eval::Eval, objects::Object};
+
+mod common;
+use common::parse;
+
+fn run(code: &str) -> Object {
+    Eval::new(&parse(code)).run()
+}
+
+#[test]
+fn arithmetic_and_precedence() {
+    assert_eq!(run("return 1 + 2 * 3 - 4 / 2"), Object::Number(5.0));
+    assert_eq!(run("return -(2 + 3) % 4"), Object::Number(-1.0));
+    assert_eq!(run("return 1.5 + 1"), Object::Number(2.5));
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    assert_eq!(run("let a = false\nreturn a and missing"), Object::Bool(false));
+    assert_eq!(run("let a = 1\nreturn a or missing"), Object::Number(1.0));
+    assert_eq!(run("return !nil"), Object::Bool(true));
+}
+
+#[test]
+fn strings_concatenate_and_compare() {
+    assert_eq!(
+        run("return \"hello \" + \"world\""),
+        Object::string("hello world")
+    );
+    assert_eq!(run("return \"a\" == \"a\""), Object::Bool(true));
+}
+
+#[test]
+fn functions_and_recursion() {
+    let code = "
+def fib(n) {
+    if n < 2 {
+        return n
+    }
+    return fib(n - 1) + fib(n - 2)
+}
+return fib(10)";
+    assert_eq!(run(code), Object::Number(55.0));
+}
+
+#[test]
+fn closures_capture_declaring_scope() {
+    let code = "
+def counter() {
+    let n = 0
+    def inc() {
+        n++
+        return n
+    }
+    return inc
+}
+let c = counter()
+c()
+c()
+return c()";
+    assert_eq!(run(code), Object::Number(3.0));
+}
+
+#[test]
+fn loops() {
+    let code = "
+let total = 0
+for let i = 0; i < 5; i++ {
+    total = total + i
+}
+let j = 0
+while j < 3 {
+    j++
+}
+return total + j";
+    assert_eq!(run(code), Object::Number(13.0));
+}
+
+#[test]
+fn arrays_and_builtins() {
+    assert_eq!(run("let a = [1, 2, 3]\nreturn a[2]"), Object::Number(3.0));
+    assert_eq!(run("return len([1, 2, 3]) + len(\"héllo\")"), Object::Number(8.0));
+    assert_eq!(
+        run("return push([1], 2)"),
+        Object::array(vec![Object::Number(1.0), Object::Number(2.0)])
+    );
+}
+
+#[test]
+fn runtime_errors_stop_the_program() {
+    assert!(matches!(run("return missing + 1"), Object::Error(_)));
+    assert!(matches!(run("let a = [1]\nreturn a[3]"), Object::Error(_)));
+    assert!(matches!(run("def f(a) {}\nf()"), Object::Error(_)));
+}
@@ -0,0 +1,110 @@
+use std::rc::Rc;
+
+use cypher::{interpreter::Interpreter, objects::Object};
+
+#[test]
+fn cycles_are_collected() {
+    let mut interp = Interpreter::new();
+    interp
+        .eval_str(
+            "class Node {\n    def init() { self.next = nil }\n}
+for let i = 0; i < 100; i++ {
+    let a = Node()
+    let b = Node()
+    a.next = b
+    b.next = a
+}",
+        )
+        .unwrap();
+    let before = interp.gc_stats();
+    assert!(before.live >= 200, "{:?}", before);
+
+    interp.collect_garbage();
+    let after = interp.gc_stats();
+    assert_eq!(after.collections, before.collections + 1);
+    assert!(after.freed >= 200, "{:?}", after);
+    // the class and its init method stay reachable from the globals
+    assert!(after.live < 10, "{:?}", after);
+}
+
+#[test]
+fn closures_capturing_themselves_are_collected() {
+    let mut interp = Interpreter::new();
+    interp
+        .eval_str(
+            "def make() {
+    def count(n) {
+        if n == 0 { return 0 }
+        return count(n - 1)
+    }
+    return count
+}
+for let i = 0; i < 50; i++ {
+    make()(3)
+}",
+        )
+        .unwrap();
+    interp.collect_garbage();
+    let stats = interp.gc_stats();
+    assert!(stats.freed >= 50, "{:?}", stats);
+    assert!(stats.live < 5, "{:?}", stats);
+}
+
+#[test]
+fn reachable_objects_survive() {
+    let mut interp = Interpreter::new();
+    interp
+        .eval_str("class Box {}\nlet kept = Box()\nkept.me = kept\nkept.value = 42")
+        .unwrap();
+    interp.collect_garbage();
+    assert_eq!(interp.eval_str("return kept.me.value").unwrap(), Object::Number(42.0));
+}
+
+#[test]
+fn objects_held_by_the_host_survive() {
+    let mut interp = Interpreter::new();
+    let held = interp
+        .eval_str("class Box {}\nlet b = Box()\nb.me = b\nb.value = 7\nreturn b")
+        .unwrap();
+    interp.eval_str("b = nil").unwrap();
+    interp.collect_garbage();
+    let Object::Instance(instance) = &held else {
+        panic!("expected an instance, got {}", held);
+    };
+    assert_eq!(instance.get_field("value"), Some(Object::Number(7.0)));
+
+    // only the host refers to the cycle now, dropping it frees it
+    let weak = Rc::downgrade(instance);
+    drop(held);
+    interp.collect_garbage();
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn arrays_are_shared_and_collected_in_cycles() {
+    let mut interp = Interpreter::new();
+    let pair = interp.eval_str("let a = [1, 2]\nlet b = a\nreturn [a, b]").unwrap();
+    let Object::Array(pair) = &pair else {
+        panic!("expected an array, got {}", pair);
+    };
+    let (Object::Array(a), Object::Array(b)) = (&pair[0], &pair[1]) else {
+        panic!("expected arrays, got {:?}", pair);
+    };
+    assert!(Rc::ptr_eq(a, b));
+
+    interp
+        .eval_str(
+            "class Node {}
+for let i = 0; i < 50; i++ {
+    let node = Node()
+    node.children = [node, [node]]
+}",
+        )
+        .unwrap();
+    let before = interp.gc_stats();
+    interp.collect_garbage();
+    let after = interp.gc_stats();
+    // each node and the two arrays holding it
+    assert!(after.freed - before.freed >= 150, "{:?}", after);
+    assert!(after.live < 10, "{:?}", after);
+}
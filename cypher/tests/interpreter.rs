@@ -0,0 +1,214 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use cypher::{
+    errors::{CypherError, ErrorKind},
+    interpreter::Interpreter,
+    objects::{
+        convert::{FromValue, IntoValue},
+        native::Arity,
+        Object,
+    },
+};
+
+fn runtime_kind(err: CypherError) -> ErrorKind {
+    match err {
+        CypherError::Runtime(err) => err.kind,
+        CypherError::Parse(err) => panic!("unexpected parse error {}", err),
+        CypherError::Compile(err) => panic!("unexpected compile error {}", err),
+    }
+}
+
+#[test]
+fn globals_persist_between_evals() {
+    let mut interp = Interpreter::new();
+    interp.eval_str("let count = 1").unwrap();
+    interp.eval_str("count = count + 1").unwrap();
+    assert_eq!(interp.eval_str("return count").unwrap(), Object::Number(2.0));
+    assert_eq!(interp.get_global::<f64>("count").unwrap(), 2.0);
+}
+
+#[test]
+fn call_function_converts_arguments_and_result() {
+    let mut interp = Interpreter::new();
+    interp
+        .eval_str("def greet(name, times) {\n let out = \"\"\n for let i = 0; i < times; i++ {\n out = out + name\n }\n return out\n}")
+        .unwrap();
+    let greeting: String = interp.call_function("greet", ("hi", 3.0)).unwrap();
+    assert_eq!(greeting, "hihihi");
+
+    let length: f64 = interp.call_function("len", (vec![1.0, 2.0],)).unwrap();
+    assert_eq!(length, 2.0);
+}
+
+#[test]
+fn call_function_reports_errors() {
+    let mut interp = Interpreter::new();
+    interp.eval_str("def fail() { return missing }").unwrap();
+    let err = interp.call_function::<_, Object>("fail", ()).unwrap_err();
+    assert_eq!(runtime_kind(err), ErrorKind::NameError);
+
+    let err = interp.call_function::<_, Object>("nope", ()).unwrap_err();
+    assert!(err.to_string().contains("identifier nope not found"), "{}", err);
+    assert_eq!(runtime_kind(err), ErrorKind::NameError);
+
+    let err = interp.call_function::<_, Object>("fail", (1.0,)).unwrap_err();
+    assert_eq!(runtime_kind(err), ErrorKind::ArityError);
+
+    // the vm is usable again after an error
+    assert_eq!(interp.eval_str("return 1 + 1").unwrap(), Object::Number(2.0));
+}
+
+#[test]
+fn syntax_errors_are_returned() {
+    let mut interp = Interpreter::new();
+    assert!(matches!(interp.eval_str("let = 5"), Err(CypherError::Parse(_))));
+    assert!(matches!(interp.eval_str("let a = 5 $"), Err(CypherError::Parse(_))));
+}
+
+#[test]
+fn truncated_source_is_a_syntax_error() {
+    let mut interp = Interpreter::new();
+    for source in ["let x =", "print(", "let a = {\"x\":", "for let i", "def f(", "x.", "class A <"] {
+        match interp.eval_str(source) {
+            Err(CypherError::Parse(err)) => assert!(err.to_string().contains("line:1"), "{}", err),
+            result => panic!("expected a syntax error for {:?}, got {:?}", source, result),
+        }
+    }
+}
+
+#[test]
+fn operands_past_a_byte_are_a_compile_error() {
+    let mut interp = Interpreter::new();
+    let globals: Vec<String> = (0..300).map(|i| format!("let a{} = {}", i, 1000 + i)).collect();
+    match interp.eval_str(&globals.join("\n")) {
+        Err(CypherError::Compile(err)) => {
+            assert_eq!(err.message, "too many constants in <eval>, the limit is 256");
+            assert_eq!(err.line_no, 129);
+        }
+        result => panic!("expected a compile error, got {:?}", result),
+    }
+
+    let locals: Vec<String> = (0..300).map(|i| format!("let a{} = {}", i, i % 10)).collect();
+    let code = format!("def f() {{\n{}\n}}", locals.join("\n"));
+    match interp.eval_str(&code) {
+        Err(CypherError::Compile(err)) => assert_eq!(err.message, "too many local variables in f, the limit is 256"),
+        result => panic!("expected a compile error, got {:?}", result),
+    }
+    assert_eq!(interp.eval_str("return 1 + 1").unwrap(), Object::Number(2.0));
+}
+
+#[test]
+fn jumps_past_two_bytes_are_a_compile_error() {
+    let mut interp = Interpreter::new();
+    let body = vec!["    x = 1"; 14000].join("\n");
+    let code = format!("let x = 0\nif x == 1 {{\n{}\n}}\nreturn x", body);
+    match interp.eval_str(&code) {
+        Err(CypherError::Compile(err)) => assert!(err.message.contains("too long to jump over"), "{}", err),
+        result => panic!("expected a compile error, got {:?}", result),
+    }
+}
+
+#[test]
+fn closures_capture_host_state() {
+    let log = Rc::new(RefCell::new(vec![]));
+    let mut interp = Interpreter::new();
+    let sink = log.clone();
+    interp.register_fn("record", Arity::Fixed(1), "record(value)", move |args| {
+        sink.borrow_mut().push(args[0].to_string());
+        Ok(Object::Nil)
+    });
+    interp
+        .eval_str("for let i = 0; i < 3; i++ { record(i) }")
+        .unwrap();
+    assert_eq!(*log.borrow(), vec!["0", "1", "2"]);
+}
+
+#[test]
+fn host_values_round_trip() {
+    let mut interp = Interpreter::new();
+    let mut scores = HashMap::new();
+    scores.insert("alice".to_owned(), 3.0);
+    scores.insert("bob".to_owned(), 5.0);
+    interp.set_global("scores", scores.clone());
+    interp.set_global("names", vec!["a".to_owned(), "b".to_owned()]);
+    interp.set_global("enabled", true);
+
+    assert_eq!(
+        interp.eval_str("return scores[\"bob\"]").unwrap(),
+        Object::Number(5.0)
+    );
+    assert_eq!(interp.eval_str("return names[1]").unwrap(), Object::string("b"));
+    assert_eq!(interp.get_global::<HashMap<String, f64>>("scores").unwrap(), scores);
+    assert!(interp.get_global::<bool>("enabled").unwrap());
+    assert_eq!(
+        interp.get_global::<f64>("enabled").unwrap_err().kind,
+        ErrorKind::TypeError
+    );
+
+    let nested = vec![vec![1.0], vec![2.0, 3.0]].into_value();
+    assert_eq!(Vec::<Vec<f64>>::from_value(nested).unwrap(), vec![vec![1.0], vec![2.0, 3.0]]);
+}
+
+#[test]
+fn map_methods() {
+    let mut interp = Interpreter::new();
+    let mut ages = HashMap::new();
+    ages.insert("b".to_owned(), 2.0);
+    ages.insert("a".to_owned(), 1.0);
+    interp.set_global("ages", ages);
+    let keys = Vec::<String>::from_value(interp.eval_str("return ages.keys()").unwrap()).unwrap();
+    assert_eq!(keys, vec!["a", "b"]);
+    assert_eq!(interp.eval_str("return ages.contains(\"a\")").unwrap(), Object::Bool(true));
+    assert_eq!(interp.eval_str("return ages.len()").unwrap(), Object::Number(2.0));
+    let err = interp.eval_str("return ages.missing()").unwrap_err();
+    assert_eq!(runtime_kind(err), ErrorKind::AttributeError);
+}
+
+#[test]
+fn module_errors_cite_the_module_file() {
+    let mut interp = Interpreter::new();
+    let err = interp
+        .eval_str("import \"tests/scripts/modules/lib/broken.cy\" as broken")
+        .unwrap_err();
+    match err {
+        CypherError::Runtime(err) => {
+            assert_eq!(err.kind, ErrorKind::NameError);
+            assert!(err.to_string().contains("broken.cy line 1"), "{}", err);
+        }
+        err => panic!("unexpected error {}", err),
+    }
+
+    interp.add_search_path("tests/scripts/modules/lib");
+    interp.eval_str("import \"shapes.cy\" as shapes").unwrap();
+    let err = interp.eval_str("return shapes.hidden").unwrap_err();
+    assert_eq!(runtime_kind(err), ErrorKind::AttributeError);
+    assert_eq!(interp.eval_str("return shapes.sides").unwrap(), Object::Number(4.0));
+}
+
+#[test]
+fn uncaught_errors_carry_a_stack_trace() {
+    let mut interp = Interpreter::new();
+    interp.set_filename("trace.cy");
+    let err = interp
+        .eval_str("def inner() {\n    return [][0]\n}\ndef outer() {\n    return inner()\n}\nouter()")
+        .unwrap_err();
+    match err {
+        CypherError::Runtime(err) => {
+            let trace: Vec<String> = err.trace.iter().map(|frame| frame.to_string()).collect();
+            assert_eq!(
+                trace,
+                vec!["inner [trace.cy line 2]", "outer [trace.cy line 5]", "<script> [trace.cy line 7]"]
+            );
+            assert_eq!(err.message(), "index 0 out of range for array of length 0");
+            assert!(err.to_string().ends_with("[trace.cy line 2]"), "{}", err);
+        }
+        err => panic!("unexpected error {}", err),
+    }
+
+    // the vm is usable again once the error unwound it
+    let caught = interp.eval_str("try {\n    outer()\n} catch e {\n    return e.stack\n}").unwrap();
+    assert_eq!(
+        caught.to_string(),
+        "[inner [trace.cy line 2], outer [trace.cy line 5], <script> [trace.cy line 2]]"
+    );
+}
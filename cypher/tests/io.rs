@@ -0,0 +1,87 @@
+use std::{env, fs, path::PathBuf, process};
+
+use cypher::{errors::ErrorKind, interpreter::Interpreter, objects::Object, stdlib::io};
+
+mod common;
+use common::run_both_with;
+
+// fresh directory per test and backend, removed by the test
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("cypher-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn run_both(code: &str) -> [Object; 2] {
+    run_both_with(
+        code,
+        |eval| eval.modules().add_builtin(io::module(vec!["one".to_owned(), "two".to_owned()])),
+        |vm| vm.modules().add_builtin(io::module(vec!["one".to_owned(), "two".to_owned()])),
+    )
+}
+
+#[test]
+fn files_round_trip() {
+    let dir = scratch_dir("files");
+    // leaves dir empty so the second backend starts from the same state
+    let code = format!(
+        "let dir = {:?}
+import \"fs\" as fs
+fs.mkdir(dir + \"/sub\")
+fs.write_file(dir + \"/sub/notes.txt\", \"héllo\\n\")
+fs.append_file(dir + \"/sub/notes.txt\", \"wörld\")
+fs.append_file(dir + \"/new.txt\", \"created\")
+let listing = fs.list_dir(dir)
+let text = fs.read_file(dir + \"/sub/notes.txt\")
+let created = fs.read_file(dir + \"/new.txt\")
+fs.remove(dir + \"/sub/notes.txt\")
+fs.remove(dir + \"/sub\")
+fs.remove(dir + \"/new.txt\")
+return [listing, text.split(\"\\n\"), created, fs.exists(dir + \"/sub\")]",
+        dir.display().to_string()
+    );
+    for result in run_both(&code) {
+        assert_eq!(result.to_string(), "[[new.txt, sub], [héllo, wörld], created, false]");
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failures_are_io_errors() {
+    let dir = scratch_dir("failures");
+    let code = format!(
+        "import \"fs\" as fs\nreturn fs.list_dir({:?})",
+        dir.join("missing").display().to_string()
+    );
+    for result in run_both(&code) {
+        match result {
+            Object::Error(err) => {
+                assert_eq!(err.kind, ErrorKind::IOError);
+                assert!(err.message().contains("list_dir"), "{}", err);
+            }
+            value => panic!("expected an error, got {}", value),
+        }
+    }
+}
+
+#[test]
+fn process_functions() {
+    for result in run_both("import \"io\" as io\nreturn io.args()") {
+        assert_eq!(result.to_string(), "[one, two]");
+    }
+    env::set_var("CYPHER_IO_TEST", "set");
+    for result in run_both("from \"io\" import env\nreturn [env(\"CYPHER_IO_TEST\"), env(\"CYPHER_IO_UNSET\")]") {
+        assert_eq!(result.to_string(), "[set, nil]");
+    }
+    for result in run_both("import \"io\" as io\nio.exit(4)\nreturn 1") {
+        match result {
+            Object::Error(err) => assert_eq!(err.kind, ErrorKind::Exit(4)),
+            value => panic!("expected exit, got {}", value),
+        }
+    }
+
+    // embedders see exit as an error they can act on
+    let mut interp = Interpreter::new();
+    let err = interp.eval_str("import \"io\" as io\nio.exit()").unwrap_err();
+    assert!(matches!(err, cypher::errors::CypherError::Runtime(err) if err.kind == ErrorKind::Exit(0)));
+}
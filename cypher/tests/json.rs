@@ -0,0 +1,43 @@
+use cypher::{errors::ErrorKind, objects::Object};
+
+mod common;
+use common::run_both;
+
+fn expect_error(code: &str, kind: ErrorKind, message: &str) {
+    for result in run_both(code) {
+        match result {
+            Object::Error(err) => {
+                assert_eq!(err.kind, kind);
+                assert!(err.message().starts_with(message), "{}", err.message());
+            }
+            obj => panic!("expected an error got {}", obj),
+        }
+    }
+}
+
+#[test]
+fn unsupported_values_name_their_path() {
+    let code = "import \"json\" as json
+def f() {}
+let config = json.parse(\"{\\\"servers\\\": [{\\\"port\\\": 80}, {\\\"port\\\": 81}]}\")
+let servers = config[\"servers\"]
+return json.stringify([config, [servers[0][\"port\"], f]])";
+    expect_error(code, ErrorKind::TypeError, "stringify() cannot write function at $[1][1]");
+
+    let code = "import \"json\" as json
+class Box {
+    def init(items) {
+        self.items = items
+    }
+}
+let b = Box([])
+b.items = [1, b]
+return json.stringify(b)";
+    expect_error(code, ErrorKind::ValueError, "stringify() cycle at $.items[1], Box instance contains itself");
+}
+
+#[test]
+fn malformed_text_cites_the_position() {
+    let code = "import \"json\" as json\nreturn json.parse(\"[1, 2\")";
+    expect_error(code, ErrorKind::ValueError, "parse() invalid json: EOF while parsing a list at line 1 column 5");
+}
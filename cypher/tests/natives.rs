@@ -0,0 +1,126 @@
+use std::{cell::Cell, rc::Rc};
+
+use cypher::{
+    errors::{ErrorKind, RuntimeError},
+    objects::{
+        native::{Arity, NativeRegistry},
+        Object,
+    },
+};
+
+mod common;
+use common::run_both_with;
+
+fn register_host(registry: &mut NativeRegistry, calls: Rc<Cell<usize>>) {
+    registry.register("sum", Arity::Variadic(0), "sum(...) -> total of the numbers", |args| {
+        let mut total = 0.0;
+        for arg in args {
+            match arg {
+                Object::Number(n) => total += n,
+                obj => {
+                    return Err(RuntimeError::new(
+                        ErrorKind::TypeError,
+                        format!("sum() expects numbers, got {}", obj.type_name()),
+                    ))
+                }
+            }
+        }
+        Ok(Object::Number(total))
+    });
+    registry.register("clamp", Arity::Range(1, 3), "clamp(x, lo?, hi?)", |args| {
+        let number = |index: usize, default: f64| match args.get(index) {
+            Some(Object::Number(n)) => *n,
+            _ => default,
+        };
+        Ok(Object::Number(number(0, 0.0).max(number(1, 0.0)).min(number(2, 1.0))))
+    });
+    registry.register("tick", Arity::Fixed(0), "tick() -> times called", move |_| {
+        calls.set(calls.get() + 1);
+        Ok(Object::Number(calls.get() as f64))
+    });
+    registry.register_with_caller("twice", Arity::Fixed(2), "twice(f, x) -> f(f(x))", |caller, args| {
+        let once = caller.call(args[0].clone(), vec![args[1].clone()])?;
+        caller.call(args[0].clone(), vec![once])
+    });
+}
+
+fn run_both(code: &str) -> [Object; 2] {
+    run_both_with(
+        code,
+        |eval| register_host(eval.natives(), Rc::new(Cell::new(0))),
+        |vm| register_host(vm.natives(), Rc::new(Cell::new(0))),
+    )
+}
+
+fn error_kind(result: &Object) -> Option<ErrorKind> {
+    match result {
+        Object::Error(err) => Some(err.kind),
+        _ => None,
+    }
+}
+
+#[test]
+fn host_natives_are_callable() {
+    for result in run_both("return sum(1, 2, 3) + clamp(5, 0, 2)") {
+        assert_eq!(result, Object::Number(8.0));
+    }
+    for result in run_both("return sum()") {
+        assert_eq!(result, Object::Number(0.0));
+    }
+    for result in run_both("tick()\ntick()\nreturn tick()") {
+        assert_eq!(result, Object::Number(3.0));
+    }
+}
+
+#[test]
+fn arity_is_checked_before_the_call() {
+    for result in run_both("return clamp()") {
+        assert_eq!(error_kind(&result), Some(ErrorKind::ArityError));
+    }
+    for result in run_both("return clamp(1, 2, 3, 4)") {
+        assert_eq!(error_kind(&result), Some(ErrorKind::ArityError));
+    }
+    for result in run_both("return tick(1)") {
+        assert_eq!(error_kind(&result), Some(ErrorKind::ArityError));
+    }
+}
+
+#[test]
+fn native_errors_stop_the_program() {
+    for result in run_both("return sum(1, \"two\")") {
+        assert_eq!(error_kind(&result), Some(ErrorKind::TypeError));
+    }
+}
+
+#[test]
+fn natives_call_back_into_the_interpreter() {
+    let code = "def inc(x) {\n return x + 1\n}\nreturn twice(inc, 1) + twice(inc, 10)";
+    for result in run_both(code) {
+        assert_eq!(result, Object::Number(15.0));
+    }
+    // the program keeps running after a callback inside a call expression
+    let code = "def wrap(x) {\n return [x]\n}\nlet a = [twice(wrap, 1), 2]\nreturn a";
+    for result in run_both(code) {
+        assert_eq!(result.to_string(), "[[[1]], 2]");
+    }
+    for result in run_both("def bad(x) {\n return x + nope\n}\nreturn twice(bad, 1)") {
+        assert_eq!(error_kind(&result), Some(ErrorKind::NameError));
+    }
+}
+
+#[test]
+fn globals_shadow_natives() {
+    for result in run_both("let len = 5\nreturn len") {
+        assert_eq!(result, Object::Number(5.0));
+    }
+}
+
+#[test]
+fn registry_exposes_docs() {
+    let registry = NativeRegistry::with_builtins();
+    assert_eq!(registry.names(), vec!["len", "push"]);
+    assert!(registry.doc("len").unwrap().starts_with("len(value)"));
+    assert!(registry.doc("missing").is_none());
+    assert!(Arity::Range(1, 2).accepts(2));
+    assert!(!Arity::Fixed(1).accepts(0));
+}
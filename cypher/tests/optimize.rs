@@ -0,0 +1,91 @@
+use cypher::{
+    compiler::{compiler::Compiler, optimize::optimize},
+    errors::ErrorKind,
+    objects::Object,
+    parser::expr::Program,
+    vm::{chunk::Chunk, disassemble::Disassembler, vm::VM},
+};
+
+mod common;
+use common::parse;
+
+fn compile(program: Program) -> Chunk {
+    Compiler::new("test.cy".to_owned()).compile_program(program)
+}
+
+fn optimized(code: &str) -> String {
+    Disassembler::new(&compile(optimize(parse(code)))).render()
+}
+
+fn plain(code: &str) -> String {
+    Disassembler::new(&compile(parse(code))).render()
+}
+
+#[track_caller]
+fn assert_optimizes_to(code: &str, expected: &str) {
+    assert_eq!(optimized(code), plain(expected), "optimizing {:?}", code);
+}
+
+#[test]
+fn constant_expressions_are_folded() {
+    assert_optimizes_to("print(2 + 3 * 4)", "print(14)");
+    assert_optimizes_to("print((1 + 1) ** 3 ~/ 3 % 2)", "print(0)");
+    assert_optimizes_to("print(-(4 - 6))", "print(2)");
+    assert_optimizes_to("print(0.1 + 0.2)", "print(0.30000000000000004)");
+    assert_optimizes_to("print(1 < 2 == (3 >= 4))", "print(false)");
+    assert_optimizes_to("print(\"ab\" + \"cd\" + \"e\")", "print(\"abcde\")");
+    assert_optimizes_to("print(\"b\" > \"a\")", "print(true)");
+    assert_optimizes_to("print(nil == false)", "print(false)");
+    assert_optimizes_to("print(!nil)", "print(true)");
+
+    let folded = optimized("let x = 1\nprint(x + 2 * 3)");
+    assert!(!folded.contains(" mul"), "{}", folded);
+    assert!(folded.contains(" add"), "{}", folded);
+}
+
+#[test]
+fn failing_operations_are_left_for_runtime() {
+    let code = "print(\"a\" - 1)";
+    assert_eq!(optimized(code), plain(code));
+    match VM::new().run(compile(optimize(parse(code)))) {
+        Object::Error(err) => assert_eq!(err.kind, ErrorKind::TypeError),
+        result => panic!("expected a type error, got {}", result),
+    }
+}
+
+#[test]
+fn logical_operators_with_constant_operands() {
+    assert_optimizes_to("def f() {\n}\nprint(false and f())", "def f() {\n}\nprint(false)");
+    assert_optimizes_to("def f() {\n}\nprint(nil or f())", "def f() {\n}\nprint(f())");
+    assert_optimizes_to("print(1 and 2)", "print(2)");
+}
+
+#[test]
+fn constant_branches_are_removed() {
+    assert_optimizes_to("if true {\nprint(1)\n} else {\nprint(2)\n}", "{\nprint(1)\n}");
+    assert_optimizes_to("if 1 > 2 {\nprint(1)\n} else {\nprint(2)\n}", "\n\n{\nprint(2)\n}");
+    assert_optimizes_to("if false {\nprint(1)\n}\nprint(3)", "\n\n\nprint(3)");
+    assert_optimizes_to("while false {\nprint(1)\n}\nprint(3)", "\n\n\nprint(3)");
+}
+
+#[test]
+fn code_after_return_is_removed() {
+    assert_optimizes_to(
+        "def f() {\nreturn 1\nprint(2)\n}\nprint(f())",
+        "def f() {\nreturn 1\n\n}\nprint(f())",
+    );
+    assert_optimizes_to(
+        "def f() {\nif true {\nreturn 1\n}\nreturn 2\nprint(3)\n}",
+        "def f() {\n{\nreturn 1\n}\nreturn 2\n\n}",
+    );
+}
+
+#[test]
+fn double_negation_is_simplified() {
+    assert_optimizes_to("let x = 1\nif !!x {\nprint(x)\n}", "let x = 1\nif x {\nprint(x)\n}");
+    assert_optimizes_to("let x = 1\nprint(!!(x < 2))", "let x = 1\nprint(x < 2)");
+    assert_optimizes_to("let x = 1\nprint(!!!x)", "let x = 1\nprint(!x)");
+    // the value of !!x is a bool, x itself may not be
+    let code = "let x = 1\nprint(!!x)";
+    assert_eq!(optimized(code), plain(code));
+}
@@ -0,0 +1,177 @@
+use cypher::{
+    errors::ErrorKind,
+    objects::Object,
+    vm::{chunk::Chunk, disassemble::Disassembler, opcode::Opcode, peephole, verify::verify, vm::VM},
+};
+
+mod common;
+use common::compile;
+
+const LOOPS: &str = "def count(n) {
+    let total = 0
+    for let i = 0; i < n; i++ {
+        if i == 3 {
+            total = total + 1
+        }
+    }
+    let j = 0
+    while j < n {
+        j = j + 1
+    }
+    return total + j
+}
+return count(1000)";
+
+fn optimized(code: &str) -> Chunk {
+    let mut chunk = compile(code);
+    peephole::optimize(&mut chunk);
+    chunk
+}
+
+// the chunk of the first function in the pool of chunk
+fn function(chunk: &Chunk) -> &Chunk {
+    chunk
+        .constants
+        .iter()
+        .find_map(|constant| match constant {
+            Object::CompiledFunction(function) => Some(&function.chunk),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn loops_use_superinstructions() {
+    let chunk = optimized(LOOPS);
+    assert_eq!(verify(&chunk), Ok(()));
+    assert_eq!(
+        Disassembler::new(function(&chunk)).render(),
+        "count:
+0000    2 lc           0 0
+0002    3 lc           0 0
+0004    3 getlocal     3
+0006    3 getlocal     1
+0008    3 jlt         27
+0011    4 getlocal     3
+0013    4 lc           1 3
+0015    4 jeq         21
+0018    5 inclocal     2
+0020    5 pop
+0021    3 inclocal     3
+0023    3 pop
+0024    3 jmp          4
+0027    3 pop
+0028    8 lc           0 0
+0030    9 getlocal     3
+0032    9 getlocal     1
+0034    9 jlt         43
+0037   10 inclocal     3
+0039   10 pop
+0040   10 jmp         30
+0043   12 getlocal     2
+0045   12 getlocal     3
+0047   12 add
+0048   12 ret
+"
+    );
+}
+
+#[test]
+fn fewer_instructions_are_dispatched() {
+    let mut plain = VM::new();
+    let expected = plain.run(compile(LOOPS));
+    let mut fused = VM::new();
+    assert_eq!(fused.run(optimized(LOOPS)), expected);
+    assert_eq!(expected, Object::Number(1001.0));
+    // each loop iteration drops from 9 or more instructions to 5
+    assert!(
+        fused.instruction_count() * 3 < plain.instruction_count() * 2,
+        "{} instructions optimized, {} plain",
+        fused.instruction_count(),
+        plain.instruction_count()
+    );
+}
+
+#[test]
+fn unused_values_are_removed() {
+    // the literal, the local only read as a statement and the local itself
+    let chunk = optimized("def f() {\n1\nlet x = 2\nx\n}\nreturn f()");
+    let text = Disassembler::new(function(&chunk)).render();
+    assert_eq!(text, "f:\n0000    4 lc           2 nil\n0002    4 ret\n");
+}
+
+#[test]
+fn jumps_to_jumps_are_threaded() {
+    let code = "def f(a, b) {
+    let x = 0
+    if a {
+        if b {
+            x = 1
+        } else {
+            x = 2
+        }
+    } else {
+        x = 3
+    }
+    return x
+}
+f(true, true)";
+    let chunk = optimized(code);
+    assert_eq!(verify(&chunk), Ok(()));
+    let body = function(&chunk);
+    let mut offset = 0;
+    while offset < body.code.len() {
+        let opcode = Opcode::from(body.code[offset]);
+        if opcode.is_jump() {
+            let target = body.read_u16(offset + 1) as usize;
+            assert_ne!(Opcode::from(body.code[target]), Opcode::JMP, "jump at {} lands on a jmp", offset);
+        }
+        offset = Disassembler::instruction(body, offset).1;
+    }
+    for (a, b, x) in [(true, true, 1.0), (true, false, 2.0), (false, true, 3.0)] {
+        let call = format!("{}\nreturn f({}, {})", &code[..code.rfind('\n').unwrap()], a, b);
+        assert_eq!(VM::new().run(optimized(&call)), Object::Number(x));
+    }
+}
+
+#[test]
+fn errors_keep_their_kind_and_line() {
+    let code = "def f(limit) {\n    let i = \"a\"\n    i++\n    return i < limit\n}\nreturn f(1)";
+    for chunk in [compile(code), optimized(code)] {
+        match VM::new().run(chunk) {
+            Object::Error(err) => {
+                assert_eq!(err.kind, ErrorKind::TypeError);
+                assert_eq!(err.message(), "Unknown operation between a ADD 1");
+                assert_eq!(err.trace[0].line, Some(3));
+            }
+            result => panic!("expected an error, got {}", result),
+        }
+    }
+
+    let code = "def f(limit) {\n    let i = 0\n    while i\n    < limit {\n        i++\n    }\n}\nreturn f(\"a\")";
+    let chunk = optimized(code);
+    assert!(Disassembler::new(function(&chunk)).render().contains("jlt"));
+    match VM::new().run(chunk) {
+        Object::Error(err) => {
+            assert_eq!(err.kind, ErrorKind::TypeError);
+            assert_eq!(err.trace[0].line, Some(4));
+        }
+        result => panic!("expected an error, got {}", result),
+    }
+}
+
+#[test]
+fn loops_of_jumps_are_left_alone() {
+    let (lc, jne, jmp, ret) = (Opcode::LC as u8, Opcode::JNE as u8, Opcode::JMP as u8, Opcode::RET as u8);
+    let mut chunk = Chunk::new("test.cy".to_owned());
+    chunk.constants.push(Object::Nil);
+    // the jumps at 5 and 11 go to each other, the return after them never runs
+    let code = [lc, 0, jne, 0, 11, jmp, 0, 11, lc, 0, ret, jmp, 0, 5];
+    for (offset, &byte) in code.iter().enumerate() {
+        chunk.write_byte(byte, offset as u32);
+    }
+    peephole::optimize(&mut chunk);
+    assert_eq!(verify(&chunk), Ok(()));
+    assert_eq!(chunk.code, [lc, 0, jne, 0, 5, jmp, 0, 5]);
+    assert_eq!(chunk.lines, [0, 0, 2, 2, 2, 11, 11, 11]);
+}
@@ -0,0 +1,85 @@
+use cypher::{
+    objects::Object,
+    vm::vm::VM,
+};
+
+mod common;
+use common::{compile, compile_register};
+
+const FIB: &str = "def fib(n) {
+    if n < 2 {
+        return n
+    }
+    return fib(n - 1) + fib(n - 2)
+}
+return fib(10)";
+
+#[test]
+fn every_instruction_is_sampled() {
+    let mut vm = VM::new();
+    assert_eq!(vm.profile(), None);
+    vm.set_profiling(true);
+    let result = vm.run(compile(FIB));
+    assert_eq!(result, Object::Number(55.0));
+
+    let profile = vm.profile().unwrap();
+    assert_eq!(profile.samples, vm.instruction_count());
+    assert_eq!(profile.opcodes.iter().map(|opcode| opcode.count).sum::<u64>(), profile.samples);
+    assert_eq!(profile.functions[0].name, "fib");
+    assert_eq!(profile.functions[0].file, "test.cy");
+    let fib = profile.function("fib").unwrap();
+    let script = profile.function("<script>").unwrap();
+    assert_eq!(fib.samples + script.samples, profile.samples);
+    assert!(fib.time > script.time);
+    // fib is called 177 times and returns once per call
+    assert_eq!(profile.opcode("ret"), 178);
+    assert_eq!(profile.opcode("call"), 177);
+
+    // the lines of fib split its samples between them
+    let lines: Vec<u32> = profile.lines.iter().filter(|line| line.function == "fib").map(|line| line.line).collect();
+    for line in [2, 3, 5] {
+        assert!(lines.contains(&line), "{:?}", lines);
+    }
+    let samples: u64 = profile.lines.iter().filter(|line| line.function == "fib").map(|line| line.samples).sum();
+    assert_eq!(samples, fib.samples);
+}
+
+#[test]
+fn register_opcodes_are_told_apart() {
+    let mut vm = VM::new();
+    vm.set_profiling(true);
+    let result = vm.run(compile_register(FIB));
+    assert_eq!(result, Object::Number(55.0));
+    let profile = vm.profile().unwrap();
+    assert_eq!(profile.opcode("call (register)"), 177);
+    assert_eq!(profile.opcode("call"), 0);
+    assert_eq!(profile.samples, vm.instruction_count());
+}
+
+#[test]
+fn runs_add_up_until_profiling_starts_over() {
+    let mut vm = VM::new();
+    vm.set_profiling(true);
+    vm.run(compile(FIB));
+    let first = vm.profile().unwrap().function("fib").unwrap().samples;
+    vm.run(compile("return fib(10)"));
+    // the same function, the global still holds it
+    assert_eq!(vm.profile().unwrap().function("fib").unwrap().samples, 2 * first);
+
+    vm.set_profiling(true);
+    assert_eq!(vm.profile().unwrap().samples, 0);
+    vm.set_profiling(false);
+    assert_eq!(vm.profile(), None);
+}
+
+#[test]
+fn the_report_lists_hot_spots() {
+    let mut vm = VM::new();
+    vm.set_profiling(true);
+    vm.run(compile(FIB));
+    let text = vm.profile().unwrap().to_string();
+    assert!(text.starts_with("profile: "), "{}", text);
+    assert!(text.contains("  fib (test.cy)\n"), "{}", text);
+    assert!(text.contains("  test.cy:5 in fib\n"), "{}", text);
+    assert!(text.contains("  getlocal "), "{}", text);
+}
@@ -0,0 +1,229 @@
+use cypher::{
+    compiler::register::RegisterCompiler,
+    errors::ErrorKind,
+    objects::Object,
+    vm::{
+        disassemble::Disassembler,
+        register::{Op, WIDTH},
+        verify::verify,
+        vm::{Machine, VM},
+    },
+};
+
+mod common;
+use common::{compile, compile_register, parse};
+
+const PROGRAMS: [&str; 6] = [
+    "def fib(n) {
+    if n < 2 {
+        return n
+    }
+    return fib(n - 1) + fib(n - 2)
+}
+return fib(15)",
+    "def counter() {
+    let count = 0
+    def next() {
+        count++
+        return count
+    }
+    return next
+}
+let total = 0
+{
+    let c = counter()
+    c()
+    c()
+    total = c() * 10
+    for let i = 0; i < 3; i++ {
+        let j = i
+        def get() {
+            return j
+        }
+        total = total + get()
+    }
+}
+return total",
+    "class Shape {
+    def init(name) {
+        self.name = name
+    }
+    def describe() {
+        return self.name + \" with \" + self.sides() + \" sides\"
+    }
+}
+class Square < Shape {
+    def init() {
+        super.init(\"square\")
+    }
+    def sides() {
+        return \"4\"
+    }
+}
+let s = Square()
+return [s.describe(), s is Shape, s.name]",
+    "def f(x) {
+    try {
+        if x > 1 {
+            throw \"big\"
+        }
+        return \"small\"
+    } catch err {
+        return \"caught \" + err.message
+    } finally {
+        x = 0
+    }
+}
+return [f(1), f(2)]",
+    "let a = 1
+let b = a + (a = 5)
+let i = 0
+let c = i + i++
+let d = nil or \"default\"
+let e = [1, 2, 3][1] and !false
+return [b, c, d, e, a, i]",
+    "def apply(f, x) {
+    return f(f(x))
+}
+def double(x) {
+    return x * 2
+}
+let words = [\"a\", \"bc\"]
+let out = 0
+for let i = 0; i < 2; i++ {
+    let word = words[i]
+    out = out * 100 + word.len() * apply(double, i + 1)
+}
+return out",
+];
+
+#[test]
+fn operands_past_their_width_are_a_compile_error() {
+    let locals: Vec<String> = (0..300).map(|i| format!("let a{} = {}", i, i % 10)).collect();
+    let code = format!("def f() {{\n{}\n}}", locals.join("\n"));
+    let err = RegisterCompiler::new("test.cy".to_owned()).try_compile_program(parse(&code)).unwrap_err();
+    assert_eq!(err.message, "too many registers in f, the limit is 256");
+    assert_eq!(err.line_no, 257);
+
+    let body = vec!["    x = 1"; 14000].join("\n");
+    let code = format!("let x = 0\nif x == 1 {{\n{}\n}}\nreturn x", body);
+    let err = RegisterCompiler::new("test.cy".to_owned()).try_compile_program(parse(&code)).unwrap_err();
+    assert!(err.message.contains("too long to jump over"), "{}", err);
+}
+
+#[test]
+fn both_machines_compute_the_same() {
+    for code in PROGRAMS {
+        let chunk = compile_register(code);
+        assert_eq!(verify(&chunk), Ok(()), "{}", code);
+        let expected = VM::new().run(compile(code));
+        assert!(!matches!(expected, Object::Error(_)), "{}: {}", code, expected);
+        assert_eq!(VM::new().run(chunk), expected, "{}", code);
+
+        let mut stressed = VM::new();
+        stressed.set_gc_stress(true);
+        assert_eq!(stressed.run(compile_register(code)), expected, "{}", code);
+    }
+}
+
+#[test]
+fn arithmetic_uses_three_registers() {
+    let text = Disassembler::new(&compile_register("def f(a, b) {\nlet c = a * b + 1\nreturn c - a\n}")).render();
+    assert!(text.ends_with(
+        "f:
+0000    2 mul        r4 r1 r2
+0004    2 addk       r3 r4 1
+0008    3 sub        r4 r3 r1
+0012    3 ret        r4
+0016    3 loadk      r3 nil
+0020    3 ret        r3
+"
+    ), "{}", text);
+}
+
+#[test]
+fn fewer_instructions_are_dispatched() {
+    let code = "let total = 0
+for let i = 0; i < 1000; i++ {
+    let square = i * i
+    if square % 2 == 0 {
+        total = total + square
+    }
+}
+return total";
+    let mut stack_vm = VM::new();
+    let expected = stack_vm.run(compile(code));
+    let mut register_vm = VM::new();
+    assert_eq!(register_vm.run(compile_register(code)), expected);
+    assert!(
+        register_vm.instruction_count() * 3 < stack_vm.instruction_count() * 2,
+        "{} register instructions, {} stack instructions",
+        register_vm.instruction_count(),
+        stack_vm.instruction_count()
+    );
+}
+
+#[test]
+fn machines_call_each_other() {
+    let mut vm = VM::new();
+    assert_eq!(vm.run(compile("def twice(f, x) {\nreturn f(f(x))\n}")), Object::Nil);
+    assert_eq!(vm.run(compile_register("def inc(x) {\nreturn x + 1\n}\nreturn twice(inc, 1)")), Object::Number(3.0));
+    assert_eq!(vm.run(compile("return [twice(inc, 5), inc(1)]")), vm.run(compile_register("return [7, 2]")));
+
+    // a register function called back by a native
+    let code = "def wrap() {\nreturn [3, 1, 2].map(inc)\n}\nreturn wrap()";
+    assert_eq!(vm.run(compile(code)), vm.run(compile_register(code)));
+}
+
+#[test]
+fn the_machine_is_selected_at_runtime() {
+    let code = "def f(n) {\nreturn n * 2\n}\nreturn f(21)";
+    let mut vm = VM::new();
+    assert_eq!(vm.machine(), Machine::Stack);
+    assert_eq!(vm.compile("test.cy".to_owned(), parse(code)).unwrap().registers, 0);
+    vm.set_machine(Machine::Register);
+    let chunk = vm.compile("test.cy".to_owned(), parse(code)).unwrap();
+    assert!(chunk.registers > 0);
+    assert_eq!(vm.run(chunk), Object::Number(42.0));
+}
+
+#[test]
+fn errors_keep_their_kind_and_line() {
+    let code = "def f(x) {\n    let y = x\n    return y\n    - \"a\"\n}\nreturn f(1)";
+    for chunk in [compile(code), compile_register(code)] {
+        match VM::new().run(chunk) {
+            Object::Error(err) => {
+                assert_eq!(err.kind, ErrorKind::TypeError);
+                assert_eq!(err.message(), "Unknown operation between 1 SUB a");
+                assert_eq!(err.trace[0].line, Some(4));
+                assert_eq!(err.trace[0].function, "f");
+            }
+            result => panic!("expected an error, got {}", result),
+        }
+    }
+    match VM::new().run(compile_register("def f(n) {\nreturn f(n + 1)\n}\nf(0)")) {
+        Object::Error(err) => assert_eq!(err.kind, ErrorKind::StackOverflow),
+        result => panic!("expected a stack overflow, got {}", result),
+    }
+}
+
+#[test]
+fn registers_outside_the_window_are_rejected() {
+    let mut chunk = compile_register("return 1");
+    assert_eq!(verify(&chunk), Ok(()));
+    let registers = chunk.registers as u8;
+    chunk.code.splice(0..0, [Op::MOVE as u8, registers, 0, 0]);
+    chunk.lines.splice(0..0, [1; WIDTH]);
+    match VM::new().run(chunk) {
+        Object::Error(err) => {
+            assert_eq!(err.kind, ErrorKind::InvalidBytecode);
+            assert!(err.message().contains("outside the"), "{}", err.message());
+        }
+        result => panic!("expected invalid bytecode, got {}", result),
+    }
+
+    // falling off the end of the code
+    let mut chunk = compile_register("return 1");
+    chunk.code.truncate(chunk.code.len() - WIDTH);
+    assert!(verify(&chunk).is_err());
+}
@@ -0,0 +1,130 @@
+use cypher::{
+    errors::ErrorKind,
+    eval::Eval,
+    interpreter::Interpreter,
+    objects::Object,
+    policy::{Capability, Policy},
+};
+
+mod common;
+use common::{parse, run_both_with};
+
+fn run_both(code: &str, policy: Policy) -> [Object; 2] {
+    let tree = policy.clone();
+    run_both_with(code, |eval| eval.set_policy(tree).unwrap(), |vm| vm.set_policy(policy))
+}
+
+fn error_kind(result: &Object) -> ErrorKind {
+    match result {
+        Object::Error(err) => err.kind,
+        obj => panic!("expected an error got {}", obj),
+    }
+}
+
+#[test]
+fn denied_modules_cannot_be_imported() {
+    let code = "import \"fs\" as fs\nreturn fs.exists(\".\")";
+    for result in run_both(code, Policy::sandboxed()) {
+        assert_eq!(error_kind(&result), ErrorKind::ImportError);
+        assert!(result.to_string().contains("not allowed by the sandbox policy"));
+    }
+    for result in run_both(code, Policy::permissive().deny_module("fs")) {
+        assert_eq!(error_kind(&result), ErrorKind::ImportError);
+    }
+    for result in run_both(code, Policy::sandboxed().allow(Capability::Fs)) {
+        assert_eq!(result, Object::Bool(true));
+    }
+}
+
+#[test]
+fn denied_natives_raise_permission_errors() {
+    let code = "import \"math\" as math\nreturn math.floor(2.5)";
+    for result in run_both(code, Policy::sandboxed()) {
+        assert_eq!(result, Object::Number(2.0));
+    }
+    let code = "import \"math\" as math\nreturn math.random()";
+    for result in run_both(code, Policy::sandboxed()) {
+        assert_eq!(error_kind(&result), ErrorKind::PermissionError);
+        assert!(result.to_string().contains("math.random needs the random capability"));
+    }
+    for result in run_both(code, Policy::sandboxed().allow(Capability::Random)) {
+        assert!(matches!(result, Object::Number(n) if (0.0..1.0).contains(&n)));
+    }
+}
+
+#[test]
+fn script_imports_need_fs() {
+    let code = "import \"helper\" as helper";
+    for result in run_both(code, Policy::sandboxed()) {
+        assert_eq!(error_kind(&result), ErrorKind::ImportError);
+        assert!(result.to_string().contains("needs the fs capability"));
+    }
+}
+
+#[test]
+fn instruction_limit_stops_infinite_loops() {
+    let mut interp = Interpreter::new();
+    interp.set_policy(Policy::sandboxed().with_instruction_limit(10_000));
+    let err = interp.eval_str("let i = 0\nwhile true { i = i + 1 }").unwrap_err();
+    assert!(err.to_string().contains("BudgetExceeded: instruction limit of 10000 exceeded"));
+
+    // try cannot catch running out of budget
+    let err = interp.eval_str("try {\n    while true {}\n} catch e {\n    return 1\n}").unwrap_err();
+    assert!(err.to_string().contains("BudgetExceeded"));
+
+    // the budget is per run
+    assert_eq!(interp.eval_str("return 1 + 2").unwrap(), Object::Number(3.0));
+}
+
+#[test]
+fn memory_limit_stops_runaway_allocation() {
+    let mut interp = Interpreter::new();
+    interp.set_policy(Policy::permissive().with_memory_limit(1 << 16));
+    let err = interp.eval_str("let s = \"ab\"\nwhile true { s = s + s }").unwrap_err();
+    assert!(err.to_string().contains("BudgetExceeded: memory limit of 65536 bytes exceeded"));
+
+    let err = interp.eval_str("let a = []\nwhile true { a = a.push(a.len()) }").unwrap_err();
+    assert!(err.to_string().contains("BudgetExceeded"));
+
+    let mut interp = Interpreter::new();
+    interp.set_policy(Policy::permissive().with_memory_limit(1 << 16));
+    assert_eq!(interp.eval_str("return \"ab\" + \"cd\"").unwrap(), Object::string("abcd"));
+}
+
+#[test]
+fn the_tree_walker_refuses_limits_it_cannot_enforce() {
+    let program = parse("return 1");
+    let mut eval = Eval::new(&program);
+    for policy in [Policy::sandboxed().with_instruction_limit(10), Policy::permissive().with_memory_limit(10)] {
+        let err = eval.set_policy(policy).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ValueError);
+    }
+    assert!(eval.set_policy(Policy::sandboxed()).is_ok());
+    assert_eq!(eval.run(), Object::Number(1.0));
+}
+
+#[test]
+fn natives_are_stopped_before_allocating_past_the_limit() {
+    let mut interp = Interpreter::new();
+    interp.set_policy(Policy::sandboxed().with_memory_limit(1_000_000));
+    let err = interp.eval_str("return \"xy\".repeat(100000000000)").unwrap_err();
+    assert!(err.to_string().contains("BudgetExceeded: memory limit of 1000000 bytes exceeded"), "{}", err);
+
+    let err = interp
+        .eval_str("import \"json\" as json\nlet s = \"x\".repeat(400000)\nreturn json.stringify([s, s])")
+        .unwrap_err();
+    assert!(err.to_string().contains("BudgetExceeded"), "{}", err);
+
+    // a result within the limit is charged once
+    assert_eq!(interp.eval_str("return \"ab\".repeat(3)").unwrap(), Object::string("ababab"));
+    interp.set_policy(Policy::sandboxed().with_memory_limit(100));
+    assert_eq!(interp.eval_str("return \"ab\".repeat(30).len()").unwrap(), Object::Number(60.0));
+}
+
+#[test]
+fn permissive_policy_changes_nothing() {
+    let code = "import \"math\" as math\nimport \"time\" as time\nmath.seed(1)\nreturn time.now() > 0";
+    for result in run_both(code, Policy::default()) {
+        assert_eq!(result, Object::Bool(true));
+    }
+}
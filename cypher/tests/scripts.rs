@@ -0,0 +1,271 @@
+/*
+  runs every script under tests/scripts on the tree walking interpreter,
+  on the vm, on the vm with the garbage collector stressed, on the vm
+  after the optimizer and on the register machine, checking each against
+  the annotations in the script
+
+    // expect: <line>          a line printed to stdout, in order
+    // expect return: <value>  the value of a top level return statement
+    // expect error: <kind>    the kind of the runtime error that stops the script
+
+  directories named lib hold modules imported by the scripts and are
+  not run on their own, tests/scripts/modules/lib/search is on the module
+  search path
+*/
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use cypher::{
+    compiler::{compiler::Compiler, optimize::optimize},
+    eval::Eval, lexer::scanner::Scanner, objects::Object,
+    parser::parser::Parser,
+    vm::{peephole, vm::{Machine, VM}},
+};
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Backend {
+    Tree,
+    Vm,
+    // the vm collecting garbage on every allocation
+    VmGcStress,
+    // the vm running the program after compiler::optimize and vm::peephole
+    VmOptimized,
+    // the vm running register code, modules included
+    Register,
+}
+
+#[derive(Default)]
+struct Expectations {
+    output: Vec<String>,
+    value: Option<String>,
+    error: Option<String>,
+}
+
+struct Outcome {
+    output: Vec<String>,
+    value: String,
+    error: Option<String>,
+}
+
+fn parse_expectations(source: &str) -> Expectations {
+    let mut expectations = Expectations::default();
+    for line in source.lines() {
+        let Some(index) = line.find("// expect") else {
+            continue;
+        };
+        let annotation = &line[index + "// expect".len()..];
+        if let Some(value) = annotation.strip_prefix(": ") {
+            expectations.output.push(value.to_owned());
+        } else if let Some(value) = annotation.strip_prefix(" return: ") {
+            expectations.value = Some(value.trim().to_owned());
+        } else if let Some(kind) = annotation.strip_prefix(" error: ") {
+            expectations.error = Some(kind.trim().to_owned());
+        }
+    }
+    expectations
+}
+
+fn search_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts/modules/lib/search")
+}
+
+// runs the script, a script that does not parse or compile is an Err with the message
+fn run_script(backend: Backend, path: &Path, source: &str) -> Result<Outcome, String> {
+    // the full path so that imports resolve next to the script
+    let filename = path.display().to_string();
+    let lines: Vec<&str> = source.split('\n').collect();
+    let mut lex = Scanner::new(source, filename.to_owned(), &lines);
+    let mut par = Parser::new(&mut lex, &filename, &lines);
+    let mut program = par.try_parse_program().map_err(|err| err.to_string())?;
+    if matches!(backend, Backend::VmOptimized) {
+        program = optimize(program);
+    }
+
+    let buffer = SharedBuffer::default();
+    let result = match backend {
+        Backend::Tree => {
+            let mut eval = Eval::new(&program);
+            eval.set_filename(filename);
+            eval.modules().add_search_path(search_dir());
+            eval.set_output(Box::new(buffer.clone()));
+            eval.run()
+        }
+        Backend::Register => {
+            let mut vm = VM::new();
+            vm.set_machine(Machine::Register);
+            vm.modules().add_search_path(search_dir());
+            vm.set_output(Box::new(buffer.clone()));
+            let chunk = vm.compile(filename, program).map_err(|err| err.to_string())?;
+            vm.run(chunk)
+        }
+        Backend::Vm | Backend::VmGcStress | Backend::VmOptimized => {
+            let mut vm = VM::new();
+            let mut chunk = Compiler::with_interner(filename, vm.interner())
+                .try_compile_program(program)
+                .map_err(|err| err.to_string())?;
+            if matches!(backend, Backend::VmOptimized) {
+                peephole::optimize(&mut chunk);
+            }
+            vm.set_gc_stress(matches!(backend, Backend::VmGcStress));
+            vm.modules().add_search_path(search_dir());
+            vm.set_output(Box::new(buffer.clone()));
+            vm.run(chunk)
+        }
+    };
+
+    let output = String::from_utf8_lossy(&buffer.0.borrow()).to_string();
+    let (value, error) = match result {
+        Object::Error(err) => ("nil".to_owned(), Some(err.kind.to_string())),
+        value => (value.to_string(), None),
+    };
+    Ok(Outcome {
+        output: output.lines().map(str::to_owned).collect(),
+        value,
+        error,
+    })
+}
+
+/*
+  line diff of expected against actual, built from their longest
+  common subsequence
+*/
+fn diff(expected: &[String], actual: &[String]) -> String {
+    let (n, m) = (expected.len(), actual.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            out.push_str(&format!("      {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("    - {}\n", expected[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("    + {}\n", actual[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+fn check(backend: Backend, root: &Path, path: &Path, source: &str) -> Option<String> {
+    let expected = parse_expectations(source);
+    let outcome = run_script(backend, path, source);
+    let path = path.strip_prefix(root).unwrap();
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(message) => return Some(format!("{} [{:?}]\n  {}", path.display(), backend, message)),
+    };
+    let mut problems = vec![];
+
+    if outcome.output != expected.output {
+        problems.push(format!(
+            "stdout differs (- expected, + actual):\n{}",
+            diff(&expected.output, &outcome.output)
+        ));
+    }
+    if outcome.error != expected.error {
+        problems.push(format!(
+            "expected error {:?}, got {:?}",
+            expected.error, outcome.error
+        ));
+    }
+    if let Some(value) = expected.value {
+        if outcome.value != value {
+            problems.push(format!(
+                "expected return value {}, got {}",
+                value, outcome.value
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{} [{:?}]\n  {}",
+        path.display(),
+        backend,
+        problems.join("\n  ")
+    ))
+}
+
+fn collect_scripts(dir: &Path, scripts: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name == "lib") {
+                continue;
+            }
+            collect_scripts(&path, scripts);
+        } else if path.extension().is_some_and(|ext| ext == "cy") {
+            scripts.push(path);
+        }
+    }
+}
+
+#[test]
+fn scripts() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts");
+    let mut scripts = vec![];
+    collect_scripts(&root, &mut scripts);
+    scripts.sort();
+    assert!(!scripts.is_empty(), "no scripts found in {}", root.display());
+
+    let backends = [
+        Backend::Tree,
+        Backend::Vm,
+        Backend::VmGcStress,
+        Backend::VmOptimized,
+        Backend::Register,
+    ];
+    let mut failures = vec![];
+    for path in &scripts {
+        let source = fs::read_to_string(path).unwrap();
+        for backend in backends {
+            if let Some(failure) = check(backend, &root, path, &source) {
+                failures.push(failure);
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!(
+            "{} of {} script runs failed\n\n{}",
+            failures.len(),
+            scripts.len() * backends.len(),
+            failures.join("\n\n")
+        );
+    }
+}
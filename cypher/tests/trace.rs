@@ -0,0 +1,104 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use cypher::{
+    objects::Object,
+    vm::{
+        trace::{TraceFormat, Tracer},
+        vm::VM,
+    },
+};
+
+mod common;
+use common::{compile, compile_register};
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8_lossy(&self.0.borrow()).lines().map(str::to_owned).collect()
+    }
+}
+
+const CODE: &str = "def add(a, b) {
+    return a + b
+}
+return add(1, \"x\")";
+
+fn traced(format: TraceFormat, code: &str) -> (VM, SharedBuffer, Object) {
+    let buffer = SharedBuffer::default();
+    let mut vm = VM::new();
+    vm.set_tracer(Some(Tracer::new(Box::new(buffer.clone()), format)));
+    let result = vm.run(compile(code));
+    (vm, buffer, result)
+}
+
+#[test]
+fn every_instruction_is_printed_before_it_runs() {
+    let (vm, buffer, result) = traced(TraceFormat::Text, CODE);
+    assert!(matches!(result, Object::Error(_)));
+    let lines = buffer.lines();
+    assert_eq!(lines.len() as u64, vm.instruction_count());
+    assert_eq!(
+        lines[5],
+        "[1] <script> test.cy:4           0010 call         2               [function(<script>), function(add), 1, \"x\"]"
+    );
+    // the failing instruction is the last one traced
+    assert_eq!(
+        lines.last().unwrap(),
+        "[2] add test.cy:2                0004 add                          [function(add), 1, \"x\", 1, \"x\"]"
+    );
+}
+
+#[test]
+fn json_lines_describe_each_step() {
+    let (vm, buffer, _) = traced(TraceFormat::Json, CODE);
+    let records: Vec<serde_json::Value> =
+        buffer.lines().iter().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(records.len() as u64, vm.instruction_count());
+    assert_eq!(
+        records.last().unwrap(),
+        &serde_json::json!({
+            "step": records.len(),
+            "depth": 2,
+            "function": "add",
+            "file": "test.cy",
+            "line": 2,
+            "ip": 4,
+            "instruction": "add",
+            "base": 1,
+            "stack": ["function(add)", "1", "\"x\"", "1", "\"x\""],
+        })
+    );
+    assert_eq!(records[0]["instruction"], "closure 0 function(add)");
+}
+
+#[test]
+fn register_code_shows_its_window() {
+    let buffer = SharedBuffer::default();
+    let mut vm = VM::new();
+    vm.set_tracer(Some(Tracer::new(Box::new(buffer.clone()), TraceFormat::Text)));
+    let chunk = compile_register(CODE);
+    vm.run(chunk);
+    assert_eq!(
+        buffer.lines().last().unwrap(),
+        "[2] add test.cy:2                0000 add        r3 r1 r2          [function(add), 1, \"x\", nil]"
+    );
+
+    // nothing is written once tracing is off
+    vm.set_tracer(None);
+    let before = buffer.lines().len();
+    vm.run(compile("return 1"));
+    assert_eq!(buffer.lines().len(), before);
+}
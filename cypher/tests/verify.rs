@@ -0,0 +1,136 @@
+use std::rc::Rc;
+
+use cypher::{
+    errors::{bytecode::BytecodeError, ErrorKind},
+    objects::Object,
+    vm::{chunk::Chunk, object::CompiledFunction, opcode::Opcode, verify::verify, vm::VM},
+};
+
+mod common;
+use common::compile;
+
+fn chunk(code: &[u8], constants: Vec<Object>) -> Chunk {
+    let mut chunk = Chunk::new("test.cy".to_owned());
+    for constant in constants {
+        chunk.constants.push(constant);
+    }
+    for &byte in code {
+        chunk.write_byte(byte, 1);
+    }
+    chunk
+}
+
+fn rejected(chunk: &Chunk) -> String {
+    match verify(chunk) {
+        Err(err @ BytecodeError::Invalid { .. }) => err.to_string(),
+        result => panic!("expected the chunk to be rejected, got {:?}", result),
+    }
+}
+
+const LC: u8 = Opcode::LC as u8;
+const POP: u8 = Opcode::POP as u8;
+const RET: u8 = Opcode::RET as u8;
+const JMP: u8 = Opcode::JMP as u8;
+const JNE: u8 = Opcode::JNE as u8;
+
+#[test]
+fn compiled_code_is_accepted() {
+    let nil = vec![Object::Nil];
+    assert_eq!(verify(&chunk(&[LC, 0, RET], nil.clone())), Ok(()));
+    // an if without else, both paths meet at the return with the same depth
+    let code = [LC, 0, JNE, 0, 8, LC, 0, POP, LC, 0, RET];
+    assert_eq!(verify(&chunk(&code, nil)), Ok(()));
+}
+
+#[test]
+fn malformed_instructions_are_rejected() {
+    let nil = vec![Object::Nil];
+    assert!(rejected(&chunk(&[200, LC, 0, RET], nil.clone())).contains("at 0000: unknown opcode 200"));
+    assert!(rejected(&chunk(&[LC], nil.clone())).contains("LC needs 1 bytes of operands"));
+    assert!(rejected(&chunk(&[LC, 3, RET], nil.clone())).contains("constant 3 out of range, the pool holds 1"));
+    let message = rejected(&chunk(&[Opcode::GETGLOBAL as u8, 0, RET], vec![Object::Number(1.0)]));
+    assert!(message.contains("GETGLOBAL names a number constant"), "{}", message);
+    let message = rejected(&chunk(&[Opcode::GETUPVAL as u8, 0, RET], nil.clone()));
+    assert!(message.contains("upvalue 0 out of range"), "{}", message);
+    let message = rejected(&chunk(&[Opcode::CLOSURE as u8, 0, RET], nil));
+    assert!(message.contains("CLOSURE of nil which is not a function"), "{}", message);
+}
+
+#[test]
+fn jumps_must_land_on_instructions() {
+    let nil = vec![Object::Nil];
+    let message = rejected(&chunk(&[JMP, 0, 4, LC, 0, RET], nil.clone()));
+    assert!(message.contains("at 0000: jump to 0004 is not the start of an instruction"), "{}", message);
+    let message = rejected(&chunk(&[JMP, 0, 90, LC, 0, RET], nil));
+    assert!(message.contains("jump to 0090"), "{}", message);
+}
+
+#[test]
+fn stack_depth_is_checked_on_every_path() {
+    let nil = vec![Object::Nil];
+    let message = rejected(&chunk(&[POP, LC, 0, RET], nil.clone()));
+    assert!(message.contains("POP pops 1 values from a stack of 0"), "{}", message);
+
+    // the taken branch skips pushing the second value
+    let code = [LC, 0, LC, 0, JNE, 0, 9, LC, 0, RET];
+    let message = rejected(&chunk(&code, nil.clone()));
+    assert!(message.contains("at 0009: stack depth is 3 on one path and 2 on another"), "{}", message);
+
+    let message = rejected(&chunk(&[LC, 0, POP], nil.clone()));
+    assert!(message.contains("runs past the end of the code"), "{}", message);
+
+    let message = rejected(&chunk(&[Opcode::GETLOCAL as u8, 4, RET], nil));
+    assert!(message.contains("local slot 4 is past the top"), "{}", message);
+}
+
+#[test]
+fn nested_functions_are_verified() {
+    let body = chunk(&[Opcode::GETLOCAL as u8, 1, RET, 99], vec![]);
+    let function = Rc::new(CompiledFunction::new("f".to_owned(), 1, 0, body));
+    let script = chunk(
+        &[Opcode::CLOSURE as u8, 0, POP, LC, 1, RET],
+        vec![Object::CompiledFunction(function), Object::Nil],
+    );
+    assert!(rejected(&script).contains("invalid bytecode in f at 0003: unknown opcode 99"));
+}
+
+#[test]
+fn captured_locals_are_closed_before_they_are_dropped() {
+    let code = "if true {
+    let a = 1
+    def g() {
+        return a
+    }
+    print(g())
+}
+return 1";
+    let mut chunk = compile(code);
+    assert_eq!(verify(&chunk), Ok(()));
+
+    // g captures its own slot instead of a, which is popped when the block ends
+    let mut offset = 0;
+    while chunk.code[offset] != Opcode::CLOSURE as u8 {
+        offset += 1 + Opcode::from(chunk.code[offset]).operand_len();
+    }
+    assert_eq!(chunk.code[offset + 2..offset + 4], [1, 1]);
+    chunk.code[offset + 3] = 2;
+    let message = rejected(&chunk);
+    assert!(message.contains("POP drops local slot 2 while an upvalue captures it"), "{}", message);
+    match VM::new().run(chunk) {
+        Object::Error(err) => assert_eq!(err.kind, ErrorKind::InvalidBytecode),
+        result => panic!("expected an error, got {}", result),
+    }
+}
+
+#[test]
+fn the_vm_refuses_to_run_invalid_chunks() {
+    let code = [LC, 0, Opcode::PRINT as u8, JMP, 0, 1, LC, 0, RET];
+    let result = VM::new().run(chunk(&code, vec![Object::string("never printed")]));
+    match result {
+        Object::Error(err) => {
+            assert_eq!(err.kind, ErrorKind::InvalidBytecode);
+            assert!(err.message().contains("jump to 0001"), "{}", err);
+        }
+        result => panic!("expected an error, got {}", result),
+    }
+}